@@ -0,0 +1,229 @@
+//! Control-Flow Graph
+//!
+//! Splits a program into basic blocks, runs of instructions that always
+//! execute together, linked by fall-through and SKP edges. A block starts at
+//! the first instruction, at every skip destination and after every SKP.
+//! Every SKP is treated as conditional, including `SKP RUN`, which falls
+//! through on the first sample.
+
+use crate::{analysis::paths::skip_destination, ast::Program, codegen::format_instruction};
+
+/// Where a control-flow edge leads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Index of a block
+    Block(usize),
+    /// End of the program
+    End,
+}
+
+/// How control passes along an edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// On to the next instruction
+    Fallthrough,
+    /// A SKP taking its jump
+    Skip,
+}
+
+/// An edge leaving a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub target: Target,
+    pub kind: EdgeKind,
+}
+
+/// A run of instructions with one entry and one exit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Index of the first instruction
+    pub start: usize,
+    /// Index one past the last instruction
+    pub end: usize,
+    /// Edges to the blocks that can run next
+    pub successors: Vec<Edge>,
+}
+
+/// Basic blocks of a program in program order, starting with the entry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    /// Get the index of the block containing an instruction
+    pub fn block_of(&self, index: usize) -> Option<usize> {
+        self.blocks
+            .iter()
+            .position(|block| (block.start..block.end).contains(&index))
+    }
+
+    /// Get the indices of the blocks with an edge to `block`
+    pub fn predecessors(&self, block: usize) -> Vec<usize> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| {
+                b.successors
+                    .iter()
+                    .any(|edge| edge.target == Target::Block(block))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Export the graph in Graphviz DOT format, listing the instructions of
+    /// `program` in each block
+    pub fn to_dot(&self, program: &Program) -> String {
+        let instructions = program.instructions();
+        let mut dot = String::from("digraph cfg {\n");
+        dot.push_str("    node [shape=box, fontname=monospace];\n");
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            let label: String = (block.start..block.end)
+                .map(|index| {
+                    let text = format_instruction(instructions[index]).replace('"', "\\\"");
+                    format!("{}: {}\\l", index, text)
+                })
+                .collect();
+            dot.push_str(&format!("    b{} [label=\"{}\"];\n", i, label));
+        }
+        dot.push_str("    end [shape=oval];\n");
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            for edge in &block.successors {
+                let target = match edge.target {
+                    Target::Block(next) => format!("b{}", next),
+                    Target::End => "end".to_string(),
+                };
+                let style = match edge.kind {
+                    EdgeKind::Fallthrough => "",
+                    EdgeKind::Skip => " [label=\"skp\", style=dashed]",
+                };
+                dot.push_str(&format!("    b{} -> {}{};\n", i, target, style));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Build the control-flow graph of a program
+pub fn control_flow_graph(program: &Program) -> ControlFlowGraph {
+    let instructions = program.instructions();
+    let len = instructions.len();
+
+    // Instructions that start a block, with the program end as a sentinel
+    let mut leaders = vec![false; len + 1];
+    leaders[0] = true;
+    leaders[len] = true;
+    for (index, inst) in instructions.iter().enumerate() {
+        if let Some(target) = skip_destination(program, index, inst) {
+            leaders[index + 1] = true;
+            leaders[target.min(len)] = true;
+        }
+    }
+
+    let starts: Vec<usize> = (0..=len).filter(|&i| leaders[i]).collect();
+    let target = |index: usize| match starts.binary_search(&index) {
+        Ok(block) if index < len => Target::Block(block),
+        _ => Target::End,
+    };
+
+    let blocks = starts
+        .windows(2)
+        .map(|window| {
+            let (start, end) = (window[0], window[1]);
+            let mut successors = vec![Edge {
+                target: target(end),
+                kind: EdgeKind::Fallthrough,
+            }];
+            let last = end - 1;
+            if let Some(dest) = skip_destination(program, last, instructions[last]) {
+                if dest.min(len) != end {
+                    successors.push(Edge {
+                        target: target(dest.min(len)),
+                        kind: EdgeKind::Skip,
+                    });
+                }
+            }
+            BasicBlock {
+                start,
+                end,
+                successors,
+            }
+        })
+        .collect();
+
+    ControlFlowGraph { blocks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn cfg(source: &str) -> (Program, ControlFlowGraph) {
+        let program = Parser::new(source).parse().unwrap();
+        let cfg = control_flow_graph(&program);
+        (program, cfg)
+    }
+
+    #[test]
+    fn test_straight_line() {
+        let (_, cfg) = cfg("rdax adcl, 1.0\nwrax dacl, 0.0");
+        assert_eq!(
+            cfg.blocks,
+            vec![BasicBlock {
+                start: 0,
+                end: 2,
+                successors: vec![Edge {
+                    target: Target::End,
+                    kind: EdgeKind::Fallthrough
+                }],
+            }]
+        );
+        assert!(control_flow_graph(&Program::new()).blocks.is_empty());
+    }
+
+    #[test]
+    fn test_skip_blocks() {
+        let (program, cfg) = cfg("\
+skp run, init
+wlds sin0, 12, 100
+init: rdax adcl, 1.0
+skp gez, out
+sof -1.0, 0.0
+out: wrax dacl, 0.0
+skp run, 5
+");
+        let spans: Vec<(usize, usize)> = cfg.blocks.iter().map(|b| (b.start, b.end)).collect();
+        assert_eq!(spans, vec![(0, 1), (1, 2), (2, 4), (4, 5), (5, 7)]);
+
+        assert_eq!(
+            cfg.blocks[0].successors,
+            vec![
+                Edge {
+                    target: Target::Block(1),
+                    kind: EdgeKind::Fallthrough
+                },
+                Edge {
+                    target: Target::Block(2),
+                    kind: EdgeKind::Skip
+                },
+            ]
+        );
+        assert_eq!(cfg.blocks[2].successors[1].target, Target::Block(4));
+        // A skip past the end leads to the end like the fall-through
+        assert_eq!(cfg.blocks[4].successors.len(), 1);
+        assert_eq!(cfg.predecessors(4), vec![2, 3]);
+        assert_eq!(cfg.block_of(3), Some(2));
+
+        let dot = cfg.to_dot(&program);
+        assert!(dot.starts_with("digraph cfg {\n"));
+        assert!(dot.contains("    b2 [label=\"2: RDAX ADCL, 1\\l3: SKP GEZ, 1\\l\"];\n"));
+        assert!(dot.contains("    b0 -> b2 [label=\"skp\", style=dashed];\n"));
+        assert!(dot.contains("    b4 -> end;\n"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Latency Analysis
+//!
+//! Tracks how input samples flow from the ADC registers, through registers
+//! and delay RAM, to the DAC registers, and reports the range of delays a
+//! signal can experience on the way. A delay RAM read at `r` of a value
+//! written at `w` is `r - w` samples late, and a register read before it is
+//! written holds the previous sample's value.
+//!
+//! All instructions are assumed to execute (SKP is not followed), and reads
+//! through `RMPA` are ignored since their address is only known at runtime.
+
+use crate::{
+    ast::Program, constants::SAMPLE_RATE, instruction::ChoMode, instruction::Instruction,
+    register::Register,
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// Passes after which still-growing delays are treated as unbounded
+const WIDEN_AFTER: usize = 64;
+
+/// Range of delays, in samples, between an ADC read and a DAC write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latency {
+    /// Shortest delay in samples
+    pub min: u32,
+    /// Longest delay in samples, `None` when feedback makes it unbounded
+    pub max: Option<u32>,
+}
+
+impl Latency {
+    /// Latency of a signal read straight from the ADC
+    const DIRECT: Latency = Latency {
+        min: 0,
+        max: Some(0),
+    };
+
+    /// Shortest delay in milliseconds
+    pub fn min_ms(&self) -> f32 {
+        samples_to_ms(self.min)
+    }
+
+    /// Longest delay in milliseconds, `None` when unbounded
+    pub fn max_ms(&self) -> Option<f32> {
+        self.max.map(samples_to_ms)
+    }
+
+    fn delayed(self, samples: u32) -> Self {
+        Latency {
+            min: self.min.saturating_add(samples),
+            max: self.max.map(|max| max.saturating_add(samples)),
+        }
+    }
+
+    fn join(self, other: Self) -> Self {
+        Latency {
+            min: self.min.min(other.min),
+            max: self.max.zip(other.max).map(|(a, b)| a.max(b)),
+        }
+    }
+}
+
+fn samples_to_ms(samples: u32) -> f32 {
+    samples as f32 * 1000.0 / SAMPLE_RATE
+}
+
+/// ADC-to-DAC latency of each output channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LatencyReport {
+    /// Latency to DACL, `None` if no input reaches it
+    pub left: Option<Latency>,
+    /// Latency to DACR, `None` if no input reaches it
+    pub right: Option<Latency>,
+}
+
+impl LatencyReport {
+    /// Latency across both output channels
+    pub fn overall(&self) -> Option<Latency> {
+        join(self.left, self.right)
+    }
+}
+
+/// Latency of a value, `None` if it does not depend on the input
+type Taint = Option<Latency>;
+
+fn join(a: Taint, b: Taint) -> Taint {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.join(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Replace the max of every latency that grew since the last pass
+fn widen(old: Taint, new: Taint) -> Taint {
+    match (old, new) {
+        (Some(old), Some(new)) if old.max != new.max => Some(Latency { max: None, ..new }),
+        (_, new) => new,
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct State {
+    regs: HashMap<Register, Taint>,
+    mem: BTreeMap<u16, Taint>,
+    report: LatencyReport,
+}
+
+impl State {
+    fn read_reg(&self, reg: Register) -> Taint {
+        match reg {
+            Register::ADCL | Register::ADCR => Some(Latency::DIRECT),
+            reg => self.regs.get(&reg).copied().flatten(),
+        }
+    }
+
+    fn write_reg(&mut self, reg: Register, value: Taint) {
+        match reg {
+            Register::DACL => self.report.left = join(self.report.left, value),
+            Register::DACR => self.report.right = join(self.report.right, value),
+            _ => {}
+        }
+        self.regs.insert(reg, value);
+    }
+
+    /// Read delay RAM, attributing the value to the nearest write at or
+    /// below the address
+    fn read_mem(&self, addr: u16) -> Taint {
+        self.mem
+            .range(..=addr)
+            .next_back()
+            .and_then(|(&written, value)| value.map(|l| l.delayed((addr - written) as u32)))
+    }
+
+    fn write_mem(&mut self, addr: u16, value: Taint) {
+        let entry = self.mem.entry(addr).or_default();
+        *entry = join(*entry, value);
+    }
+
+    /// Run one sample's worth of instructions
+    fn run(&mut self, instructions: &[&Instruction]) {
+        let mut acc: Taint = None;
+
+        for inst in instructions {
+            match inst {
+                Instruction::RDAX { reg, .. }
+                | Instruction::RDFX { reg, .. }
+                | Instruction::RDFX2 { reg, .. }
+                | Instruction::MULX { reg } => acc = join(acc, self.read_reg(*reg)),
+                Instruction::LDAX { reg } => acc = self.read_reg(*reg),
+                Instruction::WRAX { reg, coeff } => {
+                    self.write_reg(*reg, acc);
+                    if *coeff == 0.0 {
+                        acc = None;
+                    }
+                }
+                Instruction::WRHX { reg, .. } | Instruction::WRLX { reg, .. } => {
+                    self.write_reg(*reg, acc)
+                }
+                Instruction::RDA { addr, .. }
+                | Instruction::CHO {
+                    mode: ChoMode::RDA,
+                    addr,
+                    ..
+                } => acc = join(acc, self.read_mem(*addr)),
+                Instruction::WRA { addr, coeff } => {
+                    self.write_mem(*addr, acc);
+                    if *coeff == 0.0 {
+                        acc = None;
+                    }
+                }
+                Instruction::WRAP { addr, .. } => self.write_mem(*addr, acc),
+                Instruction::SOF { coeff, .. }
+                | Instruction::EXP { coeff, .. }
+                | Instruction::LOG { coeff, .. }
+                    if *coeff == 0.0 =>
+                {
+                    acc = None
+                }
+                Instruction::AND { mask: 0 } | Instruction::CLR => acc = None,
+                _ => {}
+            }
+        }
+    }
+
+    /// Start the next sample: registers now hold last sample's values
+    fn next_sample(&self) -> State {
+        State {
+            regs: self
+                .regs
+                .iter()
+                .map(|(&reg, value)| (reg, value.map(|l| l.delayed(1))))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    fn widen(&mut self, old: &State) {
+        for (reg, value) in &mut self.regs {
+            *value = widen(old.regs.get(reg).copied().flatten(), *value);
+        }
+        for (addr, value) in &mut self.mem {
+            *value = widen(old.mem.get(addr).copied().flatten(), *value);
+        }
+        self.report.left = widen(old.report.left, self.report.left);
+        self.report.right = widen(old.report.right, self.report.right);
+    }
+}
+
+/// Compute the ADC-to-DAC latency of a program
+pub fn latency_report(program: &Program) -> LatencyReport {
+    let instructions = program.instructions();
+    let mut state = State::default();
+    let mut pass = 0;
+
+    loop {
+        let mut next = state.next_sample();
+        next.run(&instructions);
+        pass += 1;
+        if pass > WIDEN_AFTER {
+            next.widen(&state);
+        }
+        if next == state {
+            return state.report;
+        }
+        state = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn report(source: &str) -> LatencyReport {
+        latency_report(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn test_direct_path() {
+        let report = report("rdax adcl, 1.0\nwrax dacl, 0.0");
+        assert_eq!(report.left, Some(Latency::DIRECT));
+        assert_eq!(report.right, None);
+    }
+
+    #[test]
+    fn test_delay_taps() {
+        let report =
+            report("rdax adcl, 1.0\nwra 0, 0.0\nrda 1000, 0.5\nrda 2000, 0.5\nwrax dacr, 0.0");
+        let latency = report.right.unwrap();
+        assert_eq!(latency.min, 1000);
+        assert_eq!(latency.max, Some(2000));
+        assert_eq!(report.overall(), Some(latency));
+        assert!((latency.min_ms() - 30.517578).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_register_state_adds_a_sample() {
+        let report = report("rdax reg0, 1.0\nwrax dacl, 0.0\nrdax adcl, 1.0\nwrax reg0, 0.0");
+        assert_eq!(
+            report.left,
+            Some(Latency {
+                min: 1,
+                max: Some(1)
+            })
+        );
+    }
+
+    #[test]
+    fn test_feedback_is_unbounded() {
+        let report = report("rdax adcl, 1.0\nrda 100, 0.5\nwra 0, 1.0\nwrax dacl, 0.0");
+        assert_eq!(report.left, Some(Latency { min: 0, max: None }));
+    }
+
+    #[test]
+    fn test_no_input_path() {
+        let report = report("sof 0.0, 0.5\nwrax dacl, 0.0");
+        assert_eq!(report.overall(), None);
+    }
+}
@@ -0,0 +1,18 @@
+//! FV-1 Program Analysis
+//!
+//! Static analyses over parsed programs.
+
+pub mod cfg;
+pub mod latency;
+pub mod paths;
+pub mod resources;
+pub mod roles;
+pub mod validate;
+
+// Re-export main types for convenience
+pub use cfg::{control_flow_graph, BasicBlock, ControlFlowGraph, Edge, EdgeKind, Target};
+pub use latency::{latency_report, Latency, LatencyReport};
+pub use paths::{enumerate_paths, path_report, PathReport};
+pub use resources::{resource_report, ResourceReport};
+pub use roles::{register_roles, RegisterRole};
+pub use validate::{validate, Finding};
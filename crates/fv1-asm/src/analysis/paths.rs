@@ -0,0 +1,164 @@
+//! SKP Path Analysis
+//!
+//! Enumerates the control-flow paths created by SKP instructions. Skips only
+//! jump forward, so the paths through a program form a DAG from the first
+//! instruction to the end of the program.
+
+use crate::{ast::Program, instruction::Instruction};
+
+/// Summary of the control-flow paths through a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathReport {
+    /// Number of distinct paths (saturates at `u64::MAX`)
+    pub path_count: u64,
+    /// Fewest instructions executed on any path
+    pub shortest: usize,
+    /// Most instructions executed on any path
+    pub longest: usize,
+    /// Indices of instructions no path reaches
+    pub unreachable: Vec<usize>,
+}
+
+/// Get the index of the instruction a SKP at `index` jumps to
+///
+/// Label targets are resolved through the program; numeric offsets count
+/// the instructions skipped after the SKP. Returns `None` for instructions
+/// other than SKP.
+pub(crate) fn skip_destination(
+    program: &Program,
+    index: usize,
+    inst: &Instruction,
+) -> Option<usize> {
+    let Instruction::SKP { offset, .. } = inst else {
+        return None;
+    };
+
+    let label_target = program
+        .skip_target(index)
+        .and_then(|label| program.resolve_label(label));
+    Some(label_target.unwrap_or(index + 1 + (*offset).max(0) as usize))
+}
+
+/// Successors of the instruction at `index`; `len` denotes the program end
+fn successors(program: &Program, instructions: &[&Instruction], index: usize) -> Vec<usize> {
+    let len = instructions.len();
+    let next = (index + 1).min(len);
+
+    match skip_destination(program, index, instructions[index]) {
+        Some(target) if target.min(len) != next => vec![next, target.min(len)],
+        _ => vec![next],
+    }
+}
+
+/// Summarize the paths through a program
+pub fn path_report(program: &Program) -> PathReport {
+    let instructions = program.instructions();
+    let len = instructions.len();
+
+    // Walk backwards so every successor is already computed; the entry at
+    // `len` is the program end
+    let mut count = vec![1u64; len + 1];
+    let mut shortest = vec![0usize; len + 1];
+    let mut longest = vec![0usize; len + 1];
+    for index in (0..len).rev() {
+        let next = successors(program, &instructions, index);
+        count[index] = next
+            .iter()
+            .fold(0u64, |acc, &n| acc.saturating_add(count[n]));
+        shortest[index] = 1 + next.iter().map(|&n| shortest[n]).min().unwrap_or(0);
+        longest[index] = 1 + next.iter().map(|&n| longest[n]).max().unwrap_or(0);
+    }
+
+    let mut reachable = vec![false; len + 1];
+    reachable[0] = true;
+    for index in 0..len {
+        if reachable[index] {
+            for n in successors(program, &instructions, index) {
+                reachable[n] = true;
+            }
+        }
+    }
+
+    PathReport {
+        path_count: count[0],
+        shortest: shortest[0],
+        longest: longest[0],
+        unreachable: (0..len).filter(|&i| !reachable[i]).collect(),
+    }
+}
+
+/// Enumerate up to `limit` paths through a program, each as the list of
+/// instruction indices it executes
+pub fn enumerate_paths(program: &Program, limit: usize) -> Vec<Vec<usize>> {
+    let instructions = program.instructions();
+    let len = instructions.len();
+    let mut paths = Vec::new();
+    let mut stack = vec![(0usize, Vec::new())];
+
+    while let Some((index, mut path)) = stack.pop() {
+        if paths.len() >= limit {
+            break;
+        }
+        if index >= len {
+            paths.push(path);
+            continue;
+        }
+
+        path.push(index);
+        // Push in reverse so the fall-through path is explored first
+        for n in successors(program, &instructions, index).into_iter().rev() {
+            stack.push((n, path.clone()));
+        }
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> Program {
+        Parser::new(source).parse().unwrap()
+    }
+
+    #[test]
+    fn test_straight_line_program() {
+        let report = path_report(&parse("rdax adcl, 1.0\nwrax dacl, 0.0"));
+        assert_eq!(report.path_count, 1);
+        assert_eq!(report.shortest, 2);
+        assert_eq!(report.longest, 2);
+        assert!(report.unreachable.is_empty());
+    }
+
+    #[test]
+    fn test_skip_paths() {
+        let program = parse(
+            "skp run, init\nclr\nwrax reg0, 0.0\ninit: rdax adcl, 1.0\nskp gez, 1\nsof -1.0, 0.0\nwrax dacl, 0.0",
+        );
+        let report = path_report(&program);
+        assert_eq!(report.path_count, 4);
+        assert_eq!(report.shortest, 4);
+        assert_eq!(report.longest, 7);
+
+        let paths = enumerate_paths(&program, 10);
+        assert_eq!(paths.len(), 4);
+        assert_eq!(paths[0], vec![0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(paths[3], vec![0, 3, 4, 6]);
+    }
+
+    #[test]
+    fn test_enumerate_paths_limit() {
+        let program = parse("skp neg, 1\nclr\nskp neg, 1\nclr\nskp neg, 1\nclr");
+        assert_eq!(path_report(&program).path_count, 8);
+        assert_eq!(enumerate_paths(&program, 3).len(), 3);
+    }
+
+    #[test]
+    fn test_skip_past_end() {
+        let report = path_report(&parse("skp zro, 10\nclr"));
+        assert_eq!(report.path_count, 2);
+        assert_eq!(report.shortest, 1);
+    }
+}
@@ -0,0 +1,279 @@
+//! Resource Usage Analysis
+//!
+//! Reports which general purpose registers a program reads and writes, the
+//! delay RAM addresses its instructions touch, and how the `MEM` regions
+//! are laid out. Regions are placed one after another as the parser
+//! allocates them, each taking one word more than its size, and wrap around
+//! the end of delay RAM, so a program whose regions exceed it reports the
+//! regions that collide as overlapping.
+//!
+//! Reads through `RMPA` are counted but not located, since their address
+//! is only known at runtime. `CHO RDA` accesses are located at their base
+//! address and marked as modulated.
+
+use crate::{
+    analysis::roles::{register_access, Access},
+    ast::{Directive, Program},
+    constants::DELAY_RAM_SIZE,
+    instruction::{ChoMode, Instruction},
+    register::Register,
+};
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+/// How a program uses a general purpose register
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RegisterUsage {
+    /// Read by RDAX, RDFX, LDAX, MULX or similar
+    pub read: bool,
+    /// Written by WRAX, WRHX or WRLX
+    pub written: bool,
+}
+
+/// Direction of a delay RAM access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DelayAccessKind {
+    Read,
+    Write,
+}
+
+/// A delay RAM access by an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelayAccess {
+    /// Index of the instruction
+    pub index: usize,
+    pub kind: DelayAccessKind,
+    pub addr: u16,
+    /// Whether an LFO offsets the address at runtime
+    pub modulated: bool,
+    /// Name of the `MEM` region containing the address, if any
+    pub region: Option<String>,
+}
+
+/// A delay memory region allocated with `MEM`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub name: String,
+    /// First address
+    pub start: u16,
+    /// Words the region occupies, including the extra end word
+    pub len: usize,
+}
+
+impl MemoryRegion {
+    /// Address ranges the region occupies, two when it wraps around the
+    /// end of delay RAM
+    fn ranges(&self) -> Vec<RangeInclusive<usize>> {
+        let start = self.start as usize;
+        let end = start + self.len.min(DELAY_RAM_SIZE) - 1;
+        if end < DELAY_RAM_SIZE {
+            vec![start..=end]
+        } else {
+            vec![start..=DELAY_RAM_SIZE - 1, 0..=end - DELAY_RAM_SIZE]
+        }
+    }
+
+    /// Check whether the region contains `addr`
+    pub fn contains(&self, addr: u16) -> bool {
+        self.ranges()
+            .iter()
+            .any(|range| range.contains(&(addr as usize)))
+    }
+}
+
+/// Registers and delay memory used by a program
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceReport {
+    /// Usage of each REG0-REG31 the program references, by index
+    pub registers: BTreeMap<u8, RegisterUsage>,
+    /// `MEM` regions in allocation order
+    pub regions: Vec<MemoryRegion>,
+    /// Delay RAM accesses in program order
+    pub accesses: Vec<DelayAccess>,
+    /// Address ranges touched by the accesses, merged and sorted
+    pub touched: Vec<RangeInclusive<u16>>,
+    /// Number of `RMPA` reads, whose addresses are unknown
+    pub indirect_reads: usize,
+    /// Names of regions that share addresses
+    pub overlaps: Vec<(String, String)>,
+}
+
+/// Analyze the registers and delay memory used by a program
+pub fn resource_report(program: &Program) -> ResourceReport {
+    let mut report = ResourceReport {
+        regions: memory_regions(program),
+        ..ResourceReport::default()
+    };
+
+    for (index, inst) in program.instructions().into_iter().enumerate() {
+        if let Some((Register::REG(n), access)) = register_access(inst) {
+            let usage = report.registers.entry(n).or_default();
+            match access {
+                Access::Read => usage.read = true,
+                Access::Write => usage.written = true,
+            }
+        }
+
+        let (kind, addr, modulated) = match inst {
+            Instruction::RDA { addr, .. } => (DelayAccessKind::Read, *addr, false),
+            Instruction::WRA { addr, .. } | Instruction::WRAP { addr, .. } => {
+                (DelayAccessKind::Write, *addr, false)
+            }
+            Instruction::CHO {
+                mode: ChoMode::RDA,
+                addr,
+                ..
+            } => (DelayAccessKind::Read, *addr, true),
+            Instruction::RMPA { .. } => {
+                report.indirect_reads += 1;
+                continue;
+            }
+            _ => continue,
+        };
+        let region = report
+            .regions
+            .iter()
+            .find(|region| region.contains(addr))
+            .map(|region| region.name.clone());
+        report.accesses.push(DelayAccess {
+            index,
+            kind,
+            addr,
+            modulated,
+            region,
+        });
+    }
+
+    report.touched = merge_addresses(report.accesses.iter().map(|access| access.addr));
+    for (i, a) in report.regions.iter().enumerate() {
+        for b in &report.regions[i + 1..] {
+            let overlap = a.ranges().iter().any(|x| {
+                b.ranges()
+                    .iter()
+                    .any(|y| x.start() <= y.end() && y.start() <= x.end())
+            });
+            if overlap {
+                report.overlaps.push((a.name.clone(), b.name.clone()));
+            }
+        }
+    }
+
+    report
+}
+
+/// Lay out the program's `MEM` regions as the parser allocates them
+pub(crate) fn memory_regions(program: &Program) -> Vec<MemoryRegion> {
+    let mut next = 0usize;
+    program
+        .directives
+        .iter()
+        .filter_map(|directive| match directive {
+            Directive::MemoryAllocation { name, size } => {
+                let region = MemoryRegion {
+                    name: name.clone(),
+                    start: (next % DELAY_RAM_SIZE) as u16,
+                    len: *size as usize + 1,
+                };
+                next += region.len;
+                Some(region)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Merge addresses into sorted ranges of consecutive addresses
+fn merge_addresses(addrs: impl Iterator<Item = u16>) -> Vec<RangeInclusive<u16>> {
+    let mut addrs: Vec<u16> = addrs.collect();
+    addrs.sort_unstable();
+    addrs.dedup();
+
+    let mut ranges: Vec<RangeInclusive<u16>> = Vec::new();
+    for addr in addrs {
+        match ranges.last_mut() {
+            Some(range) if *range.end() + 1 == addr => *range = *range.start()..=addr,
+            _ => ranges.push(addr..=addr),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_register_usage() {
+        let program = Parser::new("rdax reg0, 1.0\nwrax reg0, 0.0\nwrax reg5, 0.0\nmulx reg2")
+            .parse()
+            .unwrap();
+        let report = resource_report(&program);
+
+        assert_eq!(
+            report.registers[&0],
+            RegisterUsage {
+                read: true,
+                written: true
+            }
+        );
+        assert!(report.registers[&5].written && !report.registers[&5].read);
+        assert!(report.registers[&2].read && !report.registers[&2].written);
+        assert_eq!(report.registers.len(), 3);
+    }
+
+    #[test]
+    fn test_delay_accesses() {
+        let source = "\
+mem dly 100
+mem ap 10
+rdax adcl, 1.0
+wra dly, 0.0
+rda dly#, 0.5
+rda 99, 0.5
+cho rda, sin0, reg, ap
+rmpa 1.0
+wrap 500, 0.0
+";
+        let report = resource_report(&Parser::new(source).parse().unwrap());
+
+        assert_eq!(
+            report.regions,
+            vec![
+                MemoryRegion {
+                    name: "dly".to_string(),
+                    start: 0,
+                    len: 101
+                },
+                MemoryRegion {
+                    name: "ap".to_string(),
+                    start: 101,
+                    len: 11
+                },
+            ]
+        );
+        assert_eq!(report.accesses.len(), 5);
+        assert_eq!(report.accesses[0].kind, DelayAccessKind::Write);
+        assert_eq!(report.accesses[2].region.as_deref(), Some("dly"));
+        assert!(report.accesses[3].modulated);
+        assert_eq!(report.accesses[3].region.as_deref(), Some("ap"));
+        assert_eq!(report.accesses[4].region, None);
+        assert_eq!(report.touched, vec![0..=0, 99..=101, 500..=500]);
+        assert_eq!(report.indirect_reads, 1);
+        assert!(report.overlaps.is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_regions() {
+        let mut program = Program::new();
+        for (name, size) in [("a", 20000), ("b", 20000)] {
+            program.add_directive(Directive::MemoryAllocation {
+                name: name.to_string(),
+                size,
+            });
+        }
+        let report = resource_report(&program);
+
+        assert_eq!(report.regions[1].start, 20001);
+        assert_eq!(report.overlaps, vec![("a".to_string(), "b".to_string())]);
+    }
+}
@@ -0,0 +1,140 @@
+//! Register Role Analysis
+//!
+//! Classifies the registers used by a program by the role they play
+//! (audio input, audio output, pot, filter state, scratch, control).
+
+use crate::{ast::Program, instruction::Instruction, register::Register};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Role a register plays in a program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegisterRole {
+    /// ADC input register
+    Input,
+    /// DAC output register
+    Output,
+    /// Potentiometer input
+    Pot,
+    /// Register read before it is written, so its value carries over
+    /// from the previous sample (filter state, feedback, etc.)
+    State,
+    /// Register written before it is read within a sample (scratch value)
+    Temp,
+    /// LFO and address pointer control registers
+    Control,
+}
+
+impl RegisterRole {
+    /// Short lowercase name of the role
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RegisterRole::Input => "input",
+            RegisterRole::Output => "output",
+            RegisterRole::Pot => "pot",
+            RegisterRole::State => "state",
+            RegisterRole::Temp => "temp",
+            RegisterRole::Control => "control",
+        }
+    }
+}
+
+impl fmt::Display for RegisterRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How an instruction accesses its register operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+}
+
+/// Get the register operand of an instruction and how it is accessed
+pub(crate) fn register_access(inst: &Instruction) -> Option<(Register, Access)> {
+    match inst {
+        Instruction::RDAX { reg, .. }
+        | Instruction::RDFX { reg, .. }
+        | Instruction::RDFX2 { reg, .. }
+        | Instruction::MULX { reg }
+        | Instruction::LDAX { reg } => Some((*reg, Access::Read)),
+        Instruction::WRAX { reg, .. }
+        | Instruction::WRHX { reg, .. }
+        | Instruction::WRLX { reg, .. } => Some((*reg, Access::Write)),
+        _ => None,
+    }
+}
+
+/// Classify every register referenced by a program by its role
+///
+/// General purpose registers are classified by their first access in
+/// program order: a register that is read before being written holds
+/// state across samples, one that is written first is a temporary.
+pub fn register_roles(program: &Program) -> HashMap<Register, RegisterRole> {
+    let mut roles = HashMap::new();
+
+    for inst in program.instructions() {
+        let Some((reg, access)) = register_access(inst) else {
+            continue;
+        };
+
+        if roles.contains_key(&reg) {
+            continue;
+        }
+
+        let role = match reg {
+            Register::ADCL | Register::ADCR => RegisterRole::Input,
+            Register::DACL | Register::DACR => RegisterRole::Output,
+            Register::POT0 | Register::POT1 | Register::POT2 => RegisterRole::Pot,
+            Register::REG(_) | Register::ACC => match access {
+                Access::Read => RegisterRole::State,
+                Access::Write => RegisterRole::Temp,
+            },
+            _ => RegisterRole::Control,
+        };
+        roles.insert(reg, role);
+    }
+
+    roles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn roles_for(source: &str) -> HashMap<Register, RegisterRole> {
+        let program = Parser::new(source).parse().unwrap();
+        register_roles(&program)
+    }
+
+    #[test]
+    fn test_io_roles() {
+        let roles = roles_for("rdax adcl, 1.0\nwrax dacr, 0.0");
+        assert_eq!(roles[&Register::ADCL], RegisterRole::Input);
+        assert_eq!(roles[&Register::DACR], RegisterRole::Output);
+    }
+
+    #[test]
+    fn test_state_and_temp_roles() {
+        let roles = roles_for(
+            r#"
+            rdax adcl, 1.0
+            wrax reg0, 0.0
+            rdax reg1, 0.5
+            rdax reg0, 1.0
+            wrax reg1, 0.0
+            "#,
+        );
+        assert_eq!(roles[&Register::REG(0)], RegisterRole::Temp);
+        assert_eq!(roles[&Register::REG(1)], RegisterRole::State);
+    }
+
+    #[test]
+    fn test_pot_role() {
+        let roles = roles_for("rdax adcl, 1.0\nmulx pot0\nwrax dacl, 0.0");
+        assert_eq!(roles[&Register::POT0], RegisterRole::Pot);
+    }
+}
@@ -0,0 +1,389 @@
+//! Semantic Validation
+//!
+//! Flags programs that assemble but are unlikely to do what was intended:
+//! LFOs used without being set up, registers read but never written
+//! (including REG16-REG18 still standing in for pots the old way), skips
+//! past the end of the program, writes to input registers and accumulator
+//! values that are computed and then thrown away.
+//!
+//! Instructions are checked in program order without following skips,
+//! except that a conditional SKP counts as using the accumulator.
+
+use crate::{
+    analysis::{
+        paths::skip_destination,
+        roles::{register_access, Access},
+    },
+    ast::Program,
+    instruction::{Instruction, SkipCondition},
+    register::{Lfo, Register},
+};
+use miette::{Diagnostic, SourceSpan};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// A likely mistake found by [`validate`]
+#[derive(Error, Debug, Diagnostic, Clone, PartialEq)]
+pub enum Finding {
+    #[error("{lfo:?} is used but never set up with WLDS or WLDR")]
+    #[diagnostic(code(lint::uninitialized_lfo), severity(Warning))]
+    UninitializedLfo {
+        index: usize,
+        lfo: Lfo,
+        #[label("used here")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("REG{reg} is read but never written")]
+    #[diagnostic(code(lint::unwritten_register), severity(Warning))]
+    UnwrittenRegister {
+        index: usize,
+        reg: u8,
+        #[label("first read here")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("REG{reg} is read but never written, as when it stood for POT{pot}")]
+    #[diagnostic(
+        code(lint::pot_register),
+        severity(Warning),
+        help("pots are no longer REG16-REG18; read POT{pot} instead, or run `fv1 migrate --assume-pots`")
+    )]
+    PotRegister {
+        index: usize,
+        reg: u8,
+        pot: u8,
+        #[label("first read here")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("skip to instruction {target} is past the end of the program ({len} instructions)")]
+    #[diagnostic(code(lint::skip_past_end), severity(Warning))]
+    SkipPastEnd {
+        index: usize,
+        target: usize,
+        len: usize,
+        #[label("skips past the end")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("write to input register {reg:?}")]
+    #[diagnostic(code(lint::input_write), severity(Warning))]
+    InputWrite {
+        index: usize,
+        reg: Register,
+        #[label("written here")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("accumulator value is never written")]
+    #[diagnostic(code(lint::unterminated_chain), severity(Warning))]
+    UnterminatedChain {
+        index: usize,
+        #[label("computed from here")]
+        span: Option<SourceSpan>,
+    },
+}
+
+impl Finding {
+    /// Index of the instruction the finding is about
+    pub fn index(&self) -> usize {
+        match self {
+            Finding::UninitializedLfo { index, .. }
+            | Finding::UnwrittenRegister { index, .. }
+            | Finding::PotRegister { index, .. }
+            | Finding::SkipPastEnd { index, .. }
+            | Finding::InputWrite { index, .. }
+            | Finding::UnterminatedChain { index, .. } => *index,
+        }
+    }
+}
+
+/// How an instruction affects the value in the accumulator
+enum AccEffect {
+    /// Computes a new value from the accumulator or loads into it
+    Compute,
+    /// Stores or tests the accumulator
+    Use,
+    /// Replaces the accumulator without using it
+    Discard,
+    /// Leaves the accumulator alone
+    None,
+}
+
+/// Classify how an instruction affects the accumulator
+fn acc_effect(inst: &Instruction) -> AccEffect {
+    match inst {
+        Instruction::WRAX { .. }
+        | Instruction::WRHX { .. }
+        | Instruction::WRLX { .. }
+        | Instruction::WRA { .. }
+        | Instruction::WRAP { .. } => AccEffect::Use,
+        Instruction::SKP { condition, .. } if *condition != SkipCondition::RUN => AccEffect::Use,
+        // A raw word could be anything, so assume its value is used
+        Instruction::RAW(_) => AccEffect::Use,
+        Instruction::CLR | Instruction::LDAX { .. } => AccEffect::Discard,
+        Instruction::SKP { .. }
+        | Instruction::NOP
+        | Instruction::WLDS { .. }
+        | Instruction::JAM { .. } => AccEffect::None,
+        _ => AccEffect::Compute,
+    }
+}
+
+/// Register whose write sets up an LFO
+fn rate_register(lfo: Lfo) -> Register {
+    match lfo {
+        Lfo::SIN0 => Register::SIN0_RATE,
+        Lfo::SIN1 => Register::SIN1_RATE,
+        Lfo::RMP0 => Register::RMP0_RATE,
+        Lfo::RMP1 => Register::RMP1_RATE,
+    }
+}
+
+/// Check a program for common FV-1 mistakes, returning the findings in
+/// program order
+pub fn validate(program: &Program) -> Vec<Finding> {
+    let instructions = program.instructions();
+    let len = instructions.len();
+    let span = |index: usize| program.instruction_span(index).map(SourceSpan::from);
+
+    // LFOs and registers set up or written anywhere in the program
+    let mut initialized = HashSet::new();
+    let mut written = HashSet::new();
+    for inst in &instructions {
+        match inst {
+            Instruction::WLDS { lfo, .. } => {
+                initialized.insert(rate_register(*lfo));
+            }
+            _ => {
+                if let Some((reg, Access::Write)) = register_access(inst) {
+                    initialized.insert(reg);
+                    written.insert(reg);
+                }
+            }
+        }
+    }
+
+    let mut findings = Vec::new();
+    // LFOs and registers already reported, to flag each only once
+    let mut uninitialized = HashSet::new();
+    let mut unwritten = HashSet::new();
+    // First instruction of the accumulator value not yet stored or tested
+    let mut pending: Option<usize> = None;
+
+    for (index, inst) in instructions.iter().enumerate() {
+        match inst {
+            Instruction::CHO { lfo, .. }
+            | Instruction::CHO_SOF { lfo, .. }
+            | Instruction::JAM { lfo }
+                if !initialized.contains(&rate_register(*lfo)) && uninitialized.insert(*lfo) =>
+            {
+                findings.push(Finding::UninitializedLfo {
+                    index,
+                    lfo: *lfo,
+                    span: span(index),
+                });
+            }
+            _ => {}
+        }
+
+        match register_access(inst) {
+            Some((Register::REG(reg), Access::Read))
+                if !written.contains(&Register::REG(reg)) && unwritten.insert(reg) =>
+            {
+                // Pots were written REG16-REG18 before they had registers
+                if (16..=18).contains(&reg) {
+                    findings.push(Finding::PotRegister {
+                        index,
+                        reg,
+                        pot: reg - 16,
+                        span: span(index),
+                    });
+                } else {
+                    findings.push(Finding::UnwrittenRegister {
+                        index,
+                        reg,
+                        span: span(index),
+                    });
+                }
+            }
+            Some((
+                reg @ (Register::ADCL
+                | Register::ADCR
+                | Register::POT0
+                | Register::POT1
+                | Register::POT2),
+                Access::Write,
+            )) => findings.push(Finding::InputWrite {
+                index,
+                reg,
+                span: span(index),
+            }),
+            _ => {}
+        }
+
+        if let Some(target) = skip_destination(program, index, inst) {
+            if target > len {
+                findings.push(Finding::SkipPastEnd {
+                    index,
+                    target,
+                    len,
+                    span: span(index),
+                });
+            }
+        }
+
+        match acc_effect(inst) {
+            AccEffect::Compute => {
+                pending.get_or_insert(index);
+            }
+            AccEffect::Use => pending = None,
+            AccEffect::Discard => {
+                if let Some(start) = pending.take() {
+                    findings.push(Finding::UnterminatedChain {
+                        index: start,
+                        span: span(start),
+                    });
+                }
+            }
+            AccEffect::None => {}
+        }
+    }
+
+    if let Some(start) = pending {
+        findings.push(Finding::UnterminatedChain {
+            index: start,
+            span: span(start),
+        });
+    }
+
+    findings.sort_by_key(Finding::index);
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn findings(source: &str) -> Vec<Finding> {
+        validate(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn test_clean_program() {
+        let source = "\
+mem dly 1000
+skp run, start
+wlds sin0, 12, 100
+start: rdax adcl, 1.0
+wra dly, 0.0
+cho rda, sin0, reg|compc, dly
+cho rda, sin0, 0, dly^
+wrax reg0, 1.0
+rdax reg0, 0.5
+skp gez, out
+clr
+out: wrax dacl, 0.0
+";
+        assert_eq!(findings(source), vec![]);
+    }
+
+    #[test]
+    fn test_uninitialized_lfo() {
+        let findings =
+            findings("cho rda, sin1, reg, 100\ncho rda, sin1, 0, 101\nwrax dacl, 0.0\njam rmp0");
+        assert_eq!(findings.len(), 2);
+        assert!(matches!(
+            findings[0],
+            Finding::UninitializedLfo {
+                index: 0,
+                lfo: Lfo::SIN1,
+                ..
+            }
+        ));
+        assert_eq!(
+            findings[1].to_string(),
+            "RMP0 is used but never set up with WLDS or WLDR"
+        );
+    }
+
+    #[test]
+    fn test_register_findings() {
+        let source =
+            "rdax reg3, 1.0\nrdax reg3, 1.0\nwrax adcl, 0.0\nrdax pot0, 1.0\nwrax pot1, 0.0";
+        let findings = findings(source);
+        assert_eq!(findings.len(), 3);
+        assert!(matches!(
+            findings[0],
+            Finding::UnwrittenRegister {
+                index: 0,
+                reg: 3,
+                ..
+            }
+        ));
+        assert_eq!(findings[1].to_string(), "write to input register ADCL");
+        assert!(matches!(
+            findings[2],
+            Finding::InputWrite {
+                index: 4,
+                reg: Register::POT1,
+                ..
+            }
+        ));
+        assert_eq!(findings[2].to_string(), "write to input register POT1");
+    }
+
+    #[test]
+    fn test_pot_register() {
+        let findings = findings(
+            "rdax adcl, 1.0\nmulx reg16\nwrax reg17, 0.0\nrdax reg17, 1.0\nwrax dacl, 0.0",
+        );
+        assert!(matches!(
+            findings[..],
+            [Finding::PotRegister {
+                index: 1,
+                reg: 16,
+                pot: 0,
+                ..
+            }]
+        ));
+        assert_eq!(
+            findings[0].help().unwrap().to_string(),
+            "pots are no longer REG16-REG18; read POT0 instead, or run `fv1 migrate --assume-pots`"
+        );
+    }
+
+    #[test]
+    fn test_skip_past_end() {
+        let findings = findings("skp run, 3\nrdax adcl, 1.0\nwrax dacl, 0.0");
+        assert!(matches!(
+            findings[..],
+            [Finding::SkipPastEnd {
+                index: 0,
+                target: 4,
+                len: 3,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_unterminated_chain() {
+        let source =
+            "rdax adcl, 1.0\nwrax dacl, 0.0\nrdax pot0, 1.0\nsof 0.5, 0.0\nclr\nrdax adcr, 1.0";
+        let findings = findings(source);
+        assert_eq!(findings.len(), 2);
+        let Finding::UnterminatedChain { index, span } = findings[0] else {
+            panic!("expected unterminated chain, got {:?}", findings[0]);
+        };
+        assert_eq!(index, 2);
+        let span = span.unwrap();
+        assert_eq!(
+            &source[span.offset()..span.offset() + span.len()],
+            "rdax pot0, 1.0"
+        );
+        assert_eq!(findings[1].index(), 5);
+    }
+}
@@ -1,5 +1,5 @@
-use crate::instruction::Instruction;
-use std::collections::HashMap;
+use crate::{instruction::Instruction, register::Register};
+use std::{collections::HashMap, ops::Range};
 
 /// Complete FV-1 program
 #[derive(Debug, Clone)]
@@ -8,8 +8,21 @@ pub struct Program {
     pub directives: Vec<Directive>,
     /// Program statements (labels and instructions)
     pub statements: Vec<Statement>,
+    /// Source span of each statement, parallel to `statements` (empty for
+    /// statements that were not parsed from source)
+    pub spans: Vec<Range<usize>>,
+    /// Source span of each directive, parallel to `directives` (empty for
+    /// directives that were not parsed from source)
+    pub directive_spans: Vec<Range<usize>>,
     /// Label name to instruction index mapping
     pub labels: HashMap<String, usize>,
+    /// Instruction index to target label mapping for SKP instructions
+    /// whose offset is resolved at assembly time
+    pub skip_targets: HashMap<usize, String>,
+    /// Comments at the end of a statement's line, keyed by statement index
+    pub trailing_comments: HashMap<usize, String>,
+    /// Comments at the end of a directive's line, keyed by directive index
+    pub directive_comments: HashMap<usize, String>,
 }
 
 /// Assembly directive
@@ -39,10 +52,13 @@ pub enum Statement {
         label: String,
         instruction: Instruction,
     },
+
+    /// ; Comment - A comment on its own line, without the semicolon
+    Comment(String),
 }
 
 /// Value in an expression or directive
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     /// Floating-point literal
     Float(f32),
@@ -50,6 +66,8 @@ pub enum Value {
     Integer(i64),
     /// Reference to a symbolic constant (equate)
     Identifier(String),
+    /// Register alias
+    Register(Register),
 }
 
 impl Program {
@@ -58,7 +76,12 @@ impl Program {
         Self {
             directives: Vec::new(),
             statements: Vec::new(),
+            spans: Vec::new(),
+            directive_spans: Vec::new(),
             labels: HashMap::new(),
+            skip_targets: HashMap::new(),
+            trailing_comments: HashMap::new(),
+            directive_comments: HashMap::new(),
         }
     }
 
@@ -69,18 +92,89 @@ impl Program {
             .filter_map(|s| match s {
                 Statement::Instruction(i) => Some(i),
                 Statement::LabeledInstruction { instruction, .. } => Some(instruction),
-                Statement::Label(_) => None,
+                Statement::Label(_) | Statement::Comment(_) => None,
             })
             .collect()
     }
 
+    /// Get mutable access to the instruction at `index`
+    pub fn instruction_mut(&mut self, index: usize) -> Option<&mut Instruction> {
+        self.statements
+            .iter_mut()
+            .filter_map(|s| match s {
+                Statement::Instruction(i) => Some(i),
+                Statement::LabeledInstruction { instruction, .. } => Some(instruction),
+                Statement::Label(_) | Statement::Comment(_) => None,
+            })
+            .nth(index)
+    }
+
     /// Resolve a label to its instruction index
     pub fn resolve_label(&self, label: &str) -> Option<usize> {
         self.labels.get(label).copied()
     }
 
+    /// Resolve the target label of the SKP instruction at `index`, if any
+    pub fn skip_target(&self, index: usize) -> Option<&str> {
+        self.skip_targets.get(&index).map(String::as_str)
+    }
+
+    /// Check the program for common FV-1 mistakes, see
+    /// [`crate::analysis::validate`]
+    pub fn validate(&self) -> Vec<crate::analysis::Finding> {
+        crate::analysis::validate(self)
+    }
+
+    /// Describe the program as JSON, see [`crate::codegen::json`]
+    pub fn to_json(&self) -> String {
+        crate::codegen::json::program_json(self, None)
+    }
+
+    /// Get the source span of the instruction at `index`, if known
+    pub fn instruction_span(&self, index: usize) -> Option<Range<usize>> {
+        self.statements
+            .iter()
+            .zip(&self.spans)
+            .filter(|(s, _)| !matches!(s, Statement::Label(_) | Statement::Comment(_)))
+            .nth(index)
+            .map(|(_, span)| span.clone())
+            .filter(|span| !span.is_empty())
+    }
+
+    /// Get the source span of the statement at `index`, if known
+    pub fn statement_span(&self, index: usize) -> Option<Range<usize>> {
+        self.spans
+            .get(index)
+            .cloned()
+            .filter(|span| !span.is_empty())
+    }
+
+    /// Get the source span of the directive at `index`, if known
+    pub fn directive_span(&self, index: usize) -> Option<Range<usize>> {
+        self.directive_spans
+            .get(index)
+            .cloned()
+            .filter(|span| !span.is_empty())
+    }
+
+    /// Add a directive
+    pub fn add_directive(&mut self, directive: Directive) {
+        self.add_directive_with_span(directive, 0..0);
+    }
+
+    /// Add a directive parsed from `span` of the source
+    pub fn add_directive_with_span(&mut self, directive: Directive, span: Range<usize>) {
+        self.directives.push(directive);
+        self.directive_spans.push(span);
+    }
+
     /// Add a statement and update label mappings if needed
     pub fn add_statement(&mut self, statement: Statement) {
+        self.add_statement_with_span(statement, 0..0);
+    }
+
+    /// Add a statement parsed from `span` of the source
+    pub fn add_statement_with_span(&mut self, statement: Statement, span: Range<usize>) {
         match &statement {
             Statement::Label(name) => {
                 // Label points to the next instruction
@@ -90,9 +184,10 @@ impl Program {
                 // Label points to this instruction
                 self.labels.insert(label.clone(), self.instruction_count());
             }
-            Statement::Instruction(_) => {}
+            Statement::Instruction(_) | Statement::Comment(_) => {}
         }
         self.statements.push(statement);
+        self.spans.push(span);
     }
 
     /// Get the current instruction count (for label resolution)
@@ -197,4 +292,16 @@ mod tests {
             _ => panic!("Wrong value type"),
         }
     }
+
+    #[test]
+    fn test_instruction_span() {
+        let mut program = Program::new();
+        program.add_statement(Statement::Label("start".to_string()));
+        program.add_statement_with_span(Statement::Instruction(Instruction::CLR), 7..10);
+        program.add_statement(Statement::Instruction(Instruction::CLR));
+
+        assert_eq!(program.instruction_span(0), Some(7..10));
+        assert_eq!(program.instruction_span(1), None);
+        assert_eq!(program.instruction_span(2), None);
+    }
 }
@@ -3,19 +3,103 @@
 //! Assembles parsed programs into FV-1 binary format
 
 use crate::{
-    ast::Program, codegen::encoder::encode_instruction, constants::MAX_INSTRUCTIONS,
-    error::CodegenError,
+    analysis::{
+        path_report,
+        roles::{register_access, Access},
+    },
+    ast::{Program, Statement},
+    codegen::{
+        decoder::decode_instruction,
+        diff::{diff_words, WordDiff},
+        encoder::{encode_instruction_with, quantize, RoundingMode},
+        hex::{intel_hex, intel_hex_with, parse_intel_hex, HexOptions},
+        json::program_json,
+        optimizer::optimize,
+        srec::motorola_srec,
+        symbols::SymbolTable,
+        uf2::uf2,
+        verify::{verify, VerifyReport},
+    },
+    constants::MAX_INSTRUCTIONS,
+    error::{CodegenError, SourceError, Warning},
+    instruction::Instruction,
+    isa::{self, Operand, OperandKind},
+    register::Register,
 };
+use sha2::{Digest, Sha256};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    io::{Read, Write},
+};
+
+/// Programs with at least this many instructions are warned to be close to
+/// the program memory limit
+const NEARLY_FULL: usize = MAX_INSTRUCTIONS - 8;
+
+/// Word used to fill the program memory after the last instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaddingKind {
+    /// All-zero words. These are `RDA 0, 0`, which adds nothing to ACC,
+    /// not the NOP encoding `SKP 0, 0`
+    #[default]
+    Zero,
+    /// `SKP 0, 0`, as SpinASM pads
+    Skip,
+    /// No padding, leaving the binary as long as the program
+    None,
+}
+
+impl PaddingKind {
+    /// Word to pad with, if any
+    pub fn word(&self) -> Option<u32> {
+        match self {
+            PaddingKind::Zero => Some(0x00000000),
+            PaddingKind::Skip => Some(0x00000011),
+            PaddingKind::None => None,
+        }
+    }
+}
+
+/// How much of the program memory a program uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetReport {
+    /// Number of instructions in the program
+    pub instructions: usize,
+    /// Instruction slots left before the limit (zero when over it)
+    pub remaining: usize,
+    /// Instructions executed per sample on the shortest path through the
+    /// program's skips
+    pub min_cycles: usize,
+    /// Instructions executed per sample on the longest path through the
+    /// program's skips
+    pub max_cycles: usize,
+}
+
+impl BudgetReport {
+    /// Check whether the program fits in program memory
+    pub fn fits(&self) -> bool {
+        self.instructions <= MAX_INSTRUCTIONS
+    }
+}
 
 /// FV-1 program assembler
 pub struct Assembler {
     optimize: bool,
+    padding: PaddingKind,
+    rounding: RoundingMode,
+    quantization_warnings: bool,
 }
 
 impl Assembler {
     /// Create a new assembler
     pub fn new() -> Self {
-        Self { optimize: false }
+        Self {
+            optimize: false,
+            padding: PaddingKind::default(),
+            rounding: RoundingMode::default(),
+            quantization_warnings: false,
+        }
     }
 
     /// Enable or disable optimization
@@ -24,49 +108,322 @@ impl Assembler {
         self
     }
 
-    /// Assemble a program into FV-1 binary
+    /// Set the word that fills the program memory after the last instruction
+    pub fn with_padding(mut self, padding: PaddingKind) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Set how coefficients are rounded to fixed point, for reproducing
+    /// binaries from assemblers that do not round to nearest
+    pub fn with_rounding(mut self, rounding: RoundingMode) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Enable or disable warnings for coefficients that cannot be encoded
+    /// exactly
+    pub fn with_quantization_warnings(mut self, enable: bool) -> Self {
+        self.quantization_warnings = enable;
+        self
+    }
+
+    /// Apply optimizations if enabled
+    fn optimized<'a>(&self, program: &'a Program) -> Cow<'a, Program> {
+        if self.optimize {
+            Cow::Owned(optimize(program).0)
+        } else {
+            Cow::Borrowed(program)
+        }
+    }
+
+    /// Report the program memory and execution budget a program uses,
+    /// after optimization if it is enabled
+    pub fn analyze(&self, program: &Program) -> BudgetReport {
+        let program = self.optimized(program);
+        let program = program.as_ref();
+
+        let instructions = program.instructions().len();
+        let paths = path_report(program);
+        BudgetReport {
+            instructions,
+            remaining: MAX_INSTRUCTIONS.saturating_sub(instructions),
+            min_cycles: paths.shortest,
+            max_cycles: paths.longest,
+        }
+    }
+
+    /// Assemble a program parsed from `source`, naming the source `name`
+    /// in any error so that the diagnostic renders with the source
+    /// highlighted. Every error is reported, grouped under
+    /// [`CodegenError::Multiple`] when there is more than one.
+    pub fn assemble_named(
+        &self,
+        program: &Program,
+        name: &str,
+        source: &str,
+    ) -> Result<Binary, SourceError<CodegenError>> {
+        self.assemble_named_with_warnings(program, name, source)
+            .map(|(binary, _)| binary)
+    }
+
+    /// Assemble a program parsed from `source` like
+    /// [`Assembler::assemble_named`], also returning warnings about
+    /// problems that do not stop it from assembling
+    pub fn assemble_named_with_warnings(
+        &self,
+        program: &Program,
+        name: &str,
+        source: &str,
+    ) -> Result<(Binary, Vec<Warning>), SourceError<CodegenError>> {
+        let (program, binary) = self.build_all_errors(program).map_err(|mut errors| {
+            let error = if errors.len() == 1 {
+                errors.remove(0)
+            } else {
+                CodegenError::Multiple { errors }
+            };
+            SourceError::new(error, name, source)
+        })?;
+        Ok((binary, self.warnings(&program)))
+    }
+
+    /// Assemble a program into FV-1 binary, reporting the first error
     pub fn assemble(&self, program: &Program) -> Result<Binary, CodegenError> {
-        let instructions = program.instructions();
+        self.assemble_all_errors(program)
+            .map_err(|errors| errors.into_iter().next().expect("errors are never empty"))
+    }
+
+    /// Assemble a program into FV-1 binary, encoding every instruction it
+    /// can and returning all the errors found, in program order
+    pub fn assemble_all_errors(&self, program: &Program) -> Result<Binary, Vec<CodegenError>> {
+        self.build_all_errors(program).map(|(_, binary)| binary)
+    }
+
+    /// Assemble a program, also returning the symbols it defines. Label
+    /// values are instruction indices in the assembled binary, after
+    /// optimization if it is enabled
+    pub fn assemble_with_symbols(
+        &self,
+        program: &Program,
+    ) -> Result<(Binary, SymbolTable), CodegenError> {
+        let (program, binary) = self.build(program)?;
+        Ok((binary, SymbolTable::from_program(&program)))
+    }
+
+    /// Assemble a program, also returning warnings about problems that do
+    /// not stop it from assembling
+    pub fn assemble_with_warnings(
+        &self,
+        program: &Program,
+    ) -> Result<(Binary, Vec<Warning>), CodegenError> {
+        let (program, binary) = self.build(program)?;
+        Ok((binary, self.warnings(&program)))
+    }
+
+    /// Lower and encode a program, keeping the lowered program
+    fn build(&self, program: &Program) -> Result<(Program, Binary), CodegenError> {
+        let mut errors = Vec::new();
+        let program = self.lower_collecting(program, &mut errors);
+        let binary = self.encode_collecting(&program, &mut errors);
+        match errors.into_iter().min_by_key(|(index, _)| *index) {
+            Some((_, error)) => Err(error),
+            None => Ok((program, binary)),
+        }
+    }
+
+    /// Lower and encode a program, keeping the lowered program, or return
+    /// every error in program order
+    fn build_all_errors(&self, program: &Program) -> Result<(Program, Binary), Vec<CodegenError>> {
+        let mut errors = Vec::new();
+        let program = self.lower_collecting(program, &mut errors);
+        let binary = self.encode_collecting(&program, &mut errors);
+        if errors.is_empty() {
+            return Ok((program, binary));
+        }
+        errors.sort_by_key(|(index, _)| *index);
+        Err(errors.into_iter().map(|(_, error)| error).collect())
+    }
 
-        // Check program size
-        if instructions.len() > MAX_INSTRUCTIONS {
-            return Err(CodegenError::ProgramTooLarge {
-                size: instructions.len(),
+    /// Find the warnings for a lowered program, in program order
+    fn warnings(&self, program: &Program) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        // First write of each general purpose register, and whether it is read
+        let mut registers: BTreeMap<u8, (Option<usize>, bool)> = BTreeMap::new();
+
+        for (index, inst) in program.instructions().into_iter().enumerate() {
+            if let Some((Register::REG(n), access)) = register_access(inst) {
+                let (written, read) = registers.entry(n).or_default();
+                match access {
+                    Access::Read => *read = true,
+                    Access::Write => {
+                        written.get_or_insert(index);
+                    }
+                }
+            }
+
+            if !self.quantization_warnings {
+                continue;
+            }
+            let Some((spec, operands)) = isa::describe(inst) else {
+                continue;
+            };
+            for (field, operand) in spec.operands.iter().zip(&operands) {
+                let (OperandKind::Coefficient(format), Operand::Coefficient(value)) =
+                    (field.kind, *operand)
+                else {
+                    continue;
+                };
+                match quantize(value, format, self.rounding) {
+                    Some(encoded) if encoded != value => warnings.push(Warning::Quantized {
+                        value,
+                        encoded,
+                        error_db: 20.0 * (encoded / value).abs().log10(),
+                        span: program.instruction_span(index).map(Into::into),
+                    }),
+                    _ => {}
+                }
+            }
+        }
+
+        for (reg, (written, read)) in registers {
+            if let (Some(index), false) = (written, read) {
+                warnings.push(Warning::UnreadRegister {
+                    reg,
+                    span: program.instruction_span(index).map(Into::into),
+                });
+            }
+        }
+
+        let used = program.instructions().len();
+        if (NEARLY_FULL..=MAX_INSTRUCTIONS).contains(&used) {
+            warnings.push(Warning::NearlyFull {
+                used,
                 max: MAX_INSTRUCTIONS,
             });
         }
 
-        let mut binary = Binary::new();
+        warnings
+    }
 
-        // Encode each instruction
-        for inst in instructions {
-            let encoded = encode_instruction(inst)?;
-            binary.push(encoded);
+    /// Lower a program to the instruction stream the assembler encodes.
+    ///
+    /// `EQU` and `MEM` operands are resolved by the parser. Lowering
+    /// optimizes the program if enabled and resolves skips to labels into
+    /// offsets, so every instruction of the result encodes as it stands.
+    /// Labels, comments and spans are kept for inspection.
+    pub fn lower(&self, program: &Program) -> Result<Program, CodegenError> {
+        let mut errors = Vec::new();
+        let program = self.lower_collecting(program, &mut errors);
+        match errors.into_iter().min_by_key(|(index, _)| *index) {
+            Some((_, error)) => Err(error),
+            None => Ok(program),
         }
+    }
 
-        // Pad to 128 instructions with NOPs
-        while binary.len() < MAX_INSTRUCTIONS {
-            binary.push(0x00000000); // NOP
+    /// Lower a program, adding any errors to `errors` by instruction index
+    /// and leaving the skips that cannot be resolved as they are
+    fn lower_collecting(
+        &self,
+        program: &Program,
+        errors: &mut Vec<(usize, CodegenError)>,
+    ) -> Program {
+        let mut program = self.optimized(program).into_owned();
+
+        // Point at the first instruction that does not fit
+        let instructions = program.instructions().len();
+        if instructions > MAX_INSTRUCTIONS {
+            let error = CodegenError::ProgramTooLarge {
+                size: instructions,
+                max: MAX_INSTRUCTIONS,
+            };
+            errors.push((
+                MAX_INSTRUCTIONS,
+                in_instruction(&program, MAX_INSTRUCTIONS, error),
+            ));
         }
 
-        // Apply optimizations if enabled
-        if self.optimize {
-            binary = self.optimize_binary(binary)?;
+        let mut skip_targets: Vec<(usize, String)> = program.skip_targets.drain().collect();
+        skip_targets.sort();
+        for (index, label) in skip_targets {
+            if !matches!(
+                program.instruction_mut(index),
+                Some(Instruction::SKP { .. })
+            ) {
+                continue;
+            }
+            match resolve_skip_offset(&program, index, &label) {
+                Ok(offset) => {
+                    if let Some(Instruction::SKP { offset: skip, .. }) =
+                        program.instruction_mut(index)
+                    {
+                        *skip = offset;
+                    }
+                }
+                Err(error) => errors.push((index, in_instruction(&program, index, error))),
+            }
+        }
+
+        program
+    }
+
+    /// Encode a lowered program, adding any errors to `errors` by
+    /// instruction index and leaving a NOP in place of each instruction
+    /// that fails to encode
+    fn encode_collecting(
+        &self,
+        program: &Program,
+        errors: &mut Vec<(usize, CodegenError)>,
+    ) -> Binary {
+        let mut binary = Binary::new();
+        for (index, inst) in program.instructions().into_iter().enumerate() {
+            let encoded = encode_instruction_with(inst, self.rounding).unwrap_or_else(|error| {
+                errors.push((index, in_instruction(program, index, error)));
+                0
+            });
+            binary.push(encoded);
+        }
+
+        // Pad to 128 instructions
+        if let Some(word) = self.padding.word() {
+            while binary.len() < MAX_INSTRUCTIONS {
+                binary.push(word);
+            }
         }
 
-        Ok(binary)
+        binary
     }
+}
 
-    /// Apply peephole optimizations to the binary
-    fn optimize_binary(&self, binary: Binary) -> Result<Binary, CodegenError> {
-        // TODO: Implement optimizations:
-        // - Remove redundant CLR instructions
-        // - Combine consecutive SOF operations
-        // - Dead code elimination after unconditional skips
-        Ok(binary)
+/// Attach the source span of the instruction at `index` to an error
+fn in_instruction(program: &Program, index: usize, error: CodegenError) -> CodegenError {
+    match program.instruction_span(index) {
+        Some(span) => CodegenError::InInstruction {
+            error: Box::new(error),
+            span,
+        },
+        None => error,
     }
 }
 
+/// Compute the offset of a SKP at `index` to the instruction at `label`
+fn resolve_skip_offset(program: &Program, index: usize, label: &str) -> Result<i8, CodegenError> {
+    let target = program
+        .resolve_label(label)
+        .ok_or_else(|| CodegenError::UndefinedLabel {
+            name: label.to_string(),
+        })?;
+
+    // Offsets count the instructions skipped after the SKP itself
+    let offset = target as isize - index as isize - 1;
+    if !(0..=63).contains(&offset) {
+        return Err(CodegenError::SkipOutOfRange {
+            label: label.to_string(),
+            offset,
+        });
+    }
+    Ok(offset as i8)
+}
+
 impl Default for Assembler {
     fn default() -> Self {
         Self::new()
@@ -107,6 +464,48 @@ impl Binary {
         &self.instructions
     }
 
+    /// Get the instructions without trailing NOP or `SKP 0, 0` padding
+    pub fn without_padding(&self) -> &[u32] {
+        let len = self
+            .instructions
+            .iter()
+            .rposition(|&word| word != 0x00000000 && word != 0x00000011)
+            .map_or(0, |last| last + 1);
+        &self.instructions[..len]
+    }
+
+    /// CRC-32 (IEEE) of the exported bytes, as most EEPROM programmers
+    /// report it
+    pub fn crc32(&self) -> u32 {
+        crc32fast::hash(&self.to_bytes())
+    }
+
+    /// SHA-256 digest of the exported bytes
+    pub fn sha256(&self) -> [u8; 32] {
+        Sha256::digest(self.to_bytes()).into()
+    }
+
+    /// Short fingerprint for comparing binaries by eye: the first four
+    /// bytes of the SHA-256 digest, as `xxxx-xxxx`
+    pub fn fingerprint(&self) -> String {
+        let digest = self.sha256();
+        format!(
+            "{:02x}{:02x}-{:02x}{:02x}",
+            digest[0], digest[1], digest[2], digest[3]
+        )
+    }
+
+    /// Decode every word and check that the binary looks like a working
+    /// FV-1 program, e.g. before flashing an unknown dump
+    pub fn verify(&self) -> VerifyReport {
+        verify(self)
+    }
+
+    /// Compare with another binary word by word, ignoring trailing padding
+    pub fn diff(&self, other: &Binary) -> Vec<WordDiff> {
+        diff_words(self, other)
+    }
+
     /// Create a Binary from raw bytes (512 bytes, big-endian)
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, CodegenError> {
         if bytes.len() != 512 {
@@ -125,7 +524,36 @@ impl Binary {
         Ok(Self { instructions })
     }
 
-    /// Export as raw binary bytes (512 bytes, big-endian)
+    /// Read a 512-byte binary from a reader, leaving anything after it
+    /// unread
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, CodegenError> {
+        let mut bytes = Vec::with_capacity(MAX_INSTRUCTIONS * 4);
+        reader
+            .take((MAX_INSTRUCTIONS * 4) as u64)
+            .read_to_end(&mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Read a binary from Intel HEX, such as a dump of one program from an
+    /// EEPROM programmer. Programs shorter than 128 instructions are
+    /// accepted as they are.
+    pub fn from_hex(text: &str) -> Result<Self, CodegenError> {
+        let bytes = parse_intel_hex(text, MAX_INSTRUCTIONS * 4)?;
+        if bytes.len() > MAX_INSTRUCTIONS * 4 || bytes.len() % 4 != 0 {
+            return Err(CodegenError::InvalidBinarySize {
+                size: bytes.len(),
+                expected: MAX_INSTRUCTIONS * 4,
+            });
+        }
+
+        let instructions = bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        Ok(Self { instructions })
+    }
+
+    /// Export as raw binary bytes (big-endian, 512 bytes unless unpadded)
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(512);
         for &inst in &self.instructions {
@@ -134,6 +562,14 @@ impl Binary {
         bytes
     }
 
+    /// Write the raw binary bytes, as [`Binary::to_bytes`], to a writer
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for &inst in &self.instructions {
+            writer.write_all(&inst.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
     /// Export as Intel HEX format
     ///
     /// Intel HEX format is commonly used for programming microcontrollers
@@ -145,32 +581,39 @@ impl Binary {
     /// - Data bytes (2 hex digits each)
     /// - Checksum (2 hex digits)
     pub fn to_hex(&self) -> String {
-        let mut hex = String::new();
-        let bytes = self.to_bytes();
+        intel_hex(&self.to_bytes())
+    }
 
-        // Generate data records (16 bytes per line)
-        for (i, chunk) in bytes.chunks(16).enumerate() {
-            let addr = i * 16;
-            let len = chunk.len();
+    /// Export as Intel HEX with a different record length, base address
+    /// or letter case
+    pub fn to_hex_with(&self, options: &HexOptions) -> String {
+        intel_hex_with(&self.to_bytes(), options)
+    }
 
-            // Record header: :LLAAAATT
-            hex.push_str(&format!(":{:02X}{:04X}00", len, addr));
+    /// Export as Motorola S-records (S19), for programmers that do not
+    /// accept Intel HEX
+    pub fn to_srec(&self) -> String {
+        motorola_srec(&self.to_bytes())
+    }
 
-            // Data bytes and calculate checksum
-            let mut checksum = len + (addr >> 8) + (addr & 0xFF);
-            for &byte in chunk {
-                hex.push_str(&format!("{:02X}", byte));
-                checksum += byte as usize;
-            }
+    /// Export as UF2 blocks for boards with a USB drag-and-drop bootloader.
+    /// `family_id` identifies the board's microcontroller family and
+    /// `base_addr` is where the bootloader stores the program.
+    pub fn to_uf2(&self, family_id: u32, base_addr: u32) -> Vec<u8> {
+        uf2(&self.to_bytes(), family_id, base_addr)
+    }
 
-            // Two's complement checksum
-            checksum = (256 - (checksum & 0xFF)) & 0xFF;
-            hex.push_str(&format!("{:02X}\n", checksum));
+    /// Describe the decoded program as JSON, including each instruction
+    /// word. Trailing padding is left out and words that do not decode have
+    /// a null mnemonic.
+    pub fn to_json(&self) -> String {
+        let words = self.without_padding();
+        let mut program = Program::new();
+        for &word in words {
+            let inst = decode_instruction(word).unwrap_or(Instruction::RAW(word));
+            program.add_statement(Statement::Instruction(inst));
         }
-
-        // End of file record
-        hex.push_str(":00000001FF\n");
-        hex
+        program_json(&program, Some(words))
     }
 
     /// Export as C array for embedding in firmware
@@ -205,6 +648,139 @@ impl Binary {
         c_code.push_str("};\n");
         c_code
     }
+
+    /// Export a C header declaring the array written by
+    /// [`Binary::to_c_array`], with macros describing the program for
+    /// firmware UIs: its name, the labels of POT0-POT2 (empty when not
+    /// given), the number of instructions before padding and the CRC-32
+    pub fn to_c_header(&self, name: &str, pot_labels: &[&str]) -> String {
+        let prefix = name.to_uppercase();
+        let string =
+            |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+
+        let mut header = format!("// FV-1 program: {} ({} instructions)\n", name, self.len());
+        header.push_str(&format!("#ifndef {}_H\n#define {}_H\n\n", prefix, prefix));
+        header.push_str("#include <stdint.h>\n\n");
+        header.push_str(&format!("#define {}_NAME {}\n", prefix, string(name)));
+        for pot in 0..3 {
+            let label = pot_labels.get(pot).copied().unwrap_or("");
+            header.push_str(&format!(
+                "#define {}_POT{}_LABEL {}\n",
+                prefix,
+                pot,
+                string(label)
+            ));
+        }
+        header.push_str(&format!(
+            "#define {}_INSTRUCTIONS {}\n",
+            prefix,
+            self.without_padding().len()
+        ));
+        header.push_str(&format!(
+            "#define {}_CRC32 0x{:08X}u\n\n",
+            prefix,
+            self.crc32()
+        ));
+        header.push_str(&format!(
+            "extern const uint32_t {}[{}];\n\n",
+            name,
+            self.len()
+        ));
+        header.push_str(&format!("#endif // {}_H\n", prefix));
+        header
+    }
+
+    /// Export as a Rust constant for embedding in embedded Rust firmware.
+    /// The constant is named `name` in upper case.
+    pub fn to_rust_array(&self, name: &str) -> String {
+        let name = name.to_uppercase();
+        let mut rust_code = format!("// FV-1 program: {} ({} instructions)\n", name, self.len());
+        rust_code.push_str(&format!("pub const {}: [u32; {}] = [\n", name, self.len()));
+
+        for row in self.instructions.chunks(4) {
+            let words: Vec<String> = row.iter().map(|word| format!("0x{:08X},", word)).collect();
+            rust_code.push_str(&format!("    {}\n", words.join(" ")));
+        }
+
+        rust_code.push_str("];\n");
+        rust_code
+    }
+
+    /// Export as an Arduino sketch that writes the program into a 24LC32A
+    /// EEPROM over I2C and reads it back to verify it. The EEPROM address
+    /// and program slot are `#define`s at the top of the sketch.
+    pub fn to_arduino_sketch(&self, name: &str) -> String {
+        let bytes = self.to_bytes();
+        let data: String = bytes
+            .chunks(16)
+            .map(|row| {
+                let row: Vec<String> = row.iter().map(|byte| format!("0x{:02X},", byte)).collect();
+                format!("  {}\n", row.join(" "))
+            })
+            .collect();
+
+        format!(
+            r#"// FV-1 program: {name} ({len} instructions)
+// Writes the program into a 24LC32A EEPROM over I2C, then verifies it.
+#include <Wire.h>
+
+#define EEPROM_ADDRESS 0x50  // I2C address of the EEPROM (0x50-0x57)
+#define PROGRAM_SLOT 0       // Program slot to write (0-7)
+
+const uint8_t {name}[{size}] PROGMEM = {{
+{data}}};
+
+// Bytes per I2C transfer. Fits the Wire buffer and divides the
+// 32-byte EEPROM page, so no write crosses a page boundary.
+#define CHUNK 16
+
+void setup() {{
+  Serial.begin(9600);
+  Wire.begin();
+
+  const uint16_t base = PROGRAM_SLOT * 512;
+  for (uint16_t i = 0; i < sizeof({name}); i += CHUNK) {{
+    Wire.beginTransmission(EEPROM_ADDRESS);
+    Wire.write((uint8_t)((base + i) >> 8));
+    Wire.write((uint8_t)((base + i) & 0xFF));
+    for (uint16_t j = i; j < i + CHUNK && j < sizeof({name}); j++) {{
+      Wire.write(pgm_read_byte(&{name}[j]));
+    }}
+    Wire.endTransmission();
+    delay(5);  // Write cycle time
+  }}
+
+  uint16_t errors = 0;
+  for (uint16_t i = 0; i < sizeof({name}); i += CHUNK) {{
+    Wire.beginTransmission(EEPROM_ADDRESS);
+    Wire.write((uint8_t)((base + i) >> 8));
+    Wire.write((uint8_t)((base + i) & 0xFF));
+    Wire.endTransmission();
+    Wire.requestFrom(EEPROM_ADDRESS, CHUNK);
+    for (uint16_t j = i; j < i + CHUNK && j < sizeof({name}); j++) {{
+      if (Wire.read() != pgm_read_byte(&{name}[j])) {{
+        errors++;
+      }}
+    }}
+  }}
+
+  if (errors == 0) {{
+    Serial.println("FV-1 program written and verified");
+  }} else {{
+    Serial.print("Verify failed: ");
+    Serial.print(errors);
+    Serial.println(" bytes differ");
+  }}
+}}
+
+void loop() {{}}
+"#,
+            name = name,
+            len = self.len(),
+            size = bytes.len(),
+            data = data,
+        )
+    }
 }
 
 impl Default for Binary {
@@ -216,7 +792,7 @@ impl Default for Binary {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{ast::Statement, instruction::Instruction, register::Register};
+    use crate::{ast::Statement, instruction::SkipCondition, parser::Parser};
 
     #[test]
     fn test_assembler_creation() {
@@ -230,6 +806,104 @@ mod tests {
         assert!(assembler.optimize);
     }
 
+    #[test]
+    fn test_analyze_budget() {
+        let program = Parser::new("skp run, done\nclr\nnop\ndone: rdax adcl, 1.0\nwrax dacl, 0.0")
+            .parse()
+            .unwrap();
+
+        let report = Assembler::new().analyze(&program);
+        assert_eq!(report.instructions, 5);
+        assert_eq!(report.remaining, 123);
+        assert_eq!(report.min_cycles, 3);
+        assert_eq!(report.max_cycles, 5);
+        assert!(report.fits());
+
+        // The optimizer drops the NOP
+        let report = Assembler::new().with_optimization(true).analyze(&program);
+        assert_eq!(report.instructions, 4);
+    }
+
+    #[test]
+    fn test_assemble_with_warnings() {
+        let source = "rdax adcl, 0.33333\nwrax reg5, 0.0\nwrax reg6, 0.5\nrdax reg6, 1.0";
+        let program = Parser::new(source).parse().unwrap();
+
+        let (binary, warnings) = Assembler::new().assemble_with_warnings(&program).unwrap();
+        assert_eq!(binary.len(), 128);
+        assert_eq!(
+            warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+            vec!["register REG5 written but never read"]
+        );
+
+        // Quantization warnings are opt-in
+        let (_, warnings) = Assembler::new()
+            .with_quantization_warnings(true)
+            .assemble_with_warnings(&program)
+            .unwrap();
+        assert_eq!(warnings.len(), 2);
+        let Warning::Quantized {
+            value,
+            encoded,
+            error_db,
+            span,
+        } = &warnings[0]
+        else {
+            panic!("expected quantization warning, got {:?}", warnings[0]);
+        };
+        assert_eq!(*value, 0.33333);
+        assert_eq!(*encoded, 5461.0 / 16384.0);
+        assert!((error_db + 0.00045).abs() < 0.00001, "{}", error_db);
+        assert_eq!(span.unwrap().offset(), 0);
+        assert_eq!(
+            warnings[0].to_string(),
+            "coefficient quantized from 0.33333 to 0.333313 (-0.0004 dB)"
+        );
+
+        // The named variant finds the same warnings
+        let (_, named) = Assembler::new()
+            .assemble_named_with_warnings(&program, "test.asm", source)
+            .unwrap();
+        assert_eq!(
+            named.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+            vec!["register REG5 written but never read"]
+        );
+
+        let program = Parser::new(&"clr\n".repeat(127)).parse().unwrap();
+        let (_, warnings) = Assembler::new().assemble_with_warnings(&program).unwrap();
+        assert_eq!(
+            warnings,
+            vec![Warning::NearlyFull {
+                used: 127,
+                max: 128
+            }]
+        );
+    }
+
+    #[test]
+    fn test_padding() {
+        let program = Parser::new("clr").parse().unwrap();
+        let assemble = |padding| {
+            Assembler::new()
+                .with_padding(padding)
+                .assemble(&program)
+                .unwrap()
+        };
+
+        let binary = assemble(PaddingKind::Zero);
+        assert_eq!(binary.len(), 128);
+        assert_eq!(binary.instructions()[127], 0x00000000);
+
+        let binary = assemble(PaddingKind::Skip);
+        assert_eq!(binary.len(), 128);
+        assert_eq!(binary.instructions()[1], 0x00000011);
+        assert_eq!(binary.without_padding().len(), 1);
+
+        let binary = assemble(PaddingKind::None);
+        assert_eq!(binary.len(), 1);
+        assert_eq!(binary.to_bytes().len(), 4);
+    }
+
     #[test]
     fn test_assemble_simple_program() {
         let mut program = Program::new();
@@ -247,9 +921,9 @@ mod tests {
         let binary = assembler.assemble(&program).unwrap();
 
         assert_eq!(binary.len(), MAX_INSTRUCTIONS);
-        assert_eq!(binary.instructions()[0] >> 27, 0b01110); // CLR
-        assert_eq!(binary.instructions()[1] >> 27, 0b00000); // RDAX
-        assert_eq!(binary.instructions()[2] >> 27, 0b00110); // WRAX
+        assert_eq!(binary.instructions()[0] & 0x1F, 0b01110); // CLR
+        assert_eq!(binary.instructions()[1] & 0x1F, 0b00100); // RDAX
+        assert_eq!(binary.instructions()[2] & 0x1F, 0b00110); // WRAX
     }
 
     #[test]
@@ -314,6 +988,108 @@ mod tests {
         assert!(hex.contains("00000000"));
     }
 
+    #[test]
+    fn test_binary_to_srec() {
+        let mut binary = Binary::new();
+        binary.push(0x0000_0011);
+        binary.push(0x8008_0014);
+        assert_eq!(
+            binary.to_srec(),
+            "S0030000FC\nS10B0000000000118008001447\nS5030001FB\nS9030000FC\n"
+        );
+    }
+
+    #[test]
+    fn test_binary_to_uf2() {
+        let mut binary = Binary::new();
+        for _ in 0..MAX_INSTRUCTIONS {
+            binary.push(0x0000_0011);
+        }
+
+        let uf2 = binary.to_uf2(0xE48B_FF56, 0x1003_0000);
+        // 512 bytes of program in two blocks of 256
+        assert_eq!(uf2.len(), 1024);
+        assert_eq!(&uf2[..4], b"UF2\n");
+        assert_eq!(&uf2[12..16], &0x1003_0000u32.to_le_bytes());
+        assert_eq!(&uf2[32..36], &[0, 0, 0, 0x11]);
+        assert_eq!(&uf2[512 + 12..512 + 16], &0x1003_0100u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_binary_to_json() {
+        let mut binary = Binary::new();
+        binary.push(0x8008_0014);
+        binary.push(0xFFFF_FFFF);
+        binary.push(0x0000_0011);
+
+        let json = binary.to_json();
+        assert!(json.contains(
+            r#"{"index": 0, "word": "0x80080014", "mnemonic": "CHO", "operands": {"mode": "SOF", "lfo": "SIN0", "flags": "SIN", "offset": 0.5}, "text": "CHO SOF, SIN0, SIN, 0.5"}"#
+        ));
+        assert!(json.contains(
+            r#"{"index": 1, "word": "0xFFFFFFFF", "mnemonic": null, "operands": {}, "text": ".dw 0xFFFFFFFF"}"#
+        ));
+        assert!(!json.contains(r#""index": 2"#));
+    }
+
+    #[test]
+    fn test_binary_checksums() {
+        let mut binary = Binary::new();
+        for word in [0x6162_6364, 0x6566_6768] {
+            binary.push(word);
+        }
+        // Check values for "abcdefgh"
+        assert_eq!(binary.crc32(), 0xAEEF_2A50);
+        assert_eq!(binary.sha256()[..4], [0x9c, 0x56, 0xcc, 0x51]);
+        assert_eq!(binary.fingerprint(), "9c56-cc51");
+
+        binary.push(0x11);
+        assert_ne!(binary.fingerprint(), "9c56-cc51");
+    }
+
+    #[test]
+    fn test_binary_streaming() {
+        let mut binary = Binary::new();
+        for i in 0..MAX_INSTRUCTIONS as u32 {
+            binary.push(i);
+        }
+
+        let mut bytes = Vec::new();
+        binary.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, binary.to_bytes());
+
+        // Two binaries back to back are read one at a time
+        bytes.extend(binary.to_bytes());
+        let mut reader = &bytes[..];
+        for _ in 0..2 {
+            let read = Binary::read_from(&mut reader).unwrap();
+            assert_eq!(read.instructions(), binary.instructions());
+        }
+        assert!(matches!(
+            Binary::read_from(&mut reader),
+            Err(CodegenError::InvalidBinarySize { size: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_binary_from_hex() {
+        let mut binary = Binary::new();
+        binary.push(0x0000_0011);
+        binary.push(0x8008_0014);
+        let read = Binary::from_hex(&binary.to_hex()).unwrap();
+        assert_eq!(read.instructions(), binary.instructions());
+
+        // Three bytes is not a whole instruction
+        assert!(matches!(
+            Binary::from_hex(":03000000010203F7\n:00000001FF\n"),
+            Err(CodegenError::InvalidBinarySize { size: 3, .. })
+        ));
+        assert!(matches!(
+            Binary::from_hex(":0400000001020304F3\n"),
+            Err(CodegenError::InvalidHex { line: 1, .. })
+        ));
+    }
+
     #[test]
     fn test_binary_to_c_array() {
         let mut binary = Binary::new();
@@ -326,6 +1102,81 @@ mod tests {
         assert!(c_code.contains("0xABCDEF00"));
     }
 
+    #[test]
+    fn test_binary_to_c_header() {
+        let mut binary = Binary::new();
+        binary.push(0x6162_6364);
+        binary.push(0x6566_6768);
+        binary.push(0x0000_0011);
+
+        assert_eq!(
+            binary.to_c_header("echo", &["Time", "Feedback \"fb\""]),
+            r#"// FV-1 program: echo (3 instructions)
+#ifndef ECHO_H
+#define ECHO_H
+
+#include <stdint.h>
+
+#define ECHO_NAME "echo"
+#define ECHO_POT0_LABEL "Time"
+#define ECHO_POT1_LABEL "Feedback \"fb\""
+#define ECHO_POT2_LABEL ""
+#define ECHO_INSTRUCTIONS 2
+#define ECHO_CRC32 0x"#
+                .to_string()
+                + &format!("{:08X}", binary.crc32())
+                + "u
+
+extern const uint32_t echo[3];
+
+#endif // ECHO_H
+"
+        );
+    }
+
+    #[test]
+    fn test_binary_to_rust_array() {
+        let mut binary = Binary::new();
+        for word in [0x12345678, 0xABCDEF00, 0, 0x11, 0xFF] {
+            binary.push(word);
+        }
+
+        assert_eq!(
+            binary.to_rust_array("test_program"),
+            "\
+// FV-1 program: TEST_PROGRAM (5 instructions)
+pub const TEST_PROGRAM: [u32; 5] = [
+    0x12345678, 0xABCDEF00, 0x00000000, 0x00000011,
+    0x000000FF,
+];
+"
+        );
+    }
+
+    #[test]
+    fn test_binary_to_arduino_sketch() {
+        let mut binary = Binary::new();
+        for _ in 0..MAX_INSTRUCTIONS {
+            binary.push(0x8008_0014);
+        }
+
+        let sketch = binary.to_arduino_sketch("delay");
+        assert!(sketch.starts_with("// FV-1 program: delay (128 instructions)\n"));
+        assert!(sketch.contains("#include <Wire.h>\n"));
+        assert!(sketch.contains("#define EEPROM_ADDRESS 0x50"));
+        assert!(sketch.contains("const uint8_t delay[512] PROGMEM = {\n  0x80, 0x08, 0x00, 0x14,"));
+        // 32 rows of 16 bytes
+        assert_eq!(
+            sketch
+                .lines()
+                .filter(|line| line.starts_with("  0x80"))
+                .count(),
+            32
+        );
+        assert!(sketch.contains("Wire.write(pgm_read_byte(&delay[j]));"));
+        assert!(sketch.ends_with("void loop() {}\n"));
+    }
+
     #[test]
     fn test_assemble_with_labels() {
         let mut program = Program::new();
@@ -343,4 +1194,126 @@ mod tests {
         assert_eq!(program.resolve_label("start"), Some(0));
         assert_eq!(program.resolve_label("loop"), Some(1));
     }
+
+    #[test]
+    fn test_assemble_skip_to_label() {
+        let source = "SKP RUN, done\nCLR\nCLR\ndone: WRAX DACL, 0.0\n";
+        let program = Parser::new(source).parse().unwrap();
+
+        let binary = Assembler::new().assemble(&program).unwrap();
+
+        // SKP RUN, 2
+        assert_eq!(binary.instructions()[0], 0x80400011);
+    }
+
+    #[test]
+    fn test_lower_resolves_skips() {
+        let source = "SKP RUN, done\nNOP\nCLR\ndone: WRAX DACL, 0.0\n";
+        let program = Parser::new(source).parse().unwrap();
+
+        let lowered = Assembler::new().lower(&program).unwrap();
+        assert!(lowered.skip_target(0).is_none());
+        assert!(matches!(
+            lowered.instructions()[0],
+            Instruction::SKP { offset: 2, .. }
+        ));
+        assert_eq!(lowered.resolve_label("done"), Some(3));
+
+        // Offsets follow the optimized layout
+        let lowered = Assembler::new()
+            .with_optimization(true)
+            .lower(&program)
+            .unwrap();
+        assert!(matches!(
+            lowered.instructions()[0],
+            Instruction::SKP { offset: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_assemble_skip_to_undefined_label() {
+        let mut program = Program::new();
+        program.add_statement(Statement::Instruction(Instruction::SKP {
+            condition: SkipCondition::ZRO,
+            offset: 0,
+        }));
+        program.add_statement(Statement::Instruction(Instruction::CLR));
+        program.skip_targets.insert(0, "nowhere".to_string());
+
+        let error = Assembler::new().assemble(&program).unwrap_err();
+        assert!(matches!(
+            error.without_span(),
+            CodegenError::UndefinedLabel { name } if name == "nowhere"
+        ));
+    }
+
+    #[test]
+    fn test_assemble_skip_backwards_out_of_range() {
+        let mut program = Program::new();
+        program.add_statement(Statement::LabeledInstruction {
+            label: "top".to_string(),
+            instruction: Instruction::CLR,
+        });
+        program.add_statement(Statement::Instruction(Instruction::SKP {
+            condition: SkipCondition::GEZ,
+            offset: 0,
+        }));
+        program.skip_targets.insert(1, "top".to_string());
+
+        let error = Assembler::new().assemble(&program).unwrap_err();
+        assert!(matches!(
+            error.without_span(),
+            CodegenError::SkipOutOfRange { offset: -2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_assemble_all_errors() {
+        let source = "WLDS RMP0, 10, 100\nRDAX ADCL, 1.0\nWLDS RMP1, 10, 100\n";
+        let program = Parser::new(source).parse().unwrap();
+
+        let errors = Assembler::new().assemble_all_errors(&program).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            &errors[1],
+            CodegenError::InInstruction { span, .. } if &source[span.clone()] == "WLDS RMP1, 10, 100"
+        ));
+
+        // The first error is the one assemble reports
+        let error = Assembler::new().assemble(&program).unwrap_err();
+        assert_eq!(error.to_string(), errors[0].to_string());
+
+        let error = Assembler::new()
+            .assemble_named(&program, "lfo.asm", source)
+            .unwrap_err();
+        assert!(matches!(error.error(), CodegenError::Multiple { errors } if errors.len() == 2));
+    }
+
+    #[test]
+    fn test_codegen_error_points_at_source() {
+        let source = "RDAX ADCL, 1.0\nWLDS RMP0, 10, 100\nWRAX DACL, 0.0\n";
+        let program = Parser::new(source).parse().unwrap();
+
+        let error = Assembler::new().assemble(&program).unwrap_err();
+        let CodegenError::InInstruction { error, span } = error else {
+            panic!("expected error with source span, got {error:?}");
+        };
+        assert!(matches!(
+            *error,
+            CodegenError::InvalidLfoForInstruction { .. }
+        ));
+        assert_eq!(&source[span], "WLDS RMP0, 10, 100");
+
+        let source = "CLR\n".repeat(MAX_INSTRUCTIONS) + "NOT\n";
+        let program = Parser::new(&source).parse().unwrap();
+        let error = Assembler::new().assemble(&program).unwrap_err();
+        let CodegenError::InInstruction { error, span } = error else {
+            panic!("expected error with source span, got {error:?}");
+        };
+        assert!(matches!(
+            *error,
+            CodegenError::ProgramTooLarge { size: 129, .. }
+        ));
+        assert_eq!(&source[span], "NOT");
+    }
 }
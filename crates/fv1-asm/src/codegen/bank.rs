@@ -0,0 +1,469 @@
+//! FV-1 EEPROM Banks
+//!
+//! The FV-1 loads its programs from a 4KB EEPROM holding eight programs of
+//! 128 instructions each, selected with the S0-S2 pins. A bank collects the
+//! binaries for each slot and exports the combined image. An
+//! [`EepromImage`] builds one slot by slot, on top of an existing image if
+//! wanted, and warns when a placement replaces a program.
+
+use crate::{
+    ast::Program,
+    codegen::{
+        assembler::{Assembler, Binary},
+        hex::{intel_hex, intel_hex_with, parse_intel_hex, HexOptions},
+        srec::motorola_srec,
+    },
+    constants::{MAX_INSTRUCTIONS, PROGRAM_SLOTS},
+    error::{CodegenError, Warning},
+};
+use std::io::{Read, Write};
+
+/// Eight program slots of an FV-1 EEPROM
+#[derive(Debug, Clone)]
+pub struct Bank {
+    slots: [Option<Binary>; PROGRAM_SLOTS],
+    /// Program written to slots that are left empty
+    default: Binary,
+}
+
+impl Bank {
+    /// Create a bank with every slot empty. Empty slots are filled with
+    /// NOPs unless another default is set.
+    pub fn new() -> Self {
+        Self {
+            slots: Default::default(),
+            default: Binary::new(),
+        }
+    }
+
+    /// Create a bank from up to eight binaries, placed in slots 0 onward
+    pub fn from_binaries(binaries: impl IntoIterator<Item = Binary>) -> Result<Self, CodegenError> {
+        let binaries: Vec<Binary> = binaries.into_iter().collect();
+        if binaries.len() > PROGRAM_SLOTS {
+            return Err(CodegenError::TooManyPrograms {
+                count: binaries.len(),
+                max: PROGRAM_SLOTS,
+            });
+        }
+
+        let mut bank = Self::new();
+        for (slot, binary) in binaries.into_iter().enumerate() {
+            bank.set(slot, binary)?;
+        }
+        Ok(bank)
+    }
+
+    /// Assemble up to eight programs into slots 0 onward
+    pub fn from_programs(
+        programs: &[Program],
+        assembler: &Assembler,
+    ) -> Result<Self, CodegenError> {
+        if programs.len() > PROGRAM_SLOTS {
+            return Err(CodegenError::TooManyPrograms {
+                count: programs.len(),
+                max: PROGRAM_SLOTS,
+            });
+        }
+
+        let binaries = programs
+            .iter()
+            .map(|program| assembler.assemble(program))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_binaries(binaries)
+    }
+
+    /// Read a 4096-byte EEPROM image, filling every slot
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CodegenError> {
+        let expected = PROGRAM_SLOTS * MAX_INSTRUCTIONS * 4;
+        if bytes.len() != expected {
+            return Err(CodegenError::InvalidBinarySize {
+                size: bytes.len(),
+                expected,
+            });
+        }
+
+        let mut bank = Self::new();
+        for (slot, chunk) in bytes.chunks_exact(MAX_INSTRUCTIONS * 4).enumerate() {
+            bank.set(slot, Binary::from_bytes(chunk)?)?;
+        }
+        Ok(bank)
+    }
+
+    /// Read a 4096-byte EEPROM image from a reader, leaving anything after
+    /// it unread
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, CodegenError> {
+        let size = PROGRAM_SLOTS * MAX_INSTRUCTIONS * 4;
+        let mut bytes = Vec::with_capacity(size);
+        reader.take(size as u64).read_to_end(&mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Read an EEPROM image from Intel HEX. Addresses the file does not
+    /// write, up to the end of the 4KB image, are zero (NOPs).
+    pub fn from_hex(text: &str) -> Result<Self, CodegenError> {
+        let expected = PROGRAM_SLOTS * MAX_INSTRUCTIONS * 4;
+        let mut bytes = parse_intel_hex(text, expected)?;
+        bytes.resize(expected, 0);
+        Self::from_bytes(&bytes)
+    }
+
+    /// Place a binary in a slot, replacing any binary already there
+    pub fn set(&mut self, slot: usize, binary: Binary) -> Result<(), CodegenError> {
+        check_size(&binary)?;
+        let entry = self
+            .slots
+            .get_mut(slot)
+            .ok_or(CodegenError::InvalidSlot { slot })?;
+        *entry = Some(binary);
+        Ok(())
+    }
+
+    /// Empty a slot, returning the binary it held
+    pub fn clear(&mut self, slot: usize) -> Option<Binary> {
+        self.slots.get_mut(slot)?.take()
+    }
+
+    /// Set the program written to empty slots
+    pub fn set_default(&mut self, binary: Binary) -> Result<(), CodegenError> {
+        check_size(&binary)?;
+        self.default = binary;
+        Ok(())
+    }
+
+    /// Get the binary in a slot, if it is filled
+    pub fn get(&self, slot: usize) -> Option<&Binary> {
+        self.slots.get(slot)?.as_ref()
+    }
+
+    /// Export the 4096-byte EEPROM image. Binaries shorter than 128
+    /// instructions are padded with NOPs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PROGRAM_SLOTS * MAX_INSTRUCTIONS * 4);
+        for slot in &self.slots {
+            let binary = slot.as_ref().unwrap_or(&self.default);
+            let start = bytes.len();
+            bytes.extend(binary.to_bytes());
+            bytes.resize(start + MAX_INSTRUCTIONS * 4, 0);
+        }
+        bytes
+    }
+
+    /// Write the 4096-byte EEPROM image, as [`Bank::to_bytes`], to a
+    /// writer one slot at a time
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for slot in &self.slots {
+            let binary = slot.as_ref().unwrap_or(&self.default);
+            binary.write_to(&mut writer)?;
+            // Pad short binaries with NOPs
+            let padding = (MAX_INSTRUCTIONS - binary.len()) * 4;
+            writer.write_all(&vec![0; padding])?;
+        }
+        Ok(())
+    }
+
+    /// Export the EEPROM image as Intel HEX
+    pub fn to_hex(&self) -> String {
+        intel_hex(&self.to_bytes())
+    }
+
+    /// Export the EEPROM image as Intel HEX with a different record
+    /// length, base address or letter case
+    pub fn to_hex_with(&self, options: &HexOptions) -> String {
+        intel_hex_with(&self.to_bytes(), options)
+    }
+
+    /// Export the EEPROM image as Motorola S-records (S19)
+    pub fn to_srec(&self) -> String {
+        motorola_srec(&self.to_bytes())
+    }
+}
+
+impl Default for Bank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder for an EEPROM image with programs placed in chosen slots
+#[derive(Debug, Clone, Default)]
+pub struct EepromImage {
+    bank: Bank,
+    warnings: Vec<Warning>,
+}
+
+impl EepromImage {
+    /// Create an image with every slot empty
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from the slots of an existing bank
+    pub fn from_bank(bank: Bank) -> Self {
+        Self {
+            bank,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Start from an existing 4096-byte image, such as one read back from
+    /// an EEPROM
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CodegenError> {
+        Ok(Self::from_bank(Bank::from_bytes(bytes)?))
+    }
+
+    /// Place a binary in a slot, warning if it replaces a program
+    pub fn with_slot(mut self, slot: usize, binary: Binary) -> Result<Self, CodegenError> {
+        let replaced = self.holds_program(slot);
+        self.bank.set(slot, binary)?;
+        if replaced {
+            self.warnings.push(Warning::SlotOverwritten { slot });
+        }
+        Ok(self)
+    }
+
+    /// Empty a slot, warning if it held a program. Empty slots are
+    /// written as NOPs.
+    pub fn with_empty_slot(mut self, slot: usize) -> Result<Self, CodegenError> {
+        if slot >= PROGRAM_SLOTS {
+            return Err(CodegenError::InvalidSlot { slot });
+        }
+        if self.holds_program(slot) {
+            self.warnings.push(Warning::SlotOverwritten { slot });
+        }
+        self.bank.clear(slot);
+        Ok(self)
+    }
+
+    /// Whether a slot holds a program, rather than nothing, padding or
+    /// erased (all ones) memory
+    fn holds_program(&self, slot: usize) -> bool {
+        self.bank.get(slot).is_some_and(|binary| {
+            binary
+                .without_padding()
+                .iter()
+                .any(|&word| word != 0xFFFF_FFFF)
+        })
+    }
+
+    /// Get the warnings from placing programs, in order
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Get the bank of slots built so far
+    pub fn bank(&self) -> &Bank {
+        &self.bank
+    }
+
+    /// Finish building, returning the bank
+    pub fn into_bank(self) -> Bank {
+        self.bank
+    }
+
+    /// Export the 4096-byte EEPROM image
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bank.to_bytes()
+    }
+
+    /// Export the EEPROM image as Intel HEX
+    pub fn to_hex(&self) -> String {
+        self.bank.to_hex()
+    }
+}
+
+/// Check that a binary fits in one slot
+fn check_size(binary: &Binary) -> Result<(), CodegenError> {
+    if binary.len() > MAX_INSTRUCTIONS {
+        return Err(CodegenError::ProgramTooLarge {
+            size: binary.len(),
+            max: MAX_INSTRUCTIONS,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{codegen::PaddingKind, parser::Parser};
+
+    fn binary(word: u32, len: usize) -> Binary {
+        let mut binary = Binary::new();
+        for _ in 0..len {
+            binary.push(word);
+        }
+        binary
+    }
+
+    #[test]
+    fn test_bank_image() {
+        let mut bank = Bank::from_binaries([binary(0x11, 128), binary(0x22, 2)]).unwrap();
+        bank.set(7, binary(0x77, 128)).unwrap();
+
+        let bytes = bank.to_bytes();
+        assert_eq!(bytes.len(), 4096);
+        assert_eq!(&bytes[..4], &[0, 0, 0, 0x11]);
+        assert_eq!(&bytes[512..520], &[0, 0, 0, 0x22, 0, 0, 0, 0x22]);
+        // The short binary is padded and empty slots are NOPs
+        assert!(bytes[520..7 * 512].iter().all(|&byte| byte == 0));
+        assert_eq!(&bytes[4092..], &[0, 0, 0, 0x77]);
+
+        let hex = bank.to_hex();
+        assert_eq!(hex.lines().count(), 257);
+        assert!(hex.contains(":100FF000"));
+
+        let srec = bank.to_srec();
+        // Header, 256 data records, count and termination
+        assert_eq!(srec.lines().count(), 259);
+        assert!(srec.contains("S1130FF000000077000000770000007700000077"));
+        assert!(srec.ends_with("S5030100FB\nS9030000FC\n"));
+    }
+
+    #[test]
+    fn test_bank_default_program() {
+        let mut bank = Bank::new();
+        bank.set_default(binary(0x11, 128)).unwrap();
+        bank.set(3, binary(0x33, 128)).unwrap();
+        assert_eq!(bank.get(3).unwrap().instructions()[0], 0x33);
+        assert!(bank.get(0).is_none());
+
+        let bytes = bank.to_bytes();
+        assert_eq!(&bytes[..4], &[0, 0, 0, 0x11]);
+        assert_eq!(&bytes[3 * 512..3 * 512 + 4], &[0, 0, 0, 0x33]);
+
+        assert!(bank.clear(3).is_some());
+        assert_eq!(&bank.to_bytes()[3 * 512..3 * 512 + 4], &[0, 0, 0, 0x11]);
+    }
+
+    #[test]
+    fn test_bank_from_bytes() {
+        let bank = Bank::from_binaries([binary(0x11, 128), binary(0x22, 128)]).unwrap();
+        let read = Bank::from_bytes(&bank.to_bytes()).unwrap();
+        assert_eq!(read.get(1).unwrap().instructions()[127], 0x22);
+        assert_eq!(read.get(7).unwrap().instructions()[0], 0);
+        assert_eq!(read.to_bytes(), bank.to_bytes());
+
+        assert!(matches!(
+            Bank::from_bytes(&[0; 512]),
+            Err(CodegenError::InvalidBinarySize {
+                size: 512,
+                expected: 4096
+            })
+        ));
+    }
+
+    #[test]
+    fn test_bank_from_hex() {
+        let bank = Bank::from_binaries([binary(0x11, 128), binary(0x22, 2)]).unwrap();
+        let read = Bank::from_hex(&bank.to_hex()).unwrap();
+        assert_eq!(read.to_bytes(), bank.to_bytes());
+
+        // A dump of only the first slot leaves the rest empty
+        let read = Bank::from_hex(&binary(0x11, 128).to_hex()).unwrap();
+        assert_eq!(read.get(0).unwrap().instructions()[0], 0x11);
+        assert!(read
+            .get(7)
+            .unwrap()
+            .instructions()
+            .iter()
+            .all(|&word| word == 0));
+
+        // A binary placed in slot 2 by its base address
+        let options = HexOptions {
+            base_address: 2 * 512,
+            ..HexOptions::new()
+        };
+        let read = Bank::from_hex(&binary(0x22, 128).to_hex_with(&options)).unwrap();
+        assert_eq!(read.get(2).unwrap().instructions()[0], 0x22);
+        assert_eq!(read.get(0).unwrap().instructions()[0], 0);
+    }
+
+    #[test]
+    fn test_bank_streaming() {
+        let bank = Bank::from_binaries([binary(0x11, 128), binary(0x22, 2)]).unwrap();
+        let mut bytes = Vec::new();
+        bank.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, bank.to_bytes());
+
+        let read = Bank::read_from(&bytes[..]).unwrap();
+        assert_eq!(read.to_bytes(), bank.to_bytes());
+        assert!(matches!(
+            Bank::read_from(&bytes[..100]),
+            Err(CodegenError::InvalidBinarySize { size: 100, .. })
+        ));
+    }
+
+    #[test]
+    fn test_eeprom_image() {
+        let image = EepromImage::new()
+            .with_slot(3, binary(0x33, 128))
+            .unwrap()
+            .with_slot(5, binary(0x55, 2))
+            .unwrap();
+        assert!(image.warnings().is_empty());
+        let bytes = image.to_bytes();
+        assert_eq!(&bytes[3 * 512..3 * 512 + 4], &[0, 0, 0, 0x33]);
+        assert!(bytes[..3 * 512].iter().all(|&byte| byte == 0));
+        assert_eq!(Bank::from_hex(&image.to_hex()).unwrap().to_bytes(), bytes);
+
+        // Building on the image replaces slot 3, empties slot 5 and fills
+        // slot 0, which only held NOPs
+        let image = EepromImage::from_bytes(&bytes)
+            .unwrap()
+            .with_slot(3, binary(0x44, 128))
+            .unwrap()
+            .with_empty_slot(5)
+            .unwrap()
+            .with_slot(0, binary(0x11, 128))
+            .unwrap();
+        assert_eq!(
+            image.warnings(),
+            &[
+                Warning::SlotOverwritten { slot: 3 },
+                Warning::SlotOverwritten { slot: 5 }
+            ]
+        );
+        assert_eq!(
+            image.warnings()[0].to_string(),
+            "program in slot 3 overwritten"
+        );
+
+        let bank = image.into_bank();
+        assert_eq!(bank.get(3).unwrap().instructions()[0], 0x44);
+        assert!(bank.get(5).is_none());
+        assert!(matches!(
+            EepromImage::new().with_empty_slot(8),
+            Err(CodegenError::InvalidSlot { slot: 8 })
+        ));
+    }
+
+    #[test]
+    fn test_bank_validation() {
+        let mut bank = Bank::new();
+        assert!(matches!(
+            bank.set(8, binary(0, 128)),
+            Err(CodegenError::InvalidSlot { slot: 8 })
+        ));
+        assert!(matches!(
+            bank.set(0, binary(0, 129)),
+            Err(CodegenError::ProgramTooLarge { size: 129, .. })
+        ));
+        assert!(matches!(
+            Bank::from_binaries((0..9).map(|_| binary(0, 128))),
+            Err(CodegenError::TooManyPrograms { count: 9, max: 8 })
+        ));
+    }
+
+    #[test]
+    fn test_bank_from_programs() {
+        let programs = ["rdax adcl, 1.0\nwrax dacl, 0.0", "clr"]
+            .map(|source| Parser::new(source).parse().unwrap());
+        let assembler = Assembler::new().with_padding(PaddingKind::Skip);
+        let bank = Bank::from_programs(&programs, &assembler).unwrap();
+
+        assert_eq!(bank.get(1).unwrap().len(), 128);
+        assert!(bank.get(2).is_none());
+        // Slot 1 is padded by the assembler, not with NOPs
+        assert_eq!(&bank.to_bytes()[516..520], &[0, 0, 0, 0x11]);
+    }
+}
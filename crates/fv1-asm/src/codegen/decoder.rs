@@ -5,246 +5,145 @@
 use crate::{
     error::CodegenError,
     instruction::{ChoFlags, ChoMode, Instruction, SkipCondition},
+    isa::{
+        self, FixedFormat, InstructionSpec, Operand, OperandKind, OperandSpec, INSTRUCTIONS,
+        OPCODE_MASK,
+    },
     register::{Lfo, Register},
 };
 
 /// Decode a 32-bit FV-1 machine code instruction
+///
+/// Fields are unpacked as the instruction table describes. Aliases such as
+/// CLR or NOP are exact forms of another instruction and are preferred when
+/// the word matches one.
 pub fn decode_instruction(word: u32) -> Result<Instruction, CodegenError> {
-    // Special case for NOP (all zeros)
-    if word == 0x00000000 {
-        return Ok(Instruction::NOP);
-    }
-
-    let opcode = (word >> 27) & 0x1F;
-
-    match opcode {
-        // Accumulator operations
-        0b00000 => {
-            // RDAX
-            let reg = decode_register((word >> 21) & 0x3F)?;
-            let coeff = decode_s114((word >> 6) & 0x7FFF)?;
-            Ok(Instruction::RDAX { reg, coeff })
-        }
-
-        0b00001 => {
-            // RDA
-            let addr = ((word >> 11) & 0xFFFF) as u16;
-            let coeff = decode_s114(word & 0x7FF)?;
-            Ok(Instruction::RDA { addr, coeff })
-        }
-
-        0b00010 => {
-            // RMPA
-            let coeff = decode_s114(word & 0x7FFFFF)?;
-            Ok(Instruction::RMPA { coeff })
-        }
-
-        0b00110 => {
-            // WRAX
-            let reg = decode_register((word >> 21) & 0x3F)?;
-            let coeff = decode_s114((word >> 6) & 0x7FFF)?;
-            Ok(Instruction::WRAX { reg, coeff })
-        }
-
-        0b00111 => {
-            // WRA
-            let addr = ((word >> 11) & 0xFFFF) as u16;
-            let coeff = decode_s114(word & 0x7FF)?;
-            Ok(Instruction::WRA { addr, coeff })
-        }
-
-        0b01000 => {
-            // WRAP
-            let addr = ((word >> 11) & 0xFFFF) as u16;
-            let coeff = decode_s114(word & 0x7FF)?;
-            Ok(Instruction::WRAP { addr, coeff })
-        }
-
-        // Mathematical operations
-        0b01010 => {
-            // MULX
-            let reg = decode_register((word >> 21) & 0x3F)?;
-            Ok(Instruction::MULX { reg })
-        }
-
-        0b01001 => {
-            // RDFX
-            let reg = decode_register((word >> 21) & 0x3F)?;
-            let coeff = decode_s114((word >> 6) & 0x7FFF)?;
-            Ok(Instruction::RDFX { reg, coeff })
-        }
-
-        0b01100 => {
-            // RDFX2
-            let reg = decode_register((word >> 21) & 0x3F)?;
-            let coeff = decode_s114((word >> 6) & 0x7FFF)?;
-            Ok(Instruction::RDFX2 { reg, coeff })
-        }
-
-        0b00101 => {
-            // LDAX
-            let reg = decode_register((word >> 21) & 0x3F)?;
-            Ok(Instruction::LDAX { reg })
-        }
-
-        0b01011 => {
-            // ABSA
-            Ok(Instruction::ABSA)
-        }
-
-        // Logic and control
-        0b01101 => {
-            // SOF
-            let coeff = decode_s114((word >> 11) & 0xFFFF)?;
-            let offset = decode_s10(word & 0x7FF)?;
-            Ok(Instruction::SOF { coeff, offset })
-        }
-
-        0b01111 => {
-            // AND
-            let mask = word & 0xFFFFFF;
-            Ok(Instruction::AND { mask })
-        }
-
-        0b10000 => {
-            // OR
-            let mask = word & 0xFFFFFF;
-            Ok(Instruction::OR { mask })
-        }
-
-        0b10001 => {
-            // XOR
-            let mask = word & 0xFFFFFF;
-            Ok(Instruction::XOR { mask })
-        }
-
-        0b10010 => {
-            // SHL
-            Ok(Instruction::SHL)
-        }
-
-        0b10011 => {
-            // SHR
-            Ok(Instruction::SHR)
-        }
-
-        0b01110 => {
-            // CLR
-            Ok(Instruction::CLR)
-        }
-
-        // Conversion operations
-        0b10100 => {
-            // EXP
-            let coeff = decode_s114((word >> 11) & 0xFFFF)?;
-            let offset = decode_s10(word & 0x7FF)?;
-            Ok(Instruction::EXP { coeff, offset })
-        }
-
-        0b10101 => {
-            // LOG
-            let coeff = decode_s114((word >> 11) & 0xFFFF)?;
-            let offset = decode_s10(word & 0x7FF)?;
-            Ok(Instruction::LOG { coeff, offset })
-        }
-
-        // Conditional skipping
-        0b10110 => {
-            // SKP
-            let condition = decode_skip_condition((word >> 24) & 0x07)?;
-            let offset = ((word >> 18) & 0x3F) as i8;
-            Ok(Instruction::SKP { condition, offset })
-        }
+    let opcode = word & OPCODE_MASK;
+    let candidates = || INSTRUCTIONS.iter().filter(|spec| spec.opcode == opcode);
+    let spec = candidates()
+        .find(|spec| spec.alias_of.is_some() && matches_fixed_bits(spec, word))
+        .or_else(|| candidates().find(|spec| spec.alias_of.is_none()))
+        .ok_or(CodegenError::InvalidOpcode {
+            opcode: opcode as u8,
+        })?;
 
-        // LFO control
-        0b10111 => {
-            // WLDS
-            let lfo = decode_lfo((word >> 25) & 0x03)?;
-            let freq = ((word >> 9) & 0x1FF) as u16;
-            let amplitude = (word & 0x1FF) as u16;
-            Ok(Instruction::WLDS {
-                lfo,
-                freq,
-                amplitude,
-            })
-        }
+    if !matches_fixed_bits(spec, word) {
+        return Err(CodegenError::ReservedBits {
+            mnemonic: spec.mnemonic,
+            word,
+        });
+    }
 
-        0b11000 => {
-            // JAM
-            let lfo = decode_lfo((word >> 25) & 0x03)?;
-            Ok(Instruction::JAM { lfo })
-        }
+    let decode = |field: &OperandSpec| decode_operand(field, (word & field.mask()) >> field.lsb);
+    let spec = match spec.operands.first() {
+        Some(field) => isa::variant(spec, &decode(field)?),
+        None => spec,
+    };
+    let operands = spec
+        .operands
+        .iter()
+        .map(decode)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(isa::build(spec, &operands).expect("decoded operands match their fields"))
+}
 
-        0b11001 => {
-            // CHO
-            let mode = decode_cho_mode((word >> 24) & 0x03)?;
-            let lfo = decode_lfo((word >> 22) & 0x03)?;
-            let flags = decode_cho_flags((word >> 16) & 0x3F);
-            let addr = (word & 0xFFFF) as u16;
-            Ok(Instruction::CHO {
-                mode,
-                lfo,
-                flags,
-                addr,
-            })
-        }
+/// Check that every bit outside the operand fields is the opcode or one of
+/// the instruction's fixed bits
+fn matches_fixed_bits(spec: &InstructionSpec, word: u32) -> bool {
+    word & !spec.operand_mask() == spec.opcode | spec.fixed
+}
 
-        _ => Err(CodegenError::InvalidOpcode {
-            opcode: opcode as u8,
+/// Decode the raw bits of a field into an operand
+fn decode_operand(field: &OperandSpec, bits: u32) -> Result<Operand, CodegenError> {
+    Ok(match field.kind {
+        OperandKind::Register => Operand::Register(decode_register(bits)?),
+        OperandKind::Address => Operand::Address(bits as u16),
+        OperandKind::Coefficient(format) => Operand::Coefficient(match format {
+            FixedFormat::S114 => decode_s114(bits),
+            FixedFormat::S19 => decode_s19(bits),
+            FixedFormat::S10 => decode_s10(bits),
+            FixedFormat::S46 => decode_s46(bits),
+            FixedFormat::S15 => decode_s15(bits),
         }),
-    }
+        OperandKind::Mask => Operand::Mask(bits),
+        OperandKind::SkipCondition => Operand::SkipCondition(decode_skip_condition(bits)?),
+        OperandKind::SkipOffset => Operand::SkipOffset(bits as i8),
+        OperandKind::Lfo => Operand::Lfo(decode_lfo(bits)?),
+        OperandKind::SinLfo => Operand::Lfo(decode_lfo(bits)?),
+        OperandKind::RmpLfo => Operand::Lfo(decode_lfo(0b10 | bits)?),
+        OperandKind::Frequency | OperandKind::Amplitude => Operand::Integer(bits as u16),
+        OperandKind::ChoMode => Operand::ChoMode(decode_cho_mode(bits)?),
+        OperandKind::ChoFlags => Operand::ChoFlags(ChoFlags::from_bits(bits as u8)),
+    })
 }
 
-/// Decode register from 5-bit or 6-bit field
-fn decode_register(bits: u32) -> Result<Register, CodegenError> {
+/// Decode register from its 6-bit FV-1 register address
+pub(crate) fn decode_register(bits: u32) -> Result<Register, CodegenError> {
     match bits {
-        0b00000 => Ok(Register::ADCL),
-        0b00001 => Ok(Register::ADCR),
-        0b00010 => Ok(Register::DACL),
-        0b00011 => Ok(Register::DACR),
-        0b00100 => Ok(Register::ADDR_PTR),
-        0b00101 => Ok(Register::LR),
-        n if (16..48).contains(&n) => Ok(Register::REG((n - 16) as u8)),
+        0x00 => Ok(Register::SIN0_RATE),
+        0x01 => Ok(Register::SIN0_RANGE),
+        0x02 => Ok(Register::SIN1_RATE),
+        0x03 => Ok(Register::SIN1_RANGE),
+        0x04 => Ok(Register::RMP0_RATE),
+        0x05 => Ok(Register::RMP0_RANGE),
+        0x06 => Ok(Register::RMP1_RATE),
+        0x07 => Ok(Register::RMP1_RANGE),
+        0x10 => Ok(Register::POT0),
+        0x11 => Ok(Register::POT1),
+        0x12 => Ok(Register::POT2),
+        0x14 => Ok(Register::ADCL),
+        0x15 => Ok(Register::ADCR),
+        0x16 => Ok(Register::DACL),
+        0x17 => Ok(Register::DACR),
+        0x18 => Ok(Register::ADDR_PTR),
+        0x19 => Ok(Register::LR),
+        n if (0x20..0x40).contains(&n) => Ok(Register::REG((n - 0x20) as u8)),
         _ => Err(CodegenError::InvalidRegister { bits: bits as u8 }),
     }
 }
 
-/// Decode S1.14 fixed-point coefficient
-fn decode_s114(bits: u32) -> Result<f32, CodegenError> {
-    // Convert from S1.14 format: 15-bit signed value (1 sign + 14 fractional)
-    // The sign bit is bit 14 (0x4000)
-    let value = if bits & 0x4000 != 0 {
-        // Negative: sign extend from 15 bits to 32 bits
-        ((bits | 0xFFFF8000) as i32) as f32 / 16384.0
-    } else {
-        // Positive
-        (bits as i32) as f32 / 16384.0
-    };
-    Ok(value)
+/// Decode a `width`-bit two's complement field with `frac_bits` fractional
+/// bits
+fn decode_fixed(bits: u32, width: u32, frac_bits: u32) -> f32 {
+    // Shift the sign bit up to bit 31 and back down to sign-extend
+    let shift = 32 - width;
+    let value = ((bits << shift) as i32) >> shift;
+    value as f32 / (1u32 << frac_bits) as f32
 }
 
-/// Decode S.10 fixed-point coefficient
-fn decode_s10(bits: u32) -> Result<f32, CodegenError> {
-    // Convert from S.10 format: 11-bit signed value (1 sign + 10 fractional)
-    // The sign bit is bit 10 (0x400)
-    let value = if bits & 0x400 != 0 {
-        // Negative: sign extend from 11 bits to 32 bits
-        ((bits | 0xFFFFF800) as i32) as f32 / 512.0
-    } else {
-        // Positive
-        (bits as i32) as f32 / 512.0
-    };
-    Ok(value)
+/// Decode S1.14 fixed-point coefficient (16 bits)
+fn decode_s114(bits: u32) -> f32 {
+    decode_fixed(bits & 0xFFFF, 16, 14)
+}
+
+/// Decode S1.9 fixed-point coefficient (11 bits)
+fn decode_s19(bits: u32) -> f32 {
+    decode_fixed(bits & 0x7FF, 11, 9)
+}
+
+/// Decode S.10 fixed-point coefficient (11 bits)
+fn decode_s10(bits: u32) -> f32 {
+    decode_fixed(bits & 0x7FF, 11, 10)
+}
+
+/// Decode S4.6 fixed-point coefficient (11 bits)
+fn decode_s46(bits: u32) -> f32 {
+    decode_fixed(bits & 0x7FF, 11, 6)
+}
+
+/// Decode S.15 fixed-point coefficient (16 bits)
+fn decode_s15(bits: u32) -> f32 {
+    decode_fixed(bits & 0xFFFF, 16, 15)
 }
 
-/// Decode skip condition from 3-bit field
+/// Decode skip condition from 5-bit flag field
 fn decode_skip_condition(bits: u32) -> Result<SkipCondition, CodegenError> {
     match bits {
-        0b000 => Ok(SkipCondition::RUN),
-        0b001 => Ok(SkipCondition::NEG),
-        0b010 => Ok(SkipCondition::GEZ),
-        0b011 => Ok(SkipCondition::ZRO),
-        0b100 => Ok(SkipCondition::ZRC),
+        0b10000 => Ok(SkipCondition::RUN),
+        0b01000 => Ok(SkipCondition::ZRC),
+        0b00100 => Ok(SkipCondition::ZRO),
+        0b00010 => Ok(SkipCondition::GEZ),
+        0b00001 => Ok(SkipCondition::NEG),
         _ => Err(CodegenError::InvalidSkipCondition { bits: bits as u8 }),
     }
 }
@@ -270,21 +169,10 @@ fn decode_cho_mode(bits: u32) -> Result<ChoMode, CodegenError> {
     }
 }
 
-/// Decode CHO flags from 6-bit field
-fn decode_cho_flags(bits: u32) -> ChoFlags {
-    ChoFlags {
-        rptr2: (bits & 0b100000) != 0,
-        na: (bits & 0b010000) != 0,
-        compc: (bits & 0b001000) != 0,
-        compa: (bits & 0b000100) != 0,
-        rptr2_select: (bits & 0b000010) != 0,
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::codegen::encoder::encode_instruction;
+    use crate::codegen::{disassembler::format_instruction, encoder::encode_instruction};
 
     #[test]
     fn test_decode_rdax() {
@@ -335,6 +223,77 @@ mod tests {
         assert_eq!(decoded, inst);
     }
 
+    #[test]
+    fn test_decode_zero_word() {
+        // All zeros is RDA 0, 0, not NOP
+        let decoded = decode_instruction(0x00000000).unwrap();
+        assert_eq!(format_instruction(&decoded), "RDA 0, 0");
+        assert_eq!(encode_instruction(&decoded).unwrap(), 0x00000000);
+    }
+
+    #[test]
+    fn test_decode_not() {
+        let encoded = encode_instruction(&Instruction::NOT).unwrap();
+        assert_eq!(decode_instruction(encoded).unwrap(), Instruction::NOT);
+
+        let inst = Instruction::XOR { mask: 0x00FF00 };
+        let encoded = encode_instruction(&inst).unwrap();
+        assert_eq!(decode_instruction(encoded).unwrap(), inst);
+    }
+
+    #[test]
+    fn test_decode_spinasm_words() {
+        assert_eq!(
+            decode_instruction(0x40000284).unwrap(),
+            Instruction::RDAX {
+                reg: Register::ADCL,
+                coeff: 1.0
+            }
+        );
+        assert_eq!(
+            decode_instruction(0x20007D00).unwrap(),
+            Instruction::RDA {
+                addr: 1000,
+                coeff: 0.5
+            }
+        );
+        assert_eq!(decode_instruction(0x00000011).unwrap(), Instruction::NOP);
+        assert_eq!(
+            decode_instruction(0x00000405).unwrap(),
+            Instruction::LDAX {
+                reg: Register::REG(0)
+            }
+        );
+        assert_eq!(
+            decode_instruction(0x000000D3).unwrap(),
+            Instruction::JAM { lfo: Lfo::RMP1 }
+        );
+    }
+
+    #[test]
+    fn test_decode_reserved_bits() {
+        // RDAX ADCL with bits set between the register and coefficient fields
+        assert!(matches!(
+            decode_instruction(0x0000_F284),
+            Err(CodegenError::ReservedBits {
+                mnemonic: "RDAX",
+                ..
+            })
+        ));
+        // 0x09 is MAXX, which only decodes in its ABSA form
+        assert!(matches!(
+            decode_instruction(0x4000_0009),
+            Err(CodegenError::InvalidOpcode { opcode: 0x09 })
+        ));
+    }
+
+    #[test]
+    fn test_decode_s114_range() {
+        assert_eq!(decode_s114(0x8000), -2.0);
+        assert_eq!(decode_s114(0x7FFF), 32767.0 / 16384.0);
+        assert_eq!(decode_s46(0x7FF), -1.0 / 64.0);
+    }
+
     #[test]
     fn test_roundtrip_all_instructions() {
         let instructions = vec![
@@ -350,11 +309,78 @@ mod tests {
                 coeff: 0.75,
                 offset: -0.25,
             },
+            Instruction::WRLX {
+                reg: Register::REG(5),
+                coeff: -0.5,
+            },
+            Instruction::WRHX {
+                reg: Register::REG(6),
+                coeff: -0.25,
+            },
             Instruction::CLR,
             Instruction::NOP,
             Instruction::MULX {
                 reg: Register::REG(0),
             },
+            Instruction::RDA {
+                addr: 1000,
+                coeff: -0.5,
+            },
+            Instruction::WRAP {
+                addr: 32767,
+                coeff: 1.5,
+            },
+            Instruction::RMPA { coeff: 0.25 },
+            Instruction::RDFX {
+                reg: Register::REG(31),
+                coeff: 0.125,
+            },
+            Instruction::LDAX {
+                reg: Register::ADCR,
+            },
+            Instruction::MULX {
+                reg: Register::POT1,
+            },
+            Instruction::WRAX {
+                reg: Register::SIN0_RATE,
+                coeff: 0.0,
+            },
+            Instruction::RDAX {
+                reg: Register::RMP1_RANGE,
+                coeff: 1.0,
+            },
+            Instruction::ABSA,
+            Instruction::LOG {
+                coeff: -1.0,
+                offset: -8.5,
+            },
+            Instruction::EXP {
+                coeff: 1.0,
+                offset: 0.5,
+            },
+            Instruction::AND { mask: 0x7FFF00 },
+            Instruction::OR { mask: 0x000001 },
+            Instruction::SKP {
+                condition: SkipCondition::ZRC,
+                offset: 63,
+            },
+            Instruction::WLDS {
+                lfo: Lfo::SIN1,
+                freq: 511,
+                amplitude: 32767,
+            },
+            Instruction::JAM { lfo: Lfo::RMP0 },
+            Instruction::CHO {
+                mode: ChoMode::RDAL,
+                lfo: Lfo::RMP1,
+                flags: ChoFlags::from_bits(0x3F),
+                addr: 0,
+            },
+            Instruction::CHO_SOF {
+                lfo: Lfo::SIN1,
+                flags: ChoFlags::from_bits(ChoFlags::REG | ChoFlags::COMPC),
+                offset: -0.5,
+            },
         ];
 
         for inst in instructions {
@@ -363,4 +389,26 @@ mod tests {
             assert_eq!(decoded, inst);
         }
     }
+
+    #[test]
+    fn test_decode_cho_sof_offset() {
+        // The address field of CHO SOF is an S.15 offset
+        assert_eq!(
+            decode_instruction(0x8008_0014).unwrap(),
+            Instruction::CHO_SOF {
+                lfo: Lfo::SIN0,
+                flags: ChoFlags::default(),
+                offset: 0.5,
+            }
+        );
+        assert_eq!(
+            encode_instruction(&Instruction::CHO_SOF {
+                lfo: Lfo::SIN0,
+                flags: ChoFlags::default(),
+                offset: -1.0,
+            })
+            .unwrap(),
+            0x8010_0014
+        );
+    }
 }
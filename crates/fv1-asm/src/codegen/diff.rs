@@ -0,0 +1,375 @@
+//! Binary Diff
+//!
+//! Compares two binaries in one of two ways, both ignoring trailing
+//! padding. [`diff_binaries`] decodes both and lines up their instructions,
+//! so an inserted instruction shows up as one addition rather than as every
+//! later word changing. Instructions replaced in place by one with the same
+//! mnemonic are reported as changed, with the operands that differ.
+//! [`diff_words`] compares word by word at the same index, for checking
+//! output against a known-good binary, and [`diff_words_exact`] does the
+//! same without ignoring padding.
+
+use crate::{
+    codegen::{decoder::decode_instruction, disassembler::format_instruction, Binary},
+    error::CodegenError,
+    instruction::Instruction,
+    isa::{self, Operand},
+};
+use std::fmt;
+
+/// An operand that differs between two versions of an instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OperandChange {
+    /// Position of the operand in source order
+    pub position: usize,
+    pub old: Operand,
+    pub new: Operand,
+}
+
+/// A difference between two binaries
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstructionDiff {
+    /// Instruction replaced by one with the same mnemonic
+    Changed {
+        old_index: usize,
+        new_index: usize,
+        old: Instruction,
+        new: Instruction,
+        /// Operands that differ
+        operands: Vec<OperandChange>,
+    },
+    /// Instruction only in the new binary
+    Added { index: usize, inst: Instruction },
+    /// Instruction only in the old binary
+    Removed { index: usize, inst: Instruction },
+}
+
+impl fmt::Display for InstructionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstructionDiff::Changed {
+                old_index,
+                new_index,
+                old,
+                new,
+                ..
+            } => write!(
+                f,
+                "~ {}->{}: {} => {}",
+                old_index,
+                new_index,
+                format_instruction(old),
+                format_instruction(new)
+            ),
+            InstructionDiff::Added { index, inst } => {
+                write!(f, "+ {}: {}", index, format_instruction(inst))
+            }
+            InstructionDiff::Removed { index, inst } => {
+                write!(f, "- {}: {}", index, format_instruction(inst))
+            }
+        }
+    }
+}
+
+/// A word that differs between two binaries at the same index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordDiff {
+    pub index: usize,
+    /// Word in the old binary, or `None` past its end
+    pub old: Option<u32>,
+    /// Word in the new binary, or `None` past its end
+    pub new: Option<u32>,
+    /// Disassembly of the old word
+    pub old_text: Option<String>,
+    /// Disassembly of the new word
+    pub new_text: Option<String>,
+}
+
+impl fmt::Display for WordDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |word: Option<u32>, text: &Option<String>| match (word, text) {
+            (Some(word), Some(text)) => format!("{:08X} {}", word, text),
+            _ => "(none)".to_string(),
+        };
+        write!(
+            f,
+            "{}: {} => {}",
+            self.index,
+            side(self.old, &self.old_text),
+            side(self.new, &self.new_text)
+        )
+    }
+}
+
+/// Compare two binaries word by word, returning the indices where they
+/// differ in order. Words that do not decode are shown as `.dw`.
+pub fn diff_words(old: &Binary, new: &Binary) -> Vec<WordDiff> {
+    compare_words(old.without_padding(), new.without_padding())
+}
+
+/// Compare two binaries word by word like [`diff_words`], including their
+/// padding
+pub fn diff_words_exact(old: &Binary, new: &Binary) -> Vec<WordDiff> {
+    compare_words(old.instructions(), new.instructions())
+}
+
+fn compare_words(old: &[u32], new: &[u32]) -> Vec<WordDiff> {
+    let disassemble = |word: u32| {
+        let inst = decode_instruction(word).unwrap_or(Instruction::RAW(word));
+        format_instruction(&inst)
+    };
+
+    (0..old.len().max(new.len()))
+        .filter_map(|index| {
+            let old = old.get(index).copied();
+            let new = new.get(index).copied();
+            (old != new).then(|| WordDiff {
+                index,
+                old,
+                new,
+                old_text: old.map(disassemble),
+                new_text: new.map(disassemble),
+            })
+        })
+        .collect()
+}
+
+/// Compare the instructions of two binaries, returning the differences in
+/// program order. Identical binaries give no differences.
+pub fn diff_binaries(old: &Binary, new: &Binary) -> Result<Vec<InstructionDiff>, CodegenError> {
+    let decode = |binary: &Binary| {
+        binary
+            .without_padding()
+            .iter()
+            .map(|&word| decode_instruction(word))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let old = decode(old)?;
+    let new = decode(new)?;
+
+    // lcs[i][j] is the length of the common subsequence of old[i..], new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diffs = Vec::new();
+    // Removed and added instructions since the last common one
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            pair_up(&old, &new, &mut removed, &mut added, &mut diffs);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            removed.push(i);
+            i += 1;
+        } else {
+            added.push(j);
+            j += 1;
+        }
+    }
+    pair_up(&old, &new, &mut removed, &mut added, &mut diffs);
+
+    Ok(diffs)
+}
+
+/// Report a run of removed and added instructions, pairing them in order
+/// as changes where their mnemonics match
+fn pair_up(
+    old: &[Instruction],
+    new: &[Instruction],
+    removed: &mut Vec<usize>,
+    added: &mut Vec<usize>,
+    diffs: &mut Vec<InstructionDiff>,
+) {
+    let mut removed = removed.drain(..).peekable();
+    let mut added = added.drain(..).peekable();
+    loop {
+        match (removed.peek(), added.peek()) {
+            (Some(&i), Some(&j)) => {
+                if let Some(operands) = operand_changes(&old[i], &new[j]) {
+                    diffs.push(InstructionDiff::Changed {
+                        old_index: i,
+                        new_index: j,
+                        old: old[i].clone(),
+                        new: new[j].clone(),
+                        operands,
+                    });
+                    removed.next();
+                    added.next();
+                } else {
+                    diffs.push(InstructionDiff::Removed {
+                        index: i,
+                        inst: old[i].clone(),
+                    });
+                    removed.next();
+                }
+            }
+            (Some(&i), None) => {
+                diffs.push(InstructionDiff::Removed {
+                    index: i,
+                    inst: old[i].clone(),
+                });
+                removed.next();
+            }
+            (None, Some(&j)) => {
+                diffs.push(InstructionDiff::Added {
+                    index: j,
+                    inst: new[j].clone(),
+                });
+                added.next();
+            }
+            (None, None) => break,
+        }
+    }
+}
+
+/// Get the operands that differ between two instructions with the same
+/// mnemonic, or `None` if the mnemonics differ
+fn operand_changes(old: &Instruction, new: &Instruction) -> Option<Vec<OperandChange>> {
+    let (old_spec, old_operands) = isa::describe(old)?;
+    let (new_spec, new_operands) = isa::describe(new)?;
+    if old_spec.mnemonic != new_spec.mnemonic {
+        return None;
+    }
+
+    let changes = old_operands
+        .into_iter()
+        .zip(new_operands)
+        .enumerate()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(position, (old, new))| OperandChange { position, old, new })
+        .collect();
+    Some(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codegen::{Assembler, PaddingKind},
+        parser::Parser,
+        register::Register,
+    };
+
+    fn assemble(source: &str, padding: PaddingKind) -> Binary {
+        Assembler::new()
+            .with_padding(padding)
+            .assemble(&Parser::new(source).parse().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_identical_ignoring_padding() {
+        let source = "rdax adcl, 1.0\nwrax dacl, 0.0";
+        let diffs = diff_binaries(
+            &assemble(source, PaddingKind::Zero),
+            &assemble(source, PaddingKind::Skip),
+        )
+        .unwrap();
+        assert!(diffs.is_empty());
+    }
+
+    #[test]
+    fn test_instruction_diff() {
+        let old = assemble(
+            "rdax adcl, 1.0\nsof 0.5, 0.0\nwrax reg0, 0.0\nwrax dacl, 0.0",
+            PaddingKind::Zero,
+        );
+        let new = assemble(
+            "clr\nrdax adcl, 1.0\nsof 0.25, 0.0\nwrax dacl, 0.0",
+            PaddingKind::Zero,
+        );
+        let diffs = diff_binaries(&old, &new).unwrap();
+
+        assert_eq!(diffs.len(), 3);
+        assert_eq!(
+            diffs[0],
+            InstructionDiff::Added {
+                index: 0,
+                inst: Instruction::CLR
+            }
+        );
+        let InstructionDiff::Changed {
+            old_index,
+            new_index,
+            operands,
+            ..
+        } = &diffs[1]
+        else {
+            panic!("expected a change, got {:?}", diffs[1]);
+        };
+        assert_eq!((*old_index, *new_index), (1, 2));
+        assert_eq!(
+            operands,
+            &vec![OperandChange {
+                position: 0,
+                old: Operand::Coefficient(0.5),
+                new: Operand::Coefficient(0.25),
+            }]
+        );
+        assert_eq!(diffs[1].to_string(), "~ 1->2: SOF 0.5, 0 => SOF 0.25, 0");
+        assert_eq!(
+            diffs[2],
+            InstructionDiff::Removed {
+                index: 2,
+                inst: Instruction::WRAX {
+                    reg: Register::REG(0),
+                    coeff: 0.0
+                }
+            }
+        );
+        assert_eq!(diffs[2].to_string(), "- 2: WRAX REG0, 0");
+    }
+
+    #[test]
+    fn test_word_diff() {
+        let source = "rdax adcl, 1.0\nwrax dacl, 0.0";
+        assert!(diff_words(
+            &assemble(source, PaddingKind::Zero),
+            &assemble(source, PaddingKind::Skip)
+        )
+        .is_empty());
+
+        let old = assemble("rdax adcl, 1.0\nsof 0.5, 0.0", PaddingKind::Zero);
+        let mut new = assemble("rdax adcl, 1.0\nsof 0.25, 0.0", PaddingKind::None);
+        new.push(0xFFFF_FFFF);
+        let diffs = diff_words(&old, &new);
+
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].index, 1);
+        assert_eq!(diffs[0].old_text.as_deref(), Some("SOF 0.5, 0"));
+        assert_eq!(diffs[0].new_text.as_deref(), Some("SOF 0.25, 0"));
+        assert_eq!(
+            diffs[1],
+            WordDiff {
+                index: 2,
+                old: None,
+                new: Some(0xFFFF_FFFF),
+                old_text: None,
+                new_text: Some(".dw 0xFFFFFFFF".to_string()),
+            }
+        );
+        assert_eq!(diffs[1].to_string(), "2: (none) => FFFFFFFF .dw 0xFFFFFFFF");
+    }
+
+    #[test]
+    fn test_word_diff_exact() {
+        let source = "rdax adcl, 1.0\nwrax dacl, 0.0";
+        let nop = assemble(source, PaddingKind::Zero);
+        assert!(diff_words_exact(&nop, &nop).is_empty());
+
+        let diffs = diff_words_exact(&nop, &assemble(source, PaddingKind::Skip));
+        assert_eq!(diffs.len(), 126);
+        assert_eq!(diffs[0].to_string(), "2: 00000000 RDA 0, 0 => 00000011 NOP");
+    }
+}
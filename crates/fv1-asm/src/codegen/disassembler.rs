@@ -2,60 +2,481 @@
 //!
 //! Disassembles FV-1 binary format into assembly source code
 
+use std::collections::HashMap;
+
 use crate::{
-    ast::{Program, Statement},
-    codegen::{decoder::decode_instruction, Binary},
+    analysis::{register_roles, roles::register_access, RegisterRole},
+    ast::{Directive, Program, Statement, Value},
+    codegen::{
+        decoder::decode_instruction,
+        dsl::format_dsl,
+        encoder::{encode_register, quantize, RoundingMode},
+        Bank, Binary,
+    },
+    constants::PROGRAM_SLOTS,
     error::CodegenError,
-    instruction::{ChoMode, Instruction, SkipCondition},
+    instruction::{ChoFlags, ChoMode, Instruction, SkipCondition},
+    isa::{self, FixedFormat, Operand, OperandKind},
     register::{Lfo, Register},
 };
 
+/// Number base for integer operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    /// Decimal, e.g. `4000`
+    Decimal,
+    /// Hexadecimal with a `0x` prefix, e.g. `0x0FA0`
+    Hex,
+    /// Binary with a `%` prefix, in groups of 8 bits, e.g. `%00001111_10100000`
+    Binary,
+}
+
+/// How coefficients are printed. Every format reassembles to the same
+/// binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoefficientFormat {
+    /// Every digit of the encoded value, e.g. `0.33331299`
+    Exact,
+    /// The fewest decimals that encode to the same value, e.g. `0.3333`
+    Shortest,
+    /// At least this many decimals, more where needed to encode to the
+    /// same value
+    Decimals(usize),
+    /// The raw field bits in hex, e.g. `0x1555`
+    Hex,
+}
+
+/// Layout and letter case of disassembled source lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStyle {
+    /// Upper case, one space after the mnemonic, e.g. `RDAX ADCL, 1`
+    Standard,
+    /// Upper case with a tab after the mnemonic, as in SpinASM sources
+    Classic,
+    /// Lower case mnemonics, registers and keywords, e.g. `rdax adcl, 1`
+    Lowercase,
+    /// Upper case with mnemonics and operands padded into columns
+    Aligned,
+}
+
+/// Width of the mnemonic column in [`OutputStyle::Aligned`]
+const MNEMONIC_WIDTH: usize = 6;
+/// Width of each operand column but the last in [`OutputStyle::Aligned`]
+const OPERAND_WIDTH: usize = 12;
+
+/// Options controlling how instruction operands are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Base for AND, OR and XOR masks
+    pub mask_radix: Radix,
+    /// Base for delay memory addresses
+    pub address_radix: Radix,
+    /// Print coefficients that encode exactly like a simple fraction
+    /// (1/2, 1/3, 3/4, ...) as that fraction
+    pub fractions: bool,
+    /// Format of other coefficients
+    pub coefficients: CoefficientFormat,
+    /// Print registers by name (POT0, SIN0_RATE, REG5) rather than by
+    /// their numeric address, which reassembles only in SpinASM
+    /// compatibility mode
+    pub symbolic_names: bool,
+    /// Layout and letter case of each line
+    pub style: OutputStyle,
+}
+
+impl FormatOptions {
+    /// Create the default options: hex masks, decimal addresses, decimal
+    /// coefficients and register names
+    pub fn new() -> Self {
+        Self {
+            mask_radix: Radix::Hex,
+            address_radix: Radix::Decimal,
+            fractions: false,
+            coefficients: CoefficientFormat::Exact,
+            symbolic_names: true,
+            style: OutputStyle::Standard,
+        }
+    }
+
+    /// Apply the letter case of the style to a mnemonic or keyword
+    fn keyword(&self, text: &str) -> String {
+        match self.style {
+            OutputStyle::Lowercase => text.to_lowercase(),
+            _ => text.to_string(),
+        }
+    }
+
+    /// Lay out a mnemonic and its operands as the style asks
+    fn layout(&self, mnemonic: &str, operands: &[String]) -> String {
+        let mnemonic = self.keyword(mnemonic);
+        if operands.is_empty() {
+            return mnemonic;
+        }
+        match self.style {
+            OutputStyle::Standard | OutputStyle::Lowercase => {
+                format!("{} {}", mnemonic, operands.join(", "))
+            }
+            OutputStyle::Classic => format!("{}\t{}", mnemonic, operands.join(", ")),
+            OutputStyle::Aligned => {
+                let mut line = format!("{:<width$}", mnemonic, width = MNEMONIC_WIDTH);
+                let (last, rest) = operands.split_last().expect("operands are not empty");
+                for operand in rest {
+                    line.push_str(&format!(
+                        "{:<width$}",
+                        format!("{},", operand),
+                        width = OPERAND_WIDTH
+                    ));
+                }
+                line.push_str(last);
+                line
+            }
+        }
+    }
+
+    /// Format a register by name or address, as the options ask
+    fn register(&self, reg: &Register) -> String {
+        match (self.symbolic_names, encode_register(reg)) {
+            (false, Ok(addr)) => addr.to_string(),
+            _ => self.keyword(&format_register(reg)),
+        }
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// FV-1 program disassembler
 pub struct Disassembler {
     strip_nops: bool,
+    register_roles: bool,
+    color: bool,
+    infer_symbols: bool,
+    lenient: bool,
+    format: FormatOptions,
 }
 
 impl Disassembler {
     /// Create a new disassembler
     pub fn new() -> Self {
-        Self { strip_nops: true }
+        Self {
+            strip_nops: true,
+            register_roles: false,
+            color: false,
+            infer_symbols: false,
+            lenient: false,
+            format: FormatOptions::new(),
+        }
     }
 
-    /// Control whether to strip trailing NOPs
+    /// Control whether to strip trailing all-zero padding words
     pub fn with_strip_nops(mut self, strip: bool) -> Self {
         self.strip_nops = strip;
         self
     }
 
+    /// Control whether to tag register operands with their role
+    /// (input, output, pot, state, temp, control) as trailing comments
+    pub fn with_register_roles(mut self, enable: bool) -> Self {
+        self.register_roles = enable;
+        self
+    }
+
+    /// Control whether to colorize register operands by role using ANSI
+    /// escape codes (for terminal display; the output no longer reassembles)
+    pub fn with_color(mut self, enable: bool) -> Self {
+        self.color = enable;
+        self
+    }
+
+    /// Control whether to guess `MEM` regions from the delay addresses the
+    /// program uses and `EQU` names for its registers from their roles,
+    /// so operands print as `dly#` or `state0` instead of literal values
+    pub fn with_inferred_symbols(mut self, enable: bool) -> Self {
+        self.infer_symbols = enable;
+        self
+    }
+
+    /// Control whether words that do not decode are kept as `.dw` data
+    /// with a warning comment instead of failing the disassembly, for
+    /// inspecting corrupted dumps
+    pub fn with_lenient(mut self, enable: bool) -> Self {
+        self.lenient = enable;
+        self
+    }
+
+    /// Set the base used for AND, OR and XOR masks
+    pub fn with_mask_radix(mut self, radix: Radix) -> Self {
+        self.format.mask_radix = radix;
+        self
+    }
+
+    /// Set the base used for delay memory addresses
+    pub fn with_address_radix(mut self, radix: Radix) -> Self {
+        self.format.address_radix = radix;
+        self
+    }
+
+    /// Control whether to print coefficients as fractions when exact
+    pub fn with_fractions(mut self, enable: bool) -> Self {
+        self.format.fractions = enable;
+        self
+    }
+
+    /// Set how coefficients that are not printed as fractions are formatted
+    pub fn with_coefficient_format(mut self, format: CoefficientFormat) -> Self {
+        self.format.coefficients = format;
+        self
+    }
+
+    /// Set the layout and letter case of the output
+    pub fn with_style(mut self, style: OutputStyle) -> Self {
+        self.format.style = style;
+        self
+    }
+
+    /// Control whether to print registers by name or by numeric address
+    pub fn with_symbolic_names(mut self, enable: bool) -> Self {
+        self.format.symbolic_names = enable;
+        self
+    }
+
     /// Disassemble a binary into a Program
     pub fn disassemble(&self, binary: &Binary) -> Result<Program, CodegenError> {
         let mut program = Program::new();
 
         for (idx, &word) in binary.instructions().iter().enumerate() {
-            let inst = decode_instruction(word)?;
+            let inst = match decode_instruction(word) {
+                Ok(inst) => inst,
+                Err(error) if self.lenient => {
+                    let comment = format!(" WARNING: undecodable word: {}", error);
+                    program
+                        .trailing_comments
+                        .insert(program.statements.len(), comment);
+                    Instruction::RAW(word)
+                }
+                Err(error) => return Err(error),
+            };
 
-            // Skip trailing NOPs if enabled
-            if self.strip_nops && matches!(inst, Instruction::NOP) {
-                // Check if all remaining instructions are also NOPs
-                let all_nops = binary.instructions()[idx..]
+            // Skip trailing zero padding if enabled
+            if self.strip_nops
+                && binary.instructions()[idx..]
                     .iter()
-                    .all(|&w| w == 0x00000000);
-                if all_nops {
-                    break;
-                }
+                    .all(|&w| w == 0x00000000)
+            {
+                break;
             }
 
             program.add_statement(Statement::Instruction(inst));
         }
 
+        if self.infer_symbols {
+            for directive in infer_directives(&program) {
+                program.add_directive(directive);
+            }
+        }
+
         Ok(program)
     }
 
     /// Disassemble to assembly source code string
     pub fn disassemble_to_source(&self, binary: &Binary) -> Result<String, CodegenError> {
         let program = self.disassemble(binary)?;
-        Ok(format_program(&program))
+        Ok(self.format(&program))
+    }
+
+    /// Disassemble to Rust source for a function `program()` that rebuilds
+    /// the program with the `fv1-dsl` builder and instruction helpers
+    pub fn to_dsl_source(&self, binary: &Binary) -> Result<String, CodegenError> {
+        let program = self.disassemble(binary)?;
+        Ok(format_dsl(&program))
     }
+
+    /// Disassemble a 4096-byte EEPROM image into the programs in its eight
+    /// slots, by slot number. Slots holding only NOPs, or erased to all
+    /// ones, are left out.
+    pub fn disassemble_bank(&self, bytes: &[u8]) -> Result<Vec<(usize, Program)>, CodegenError> {
+        let bank = Bank::from_bytes(bytes)?;
+        let mut programs = Vec::new();
+
+        for slot in 0..PROGRAM_SLOTS {
+            let binary = bank.get(slot).expect("every slot of a read bank is filled");
+            let empty = binary
+                .instructions()
+                .iter()
+                .all(|&word| matches!(word, 0x00000000 | 0x00000011 | 0xFFFFFFFF));
+            if !empty {
+                programs.push((slot, self.disassemble(binary)?));
+            }
+        }
+
+        Ok(programs)
+    }
+
+    /// Disassemble a 4096-byte EEPROM image to one source file, with a
+    /// comment header before each program
+    pub fn disassemble_bank_to_source(&self, bytes: &[u8]) -> Result<String, CodegenError> {
+        let programs = self.disassemble_bank(bytes)?;
+        let sources: Vec<String> = programs
+            .iter()
+            .map(|(slot, program)| {
+                format!("; ==== Program {} ====\n{}", slot, self.format(program))
+            })
+            .collect();
+        Ok(sources.join("\n"))
+    }
+
+    /// Format a disassembled program with the configured options
+    fn format(&self, program: &Program) -> String {
+        if self.register_roles || self.color {
+            self.format_program_with_roles(program)
+        } else {
+            format_program_with(program, &self.format)
+        }
+    }
+
+    /// Format a program, annotating register operands with their roles
+    fn format_program_with_roles(&self, program: &Program) -> String {
+        let roles = register_roles(program);
+        let symbols = Symbols::new(&program.directives, &self.format);
+        let mut source = String::new();
+
+        for directive in &program.directives {
+            source.push_str(&format_directive(directive, &self.format));
+            source.push('\n');
+        }
+        if !program.directives.is_empty() {
+            source.push('\n');
+        }
+
+        for (index, inst) in program.instructions().into_iter().enumerate() {
+            let text = if self.color {
+                format_instruction_with(
+                    inst,
+                    &|reg| colorize_register(&symbols.register(reg), roles.get(reg).copied()),
+                    &|addr| symbols.address(addr),
+                    &self.format,
+                )
+            } else {
+                format_instruction_with(
+                    inst,
+                    &|reg| symbols.register(reg),
+                    &|addr| symbols.address(addr),
+                    &self.format,
+                )
+            };
+            source.push_str(&text);
+
+            if self.register_roles {
+                if let Some(role) = instruction_role(inst, &roles) {
+                    source.push_str(" ; ");
+                    source.push_str(role.as_str());
+                }
+            }
+            if let Some(comment) = program.trailing_comments.get(&index) {
+                source.push_str(&format!(" ;{}", comment));
+            }
+            source.push('\n');
+        }
+
+        source
+    }
+}
+
+/// Get the role of the register operand of an instruction, if any
+fn instruction_role(
+    inst: &Instruction,
+    roles: &HashMap<Register, RegisterRole>,
+) -> Option<RegisterRole> {
+    let (reg, _) = register_access(inst)?;
+    roles.get(&reg).copied()
+}
+
+/// Wrap a register name in the ANSI color for its role
+fn colorize_register(name: &str, role: Option<RegisterRole>) -> String {
+    let code = match role {
+        Some(RegisterRole::Input) => "32",
+        Some(RegisterRole::Output) => "31",
+        Some(RegisterRole::Pot) => "33",
+        Some(RegisterRole::State) => "35",
+        Some(RegisterRole::Temp) => "36",
+        Some(RegisterRole::Control) => "34",
+        None => return name.to_string(),
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, name)
+}
+
+/// Guess the `MEM` and `EQU` declarations of a disassembled program
+///
+/// Each delay address written by WRA or WRAP is taken as the start of a
+/// region that runs up to the next one, and the last region ends at the
+/// highest address accessed. Regions are declared in address order from 0,
+/// as the assembler lays them out, so the program reassembles to the same
+/// addresses. General purpose registers are named after their role, e.g.
+/// `state0` or `temp1`.
+fn infer_directives(program: &Program) -> Vec<Directive> {
+    let mut directives = Vec::new();
+    let instructions = program.instructions();
+
+    let mut starts = Vec::new();
+    let mut last = None;
+    for inst in &instructions {
+        let (addr, write) = match inst {
+            Instruction::WRA { addr, .. } | Instruction::WRAP { addr, .. } => (*addr, true),
+            Instruction::RDA { addr, .. }
+            | Instruction::CHO {
+                mode: ChoMode::RDA,
+                addr,
+                ..
+            } => (*addr, false),
+            _ => continue,
+        };
+        if write {
+            starts.push(addr);
+        }
+        last = last.max(Some(addr));
+    }
+    starts.sort_unstable();
+    starts.dedup();
+    if let Some(last) = last {
+        if starts.first() != Some(&0) {
+            starts.insert(0, 0);
+        }
+        for (i, &start) in starts.iter().enumerate() {
+            let size = match starts.get(i + 1) {
+                Some(&next) => next - start - 1,
+                None => (last - start).max(1),
+            };
+            directives.push(Directive::MemoryAllocation {
+                name: format!("dly{}", i),
+                size,
+            });
+        }
+    }
+
+    let roles = register_roles(program);
+    let mut counts: HashMap<RegisterRole, usize> = HashMap::new();
+    let mut named = Vec::new();
+    for inst in &instructions {
+        let Some((reg @ Register::REG(_), _)) = register_access(inst) else {
+            continue;
+        };
+        if named.contains(&reg) {
+            continue;
+        }
+        named.push(reg);
+        let role = roles[&reg];
+        let count = counts.entry(role).or_default();
+        directives.push(Directive::Equate {
+            name: format!("{}{}", role, count),
+            value: Value::Register(reg),
+        });
+        *count += 1;
+    }
+
+    directives
 }
 
 impl Default for Disassembler {
@@ -65,98 +486,276 @@ impl Default for Disassembler {
 }
 
 /// Format a program as assembly source code
-fn format_program(program: &Program) -> String {
+///
+/// `EQU` register aliases and `MEM` allocations in the program's directives
+/// are emitted first and used to name register and delay address operands.
+pub fn format_program(program: &Program) -> String {
+    format_program_with(program, &FormatOptions::new())
+}
+
+/// Format a program as assembly source code, printing operands according
+/// to `options`
+pub fn format_program_with(program: &Program, options: &FormatOptions) -> String {
+    let symbols = Symbols::new(&program.directives, options);
     let mut source = String::new();
 
-    for statement in &program.statements {
+    // Comments at the end of a line follow the statement or directive
+    let end_line = |source: &mut String, comment: Option<&String>| {
+        if let Some(comment) = comment {
+            source.push_str(&format!(" ;{}", comment));
+        }
+        source.push('\n');
+    };
+
+    for (i, directive) in program.directives.iter().enumerate() {
+        source.push_str(&format_directive(directive, options));
+        end_line(&mut source, program.directive_comments.get(&i));
+    }
+    if !program.directives.is_empty() && !program.statements.is_empty() {
+        source.push('\n');
+    }
+
+    // SKP instructions that targeted a label keep the label
+    let format_inst = |index: usize, inst: &Instruction| match (inst, program.skip_target(index)) {
+        (Instruction::SKP { condition, .. }, Some(label)) => {
+            let condition = options.keyword(format_skip_condition(condition));
+            options.layout("SKP", &[condition, label.to_string()])
+        }
+        _ => format_instruction_with(
+            inst,
+            &|reg| symbols.register(reg),
+            &|addr| symbols.address(addr),
+            options,
+        ),
+    };
+
+    let mut index = 0;
+    for (i, statement) in program.statements.iter().enumerate() {
         match statement {
             Statement::Instruction(inst) => {
-                source.push_str(&format_instruction(inst));
-                source.push('\n');
+                source.push_str(&format_inst(index, inst));
+                index += 1;
             }
             Statement::Label(label) => {
                 source.push_str(label);
-                source.push_str(":\n");
+                source.push(':');
             }
             Statement::LabeledInstruction { label, instruction } => {
                 source.push_str(label);
                 source.push_str(": ");
-                source.push_str(&format_instruction(instruction));
-                source.push('\n');
+                source.push_str(&format_inst(index, instruction));
+                index += 1;
+            }
+            Statement::Comment(comment) => {
+                source.push_str(&format!(";{}\n", comment));
+                continue;
             }
         }
+        end_line(&mut source, program.trailing_comments.get(&i));
     }
 
     source
 }
 
-/// Format a single instruction as assembly text
-fn format_instruction(inst: &Instruction) -> String {
-    match inst {
-        Instruction::RDAX { reg, coeff } => format!("RDAX {}, {}", format_register(reg), coeff),
-        Instruction::RDA { addr, coeff } => format!("RDA {}, {}", addr, coeff),
-        Instruction::RMPA { coeff } => format!("RMPA {}", coeff),
-        Instruction::WRAX { reg, coeff } => format!("WRAX {}, {}", format_register(reg), coeff),
-        Instruction::WRA { addr, coeff } => format!("WRA {}, {}", addr, coeff),
-        Instruction::WRAP { addr, coeff } => format!("WRAP {}, {}", addr, coeff),
-        Instruction::MULX { reg } => format!("MULX {}", format_register(reg)),
-        Instruction::RDFX { reg, coeff } => format!("RDFX {}, {}", format_register(reg), coeff),
-        Instruction::RDFX2 { reg, coeff } => format!("RDFX2 {}, {}", format_register(reg), coeff),
-        Instruction::LDAX { reg } => format!("LDAX {}", format_register(reg)),
-        Instruction::ABSA => "ABSA".to_string(),
-        Instruction::SOF { coeff, offset } => format!("SOF {}, {}", coeff, offset),
-        Instruction::AND { mask } => format!("AND 0x{:06X}", mask),
-        Instruction::OR { mask } => format!("OR 0x{:06X}", mask),
-        Instruction::XOR { mask } => format!("XOR 0x{:06X}", mask),
-        Instruction::SHL => "SHL".to_string(),
-        Instruction::SHR => "SHR".to_string(),
-        Instruction::CLR => "CLR".to_string(),
-        Instruction::NOP => "NOP".to_string(),
-        Instruction::EXP { coeff, offset } => format!("EXP {}, {}", coeff, offset),
-        Instruction::LOG { coeff, offset } => format!("LOG {}, {}", coeff, offset),
-        Instruction::SKP { condition, offset } => {
-            format!("SKP {}, {}", format_skip_condition(condition), offset)
-        }
-        Instruction::WLDS {
-            lfo,
-            freq,
-            amplitude,
-        } => format!("WLDS {}, {}, {}", format_lfo(lfo), freq, amplitude),
-        Instruction::JAM { lfo } => format!("JAM {}", format_lfo(lfo)),
-        Instruction::CHO {
-            mode,
-            lfo,
-            flags,
-            addr,
-        } => {
-            let mut parts: Vec<String> = vec![
-                format_cho_mode(mode).to_string(),
-                format_lfo(lfo).to_string(),
-            ];
-            if flags.rptr2 {
-                parts.push("RPTR2".to_string());
-            }
-            if flags.na {
-                parts.push("NA".to_string());
-            }
-            if flags.compc {
-                parts.push("COMPC".to_string());
-            }
-            if flags.compa {
-                parts.push("COMPA".to_string());
-            }
-            if flags.rptr2_select {
-                parts.push("RPTR2_SEL".to_string());
-            }
-            parts.push(addr.to_string());
-            format!("CHO {}", parts.join(", "))
-        }
-    }
-}
-
-fn format_register(reg: &Register) -> String {
+/// Register and delay memory names declared by a program's directives
+struct Symbols<'a> {
+    registers: HashMap<Register, &'a str>,
+    /// Memory regions as (name, start address, size)
+    memory: Vec<(&'a str, u16, u16)>,
+    options: &'a FormatOptions,
+}
+
+impl<'a> Symbols<'a> {
+    fn new(directives: &'a [Directive], options: &'a FormatOptions) -> Self {
+        let mut registers = HashMap::new();
+        let mut memory = Vec::new();
+        let mut next_addr = 0u32;
+
+        for directive in directives {
+            match directive {
+                Directive::Equate {
+                    name,
+                    value: Value::Register(reg),
+                } => {
+                    registers.entry(*reg).or_insert(name.as_str());
+                }
+                Directive::MemoryAllocation { name, size } => {
+                    // SpinASM allocates size + 1 locations so that name# is
+                    // inside the region
+                    memory.push((name.as_str(), next_addr as u16, *size));
+                    next_addr += *size as u32 + 1;
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            registers,
+            memory,
+            options,
+        }
+    }
+
+    fn register(&self, reg: &Register) -> String {
+        match self.registers.get(reg) {
+            Some(name) => name.to_string(),
+            None => self.options.register(reg),
+        }
+    }
+
+    fn address(&self, addr: u16) -> String {
+        for &(name, start, size) in &self.memory {
+            if addr == start {
+                return name.to_string();
+            }
+            if addr as u32 == start as u32 + size as u32 {
+                return format!("{}#", name);
+            }
+            if addr as u32 == start as u32 + size as u32 / 2 {
+                return format!("{}^", name);
+            }
+        }
+        format_integer(addr as u32, 16, self.options.address_radix)
+    }
+}
+
+/// Format a single instruction as assembly text with the default options
+pub fn format_instruction(inst: &Instruction) -> String {
+    let options = FormatOptions::new();
+    format_instruction_with(
+        inst,
+        &|reg| options.register(reg),
+        &|addr| format_integer(addr as u32, 16, options.address_radix),
+        &options,
+    )
+}
+
+/// Format a directive as assembly text
+fn format_directive(directive: &Directive, options: &FormatOptions) -> String {
+    match directive {
+        Directive::Equate { name, value } => {
+            let value = match value {
+                Value::Float(f) => f.to_string(),
+                Value::Integer(i) => i.to_string(),
+                Value::Identifier(id) => id.clone(),
+                Value::Register(reg) => options.keyword(&format_register(reg)),
+            };
+            format!("{} {}, {}", options.keyword("EQU"), name, value)
+        }
+        Directive::MemoryAllocation { name, size } => {
+            format!("{} {} {}", options.keyword("MEM"), name, size)
+        }
+        Directive::SpinAsm { version } => format!("{} {}", options.keyword("SPINASM"), version),
+    }
+}
+
+/// Format a single instruction, using `format_register` for register operands
+/// and `format_address` for delay address operands
+fn format_instruction_with(
+    inst: &Instruction,
+    format_register: &dyn Fn(&Register) -> String,
+    format_address: &dyn Fn(u16) -> String,
+    options: &FormatOptions,
+) -> String {
+    let Some((spec, operands)) = isa::describe(inst) else {
+        let Instruction::RAW(word) = inst else {
+            unreachable!("{:?} is missing from the instruction table", inst)
+        };
+        return format!(".dw 0x{:08X}", word);
+    };
+
+    let operands: Vec<String> = spec
+        .operands
+        .iter()
+        .zip(&operands)
+        .map(|(field, operand)| match operand {
+            Operand::Register(reg) => format_register(reg),
+            Operand::Address(addr) => format_address(*addr),
+            Operand::Coefficient(value) => {
+                let OperandKind::Coefficient(format) = field.kind else {
+                    unreachable!("coefficient in a {:?} field", field.kind)
+                };
+                format_coefficient(*value, format, options)
+            }
+            Operand::Mask(mask) => format_integer(*mask, 24, options.mask_radix),
+            Operand::SkipCondition(condition) => options.keyword(format_skip_condition(condition)),
+            Operand::SkipOffset(offset) => offset.to_string(),
+            Operand::Lfo(lfo) => options.keyword(format_lfo(lfo)),
+            Operand::Integer(value) => value.to_string(),
+            Operand::ChoMode(mode) => options.keyword(format_cho_mode(mode)),
+            Operand::ChoFlags(flags) => options.keyword(&format_cho_flags(flags)),
+        })
+        .collect();
+
+    options.layout(spec.mnemonic, &operands)
+}
+
+/// Format an integer operand of `bits` bits in the given base
+fn format_integer(value: u32, bits: usize, radix: Radix) -> String {
+    match radix {
+        Radix::Decimal => value.to_string(),
+        Radix::Hex => format!("0x{:0width$X}", value, width = bits.div_ceil(4)),
+        Radix::Binary => {
+            let digits = format!("{:0width$b}", value, width = bits);
+            let groups: Vec<&str> = digits
+                .as_bytes()
+                .rchunks(8)
+                .rev()
+                .map(|group| std::str::from_utf8(group).unwrap())
+                .collect();
+            format!("%{}", groups.join("_"))
+        }
+    }
+}
+
+/// Denominators tried when printing coefficients as fractions
+const FRACTION_DENOMINATORS: [i32; 6] = [2, 3, 4, 5, 6, 8];
+
+/// Format a coefficient of a `format` field, as a fraction when
+/// `options.fractions` is set and one encodes to exactly the same value
+fn format_coefficient(value: f32, format: FixedFormat, options: &FormatOptions) -> String {
+    let encoded = quantize(value, format, RoundingMode::Nearest);
+    let encodes_same = |text: &str| {
+        text.parse::<f32>()
+            .is_ok_and(|parsed| quantize(parsed, format, RoundingMode::Nearest) == encoded)
+    };
+
+    if options.fractions {
+        for denominator in FRACTION_DENOMINATORS {
+            let numerator = (value * denominator as f32).round() as i32;
+            if numerator % denominator != 0
+                && numerator.abs() < 2 * denominator
+                && encodes_same(&(numerator as f32 / denominator as f32).to_string())
+            {
+                return format!("{}/{}", numerator, denominator);
+            }
+        }
+    }
+
+    let min_decimals = match options.coefficients {
+        CoefficientFormat::Exact => return value.to_string(),
+        CoefficientFormat::Hex => {
+            let width = format.width();
+            let bits = (value * (1u32 << format.frac_bits()) as f32).round() as i32;
+            let bits = bits as u32 & ((1 << width) - 1);
+            return format!("0x{:0digits$X}", bits, digits = width.div_ceil(4) as usize);
+        }
+        CoefficientFormat::Shortest => 0,
+        CoefficientFormat::Decimals(decimals) => decimals,
+    };
+    // f32 needs at most 9 significant digits to round-trip
+    (min_decimals..=min_decimals.max(9))
+        .map(|decimals| format!("{:.*}", decimals, value))
+        .find(|text| encodes_same(text))
+        .unwrap_or_else(|| value.to_string())
+}
+
+pub(crate) fn format_register(reg: &Register) -> String {
     match reg {
         Register::ACC => "ACC".to_string(),
+        Register::POT0 => "POT0".to_string(),
+        Register::POT1 => "POT1".to_string(),
+        Register::POT2 => "POT2".to_string(),
         Register::ADCL => "ADCL".to_string(),
         Register::ADCR => "ADCR".to_string(),
         Register::DACL => "DACL".to_string(),
@@ -175,7 +774,7 @@ fn format_register(reg: &Register) -> String {
     }
 }
 
-fn format_skip_condition(cond: &SkipCondition) -> &str {
+pub(crate) fn format_skip_condition(cond: &SkipCondition) -> &str {
     match cond {
         SkipCondition::RUN => "RUN",
         SkipCondition::NEG => "NEG",
@@ -185,7 +784,7 @@ fn format_skip_condition(cond: &SkipCondition) -> &str {
     }
 }
 
-fn format_lfo(lfo: &Lfo) -> &str {
+pub(crate) fn format_lfo(lfo: &Lfo) -> &str {
     match lfo {
         Lfo::SIN0 => "SIN0",
         Lfo::SIN1 => "SIN1",
@@ -194,7 +793,27 @@ fn format_lfo(lfo: &Lfo) -> &str {
     }
 }
 
-fn format_cho_mode(mode: &ChoMode) -> &str {
+pub(crate) fn format_cho_flags(flags: &ChoFlags) -> String {
+    let names: Vec<&str> = [
+        (flags.cos, "COS"),
+        (flags.reg, "REG"),
+        (flags.compc, "COMPC"),
+        (flags.compa, "COMPA"),
+        (flags.rptr2, "RPTR2"),
+        (flags.na, "NA"),
+    ]
+    .into_iter()
+    .filter_map(|(set, name)| set.then_some(name))
+    .collect();
+
+    if names.is_empty() {
+        "SIN".to_string()
+    } else {
+        names.join("|")
+    }
+}
+
+pub(crate) fn format_cho_mode(mode: &ChoMode) -> &str {
     match mode {
         ChoMode::RDA => "RDA",
         ChoMode::SOF => "SOF",
@@ -239,4 +858,411 @@ mod tests {
             disassembled.instructions().len()
         );
     }
+
+    #[test]
+    fn test_roundtrip_zero_words() {
+        // Zero words inside a program are RDA 0, 0 and must reassemble as
+        // zeros, while trailing zero padding is dropped
+        let source = "RDAX ADCL, 1\nRDA 0, 0\nWRAX DACL, 0\nRDA 0, 0\n";
+        let binary = Assembler::new()
+            .assemble(&Parser::new(source).parse().unwrap())
+            .unwrap();
+        let text = Disassembler::new().disassemble_to_source(&binary).unwrap();
+        assert_eq!(text, "RDAX ADCL, 1\nRDA 0, 0\nWRAX DACL, 0\n");
+
+        let reassembled = Assembler::new()
+            .assemble(&Parser::new(&text).parse().unwrap())
+            .unwrap();
+        assert_eq!(reassembled.to_bytes(), binary.to_bytes());
+    }
+
+    #[test]
+    fn test_cho_sof_roundtrip() {
+        let source = "CHO SOF, SIN0, REG|COMPC, -0.5\nCHO SOF, RMP1, NA, 0.25\n";
+        let binary = Assembler::new()
+            .assemble(&Parser::new(source).parse().unwrap())
+            .unwrap();
+        let text = Disassembler::new().disassemble_to_source(&binary).unwrap();
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn test_disassemble_with_register_roles() {
+        let source = "RDAX ADCL, 1.0\nRDFX REG0, 0.5\nWRAX REG0, 1.0\nWRAX DACL, 0.0\n";
+        let program = Parser::new(source).parse().unwrap();
+        let binary = Assembler::new().assemble(&program).unwrap();
+
+        let disassembler = Disassembler::new().with_register_roles(true);
+        let text = disassembler.disassemble_to_source(&binary).unwrap();
+
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].starts_with("RDAX ADCL") && lines[0].ends_with("; input"));
+        assert_eq!(lines[1], "RDFX REG0, 0.5 ; state");
+        assert_eq!(lines[3], "WRAX DACL, 0 ; output");
+
+        // Tags are comments, so the listing still reassembles
+        let reparsed = Parser::new(&text).parse().unwrap();
+        assert_eq!(reparsed.instructions().len(), 4);
+    }
+
+    #[test]
+    fn test_disassemble_with_color() {
+        let source = "RDAX ADCL, 1.0\nWRAX DACL, 0.0\n";
+        let program = Parser::new(source).parse().unwrap();
+        let binary = Assembler::new().assemble(&program).unwrap();
+
+        let text = Disassembler::new()
+            .with_color(true)
+            .disassemble_to_source(&binary)
+            .unwrap();
+
+        assert!(text.contains("\x1b[32mADCL\x1b[0m"));
+        assert!(text.contains("\x1b[31mDACL\x1b[0m"));
+    }
+
+    #[test]
+    fn test_format_program_with_symbols() {
+        let mut program = Program::new();
+        program.add_directive(Directive::Equate {
+            name: "lp".to_string(),
+            value: Value::Register(Register::REG(0)),
+        });
+        program.add_directive(Directive::MemoryAllocation {
+            name: "dly".to_string(),
+            size: 1000,
+        });
+        program.add_directive(Directive::MemoryAllocation {
+            name: "ap".to_string(),
+            size: 200,
+        });
+        for inst in [
+            Instruction::WRAX {
+                reg: Register::REG(0),
+                coeff: 0.0,
+            },
+            Instruction::WRA {
+                addr: 0,
+                coeff: 0.0,
+            },
+            Instruction::RDA {
+                addr: 1000,
+                coeff: 0.5,
+            },
+            Instruction::RDA {
+                addr: 1101,
+                coeff: 0.5,
+            },
+            Instruction::RDA {
+                addr: 7,
+                coeff: 0.5,
+            },
+        ] {
+            program.add_statement(Statement::Instruction(inst));
+        }
+
+        let source = format_program(&program);
+        assert_eq!(
+            source,
+            "EQU lp, REG0\nMEM dly 1000\nMEM ap 200\n\n\
+             WRAX lp, 0\nWRA dly, 0\nRDA dly#, 0.5\nRDA ap^, 0.5\nRDA 7, 0.5\n"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_cho_flags_roundtrip() {
+        let source = "CHO RDAL, SIN0, COS|REG, 0\nCHO RDA, RMP1, REG|COMPC|NA, 400\nCHO RDA, SIN1, SIN, 12\n";
+        let mut parser = Parser::new(source);
+        let program = parser.parse().unwrap();
+        let binary = Assembler::new().assemble(&program).unwrap();
+
+        let text = Disassembler::new().disassemble_to_source(&binary).unwrap();
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn test_format_raw_word() {
+        let mut program = Program::new();
+        program.add_statement(Statement::Instruction(Instruction::RAW(0xDEADBEEF)));
+
+        let source = format_program(&program);
+        assert_eq!(source, ".dw 0xDEADBEEF\n");
+
+        let reparsed = Parser::new(&source).parse().unwrap();
+        assert_eq!(reparsed.instructions(), program.instructions());
+    }
+
+    #[test]
+    fn test_lenient_disassembly() {
+        let mut binary = Binary::new();
+        for word in [0x0000_0284, 0x0000_F284, 0x4000_0009, 0x0000_02C6] {
+            binary.push(word);
+        }
+        assert!(matches!(
+            Disassembler::new().disassemble(&binary),
+            Err(CodegenError::ReservedBits { .. })
+        ));
+
+        let source = Disassembler::new()
+            .with_lenient(true)
+            .disassemble_to_source(&binary)
+            .unwrap();
+        assert_eq!(
+            source,
+            "RDAX ADCL, 0\n\
+             .dw 0x0000F284 ; WARNING: undecodable word: RDAX instruction 0x0000F284 sets reserved bits\n\
+             .dw 0x40000009 ; WARNING: undecodable word: invalid opcode: 0x09\n\
+             WRAX DACL, 0\n"
+        );
+        let reparsed = Parser::new(&source).parse().unwrap();
+        assert_eq!(reparsed.instructions().len(), 4);
+
+        let annotated = Disassembler::new()
+            .with_lenient(true)
+            .with_register_roles(true)
+            .disassemble_to_source(&binary)
+            .unwrap();
+        assert!(annotated.contains(".dw 0x40000009 ; WARNING: undecodable word"));
+    }
+
+    #[test]
+    fn test_format_options() {
+        let source = "RDA 4000, 0.5\nAND 0x7FFF00\nSOF 1/3, -0.75\nRDAX REG0, 0.3\n";
+        let program = Parser::new(source).parse().unwrap();
+        let binary = Assembler::new().assemble(&program).unwrap();
+
+        let disassembled = Disassembler::new()
+            .with_mask_radix(Radix::Binary)
+            .with_address_radix(Radix::Hex)
+            .with_fractions(true)
+            .disassemble_to_source(&binary)
+            .unwrap();
+
+        assert_eq!(
+            disassembled,
+            "RDA 0x0FA0, 1/2\nAND %01111111_11111111_00000000\nSOF 1/3, -3/4\nRDAX REG0, 0.2999878\n"
+        );
+
+        // The formatted source assembles to the same binary
+        let reparsed = Parser::new(&disassembled).parse().unwrap();
+        let reassembled = Assembler::new().assemble(&reparsed).unwrap();
+        assert_eq!(reassembled.instructions(), binary.instructions());
+    }
+
+    #[test]
+    fn test_output_styles() {
+        let source = "\
+mem dly 100
+skp run, start
+wlds sin0, 12, 100
+start: rda dly#, 0.5
+cho rda, sin0, reg|compc, dly
+clr
+";
+        let program = Parser::new(source).parse().unwrap();
+        let format = |style| {
+            let options = FormatOptions {
+                style,
+                ..FormatOptions::new()
+            };
+            format_program_with(&program, &options)
+        };
+
+        assert_eq!(
+            format(OutputStyle::Classic),
+            "MEM dly 100\n\nSKP\tRUN, start\nWLDS\tSIN0, 12, 100\nstart: RDA\tdly#, 0.5\n\
+             CHO\tRDA, SIN0, REG|COMPC, dly\nCLR\n"
+        );
+        assert_eq!(
+            format(OutputStyle::Lowercase),
+            "mem dly 100\n\nskp run, start\nwlds sin0, 12, 100\nstart: rda dly#, 0.5\n\
+             cho rda, sin0, reg|compc, dly\nclr\n"
+        );
+        assert_eq!(
+            format(OutputStyle::Aligned),
+            "MEM dly 100\n\nSKP   RUN,        start\nWLDS  SIN0,       12,         100\n\
+             start: RDA   dly#,       0.5\nCHO   RDA,        SIN0,       REG|COMPC,  dly\nCLR\n"
+        );
+
+        // Every style reassembles to the same program
+        for style in [
+            OutputStyle::Classic,
+            OutputStyle::Lowercase,
+            OutputStyle::Aligned,
+        ] {
+            let reparsed = Parser::new(&format(style)).parse().unwrap();
+            assert_eq!(reparsed.instructions(), program.instructions());
+        }
+
+        let binary = Assembler::new().assemble(&program).unwrap();
+        let text = Disassembler::new()
+            .with_style(OutputStyle::Lowercase)
+            .disassemble_to_source(&binary)
+            .unwrap();
+        assert!(text.starts_with("skp run, 1\nwlds sin0, 12, 100\n"));
+    }
+
+    #[test]
+    fn test_inferred_symbols() {
+        let source = "\
+mem dly 4000
+mem ap 100
+rdax adcl, 1.0
+rda dly#, 0.5
+rda dly^, 0.5
+wra dly, 0.0
+rdax reg4, 0.5
+wrax reg4, 0.0
+wrax reg1, 1.0
+rda ap#, 0.5
+wrap ap, -0.5
+wrax dacl, 0.0
+";
+        let binary = Assembler::new()
+            .assemble(&Parser::new(source).parse().unwrap())
+            .unwrap();
+        let disassembler = Disassembler::new().with_inferred_symbols(true);
+        let text = disassembler.disassemble_to_source(&binary).unwrap();
+        assert!(text.starts_with(
+            "MEM dly0 4000\nMEM dly1 100\nEQU state0, REG4\nEQU temp0, REG1\n\nRDAX ADCL, 1\n"
+        ));
+        assert!(text.contains("RDA dly0#, 0.5\nRDA dly0^, 0.5\nWRA dly0, 0\nRDAX state0, 0.5"));
+        assert!(text.contains("WRAX temp0, 1\nRDA dly1#, 0.5\nWRAP dly1, -0.5"));
+
+        let reassembled = Assembler::new()
+            .assemble(&Parser::new(&text).parse().unwrap())
+            .unwrap();
+        assert_eq!(reassembled.instructions(), binary.instructions());
+
+        // Literal addresses remain the default
+        let literal = Disassembler::new().disassemble_to_source(&binary).unwrap();
+        assert!(literal.starts_with("RDAX ADCL, 1\nRDA 4000, 0.5"));
+    }
+
+    #[test]
+    fn test_disassemble_bank() {
+        let programs = ["rdax adcl, 1.0\nwrax dacl, 0.0", "sof 0.5, 0.0"]
+            .map(|source| Parser::new(source).parse().unwrap());
+        let mut bank = Bank::from_programs(&programs, &Assembler::new()).unwrap();
+        bank.clear(1);
+        bank.set(5, Assembler::new().assemble(&programs[1]).unwrap())
+            .unwrap();
+        let mut bytes = bank.to_bytes();
+        // An erased slot reads back as all ones
+        bytes[6 * 512..7 * 512].fill(0xFF);
+
+        let disassembler = Disassembler::new();
+        let slots = disassembler.disassemble_bank(&bytes).unwrap();
+        assert_eq!(
+            slots.iter().map(|(slot, _)| *slot).collect::<Vec<_>>(),
+            vec![0, 5]
+        );
+        assert_eq!(slots[0].1.instructions().len(), 2);
+
+        let source = disassembler.disassemble_bank_to_source(&bytes).unwrap();
+        assert!(source.starts_with("; ==== Program 0 ====\nRDAX ADCL, 1\n"));
+        assert!(source.contains("\n\n; ==== Program 5 ====\nSOF 0.5, 0\n"));
+
+        assert!(matches!(
+            disassembler.disassemble_bank(&bytes[..512]),
+            Err(CodegenError::InvalidBinarySize { .. })
+        ));
+    }
+
+    #[test]
+    fn test_coefficient_formats() {
+        let source = "SOF 0.33333, -0.75\nRDAX ADCL, 0.1\nRDFX REG0, 0.001\nEXP 0.5, -0.3\n";
+        let program = Parser::new(source).parse().unwrap();
+        let binary = Assembler::new().assemble(&program).unwrap();
+
+        let format = |format| {
+            let text = Disassembler::new()
+                .with_coefficient_format(format)
+                .disassemble_to_source(&binary)
+                .unwrap();
+            // Every format reassembles to the same binary
+            let reparsed = Parser::new(&text).parse().unwrap();
+            let reassembled = Assembler::new().assemble(&reparsed).unwrap();
+            assert_eq!(
+                reassembled.instructions(),
+                binary.instructions(),
+                "{}",
+                text
+            );
+            text
+        };
+
+        assert_eq!(
+            format(CoefficientFormat::Exact),
+            "SOF 0.333313, -0.75\nRDAX ADCL, 0.099975586\nRDFX REG0, 0.0009765625\nEXP 0.5, -0.2998047\n"
+        );
+        assert_eq!(
+            format(CoefficientFormat::Shortest),
+            "SOF 0.3333, -0.75\nRDAX ADCL, 0.1\nRDFX REG0, 0.001\nEXP 0.5, -0.3\n"
+        );
+        assert_eq!(
+            format(CoefficientFormat::Decimals(3)),
+            "SOF 0.3333, -0.750\nRDAX ADCL, 0.100\nRDFX REG0, 0.001\nEXP 0.500, -0.300\n"
+        );
+        assert_eq!(
+            format(CoefficientFormat::Hex),
+            "SOF 0x1555, 0x500\nRDAX ADCL, 0x0666\nRDFX REG0, 0x0010\nEXP 0x2000, 0x6CD\n"
+        );
+    }
+
+    #[test]
+    fn test_symbolic_names() {
+        let source = "RDAX POT1, 1.0\nWRAX SIN0_RATE, 0.0\nRDAX REG5, 0.5\nWRAX DACL, 0.0\n";
+        let program = Parser::new(source).parse().unwrap();
+        let binary = Assembler::new().assemble(&program).unwrap();
+
+        let symbolic = Disassembler::new().disassemble_to_source(&binary).unwrap();
+        assert_eq!(
+            symbolic,
+            "RDAX POT1, 1\nWRAX SIN0_RATE, 0\nRDAX REG5, 0.5\nWRAX DACL, 0\n"
+        );
+
+        let numeric = Disassembler::new()
+            .with_symbolic_names(false)
+            .disassemble_to_source(&binary)
+            .unwrap();
+        assert_eq!(numeric, "RDAX 17, 1\nWRAX 0, 0\nRDAX 37, 0.5\nWRAX 22, 0\n");
+
+        // Numeric addresses reassemble in SpinASM compatibility mode
+        let reparsed = Parser::new(&numeric).spinasm_compat(true).parse().unwrap();
+        let reassembled = Assembler::new().assemble(&reparsed).unwrap();
+        assert_eq!(reassembled.instructions(), binary.instructions());
+    }
+
+    #[test]
+    fn test_format_program_keeps_skip_labels() {
+        let source = "SKP RUN, done\nCLR\ndone: WRAX DACL, 0.0\n";
+        let program = Parser::new(source).parse().unwrap();
+
+        assert_eq!(
+            format_program(&program),
+            "SKP RUN, done\nCLR\ndone: WRAX DACL, 0\n"
+        );
+    }
+
+    #[test]
+    fn test_format_program_keeps_comments() {
+        let source = "EQU gain, 0.5 ; input gain\n\
+                      ; Passthrough\n\
+                      start: ; top\n\
+                      RDAX ADCL, 1.0 ; left in\n\
+                      \n\
+                      ;   indented note\n\
+                      WRAX DACL, 0.0\n";
+        let program = Parser::new(source).with_comments(true).parse().unwrap();
+
+        assert_eq!(
+            format_program(&program),
+            "EQU gain, 0.5 ; input gain\n\n\
+             ; Passthrough\n\
+             start: ; top\n\
+             RDAX ADCL, 1 ; left in\n\
+             ;   indented note\n\
+             WRAX DACL, 0\n"
+        );
+    }
 }
@@ -0,0 +1,176 @@
+//! Rust DSL Output
+//!
+//! Formats a program as Rust source that rebuilds it with the `fv1-dsl`
+//! program builder and instruction helpers, for porting existing binaries
+//! into Rust projects.
+
+use crate::{
+    ast::Program,
+    instruction::{ChoFlags, ChoMode, Instruction, SkipCondition},
+    register::{Lfo, Register},
+};
+
+/// Format a program as a Rust function `program()` that builds it with
+/// `fv1_dsl::ProgramBuilder`
+pub fn format_dsl(program: &Program) -> String {
+    let mut source = String::from("use fv1_dsl::{prelude::*, Program};\n\n");
+    source.push_str("pub fn program() -> Program {\n");
+    source.push_str("    ProgramBuilder::new()\n");
+    for inst in program.instructions() {
+        source.push_str(&format!("        .inst({})\n", format_call(inst)));
+    }
+    source.push_str("        .build()\n");
+    source.push_str("}\n");
+    source
+}
+
+/// Format an instruction as a call to its `fv1_dsl::ops` helper
+fn format_call(inst: &Instruction) -> String {
+    match inst {
+        Instruction::RDAX { reg, coeff } => call("rdax", [register(reg), float(*coeff)]),
+        Instruction::RDA { addr, coeff } => call("rda", [addr.to_string(), float(*coeff)]),
+        Instruction::RMPA { coeff } => call("rmpa", [float(*coeff)]),
+        Instruction::WRAX { reg, coeff } => call("wrax", [register(reg), float(*coeff)]),
+        Instruction::WRHX { reg, coeff } => call("wrhx", [register(reg), float(*coeff)]),
+        Instruction::WRLX { reg, coeff } => call("wrlx", [register(reg), float(*coeff)]),
+        Instruction::WRA { addr, coeff } => call("wra", [addr.to_string(), float(*coeff)]),
+        Instruction::WRAP { addr, coeff } => call("wrap", [addr.to_string(), float(*coeff)]),
+        Instruction::MULX { reg } => call("mulx", [register(reg)]),
+        Instruction::RDFX { reg, coeff } => call("rdfx", [register(reg), float(*coeff)]),
+        Instruction::ABSA => call("absa", []),
+        Instruction::LDAX { reg } => call("ldax", [register(reg)]),
+        Instruction::RDFX2 { reg, coeff } => call("rdfx2", [register(reg), float(*coeff)]),
+        Instruction::SOF { coeff, offset } => call("sof", [float(*coeff), float(*offset)]),
+        Instruction::AND { mask } => call("and", [format!("0x{:06X}", mask)]),
+        Instruction::OR { mask } => call("or", [format!("0x{:06X}", mask)]),
+        Instruction::XOR { mask } => call("xor", [format!("0x{:06X}", mask)]),
+        Instruction::NOT => call("not", []),
+        Instruction::SHL => call("shl", []),
+        Instruction::SHR => call("shr", []),
+        Instruction::CLR => call("clr", []),
+        Instruction::NOP => call("nop", []),
+        Instruction::EXP { coeff, offset } => call("exp", [float(*coeff), float(*offset)]),
+        Instruction::LOG { coeff, offset } => call("log", [float(*coeff), float(*offset)]),
+        Instruction::SKP { condition, offset } => {
+            call("skp", [skip_condition(condition), offset.to_string()])
+        }
+        Instruction::WLDS {
+            lfo,
+            freq,
+            amplitude,
+        } => call(
+            "wlds",
+            [lfo_name(lfo), freq.to_string(), amplitude.to_string()],
+        ),
+        Instruction::JAM { lfo } => call("jam", [lfo_name(lfo)]),
+        Instruction::CHO {
+            mode,
+            lfo,
+            flags,
+            addr,
+        } => call(
+            "cho",
+            [
+                cho_mode(mode),
+                lfo_name(lfo),
+                cho_flags(flags),
+                addr.to_string(),
+            ],
+        ),
+        Instruction::CHO_SOF { lfo, flags, offset } => {
+            call("cho_sof", [lfo_name(lfo), cho_flags(flags), float(*offset)])
+        }
+        Instruction::RAW(word) => call("raw", [format!("0x{:08X}", word)]),
+    }
+}
+
+fn call<const N: usize>(name: &str, args: [String; N]) -> String {
+    format!("{}({})", name, args.join(", "))
+}
+
+/// Format a coefficient as an `f32` literal, which always has a decimal
+/// point or exponent
+fn float(value: f32) -> String {
+    format!("{:?}", value)
+}
+
+fn register(reg: &Register) -> String {
+    match reg {
+        Register::REG(n) => format!("Register::REG({})", n),
+        _ => format!("Register::{:?}", reg),
+    }
+}
+
+fn lfo_name(lfo: &Lfo) -> String {
+    format!("Lfo::{:?}", lfo)
+}
+
+fn skip_condition(condition: &SkipCondition) -> String {
+    format!("SkipCondition::{:?}", condition)
+}
+
+fn cho_mode(mode: &ChoMode) -> String {
+    format!("ChoMode::{:?}", mode)
+}
+
+fn cho_flags(flags: &ChoFlags) -> String {
+    let names: Vec<&str> = [
+        (flags.cos, "ChoFlags::COS"),
+        (flags.reg, "ChoFlags::REG"),
+        (flags.compc, "ChoFlags::COMPC"),
+        (flags.compa, "ChoFlags::COMPA"),
+        (flags.rptr2, "ChoFlags::RPTR2"),
+        (flags.na, "ChoFlags::NA"),
+    ]
+    .into_iter()
+    .filter_map(|(set, name)| set.then_some(name))
+    .collect();
+
+    if names.is_empty() {
+        "ChoFlags::default()".to_string()
+    } else {
+        format!("ChoFlags::from_bits({})", names.join(" | "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_format_dsl() {
+        let source = "\
+skp run, 1
+wlds sin0, 12, 100
+rdax adcl, 1.0
+rdax reg3, -0.5
+cho rda, sin0, reg|compc, 1000
+cho rda, sin0, 0, 1001
+cho sof, sin0, reg|compc, -0.5
+and 0x7FFF00
+wrax dacl, 0.0
+";
+        let program = Parser::new(source).parse().unwrap();
+        assert_eq!(
+            format_dsl(&program),
+            "\
+use fv1_dsl::{prelude::*, Program};
+
+pub fn program() -> Program {
+    ProgramBuilder::new()
+        .inst(skp(SkipCondition::RUN, 1))
+        .inst(wlds(Lfo::SIN0, 12, 100))
+        .inst(rdax(Register::ADCL, 1.0))
+        .inst(rdax(Register::REG(3), -0.5))
+        .inst(cho(ChoMode::RDA, Lfo::SIN0, ChoFlags::from_bits(ChoFlags::REG | ChoFlags::COMPC), 1000))
+        .inst(cho(ChoMode::RDA, Lfo::SIN0, ChoFlags::default(), 1001))
+        .inst(cho_sof(Lfo::SIN0, ChoFlags::from_bits(ChoFlags::REG | ChoFlags::COMPC), -0.5))
+        .inst(and(0x7FFF00))
+        .inst(wrax(Register::DACL, 0.0))
+        .build()
+}
+"
+        );
+    }
+}
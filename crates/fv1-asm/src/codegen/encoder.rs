@@ -1,234 +1,203 @@
 //! FV-1 Instruction Encoder
 //!
 //! Converts AST instructions to 32-bit FV-1 machine code
+//!
+//! Bit layouts follow the FV-1 datasheet: the opcode occupies the low 5 bits
+//! and each instruction packs its operands above it in a fixed-point format
+//! specific to that instruction (S1.14, S1.9, S.10 or S4.6). The layouts
+//! themselves live in the instruction table in [`crate::isa`].
 
 use crate::{
     constants::DELAY_RAM_SIZE,
     error::CodegenError,
-    instruction::{ChoFlags, ChoMode, Instruction, SkipCondition},
+    instruction::{ChoMode, Instruction, SkipCondition},
+    isa::{self, FixedFormat, InstructionSpec, Operand, OperandKind, OperandSpec},
     register::{Lfo, Register},
 };
 
-/// Encode a single instruction to 32-bit FV-1 machine code
-pub fn encode_instruction(inst: &Instruction) -> Result<u32, CodegenError> {
-    match inst {
-        // Accumulator operations
-        Instruction::RDAX { reg, coeff } => {
-            let opcode = 0b00000_u32 << 27;
-            let reg_bits = encode_register(reg)? << 21;
-            let coeff_bits = (encode_s114(*coeff)? & 0x7FFF) << 6;
-            Ok(opcode | reg_bits | coeff_bits)
-        }
-
-        Instruction::RDA { addr, coeff } => {
-            let opcode = 0b00001_u32 << 27;
-            let addr_bits = encode_address(*addr)? << 11;
-            let coeff_bits = encode_s114(*coeff)? & 0x7FF;
-            Ok(opcode | addr_bits | coeff_bits)
-        }
-
-        Instruction::RMPA { coeff } => {
-            let opcode = 0b00010_u32 << 27;
-            let coeff_bits = encode_s114(*coeff)? & 0x7FFFFF;
-            Ok(opcode | coeff_bits)
-        }
-
-        Instruction::WRAX { reg, coeff } => {
-            let opcode = 0b00110_u32 << 27;
-            let reg_bits = encode_register(reg)? << 21;
-            let coeff_bits = (encode_s114(*coeff)? & 0x7FFF) << 6;
-            Ok(opcode | reg_bits | coeff_bits)
-        }
-
-        Instruction::WRA { addr, coeff } => {
-            let opcode = 0b00111_u32 << 27;
-            let addr_bits = encode_address(*addr)? << 11;
-            let coeff_bits = encode_s114(*coeff)? & 0x7FF;
-            Ok(opcode | addr_bits | coeff_bits)
-        }
-
-        Instruction::WRAP { addr, coeff } => {
-            let opcode = 0b01000_u32 << 27;
-            let addr_bits = encode_address(*addr)? << 11;
-            let coeff_bits = encode_s114(*coeff)? & 0x7FF;
-            Ok(opcode | addr_bits | coeff_bits)
-        }
-
-        // Mathematical operations
-        Instruction::MULX { reg } => {
-            let opcode = 0b01010_u32 << 27;
-            let reg_bits = encode_register(reg)? << 21;
-            Ok(opcode | reg_bits)
-        }
-
-        Instruction::RDFX { reg, coeff } => {
-            let opcode = 0b01001_u32 << 27;
-            let reg_bits = encode_register(reg)? << 21;
-            let coeff_bits = (encode_s114(*coeff)? & 0x7FFF) << 6;
-            Ok(opcode | reg_bits | coeff_bits)
-        }
-
-        Instruction::RDFX2 { reg, coeff } => {
-            let opcode = 0b01100_u32 << 27;
-            let reg_bits = encode_register(reg)? << 21;
-            let coeff_bits = (encode_s114(*coeff)? & 0x7FFF) << 6;
-            Ok(opcode | reg_bits | coeff_bits)
-        }
-
-        Instruction::LDAX { reg } => {
-            let opcode = 0b00101_u32 << 27;
-            let reg_bits = encode_register(reg)? << 21;
-            Ok(opcode | reg_bits)
-        }
-
-        Instruction::ABSA => {
-            let opcode = 0b01011_u32 << 27;
-            Ok(opcode)
-        }
-
-        // Logic and control
-        Instruction::SOF { coeff, offset } => {
-            let opcode = 0b01101_u32 << 27;
-            let coeff_bits = (encode_s114(*coeff)? & 0xFFFF) << 11;
-            let offset_bits = encode_s10(*offset)? & 0x7FF;
-            Ok(opcode | coeff_bits | offset_bits)
-        }
-
-        Instruction::AND { mask } => {
-            let opcode = 0b01111_u32 << 27;
-            let mask_bits = mask & 0xFFFFFF;
-            Ok(opcode | mask_bits)
-        }
-
-        Instruction::OR { mask } => {
-            let opcode = 0b10000_u32 << 27;
-            let mask_bits = mask & 0xFFFFFF;
-            Ok(opcode | mask_bits)
-        }
-
-        Instruction::XOR { mask } => {
-            let opcode = 0b10001_u32 << 27;
-            let mask_bits = mask & 0xFFFFFF;
-            Ok(opcode | mask_bits)
-        }
-
-        Instruction::SHL => {
-            let opcode = 0b10010_u32 << 27;
-            Ok(opcode)
-        }
-
-        Instruction::SHR => {
-            let opcode = 0b10011_u32 << 27;
-            Ok(opcode)
-        }
-
-        Instruction::CLR => {
-            let opcode = 0b01110_u32 << 27;
-            Ok(opcode)
-        }
+/// How coefficients are converted to fixed point when they fall between two
+/// representable values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// Round to the nearest value, halfway cases away from zero
+    #[default]
+    Nearest,
+    /// Round toward zero
+    Truncate,
+    /// Round toward negative infinity
+    Floor,
+}
 
-        Instruction::NOP => {
-            // NOP is encoded as a no-op instruction
-            Ok(0x00000000)
+impl RoundingMode {
+    /// Round a scaled coefficient to an integer
+    fn apply(&self, value: f32) -> f32 {
+        match self {
+            RoundingMode::Nearest => value.round(),
+            RoundingMode::Truncate => value.trunc(),
+            RoundingMode::Floor => value.floor(),
         }
+    }
+}
 
-        // Conversion operations
-        Instruction::EXP { coeff, offset } => {
-            let opcode = 0b10100_u32 << 27;
-            let coeff_bits = (encode_s114(*coeff)? & 0xFFFF) << 11;
-            let offset_bits = encode_s10(*offset)? & 0x7FF;
-            Ok(opcode | coeff_bits | offset_bits)
-        }
+/// Encode a single instruction to 32-bit FV-1 machine code
+///
+/// Operands are packed into the fields the instruction table describes.
+/// Coefficients are rounded to the nearest representable value.
+pub fn encode_instruction(inst: &Instruction) -> Result<u32, CodegenError> {
+    encode_instruction_with(inst, RoundingMode::Nearest)
+}
 
-        Instruction::LOG { coeff, offset } => {
-            let opcode = 0b10101_u32 << 27;
-            let coeff_bits = (encode_s114(*coeff)? & 0xFFFF) << 11;
-            let offset_bits = encode_s10(*offset)? & 0x7FF;
-            Ok(opcode | coeff_bits | offset_bits)
-        }
+/// Encode a single instruction, rounding coefficients with `rounding`
+pub fn encode_instruction_with(
+    inst: &Instruction,
+    rounding: RoundingMode,
+) -> Result<u32, CodegenError> {
+    let Some((spec, operands)) = isa::describe(inst) else {
+        // RAW is the only instruction without a description
+        let Instruction::RAW(word) = inst else {
+            unreachable!("{:?} is missing from the instruction table", inst)
+        };
+        return Ok(*word);
+    };
+
+    spec.operands.iter().zip(&operands).try_fold(
+        spec.opcode | spec.fixed,
+        |word, (field, operand)| {
+            let bits = encode_operand(spec, field, operand, rounding)?;
+            Ok(word | ((bits << field.lsb) & field.mask()))
+        },
+    )
+}
 
-        // Conditional skipping
-        Instruction::SKP { condition, offset } => {
-            let opcode = 0b10110_u32 << 27;
-            let cond_bits = encode_skip_condition(*condition) << 24;
-            let offset_bits = (*offset as u32 & 0x3F) << 18;
-            Ok(opcode | cond_bits | offset_bits)
+/// Encode an operand into the raw bits of its field
+fn encode_operand(
+    spec: &InstructionSpec,
+    field: &OperandSpec,
+    operand: &Operand,
+    rounding: RoundingMode,
+) -> Result<u32, CodegenError> {
+    match (field.kind, *operand) {
+        (OperandKind::Register, Operand::Register(reg)) => encode_register(&reg),
+        (OperandKind::Address, Operand::Address(addr)) => encode_address(addr),
+        (OperandKind::Coefficient(format), Operand::Coefficient(value)) => match format {
+            FixedFormat::S114 => encode_s114(value, rounding),
+            FixedFormat::S19 => encode_s19(value, rounding),
+            FixedFormat::S10 => encode_s10(value, rounding),
+            FixedFormat::S46 => encode_s46(value, rounding),
+            FixedFormat::S15 => encode_s15(value, rounding),
+        },
+        (OperandKind::Mask, Operand::Mask(mask)) => Ok(mask),
+        (OperandKind::SkipCondition, Operand::SkipCondition(condition)) => {
+            Ok(encode_skip_condition(condition))
         }
-
-        // LFO control
-        Instruction::WLDS {
-            lfo,
-            freq,
-            amplitude,
-        } => {
-            let opcode = 0b10111_u32 << 27;
-            let lfo_bits = encode_lfo(*lfo) << 25;
-            let freq_bits = (*freq as u32 & 0x1FF) << 9;
-            let amp_bits = *amplitude as u32 & 0x1FF;
-            Ok(opcode | lfo_bits | freq_bits | amp_bits)
+        (OperandKind::SkipOffset, Operand::SkipOffset(offset)) => Ok(offset as u32),
+        (OperandKind::Lfo, Operand::Lfo(lfo)) => Ok(encode_lfo(lfo)),
+        // WLDS and JAM select between the two LFOs of one type with a single bit
+        (OperandKind::SinLfo, Operand::Lfo(lfo @ (Lfo::SIN0 | Lfo::SIN1)))
+        | (OperandKind::RmpLfo, Operand::Lfo(lfo @ (Lfo::RMP0 | Lfo::RMP1))) => {
+            Ok(encode_lfo(lfo) & 1)
         }
-
-        Instruction::JAM { lfo } => {
-            let opcode = 0b11000_u32 << 27;
-            let lfo_bits = encode_lfo(*lfo) << 25;
-            Ok(opcode | lfo_bits)
+        (OperandKind::SinLfo | OperandKind::RmpLfo, Operand::Lfo(lfo)) => {
+            Err(CodegenError::InvalidLfoForInstruction {
+                instruction: spec.mnemonic,
+                lfo,
+            })
         }
-
-        Instruction::CHO {
-            mode,
-            lfo,
-            flags,
-            addr,
-        } => {
-            let opcode = 0b11001_u32 << 27;
-            let mode_bits = encode_cho_mode(*mode) << 24;
-            let lfo_bits = encode_lfo(*lfo) << 22;
-            let flags_bits = encode_cho_flags(flags) << 16;
-            let addr_bits = encode_address(*addr)? & 0xFFFF;
-            Ok(opcode | mode_bits | lfo_bits | flags_bits | addr_bits)
+        (OperandKind::Frequency | OperandKind::Amplitude, Operand::Integer(value)) => {
+            Ok(value as u32)
         }
+        (OperandKind::ChoMode, Operand::ChoMode(mode)) => Ok(encode_cho_mode(mode)),
+        (OperandKind::ChoFlags, Operand::ChoFlags(flags)) => Ok(flags.bits() as u32),
+        (kind, operand) => unreachable!(
+            "{} operand {:?} does not match its {:?} field",
+            spec.mnemonic, operand, kind
+        ),
     }
 }
 
-/// Encode register to 5-bit or 6-bit field depending on register type
-fn encode_register(reg: &Register) -> Result<u32, CodegenError> {
+/// Encode register to its 6-bit FV-1 register address
+pub(crate) fn encode_register(reg: &Register) -> Result<u32, CodegenError> {
     match reg {
-        Register::ADCL => Ok(0b00000),
-        Register::ADCR => Ok(0b00001),
-        Register::DACL => Ok(0b00010),
-        Register::DACR => Ok(0b00011),
-        Register::ADDR_PTR => Ok(0b00100),
-        Register::LR => Ok(0b00101),
-        Register::REG(n) if *n < 32 => Ok(*n as u32 + 16), // REG0-31 are offset by 16
-        Register::ACC => Ok(0),                            // ACC is implicit in most operations
-        _ => Ok(0),                                        // Default for special registers
+        Register::SIN0_RATE => Ok(0x00),
+        Register::SIN0_RANGE => Ok(0x01),
+        Register::SIN1_RATE => Ok(0x02),
+        Register::SIN1_RANGE => Ok(0x03),
+        Register::RMP0_RATE => Ok(0x04),
+        Register::RMP0_RANGE => Ok(0x05),
+        Register::RMP1_RATE => Ok(0x06),
+        Register::RMP1_RANGE => Ok(0x07),
+        Register::POT0 => Ok(0x10),
+        Register::POT1 => Ok(0x11),
+        Register::POT2 => Ok(0x12),
+        Register::ADCL => Ok(0x14),
+        Register::ADCR => Ok(0x15),
+        Register::DACL => Ok(0x16),
+        Register::DACR => Ok(0x17),
+        Register::ADDR_PTR => Ok(0x18),
+        Register::LR => Ok(0x19),
+        Register::REG(n) if *n < 32 => Ok(*n as u32 + 0x20), // REG0-31 start at 0x20
+        // ACC is implicit and has no register address
+        Register::ACC | Register::REG(_) => Err(CodegenError::RegisterNotAddressable { reg: *reg }),
     }
 }
 
-/// Encode S1.14 fixed-point coefficient (-2.0 to ~2.0)
-fn encode_s114(value: f32) -> Result<u32, CodegenError> {
-    if !value.is_finite() || !(-2.0..2.0).contains(&value) {
+/// Encode a signed fixed-point value into a `width`-bit two's complement
+/// field with `frac_bits` fractional bits
+fn encode_fixed(
+    value: f32,
+    width: u32,
+    frac_bits: u32,
+    rounding: RoundingMode,
+) -> Result<u32, CodegenError> {
+    let scale = (1u32 << frac_bits) as f32;
+    let min_int = -(1i32 << (width - 1));
+    let max_int = (1i32 << (width - 1)) - 1;
+    let min = min_int as f32 / scale;
+    let max = -min;
+
+    if !value.is_finite() || !(min..max).contains(&value) {
         return Err(CodegenError::CoefficientOutOfRange { value });
     }
 
-    // Convert to S1.14: sign bit + 14 fractional bits (15-bit signed)
-    // Range: -16384 to +16383 (representing -2.0 to +1.99993896...)
-    let scaled = (value * 16384.0).round() as i32;
-    let clamped = scaled.clamp(-16384, 16383);
-    Ok((clamped & 0x7FFF) as u32)
+    let scaled = rounding.apply(value * scale) as i32;
+    let clamped = scaled.clamp(min_int, max_int);
+    Ok((clamped as u32) & ((1 << width) - 1))
 }
 
-/// Encode S.10 fixed-point coefficient (-1.0 to ~1.0)
-fn encode_s10(value: f32) -> Result<u32, CodegenError> {
-    if !value.is_finite() || !(-1.0..1.0).contains(&value) {
-        return Err(CodegenError::CoefficientOutOfRange { value });
-    }
+/// Value a coefficient takes once encoded in `format`, or `None` if it is
+/// out of range
+pub(crate) fn quantize(value: f32, format: FixedFormat, rounding: RoundingMode) -> Option<f32> {
+    let width = format.width();
+    let bits = encode_fixed(value, width, format.frac_bits(), rounding).ok()?;
+    // Sign-extend the field
+    let shift = 32 - width;
+    let raw = ((bits << shift) as i32) >> shift;
+    Some(raw as f32 / (1u32 << format.frac_bits()) as f32)
+}
+
+/// Encode S1.14 fixed-point coefficient (-2.0 to ~2.0, 16 bits)
+fn encode_s114(value: f32, rounding: RoundingMode) -> Result<u32, CodegenError> {
+    encode_fixed(value, 16, 14, rounding)
+}
+
+/// Encode S1.9 fixed-point coefficient (-2.0 to ~2.0, 11 bits)
+fn encode_s19(value: f32, rounding: RoundingMode) -> Result<u32, CodegenError> {
+    encode_fixed(value, 11, 9, rounding)
+}
+
+/// Encode S.10 fixed-point coefficient (-1.0 to ~1.0, 11 bits)
+fn encode_s10(value: f32, rounding: RoundingMode) -> Result<u32, CodegenError> {
+    encode_fixed(value, 11, 10, rounding)
+}
 
-    // Convert to S.10: 11-bit signed format
-    // Range: -512 to +511 (representing -1.0 to +0.9990234...)
-    let scaled = (value * 512.0).round() as i32;
-    let clamped = scaled.clamp(-512, 511);
-    Ok((clamped & 0x7FF) as u32)
+/// Encode S4.6 fixed-point coefficient (-16.0 to ~16.0, 11 bits)
+fn encode_s46(value: f32, rounding: RoundingMode) -> Result<u32, CodegenError> {
+    encode_fixed(value, 11, 6, rounding)
+}
+
+/// Encode S.15 fixed-point coefficient (-1.0 to ~1.0, 16 bits)
+fn encode_s15(value: f32, rounding: RoundingMode) -> Result<u32, CodegenError> {
+    encode_fixed(value, 16, 15, rounding)
 }
 
 /// Encode 16-bit delay address
@@ -240,14 +209,14 @@ fn encode_address(addr: u16) -> Result<u32, CodegenError> {
     Ok(addr as u32)
 }
 
-/// Encode skip condition to 3-bit field
+/// Encode skip condition to 5-bit flag field
 fn encode_skip_condition(condition: SkipCondition) -> u32 {
     match condition {
-        SkipCondition::RUN => 0b000,
-        SkipCondition::NEG => 0b001,
-        SkipCondition::GEZ => 0b010,
-        SkipCondition::ZRO => 0b011,
-        SkipCondition::ZRC => 0b100,
+        SkipCondition::RUN => 0b10000,
+        SkipCondition::ZRC => 0b01000,
+        SkipCondition::ZRO => 0b00100,
+        SkipCondition::GEZ => 0b00010,
+        SkipCondition::NEG => 0b00001,
     }
 }
 
@@ -270,30 +239,10 @@ fn encode_cho_mode(mode: ChoMode) -> u32 {
     }
 }
 
-/// Encode CHO flags to 6-bit field
-fn encode_cho_flags(flags: &ChoFlags) -> u32 {
-    let mut bits = 0u32;
-    if flags.rptr2 {
-        bits |= 0b100000;
-    }
-    if flags.na {
-        bits |= 0b010000;
-    }
-    if flags.compc {
-        bits |= 0b001000;
-    }
-    if flags.compa {
-        bits |= 0b000100;
-    }
-    if flags.rptr2_select {
-        bits |= 0b000010;
-    }
-    bits
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::instruction::ChoFlags;
 
     #[test]
     fn test_encode_rdax() {
@@ -302,8 +251,8 @@ mod tests {
             coeff: 1.0,
         };
         let encoded = encode_instruction(&inst).unwrap();
-        // RDAX opcode is 0b00000, should be in top 5 bits
-        assert_eq!(encoded >> 27, 0b00000);
+        // RDAX opcode is 0b00100, in the low 5 bits
+        assert_eq!(encoded & 0x1F, 0b00100);
     }
 
     #[test]
@@ -314,7 +263,31 @@ mod tests {
         };
         let encoded = encode_instruction(&inst).unwrap();
         // WRAX opcode is 0b00110
-        assert_eq!(encoded >> 27, 0b00110);
+        assert_eq!(encoded & 0x1F, 0b00110);
+    }
+
+    #[test]
+    fn test_encode_wrlx() {
+        let inst = Instruction::WRLX {
+            reg: Register::REG(3),
+            coeff: -1.0,
+        };
+        let encoded = encode_instruction(&inst).unwrap();
+        // WRLX opcode is 0b01000
+        assert_eq!(encoded & 0x1F, 0b01000);
+        assert_eq!((encoded >> 5) & 0x3F, 0x20 + 3);
+    }
+
+    #[test]
+    fn test_encode_wrhx() {
+        let inst = Instruction::WRHX {
+            reg: Register::REG(7),
+            coeff: -0.5,
+        };
+        let encoded = encode_instruction(&inst).unwrap();
+        // WRHX opcode is 0b00111
+        assert_eq!(encoded & 0x1F, 0b00111);
+        assert_eq!((encoded >> 5) & 0x3F, 0x20 + 7);
     }
 
     #[test]
@@ -325,15 +298,28 @@ mod tests {
         };
         let encoded = encode_instruction(&inst).unwrap();
         // SOF opcode is 0b01101
-        assert_eq!(encoded >> 27, 0b01101);
+        assert_eq!(encoded & 0x1F, 0b01101);
     }
 
     #[test]
     fn test_encode_clr() {
         let inst = Instruction::CLR;
         let encoded = encode_instruction(&inst).unwrap();
-        // CLR opcode is 0b01110
-        assert_eq!(encoded >> 27, 0b01110);
+        // CLR is AND (0b01110) with a zero mask
+        assert_eq!(encoded, 0b01110);
+    }
+
+    #[test]
+    fn test_encode_not() {
+        let not = encode_instruction(&Instruction::NOT).unwrap();
+        let xor = encode_instruction(&Instruction::XOR { mask: 0xFFFFFF }).unwrap();
+        assert_eq!(not, xor);
+    }
+
+    #[test]
+    fn test_encode_raw() {
+        let encoded = encode_instruction(&Instruction::RAW(0xDEADBEEF)).unwrap();
+        assert_eq!(encoded, 0xDEADBEEF);
     }
 
     #[test]
@@ -343,26 +329,139 @@ mod tests {
         };
         let encoded = encode_instruction(&inst).unwrap();
         // MULX opcode is 0b01010
-        assert_eq!(encoded >> 27, 0b01010);
+        assert_eq!(encoded & 0x1F, 0b01010);
+    }
+
+    #[test]
+    fn test_spinasm_encodings() {
+        // Reference words as produced by SpinASM
+        let cases = [
+            (
+                Instruction::RDAX {
+                    reg: Register::ADCL,
+                    coeff: 1.0,
+                },
+                0x40000284,
+            ),
+            (
+                Instruction::WRAX {
+                    reg: Register::DACL,
+                    coeff: 0.0,
+                },
+                0x000002C6,
+            ),
+            (
+                Instruction::SOF {
+                    coeff: 0.5,
+                    offset: 0.0,
+                },
+                0x2000000D,
+            ),
+            (
+                Instruction::RDA {
+                    addr: 1000,
+                    coeff: 0.5,
+                },
+                0x20007D00,
+            ),
+            (Instruction::RMPA { coeff: 1.0 }, 0x40000301),
+            (
+                Instruction::MULX {
+                    reg: Register::ADCL,
+                },
+                0x0000028A,
+            ),
+            (
+                Instruction::LDAX {
+                    reg: Register::REG(0),
+                },
+                0x00000405,
+            ),
+            (Instruction::ABSA, 0x00000009),
+            (Instruction::CLR, 0x0000000E),
+            (Instruction::NOT, 0xFFFFFF10),
+            (Instruction::NOP, 0x00000011),
+            (
+                Instruction::SKP {
+                    condition: SkipCondition::RUN,
+                    offset: 3,
+                },
+                0x80600011,
+            ),
+            (
+                Instruction::WLDS {
+                    lfo: Lfo::SIN0,
+                    freq: 12,
+                    amplitude: 100,
+                },
+                0x00C00C92,
+            ),
+            (Instruction::JAM { lfo: Lfo::RMP1 }, 0x000000D3),
+            (
+                Instruction::CHO {
+                    mode: ChoMode::RDAL,
+                    lfo: Lfo::SIN0,
+                    flags: ChoFlags {
+                        cos: true,
+                        reg: true,
+                        ..Default::default()
+                    },
+                    addr: 0,
+                },
+                0xC3000014,
+            ),
+            (
+                Instruction::CHO {
+                    mode: ChoMode::SOF,
+                    lfo: Lfo::RMP0,
+                    flags: ChoFlags {
+                        reg: true,
+                        compc: true,
+                        na: true,
+                        ..Default::default()
+                    },
+                    addr: 0,
+                },
+                0xA6400014,
+            ),
+            (
+                Instruction::CHO {
+                    mode: ChoMode::RDA,
+                    lfo: Lfo::SIN0,
+                    flags: ChoFlags::default(),
+                    addr: 100,
+                },
+                0x00000C94,
+            ),
+        ];
+
+        for (inst, expected) in cases {
+            assert_eq!(
+                encode_instruction(&inst).unwrap(),
+                expected,
+                "encoding of {:?}",
+                inst
+            );
+        }
     }
 
     #[test]
     fn test_encode_s114_positive() {
-        let result = encode_s114(1.0).unwrap();
-        assert_eq!(result, 16383); // 1.0 * 16384 clamped to max 15-bit signed positive (16383)
+        let result = encode_s114(1.0, RoundingMode::Nearest).unwrap();
+        assert_eq!(result, 0x4000); // 1.0 * 16384
     }
 
     #[test]
     fn test_encode_s114_negative() {
-        let result = encode_s114(-1.0).unwrap();
-        // -1.0 * 16384 = -16384, in 15-bit two's complement
-        let expected = (-16384i32 & 0x7FFF) as u32;
+        let result = encode_s114(-1.0, RoundingMode::Nearest).unwrap();
+        // -1.0 * 16384 = -16384, in 16-bit two's complement
+        let expected = (-16384i32 & 0xFFFF) as u32;
         assert_eq!(result, expected);
     }
 
     #[test]
     fn test_encode_s114_out_of_range() {
-        let result = encode_s114(3.0);
+        let result = encode_s114(3.0, RoundingMode::Nearest);
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -370,28 +469,75 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_encode_s19() {
+        assert_eq!(encode_s19(0.5, RoundingMode::Nearest).unwrap(), 256); // 0.5 * 512
+        assert_eq!(encode_s19(-2.0, RoundingMode::Nearest).unwrap(), 0x400);
+        assert!(encode_s19(2.0, RoundingMode::Nearest).is_err());
+    }
+
     #[test]
     fn test_encode_s10() {
-        let result = encode_s10(0.5).unwrap();
-        assert_eq!(result, 256); // 0.5 * 512
+        let result = encode_s10(0.5, RoundingMode::Nearest).unwrap();
+        assert_eq!(result, 512); // 0.5 * 1024
+        assert!(encode_s10(1.0, RoundingMode::Nearest).is_err());
+    }
+
+    #[test]
+    fn test_encode_s46() {
+        assert_eq!(encode_s46(1.0, RoundingMode::Nearest).unwrap(), 64); // 1.0 * 64
+        assert_eq!(encode_s46(-16.0, RoundingMode::Nearest).unwrap(), 0x400);
+        assert!(encode_s46(16.0, RoundingMode::Nearest).is_err());
+    }
+
+    #[test]
+    fn test_rounding_modes() {
+        // 0.6 and -0.6 steps of S1.14
+        let up = 0.6 / 16384.0;
+        let down = -up;
+        assert_eq!(encode_s114(up, RoundingMode::Nearest).unwrap(), 1);
+        assert_eq!(encode_s114(up, RoundingMode::Truncate).unwrap(), 0);
+        assert_eq!(encode_s114(up, RoundingMode::Floor).unwrap(), 0);
+        assert_eq!(encode_s114(down, RoundingMode::Nearest).unwrap(), 0xFFFF);
+        assert_eq!(encode_s114(down, RoundingMode::Truncate).unwrap(), 0);
+        assert_eq!(encode_s114(down, RoundingMode::Floor).unwrap(), 0xFFFF);
+
+        // Every coefficient format follows the mode
+        let inst = Instruction::SOF {
+            coeff: up,
+            offset: 0.6 / 1024.0,
+        };
+        assert_eq!(encode_instruction(&inst).unwrap(), 0x0001_002D);
+        assert_eq!(
+            encode_instruction_with(&inst, RoundingMode::Truncate).unwrap(),
+            0x0000_000D
+        );
     }
 
     #[test]
     fn test_encode_register() {
-        assert_eq!(encode_register(&Register::ADCL).unwrap(), 0);
-        assert_eq!(encode_register(&Register::ADCR).unwrap(), 1);
-        assert_eq!(encode_register(&Register::DACL).unwrap(), 2);
-        assert_eq!(encode_register(&Register::DACR).unwrap(), 3);
-        assert_eq!(encode_register(&Register::ADDR_PTR).unwrap(), 4);
+        assert_eq!(encode_register(&Register::POT0).unwrap(), 0x10);
+        assert_eq!(encode_register(&Register::POT2).unwrap(), 0x12);
+        assert_eq!(encode_register(&Register::ADCL).unwrap(), 0x14);
+        assert_eq!(encode_register(&Register::ADCR).unwrap(), 0x15);
+        assert_eq!(encode_register(&Register::DACL).unwrap(), 0x16);
+        assert_eq!(encode_register(&Register::DACR).unwrap(), 0x17);
+        assert_eq!(encode_register(&Register::ADDR_PTR).unwrap(), 0x18);
+        assert_eq!(encode_register(&Register::REG(31)).unwrap(), 0x3F);
+        assert_eq!(encode_register(&Register::SIN0_RATE).unwrap(), 0x00);
+        assert_eq!(encode_register(&Register::SIN1_RANGE).unwrap(), 0x03);
+        assert_eq!(encode_register(&Register::RMP1_RANGE).unwrap(), 0x07);
+        assert!(encode_register(&Register::ACC).is_err());
+        assert!(encode_register(&Register::REG(32)).is_err());
     }
 
     #[test]
     fn test_encode_skip_condition() {
-        assert_eq!(encode_skip_condition(SkipCondition::RUN), 0b000);
-        assert_eq!(encode_skip_condition(SkipCondition::NEG), 0b001);
-        assert_eq!(encode_skip_condition(SkipCondition::GEZ), 0b010);
-        assert_eq!(encode_skip_condition(SkipCondition::ZRO), 0b011);
-        assert_eq!(encode_skip_condition(SkipCondition::ZRC), 0b100);
+        assert_eq!(encode_skip_condition(SkipCondition::RUN), 0b10000);
+        assert_eq!(encode_skip_condition(SkipCondition::ZRC), 0b01000);
+        assert_eq!(encode_skip_condition(SkipCondition::ZRO), 0b00100);
+        assert_eq!(encode_skip_condition(SkipCondition::GEZ), 0b00010);
+        assert_eq!(encode_skip_condition(SkipCondition::NEG), 0b00001);
     }
 
     #[test]
@@ -401,4 +547,16 @@ mod tests {
         assert_eq!(encode_lfo(Lfo::RMP0), 0b10);
         assert_eq!(encode_lfo(Lfo::RMP1), 0b11);
     }
+
+    #[test]
+    fn test_encode_lfo_for_wrong_instruction() {
+        let result = encode_instruction(&Instruction::JAM { lfo: Lfo::SIN0 });
+        assert!(matches!(
+            result,
+            Err(CodegenError::InvalidLfoForInstruction {
+                instruction: "JAM",
+                lfo: Lfo::SIN0
+            })
+        ));
+    }
 }
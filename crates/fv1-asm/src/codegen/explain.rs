@@ -0,0 +1,311 @@
+//! Plain-English Explanations
+//!
+//! Describes what each instruction of a binary does in words, with delay
+//! addresses as times and coefficients as gains in dB, for learning from
+//! programs whose source isn't available.
+
+use crate::{
+    codegen::{
+        decoder::decode_instruction,
+        disassembler::{format_instruction, format_lfo, format_register},
+        Binary,
+    },
+    constants::SAMPLE_RATE,
+    instruction::{ChoFlags, ChoMode, Instruction, SkipCondition},
+    register::Register,
+};
+
+/// Describe what an instruction does in a sentence
+pub fn explain_instruction(inst: &Instruction) -> String {
+    match inst {
+        Instruction::RDA { addr, coeff } => format!(
+            "Add delay memory at {} times {} to ACC",
+            delay(*addr),
+            gain(*coeff)
+        ),
+        Instruction::RMPA { coeff } => format!(
+            "Add delay memory at the address in ADDR_PTR times {} to ACC",
+            gain(*coeff)
+        ),
+        Instruction::WRA { addr, coeff } => format!(
+            "Write ACC to delay memory at {}, then scale ACC by {}",
+            delay(*addr),
+            gain(*coeff)
+        ),
+        Instruction::WRAP { addr, coeff } => format!(
+            "Write ACC to delay memory at {}, then scale ACC by {} and add the last read (all-pass)",
+            delay(*addr),
+            gain(*coeff)
+        ),
+        Instruction::RDAX { reg, coeff } => {
+            format!("Add {} times {} to ACC", register(reg), gain(*coeff))
+        }
+        Instruction::RDFX { reg, coeff } => {
+            let cutoff = if *coeff > 0.0 && *coeff < 1.0 {
+                let hz = -(1.0 - *coeff).ln() * SAMPLE_RATE / std::f32::consts::TAU;
+                format!(" (one-pole filter at about {:.0} Hz)", hz)
+            } else {
+                String::new()
+            };
+            format!(
+                "Move ACC toward {} by {}: ACC = (ACC - {}) * {} + {}{}",
+                register(reg),
+                coeff,
+                format_register(reg),
+                coeff,
+                format_register(reg),
+                cutoff
+            )
+        }
+        Instruction::LDAX { reg } => format!("Load {} into ACC", register(reg)),
+        Instruction::WRAX { reg, coeff } => format!(
+            "Write ACC to {}, then {}",
+            register(reg),
+            scale_acc(*coeff)
+        ),
+        Instruction::WRHX { reg, coeff } => format!(
+            "Write ACC to {}, then ACC = ACC * {} + PACC (high shelf)",
+            register(reg),
+            coeff
+        ),
+        Instruction::WRLX { reg, coeff } => format!(
+            "Write ACC to {}, then ACC = (PACC - ACC) * {} + PACC (low shelf)",
+            register(reg),
+            coeff
+        ),
+        Instruction::MULX { reg } => format!("Multiply ACC by {}", register(reg)),
+        Instruction::RDFX2 { reg, coeff } => format!(
+            "Double-precision filter step toward {} by {} (extension, not in SpinASM)",
+            register(reg),
+            coeff
+        ),
+        Instruction::ABSA => "Take the absolute value of ACC".to_string(),
+        Instruction::LOG { coeff, offset } => format!(
+            "Take the log of ACC: ACC = {} * LOG2(|ACC|) / 16 + {}",
+            coeff, offset
+        ),
+        Instruction::EXP { coeff, offset } => format!(
+            "Take the exponential of ACC: ACC = {} * EXP2(ACC * 16) + {}",
+            coeff, offset
+        ),
+        Instruction::SOF { coeff, offset } => {
+            if *offset == 0.0 {
+                format!("Scale ACC by {}", gain(*coeff))
+            } else {
+                format!("Scale ACC by {} and add {}", gain(*coeff), offset)
+            }
+        }
+        Instruction::AND { mask } => format!("Keep only the bits of ACC in 0x{:06X}", mask),
+        Instruction::OR { mask } => format!("Set the bits of ACC in 0x{:06X}", mask),
+        Instruction::XOR { mask } => format!("Flip the bits of ACC in 0x{:06X}", mask),
+        Instruction::CLR => "Clear ACC".to_string(),
+        Instruction::NOT => "Flip every bit of ACC".to_string(),
+        Instruction::SHL => "Shift ACC left one bit (extension, not in SpinASM)".to_string(),
+        Instruction::SHR => "Shift ACC right one bit (extension, not in SpinASM)".to_string(),
+        Instruction::NOP => "Do nothing".to_string(),
+        Instruction::SKP { condition, offset } => {
+            let when = match condition {
+                SkipCondition::RUN => "on every sample after the first",
+                SkipCondition::ZRC => "if ACC changed sign since the last sample",
+                SkipCondition::ZRO => "if ACC is zero",
+                SkipCondition::GEZ => "if ACC is zero or positive",
+                SkipCondition::NEG => "if ACC is negative",
+            };
+            let plural = if *offset == 1 { "" } else { "s" };
+            format!(
+                "Skip the next {} instruction{} {}",
+                offset, plural, when
+            )
+        }
+        Instruction::WLDS {
+            lfo,
+            freq,
+            amplitude,
+        } => {
+            let hz = f32::from(*freq) * SAMPLE_RATE / (std::f32::consts::TAU * 131072.0);
+            format!(
+                "Set up {} at {:.2} Hz with amplitude {} of 32767",
+                format_lfo(lfo),
+                hz,
+                amplitude
+            )
+        }
+        Instruction::JAM { lfo } => format!("Reset {} to its start", format_lfo(lfo)),
+        Instruction::CHO {
+            mode,
+            lfo,
+            flags,
+            addr,
+        } => {
+            let action = match mode {
+                ChoMode::RDA => format!(
+                    "Add delay memory at {} moved by {} to ACC",
+                    delay(*addr),
+                    format_lfo(lfo)
+                ),
+                ChoMode::RDAL => format!("Load the value of {} into ACC", format_lfo(lfo)),
+                ChoMode::SOF => format!("Scale ACC by {}", format_lfo(lfo)),
+            };
+            action + &cho_flags(flags)
+        }
+        Instruction::CHO_SOF { lfo, flags, offset } => format!(
+            "Scale ACC by {} and add {}{}",
+            format_lfo(lfo),
+            offset,
+            cho_flags(flags)
+        ),
+        Instruction::RAW(_) => "Data word, not a valid instruction".to_string(),
+    }
+}
+
+/// Format an explanation of every instruction in a binary: its address,
+/// encoded word and disassembly, with a description on the next line.
+/// Trailing padding is summarized in one line.
+pub fn format_explanation(binary: &Binary) -> String {
+    let mut explanation = String::new();
+
+    let words = binary.without_padding();
+    for (index, &word) in words.iter().enumerate() {
+        let inst = decode_instruction(word).unwrap_or(Instruction::RAW(word));
+        explanation.push_str(&format!(
+            "{:03}  {:08X}  {}\n",
+            index,
+            word,
+            format_instruction(&inst)
+        ));
+        explanation.push_str(&format!("               {}\n", explain_instruction(&inst)));
+    }
+
+    if binary.len() > words.len() {
+        explanation.push_str(&format!(
+            "{:03}  padding to {} instructions\n",
+            words.len(),
+            binary.len()
+        ));
+    }
+
+    explanation
+}
+
+/// Describe a register, naming the audio inputs, outputs and pots
+fn register(reg: &Register) -> String {
+    let role = match reg {
+        Register::ADCL => "left input ",
+        Register::ADCR => "right input ",
+        Register::DACL => "left output ",
+        Register::DACR => "right output ",
+        Register::POT0 | Register::POT1 | Register::POT2 => "pot ",
+        _ => "",
+    };
+    format!("{}{}", role, format_register(reg))
+}
+
+/// Describe a delay address with its time at the FV-1 sample rate
+fn delay(addr: u16) -> String {
+    format!(
+        "{} ({:.2} ms)",
+        addr,
+        f32::from(addr) * 1000.0 / SAMPLE_RATE
+    )
+}
+
+/// Describe a coefficient as a gain in dB
+fn gain(coeff: f32) -> String {
+    if coeff == 0.0 {
+        return "0".to_string();
+    }
+    let db = 20.0 * coeff.abs().log10();
+    let inverted = if coeff < 0.0 { ", inverted" } else { "" };
+    format!("{} ({:+.1} dB{})", coeff, db, inverted)
+}
+
+/// Describe scaling ACC after a write, which is usually either keeping or
+/// clearing it
+fn scale_acc(coeff: f32) -> String {
+    if coeff == 0.0 {
+        "clear ACC".to_string()
+    } else {
+        format!("scale ACC by {}", gain(coeff))
+    }
+}
+
+/// Describe the flags of a CHO instruction
+fn cho_flags(flags: &ChoFlags) -> String {
+    let notes: Vec<&str> = [
+        (flags.cos, "using the cosine output"),
+        (flags.reg, "latching the LFO value"),
+        (flags.compc, "with the coefficient complemented"),
+        (flags.compa, "with the address complemented"),
+        (flags.rptr2, "using the second ramp pointer"),
+        (flags.na, "using the crossfade coefficient"),
+    ]
+    .into_iter()
+    .filter_map(|(set, note)| set.then_some(note))
+    .collect();
+
+    if notes.is_empty() {
+        String::new()
+    } else {
+        format!(", {}", notes.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{codegen::Assembler, parser::Parser};
+
+    #[test]
+    fn test_explain_instruction() {
+        let explain = |source: &str| {
+            let program = Parser::new(source).parse().unwrap();
+            explain_instruction(program.instructions()[0])
+        };
+
+        assert_eq!(
+            explain("rdax adcl, 0.5"),
+            "Add left input ADCL times 0.5 (-6.0 dB) to ACC"
+        );
+        assert_eq!(
+            explain("wrax dacl, 0.0"),
+            "Write ACC to left output DACL, then clear ACC"
+        );
+        assert_eq!(
+            explain("rda 3277, -1.0"),
+            "Add delay memory at 3277 (100.01 ms) times -1 (+0.0 dB, inverted) to ACC"
+        );
+        assert_eq!(
+            explain("skp run, 1"),
+            "Skip the next 1 instruction on every sample after the first"
+        );
+        assert_eq!(
+            explain("wlds sin0, 12, 100"),
+            "Set up SIN0 at 0.48 Hz with amplitude 100 of 32767"
+        );
+        assert_eq!(
+            explain("cho rda, sin0, reg|compc, 1000"),
+            "Add delay memory at 1000 (30.52 ms) moved by SIN0 to ACC, latching the LFO value, \
+             with the coefficient complemented"
+        );
+        assert!(explain("rdfx reg0, 0.1").ends_with("(one-pole filter at about 549 Hz)"));
+    }
+
+    #[test]
+    fn test_format_explanation() {
+        let program = Parser::new("rdax adcl, 1.0\nwrax dacl, 0.0")
+            .parse()
+            .unwrap();
+        let binary = Assembler::new().assemble(&program).unwrap();
+
+        assert_eq!(
+            format_explanation(&binary),
+            "\
+000  40000284  RDAX ADCL, 1
+               Add left input ADCL times 1 (+0.0 dB) to ACC
+001  000002C6  WRAX DACL, 0
+               Write ACC to left output DACL, then clear ACC
+002  padding to 128 instructions
+"
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Intel HEX
+//!
+//! Reads and writes the Intel HEX files EEPROM programmers use. The reader
+//! is tolerant of the variations programmers produce: lower case digits,
+//! blank lines, records in any order and missing end of file records. It
+//! follows extended segment and linear address records, and ignores start
+//! address records.
+
+use crate::error::CodegenError;
+
+/// Options controlling how Intel HEX is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexOptions {
+    /// Data bytes per record, from 1 to 255
+    pub record_len: u8,
+    /// Address of the first byte, e.g. `slot * 512` to place a program in
+    /// a later slot of an EEPROM
+    pub base_address: u32,
+    /// Write hex digits in lower case
+    pub lowercase: bool,
+}
+
+impl HexOptions {
+    /// Create the default options: 16-byte records from address 0 in
+    /// upper case
+    pub fn new() -> Self {
+        Self {
+            record_len: 16,
+            base_address: 0,
+            lowercase: false,
+        }
+    }
+}
+
+impl Default for HexOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Format bytes as Intel HEX data records starting at address 0, followed
+/// by the end of file record
+pub(crate) fn intel_hex(bytes: &[u8]) -> String {
+    intel_hex_with(bytes, &HexOptions::new())
+}
+
+/// Format bytes as Intel HEX with the given options. Addresses above 64KB
+/// are reached with extended linear address records, and no data record
+/// crosses a 64KB boundary.
+pub(crate) fn intel_hex_with(bytes: &[u8], options: &HexOptions) -> String {
+    let mut hex = String::new();
+    let record_len = options.record_len.max(1) as usize;
+    // Upper 16 bits of the address set by the last extended address record
+    let mut upper = 0;
+
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let addr = options.base_address as usize + offset;
+        if addr >> 16 != upper {
+            upper = addr >> 16;
+            hex.push_str(&record(0x04, 0, &(upper as u16).to_be_bytes()));
+        }
+
+        let len = record_len
+            .min(bytes.len() - offset)
+            .min(0x1_0000 - (addr & 0xFFFF));
+        hex.push_str(&record(0x00, addr as u16, &bytes[offset..offset + len]));
+        offset += len;
+    }
+
+    // End of file record
+    hex.push_str(&record(0x01, 0, &[]));
+
+    if options.lowercase {
+        hex.to_lowercase()
+    } else {
+        hex
+    }
+}
+
+/// Format one record: `:LLAAAATT`, the data bytes and the two's
+/// complement checksum of all the bytes before it
+fn record(kind: u8, addr: u16, data: &[u8]) -> String {
+    let [high, low] = addr.to_be_bytes();
+    let header = [data.len() as u8, high, low, kind];
+
+    let mut line = String::from(":");
+    let mut checksum = 0u8;
+    for &byte in header.iter().chain(data) {
+        line.push_str(&format!("{:02X}", byte));
+        checksum = checksum.wrapping_add(byte);
+    }
+    line.push_str(&format!("{:02X}\n", checksum.wrapping_neg()));
+    line
+}
+
+/// Parse Intel HEX text into the bytes it describes, from address 0 up to
+/// the highest address written. Addresses no record writes are zero.
+///
+/// Data past `limit` bytes is rejected before any memory is allocated for
+/// it, so a stray extended address record can't ask for gigabytes.
+pub(crate) fn parse_intel_hex(text: &str, limit: usize) -> Result<Vec<u8>, CodegenError> {
+    let mut bytes = Vec::new();
+    // Base added to record addresses by extended address records
+    let mut base = 0usize;
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let invalid = |reason: &str| CodegenError::InvalidHex {
+            line: line_number,
+            reason: reason.to_string(),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(digits) = line.strip_prefix(':') else {
+            return Err(invalid("record does not start with ':'"));
+        };
+        if !digits.is_ascii() {
+            return Err(invalid("invalid hex digit"));
+        }
+        if digits.len() % 2 != 0 {
+            return Err(invalid("odd number of hex digits"));
+        }
+        let record = (0..digits.len())
+            .step_by(2)
+            .map(|j| u8::from_str_radix(&digits[j..j + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid("invalid hex digit"))?;
+
+        if record.len() < 5 || record.len() != record[0] as usize + 5 {
+            return Err(invalid("record length does not match its byte count"));
+        }
+        let checksum = record.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+        if checksum != 0 {
+            return Err(invalid("checksum mismatch"));
+        }
+
+        let addr = u16::from_be_bytes([record[1], record[2]]) as usize;
+        let data = &record[4..record.len() - 1];
+        match record[3] {
+            0x00 => {
+                let start = base + addr;
+                if start + data.len() > limit {
+                    return Err(CodegenError::InvalidBinarySize {
+                        size: start + data.len(),
+                        expected: limit,
+                    });
+                }
+                if bytes.len() < start + data.len() {
+                    bytes.resize(start + data.len(), 0);
+                }
+                bytes[start..start + data.len()].copy_from_slice(data);
+            }
+            0x01 => break,
+            0x02 | 0x04 => {
+                let [high, low] = data else {
+                    return Err(invalid("extended address record must hold 2 bytes"));
+                };
+                let value = u16::from_be_bytes([*high, *low]) as usize;
+                base = if record[3] == 0x02 {
+                    value << 4
+                } else {
+                    value << 16
+                };
+            }
+            0x03 | 0x05 => {}
+            _ => return Err(invalid("unknown record type")),
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let hex = intel_hex(&bytes);
+        assert_eq!(parse_intel_hex(&hex, usize::MAX).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_hex_options() {
+        let bytes: Vec<u8> = (0..40).collect();
+        let options = HexOptions {
+            record_len: 32,
+            base_address: 0x200,
+            lowercase: true,
+        };
+        let hex = intel_hex_with(&bytes, &options);
+        let lines: Vec<&str> = hex.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with(":20020000000102"));
+        assert!(lines[1].starts_with(":08022000"));
+        assert_eq!(lines[2], ":00000001ff");
+
+        let read = parse_intel_hex(&hex, usize::MAX).unwrap();
+        assert_eq!(&read[0x200..], &bytes[..]);
+    }
+
+    #[test]
+    fn test_hex_extended_address() {
+        let options = HexOptions {
+            base_address: 0xFFF8,
+            ..HexOptions::new()
+        };
+        let hex = intel_hex_with(&[0xAA; 16], &options);
+        let lines: Vec<&str> = hex.lines().collect();
+        // The data is split at the 64KB boundary
+        assert!(lines[0].starts_with(":08FFF800"));
+        assert_eq!(lines[1], ":020000040001F9");
+        assert!(lines[2].starts_with(":08000000"));
+
+        let read = parse_intel_hex(&hex, usize::MAX).unwrap();
+        assert_eq!(read.len(), 0x1_0008);
+        assert_eq!(&read[0xFFF8..], &[0xAA; 16]);
+    }
+
+    #[test]
+    fn test_tolerant_parsing() {
+        // Lower case, CRLF line endings, blank lines, out of order records,
+        // an extended linear address of 0 and no end of file record
+        let hex = ":020000040000fa\r\n\r\n:02000400AABB95\r\n:0400000001020304F2\r\n";
+        assert_eq!(
+            parse_intel_hex(hex, 4096).unwrap(),
+            vec![1, 2, 3, 4, 0xAA, 0xBB]
+        );
+
+        // Extended segment addresses are in 16-byte units
+        let hex = ":020000020001FB\n:01000000FF00\n:00000001FF\n";
+        let bytes = parse_intel_hex(hex, 4096).unwrap();
+        assert_eq!(bytes.len(), 17);
+        assert_eq!(bytes[16], 0xFF);
+    }
+
+    #[test]
+    fn test_invalid_hex() {
+        let error = |hex: &str| parse_intel_hex(hex, 4096).unwrap_err().to_string();
+        assert_eq!(
+            error(":0400000001020304F2\n:0400000001020304F3"),
+            "invalid Intel HEX on line 2: checksum mismatch"
+        );
+        assert_eq!(
+            error("0400000001020304F2"),
+            "invalid Intel HEX on line 1: record does not start with ':'"
+        );
+        assert_eq!(
+            error(":0500000001020304F1"),
+            "invalid Intel HEX on line 1: record length does not match its byte count"
+        );
+        assert_eq!(
+            error(":0400000001020G04F2"),
+            "invalid Intel HEX on line 1: invalid hex digit"
+        );
+        assert_eq!(
+            error(":0\u{e9}0"),
+            "invalid Intel HEX on line 1: invalid hex digit"
+        );
+    }
+
+    #[test]
+    fn test_hex_address_limit() {
+        // An extended linear address far past the limit
+        let hex = ":02000004FFFFFC\n:0400000001020304F2\n";
+        assert!(matches!(
+            parse_intel_hex(hex, 4096),
+            Err(CodegenError::InvalidBinarySize {
+                size: 0xFFFF_0004,
+                expected: 4096
+            })
+        ));
+
+        // Data ending exactly at the limit is accepted
+        let hex = intel_hex_with(
+            &[0xAA; 4],
+            &HexOptions {
+                base_address: 4092,
+                ..HexOptions::new()
+            },
+        );
+        assert_eq!(parse_intel_hex(&hex, 4096).unwrap().len(), 4096);
+        assert!(parse_intel_hex(&hex, 4095).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Decoded Hex Dumps
+//!
+//! Formats raw program or EEPROM bytes one word per line, with each word's
+//! byte address and disassembly side by side, for eyeballing EEPROM
+//! contents without disassembling them to a file.
+
+use crate::{
+    codegen::{decoder::decode_instruction, disassembler::format_instruction},
+    constants::{MAX_INSTRUCTIONS, PROGRAM_SLOTS},
+    error::CodegenError,
+    instruction::Instruction,
+};
+
+/// Size in bytes of one program
+const PROGRAM_BYTES: usize = MAX_INSTRUCTIONS * 4;
+
+/// Format a program or a 4096-byte EEPROM image as `address  word  text`
+/// lines. An image is dumped slot by slot under a heading for each.
+///
+/// Unless `all` is set, the trailing zero, `SKP 0, 0` or erased words of
+/// each program are summarized in one line.
+pub fn format_hexdump(bytes: &[u8], all: bool) -> Result<String, CodegenError> {
+    let bank_bytes = PROGRAM_SLOTS * PROGRAM_BYTES;
+    if bytes.len() != bank_bytes && (bytes.len() > PROGRAM_BYTES || !bytes.len().is_multiple_of(4))
+    {
+        return Err(CodegenError::InvalidBinarySize {
+            size: bytes.len(),
+            expected: PROGRAM_BYTES,
+        });
+    }
+
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+
+    if bytes.len() != bank_bytes {
+        return Ok(dump_words(&words, 0, all));
+    }
+
+    let mut dump = String::new();
+    for (slot, program) in words.chunks(MAX_INSTRUCTIONS).enumerate() {
+        if slot > 0 {
+            dump.push('\n');
+        }
+        dump.push_str(&format!("slot {}\n", slot));
+        dump.push_str(&dump_words(program, slot * PROGRAM_BYTES, all));
+    }
+    Ok(dump)
+}
+
+/// Dump words starting at byte address `base`
+fn dump_words(words: &[u32], base: usize, all: bool) -> String {
+    let len = if all {
+        words.len()
+    } else {
+        words
+            .iter()
+            .rposition(|&word| !matches!(word, 0x0000_0000 | 0x0000_0011 | 0xFFFF_FFFF))
+            .map_or(0, |last| last + 1)
+    };
+
+    let mut dump = String::new();
+    for (index, &word) in words[..len].iter().enumerate() {
+        let inst = decode_instruction(word).unwrap_or(Instruction::RAW(word));
+        dump.push_str(&format!(
+            "{:04X}  {:08X}  {}\n",
+            base + index * 4,
+            word,
+            format_instruction(&inst)
+        ));
+    }
+
+    let padding = words.len() - len;
+    if padding > 0 {
+        dump.push_str(&format!(
+            "{:04X}  ........  {} padding words\n",
+            base + len * 4,
+            padding
+        ));
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codegen::{Assembler, Bank},
+        parser::Parser,
+    };
+
+    fn binary(source: &str) -> crate::codegen::Binary {
+        Assembler::new()
+            .assemble(&Parser::new(source).parse().unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_program_dump() {
+        let bytes = binary("rdax adcl, 1.0\nwrax dacl, 0.0").to_bytes();
+        assert_eq!(
+            format_hexdump(&bytes, false).unwrap(),
+            "\
+0000  40000284  RDAX ADCL, 1
+0004  000002C6  WRAX DACL, 0
+0008  ........  126 padding words
+"
+        );
+
+        let all = format_hexdump(&bytes, true).unwrap();
+        assert_eq!(all.lines().count(), 128);
+        assert!(all.ends_with("01FC  00000000  RDA 0, 0\n"));
+
+        assert!(format_hexdump(&bytes[..6], false).is_err());
+    }
+
+    #[test]
+    fn test_bank_dump() {
+        let mut bank = Bank::new();
+        bank.set(2, binary("clr")).unwrap();
+        let dump = format_hexdump(&bank.to_bytes(), false).unwrap();
+
+        assert!(dump.starts_with("slot 0\n0000  ........  128 padding words\n\nslot 1\n"));
+        assert!(dump.contains("slot 2\n0400  0000000E  CLR\n0404  ........  127 padding words\n"));
+        assert_eq!(dump.matches("slot ").count(), 8);
+    }
+}
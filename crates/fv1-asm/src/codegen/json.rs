@@ -0,0 +1,234 @@
+//! JSON Output
+//!
+//! Describes a program as a JSON document for tools that consume assembler
+//! output without parsing assembly: each instruction with its operands by
+//! name, the labels and directives, and the registers and delay memory the
+//! program uses. Like [`crate::isa::to_json`], each instruction is written
+//! on its own line.
+
+use crate::{
+    analysis::resource_report,
+    ast::{Directive, Program, Value},
+    codegen::disassembler::{
+        format_cho_flags, format_cho_mode, format_instruction, format_lfo, format_register,
+        format_skip_condition,
+    },
+    instruction::Instruction,
+    isa::{self, Operand},
+};
+
+/// Version of the document format
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// Describe a program as JSON. `words` holds the encoded instructions when
+/// the program came from a binary.
+pub(crate) fn program_json(program: &Program, words: Option<&[u32]>) -> String {
+    let mut json = format!("{{\n  \"version\": {},\n", JSON_FORMAT_VERSION);
+
+    let instructions: Vec<String> = program
+        .instructions()
+        .into_iter()
+        .enumerate()
+        .map(|(index, inst)| {
+            let word = match words.and_then(|words| words.get(index)) {
+                Some(word) => format!("\"word\": \"0x{:08X}\", ", word),
+                None => String::new(),
+            };
+            format!(
+                "    {{\"index\": {}, {}{}, \"text\": {}}}",
+                index,
+                word,
+                instruction_fields(inst),
+                string(&format_instruction(inst))
+            )
+        })
+        .collect();
+    json.push_str(&format!("  \"instructions\": {},\n", list(&instructions)));
+
+    let mut labels: Vec<(&String, &usize)> = program.labels.iter().collect();
+    labels.sort_by_key(|&(name, index)| (*index, name));
+    let labels: Vec<String> = labels
+        .into_iter()
+        .map(|(name, index)| format!("    {{\"name\": {}, \"index\": {}}}", string(name), index))
+        .collect();
+    json.push_str(&format!("  \"labels\": {},\n", list(&labels)));
+
+    let directives: Vec<String> = program
+        .directives
+        .iter()
+        .map(|directive| format!("    {}", directive_json(directive)))
+        .collect();
+    json.push_str(&format!("  \"directives\": {},\n", list(&directives)));
+
+    json.push_str(&format!("  \"resources\": {}\n", resources_json(program)));
+    json.push_str("}\n");
+    json
+}
+
+/// Format the mnemonic and named operands of an instruction
+fn instruction_fields(inst: &Instruction) -> String {
+    let Some((spec, operands)) = isa::describe(inst) else {
+        return "\"mnemonic\": null, \"operands\": {}".to_string();
+    };
+
+    let operands: Vec<String> = spec
+        .operands
+        .iter()
+        .zip(&operands)
+        .map(|(field, operand)| format!("{}: {}", string(field.name), operand_json(operand)))
+        .collect();
+    format!(
+        "\"mnemonic\": \"{}\", \"operands\": {{{}}}",
+        spec.mnemonic,
+        operands.join(", ")
+    )
+}
+
+fn operand_json(operand: &Operand) -> String {
+    match operand {
+        Operand::Register(reg) => string(&format_register(reg)),
+        Operand::Address(addr) => addr.to_string(),
+        Operand::Coefficient(value) => value.to_string(),
+        Operand::Mask(mask) => mask.to_string(),
+        Operand::SkipCondition(condition) => string(format_skip_condition(condition)),
+        Operand::SkipOffset(offset) => offset.to_string(),
+        Operand::Lfo(lfo) => string(format_lfo(lfo)),
+        Operand::Integer(value) => value.to_string(),
+        Operand::ChoMode(mode) => string(format_cho_mode(mode)),
+        Operand::ChoFlags(flags) => string(&format_cho_flags(flags)),
+    }
+}
+
+fn directive_json(directive: &Directive) -> String {
+    match directive {
+        Directive::Equate { name, value } => {
+            let value = match value {
+                Value::Float(f) => f.to_string(),
+                Value::Integer(i) => i.to_string(),
+                Value::Identifier(id) => string(id),
+                Value::Register(reg) => string(&format_register(reg)),
+            };
+            format!(
+                "{{\"kind\": \"equ\", \"name\": {}, \"value\": {}}}",
+                string(name),
+                value
+            )
+        }
+        Directive::MemoryAllocation { name, size } => format!(
+            "{{\"kind\": \"mem\", \"name\": {}, \"size\": {}}}",
+            string(name),
+            size
+        ),
+        Directive::SpinAsm { version } => format!(
+            "{{\"kind\": \"spinasm\", \"version\": {}}}",
+            string(version)
+        ),
+    }
+}
+
+/// Format the registers and delay memory a program uses
+fn resources_json(program: &Program) -> String {
+    let report = resource_report(program);
+    let registers = |select: fn(&crate::analysis::resources::RegisterUsage) -> bool| {
+        let regs: Vec<String> = report
+            .registers
+            .iter()
+            .filter(|(_, usage)| select(usage))
+            .map(|(n, _)| n.to_string())
+            .collect();
+        format!("[{}]", regs.join(", "))
+    };
+    let regions: Vec<String> = report
+        .regions
+        .iter()
+        .map(|region| {
+            format!(
+                "{{\"name\": {}, \"start\": {}, \"len\": {}}}",
+                string(&region.name),
+                region.start,
+                region.len
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"instructions\": {}, \"registers_read\": {}, \"registers_written\": {}, \"regions\": [{}], \"indirect_reads\": {}}}",
+        program.instructions().len(),
+        registers(|usage| usage.read),
+        registers(|usage| usage.written),
+        regions.join(", "),
+        report.indirect_reads
+    )
+}
+
+/// Format lines as the items of a JSON array
+fn list(items: &[String]) -> String {
+    if items.is_empty() {
+        "[]".to_string()
+    } else {
+        format!("[\n{}\n  ]", items.join(",\n"))
+    }
+}
+
+/// Format a JSON string literal
+fn string(text: &str) -> String {
+    let mut json = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_program_json() {
+        let source = "\
+equ gain, 0.5
+mem dly 100
+start: rdax adcl, 1.0
+wra dly, gain
+cho rda, sin0, reg|compc, dly
+wrax reg2, 0.0
+";
+        let program = Parser::new(source).parse().unwrap();
+        assert_eq!(
+            program_json(&program, None),
+            r#"{
+  "version": 1,
+  "instructions": [
+    {"index": 0, "mnemonic": "RDAX", "operands": {"reg": "ADCL", "coeff": 1}, "text": "RDAX ADCL, 1"},
+    {"index": 1, "mnemonic": "WRA", "operands": {"addr": 0, "coeff": 0.5}, "text": "WRA 0, 0.5"},
+    {"index": 2, "mnemonic": "CHO", "operands": {"mode": "RDA", "lfo": "SIN0", "flags": "REG|COMPC", "addr": 0}, "text": "CHO RDA, SIN0, REG|COMPC, 0"},
+    {"index": 3, "mnemonic": "WRAX", "operands": {"reg": "REG2", "coeff": 0}, "text": "WRAX REG2, 0"}
+  ],
+  "labels": [
+    {"name": "start", "index": 0}
+  ],
+  "directives": [
+    {"kind": "equ", "name": "gain", "value": 0.5},
+    {"kind": "mem", "name": "dly", "size": 100}
+  ],
+  "resources": {"instructions": 4, "registers_read": [], "registers_written": [2], "regions": [{"name": "dly", "start": 0, "len": 101}], "indirect_reads": 0}
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(string("a\"b\\c\n"), r#""a\"b\\c\n""#);
+        assert_eq!(string("\u{1}"), r#""\u0001""#);
+    }
+}
@@ -0,0 +1,72 @@
+//! Assembly Listings
+//!
+//! Formats an assembled program as a SpinASM-style listing: each
+//! instruction's address and encoded word next to the source line it came
+//! from, for correlating encodings with source while debugging hardware.
+
+use crate::{
+    ast::Program,
+    codegen::{disassembler::format_instruction, Binary},
+};
+
+/// Format a listing of `program`, assembled to `binary` from `source`.
+/// Instructions without a source span, such as those built in code, are
+/// shown disassembled in place of their source line.
+pub fn format_listing(program: &Program, binary: &Binary, source: &str) -> String {
+    let mut listing = String::from("ADDR  WORD      LINE  SOURCE\n");
+
+    for (index, inst) in program.instructions().into_iter().enumerate() {
+        let word = binary.instructions().get(index).copied().unwrap_or(0);
+        let (line, text) = match program.instruction_span(index) {
+            Some(span) => {
+                let start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+                let end = source[span.start..]
+                    .find('\n')
+                    .map_or(source.len(), |i| span.start + i);
+                let line = source[..span.start].matches('\n').count() + 1;
+                (line.to_string(), source[start..end].trim_end().to_string())
+            }
+            None => (String::new(), format_instruction(inst)),
+        };
+        listing.push_str(&format!(
+            "{:03}   {:08X}  {:>4}  {}\n",
+            index, word, line, text
+        ));
+    }
+
+    let padding = binary.len().saturating_sub(program.instructions().len());
+    if padding > 0 {
+        listing.push_str(&format!(
+            "{:03}   padding to {} instructions\n",
+            binary.len() - padding,
+            binary.len()
+        ));
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{codegen::Assembler, parser::Parser};
+
+    #[test]
+    fn test_listing() {
+        let source = "\
+; Passthrough
+start: rdax adcl, 1.0   ; input
+wrax dacl, 0.0
+";
+        let program = Parser::new(source).parse().unwrap();
+        let binary = Assembler::new().assemble(&program).unwrap();
+        assert_eq!(
+            format_listing(&program, &binary, source),
+            "\
+ADDR  WORD      LINE  SOURCE
+000   40000284     2  start: rdax adcl, 1.0   ; input
+001   000002C6     3  wrax dacl, 0.0
+002   padding to 128 instructions
+"
+        );
+    }
+}
@@ -3,12 +3,40 @@
 //! This module handles the conversion from parsed AST to FV-1 machine code.
 
 pub mod assembler;
+pub mod bank;
 pub mod decoder;
+pub mod diff;
 pub mod disassembler;
+pub mod dsl;
 pub mod encoder;
+pub mod explain;
+pub mod hex;
+pub mod hexdump;
+pub mod json;
+pub mod listing;
+pub mod optimizer;
+pub mod srec;
+pub mod symbols;
+pub mod uf2;
+pub mod verify;
 
 // Re-export main types for convenience
-pub use assembler::{Assembler, Binary};
+pub use assembler::{Assembler, Binary, BudgetReport, PaddingKind};
+pub use bank::{Bank, EepromImage};
 pub use decoder::decode_instruction;
-pub use disassembler::Disassembler;
-pub use encoder::encode_instruction;
+pub use diff::{
+    diff_binaries, diff_words, diff_words_exact, InstructionDiff, OperandChange, WordDiff,
+};
+pub use disassembler::{
+    format_instruction, format_program, format_program_with, CoefficientFormat, Disassembler,
+    FormatOptions, OutputStyle, Radix,
+};
+pub use dsl::format_dsl;
+pub use encoder::{encode_instruction, encode_instruction_with, RoundingMode};
+pub use explain::{explain_instruction, format_explanation};
+pub use hex::HexOptions;
+pub use hexdump::format_hexdump;
+pub use listing::format_listing;
+pub use optimizer::{optimize, OptimizationReport};
+pub use symbols::{Symbol, SymbolKind, SymbolTable};
+pub use verify::{UndecodableWord, VerifyReport};
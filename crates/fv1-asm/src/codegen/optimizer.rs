@@ -0,0 +1,443 @@
+//! Peephole Optimizer
+//!
+//! Rewrites a program's instructions before encoding, repeating these
+//! passes until none applies:
+//!
+//! - remove a CLR when the instruction before it already left ACC at zero
+//! - merge consecutive SOFs into one, when the first cannot saturate ACC
+//! - turn a WRAX to a register that is never read into the equivalent SOF,
+//!   or remove it when it leaves ACC unchanged
+//! - remove NOPs
+//!
+//! Skip targets are never removed, and skip offsets are recomputed for the
+//! instructions that remain. Since removing an instruction changes the
+//! PACC seen by the one after it, nothing is removed in front of an
+//! instruction that reads PACC (WRHX, WRLX, SKP ZRC). Programs containing
+//! raw `.dw` words are left alone, as a raw word may be a skip.
+
+use crate::{
+    analysis::{
+        paths::skip_destination,
+        roles::{register_access, Access},
+    },
+    ast::{Program, Statement},
+    instruction::{Instruction, SkipCondition},
+    register::Register,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// A change made by the optimizer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Optimization {
+    /// CLR removed because ACC was already zero
+    RedundantClr,
+    /// SOF merged into the one before it, giving the coefficient and
+    /// offset of the merged SOF
+    MergedSof { coeff: f32, offset: f32 },
+    /// WRAX to a register that is never read, replaced by a SOF or removed
+    UnreadWrite { reg: Register, removed: bool },
+    /// NOP removed
+    Nop,
+}
+
+/// An optimization applied to the instruction at `index` of the original
+/// program
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub index: usize,
+    pub optimization: Optimization,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "instruction {}: ", self.index)?;
+        match &self.optimization {
+            Optimization::RedundantClr => write!(f, "removed CLR, ACC is already zero"),
+            Optimization::MergedSof { coeff, offset } => {
+                write!(f, "merged SOF into SOF {}, {}", coeff, offset)
+            }
+            Optimization::UnreadWrite { reg, removed: true } => {
+                write!(f, "removed WRAX to {:?}, which is never read", reg)
+            }
+            Optimization::UnreadWrite {
+                reg,
+                removed: false,
+            } => {
+                write!(
+                    f,
+                    "replaced WRAX to {:?}, which is never read, with SOF",
+                    reg
+                )
+            }
+            Optimization::Nop => write!(f, "removed NOP"),
+        }
+    }
+}
+
+/// What the optimizer changed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OptimizationReport {
+    /// Instruction count before optimizing
+    pub before: usize,
+    /// Instruction count after optimizing
+    pub after: usize,
+    /// Changes, in the order they were made
+    pub changes: Vec<Change>,
+}
+
+/// An instruction being optimized
+struct Slot {
+    /// Index in the original program
+    origin: usize,
+    instruction: Instruction,
+    /// Original index of the instruction a SKP jumps to
+    target: Option<usize>,
+}
+
+/// Optimize a program, returning the optimized program and what changed
+pub fn optimize(program: &Program) -> (Program, OptimizationReport) {
+    let instructions = program.instructions();
+    let mut report = OptimizationReport {
+        before: instructions.len(),
+        after: instructions.len(),
+        changes: Vec::new(),
+    };
+    if instructions
+        .iter()
+        .any(|inst| matches!(inst, Instruction::RAW(_)))
+    {
+        return (program.clone(), report);
+    }
+
+    let mut slots: Vec<Slot> = instructions
+        .iter()
+        .enumerate()
+        .map(|(index, inst)| Slot {
+            origin: index,
+            instruction: (*inst).clone(),
+            target: skip_destination(program, index, inst),
+        })
+        .collect();
+    let targets: HashSet<usize> = slots.iter().filter_map(|slot| slot.target).collect();
+    let read: HashSet<Register> = instructions
+        .iter()
+        .filter_map(|inst| match register_access(inst) {
+            Some((reg, Access::Read)) => Some(reg),
+            _ => None,
+        })
+        .collect();
+
+    while let Some(change) = apply_one(&mut slots, &targets, &read) {
+        report.changes.push(change);
+    }
+    report.after = slots.len();
+
+    (rebuild(program, &slots), report)
+}
+
+/// Apply the first optimization found, if any
+fn apply_one(
+    slots: &mut Vec<Slot>,
+    targets: &HashSet<usize>,
+    read: &HashSet<Register>,
+) -> Option<Change> {
+    for i in 0..slots.len() {
+        let removable = !targets.contains(&slots[i].origin);
+        // Removing this instruction would change the next one's PACC
+        let keeps_pacc = !slots
+            .get(i + 1)
+            .is_some_and(|next| reads_pacc(&next.instruction));
+        let origin = slots[i].origin;
+
+        let optimization = match &slots[i].instruction {
+            Instruction::CLR
+                if removable && keeps_pacc && i > 0 && clears_acc(&slots[i - 1].instruction) =>
+            {
+                slots.remove(i);
+                Optimization::RedundantClr
+            }
+            Instruction::NOP if removable && keeps_pacc => {
+                slots.remove(i);
+                Optimization::Nop
+            }
+            Instruction::WRAX {
+                reg: reg @ Register::REG(_),
+                coeff,
+            } if !read.contains(reg) => {
+                let (reg, coeff) = (*reg, *coeff);
+                if coeff == 1.0 && removable && keeps_pacc {
+                    slots.remove(i);
+                    Optimization::UnreadWrite { reg, removed: true }
+                } else {
+                    slots[i].instruction = Instruction::SOF { coeff, offset: 0.0 };
+                    Optimization::UnreadWrite {
+                        reg,
+                        removed: false,
+                    }
+                }
+            }
+            Instruction::SOF {
+                coeff: c1,
+                offset: d1,
+            } => {
+                let Some(next) = slots.get(i + 1) else {
+                    continue;
+                };
+                let Instruction::SOF {
+                    coeff: c2,
+                    offset: d2,
+                } = next.instruction
+                else {
+                    continue;
+                };
+                let (coeff, offset) = (c1 * c2, d1 * c2 + d2);
+                // The first SOF must not saturate ACC, the merged one must
+                // be representable, and nothing may jump between them
+                let mergeable = c1.abs() + d1.abs() <= 1.0
+                    && (-2.0..2.0).contains(&coeff)
+                    && (-1.0..1.0).contains(&offset)
+                    && !targets.contains(&next.origin)
+                    && !slots
+                        .get(i + 2)
+                        .is_some_and(|after| reads_pacc(&after.instruction));
+                if !mergeable {
+                    continue;
+                }
+                let merged = slots.remove(i + 1);
+                slots[i].instruction = Instruction::SOF { coeff, offset };
+                return Some(Change {
+                    index: merged.origin,
+                    optimization: Optimization::MergedSof { coeff, offset },
+                });
+            }
+            _ => continue,
+        };
+
+        return Some(Change {
+            index: origin,
+            optimization,
+        });
+    }
+    None
+}
+
+/// Check whether an instruction always leaves ACC at zero
+fn clears_acc(inst: &Instruction) -> bool {
+    match inst {
+        Instruction::CLR => true,
+        Instruction::WRAX { coeff, .. } | Instruction::WRA { coeff, .. } => *coeff == 0.0,
+        Instruction::SOF { coeff, offset } => *coeff == 0.0 && *offset == 0.0,
+        Instruction::AND { mask } => *mask == 0,
+        _ => false,
+    }
+}
+
+/// Check whether an instruction depends on the previous instruction's ACC
+fn reads_pacc(inst: &Instruction) -> bool {
+    matches!(
+        inst,
+        Instruction::WRHX { .. }
+            | Instruction::WRLX { .. }
+            | Instruction::SKP {
+                condition: SkipCondition::ZRC,
+                ..
+            }
+    )
+}
+
+/// Rebuild `program` with the optimized instructions, keeping labels,
+/// comments and source spans
+fn rebuild(program: &Program, slots: &[Slot]) -> Program {
+    let total = program.instructions().len();
+    // Original index to optimized index; instructions are never reordered
+    let positions: HashMap<usize, usize> = slots
+        .iter()
+        .enumerate()
+        .map(|(i, slot)| (slot.origin, i))
+        .collect();
+    // Skips past the end land in the padding, which keeps its length
+    let position = |origin: usize| match positions.get(&origin) {
+        Some(&position) => position,
+        None => slots.len() + origin.saturating_sub(total),
+    };
+    let by_origin: HashMap<usize, &Slot> = slots.iter().map(|slot| (slot.origin, slot)).collect();
+
+    let mut optimized = Program::new();
+    for (index, directive) in program.directives.iter().enumerate() {
+        let span = program.directive_span(index).unwrap_or(0..0);
+        optimized.add_directive_with_span(directive.clone(), span);
+    }
+    optimized.directive_comments = program.directive_comments.clone();
+
+    let mut origin = 0;
+    for (index, statement) in program.statements.iter().enumerate() {
+        let span = program.statement_span(index).unwrap_or(0..0);
+        let (label, is_instruction) = match statement {
+            Statement::Instruction(_) => (None, true),
+            Statement::LabeledInstruction { label, .. } => (Some(label), true),
+            Statement::Label(_) | Statement::Comment(_) => (None, false),
+        };
+
+        let statement = if !is_instruction {
+            Some(statement.clone())
+        } else {
+            let slot = by_origin.get(&origin);
+            origin += 1;
+            match (slot, label) {
+                (Some(slot), label) => {
+                    let new_index = optimized.instructions().len();
+                    let mut instruction = slot.instruction.clone();
+                    if let (Instruction::SKP { offset, .. }, Some(target)) =
+                        (&mut instruction, slot.target)
+                    {
+                        *offset = (position(target) - new_index - 1) as i8;
+                    }
+                    if let Some(label) = program.skip_target(slot.origin) {
+                        optimized.skip_targets.insert(new_index, label.to_string());
+                    }
+                    Some(match label {
+                        Some(label) => Statement::LabeledInstruction {
+                            label: label.clone(),
+                            instruction,
+                        },
+                        None => Statement::Instruction(instruction),
+                    })
+                }
+                (None, Some(label)) => Some(Statement::Label(label.clone())),
+                (None, None) => None,
+            }
+        };
+
+        if let Some(statement) = statement {
+            if let Some(comment) = program.trailing_comments.get(&index) {
+                optimized
+                    .trailing_comments
+                    .insert(optimized.statements.len(), comment.clone());
+            }
+            optimized.add_statement_with_span(statement, span);
+        }
+    }
+
+    optimized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn optimize_source(source: &str) -> (Program, OptimizationReport) {
+        optimize(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn test_redundant_clr_and_nop() {
+        let (program, report) =
+            optimize_source("rdax adcl, 1.0\nwrax dacl, 0.0\nclr\nnop\nrdax adcr, 1.0");
+        assert_eq!(program.instructions().len(), 3);
+        assert_eq!(report.before, 5);
+        assert_eq!(report.after, 3);
+        assert_eq!(
+            report.changes,
+            vec![
+                Change {
+                    index: 2,
+                    optimization: Optimization::RedundantClr
+                },
+                Change {
+                    index: 3,
+                    optimization: Optimization::Nop
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_sof() {
+        let (program, report) = optimize_source("sof 0.5, 0.25\nsof -1.0, 0.5");
+        assert_eq!(
+            program.instructions(),
+            vec![&Instruction::SOF {
+                coeff: -0.5,
+                offset: 0.25
+            }]
+        );
+        assert_eq!(report.changes.len(), 1);
+
+        // The first SOF could saturate, so the pair is kept
+        let (program, _) = optimize_source("sof -2.0, 0.0\nsof -2.0, 0.0");
+        assert_eq!(program.instructions().len(), 2);
+    }
+
+    #[test]
+    fn test_unread_write() {
+        let (program, report) = optimize_source(
+            "rdax adcl, 1.0\nwrax reg0, 0.5\nwrax reg1, 1.0\nwrax reg2, 1.0\nrdax reg2, 1.0\nwrax dacl, 0.0",
+        );
+        let instructions = program.instructions();
+        assert_eq!(
+            *instructions[1],
+            Instruction::SOF {
+                coeff: 0.5,
+                offset: 0.0
+            }
+        );
+        assert_eq!(
+            *instructions[2],
+            Instruction::WRAX {
+                reg: Register::REG(2),
+                coeff: 1.0
+            }
+        );
+        assert_eq!(instructions.len(), 5);
+        assert!(report.changes.iter().any(|change| change.optimization
+            == Optimization::UnreadWrite {
+                reg: Register::REG(1),
+                removed: true
+            }));
+    }
+
+    #[test]
+    fn test_skip_targets_are_kept() {
+        let source = "skp run, done\nclr\nnop\nsof 0.5, 0.0\ndone: clr\nclr\nwrax dacl, 0.0";
+        let (program, _) = optimize_source(source);
+        let instructions = program.instructions();
+
+        // The first CLR and the target CLR stay, the NOP and last CLR go
+        assert_eq!(instructions.len(), 5);
+        assert_eq!(program.resolve_label("done"), Some(3));
+        assert_eq!(
+            *instructions[0],
+            Instruction::SKP {
+                condition: SkipCondition::RUN,
+                offset: 2
+            }
+        );
+        assert_eq!(&source[program.instruction_span(3).unwrap()], "done: clr");
+    }
+
+    #[test]
+    fn test_pacc_readers_are_respected() {
+        let (program, report) = optimize_source("rdax adcl, 1.0\nnop\nwrlx reg0, 0.5");
+        assert_eq!(program.instructions().len(), 3);
+        assert!(report.changes.is_empty());
+
+        // A CLR after a write that clears ACC still sets the PACC of a
+        // following WRHX, WRLX or SKP ZRC
+        for reader in ["wrhx reg1, 0.5", "wrlx reg1, 0.5", "skp zrc, 1\nnop"] {
+            let source = format!(
+                "rdax adcl, 1.0\nwrax reg0, 0.0\nclr\n{}\nrdax reg0, 1.0\nrdax reg1, 1.0\nwrax dacl, 0.0",
+                reader
+            );
+            let (program, report) = optimize_source(&source);
+            assert_eq!(*program.instructions()[2], Instruction::CLR, "{}", reader);
+            assert!(!report
+                .changes
+                .iter()
+                .any(|change| change.optimization == Optimization::RedundantClr));
+        }
+    }
+}
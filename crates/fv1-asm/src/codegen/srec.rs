@@ -0,0 +1,79 @@
+//! Motorola S-Records
+//!
+//! Writes the S-record files some EEPROM programmers take in place of Intel
+//! HEX. Images up to 64KB use 16-bit addresses (S19); larger images use
+//! 24-bit addresses (S28). FV-1 images always fit in S19.
+
+/// Format bytes as Motorola S-records: a header record, data records
+/// starting at address 0, a record count and the termination record
+pub(crate) fn motorola_srec(bytes: &[u8]) -> String {
+    // 16-bit addresses use S1/S9 records, 24-bit addresses S2/S8
+    let (data_type, end_type, addr_len) = if bytes.len() <= 0x1_0000 {
+        ('1', '9', 2)
+    } else {
+        ('2', '8', 3)
+    };
+
+    let mut srec = record('0', 2, 0, &[]);
+    let chunks = bytes.chunks(16);
+    let count = chunks.len();
+    for (i, chunk) in chunks.enumerate() {
+        srec.push_str(&record(data_type, addr_len, i * 16, chunk));
+    }
+    if count <= 0xFFFF {
+        srec.push_str(&record('5', 2, count, &[]));
+    }
+    srec.push_str(&record(end_type, addr_len, 0, &[]));
+    srec
+}
+
+/// Format one record. The byte count covers the address, data and
+/// checksum, and the checksum is the ones' complement of the low byte of
+/// their sum.
+fn record(kind: char, addr_len: usize, addr: usize, data: &[u8]) -> String {
+    let addr_bytes = &(addr as u32).to_be_bytes()[4 - addr_len..];
+    let count = (addr_len + data.len() + 1) as u8;
+
+    let mut line = format!("S{}{:02X}", kind, count);
+    let mut sum = count;
+    for &byte in addr_bytes.iter().chain(data) {
+        line.push_str(&format!("{:02X}", byte));
+        sum = sum.wrapping_add(byte);
+    }
+    line.push_str(&format!("{:02X}\n", !sum));
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_s19_records() {
+        let srec = motorola_srec(&[0x00, 0x00, 0x00, 0x11, 0x80, 0x08, 0x00, 0x14]);
+        assert_eq!(
+            srec,
+            "\
+S0030000FC
+S10B0000000000118008001447
+S5030001FB
+S9030000FC
+"
+        );
+
+        let srec = motorola_srec(&[0xFF; 40]);
+        let lines: Vec<&str> = srec.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[2].starts_with("S1130010"));
+        assert_eq!(lines[3], "S10B0020FFFFFFFFFFFFFFFFDC");
+    }
+
+    #[test]
+    fn test_s28_records() {
+        let srec = motorola_srec(&vec![0; 0x1_0010]);
+        let lines: Vec<&str> = srec.lines().collect();
+        assert!(lines[1].starts_with("S214000000"));
+        assert!(lines[0x1001].starts_with("S21401000000"));
+        assert_eq!(lines.last(), Some(&"S804000000FB"));
+    }
+}
@@ -0,0 +1,185 @@
+//! Symbol Table
+//!
+//! Collects the names a program defines with `EQU`, `MEM` and labels, along
+//! with their values and where they were defined, for tools such as
+//! listings and disassembler re-symbolication.
+
+use crate::{
+    analysis::resources::memory_regions,
+    ast::{Directive, Program, Statement, Value},
+};
+use std::ops::Range;
+
+/// What a symbol names and its value
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolKind {
+    /// `EQU` constant, with references to earlier equates resolved
+    Equate(Value),
+    /// `MEM` delay memory region
+    Memory {
+        /// First address
+        start: u16,
+        /// Size as declared, one word less than the region occupies
+        size: u16,
+    },
+    /// Label on an instruction
+    Label {
+        /// Index of the instruction
+        index: usize,
+    },
+}
+
+/// A named definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Source span of the definition, if it was parsed from source
+    pub span: Option<Range<usize>>,
+}
+
+/// Symbols defined by a program, equates and memory in declaration order
+/// followed by labels in program order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    /// Collect the symbols a program defines
+    pub fn from_program(program: &Program) -> Self {
+        let mut table = Self::default();
+        let mut regions = memory_regions(program).into_iter();
+
+        for (index, directive) in program.directives.iter().enumerate() {
+            let (name, kind) = match directive {
+                Directive::Equate { name, value } => {
+                    let value = match value {
+                        Value::Identifier(alias) => match table.get(alias) {
+                            Some(Symbol {
+                                kind: SymbolKind::Equate(value),
+                                ..
+                            }) => value.clone(),
+                            _ => value.clone(),
+                        },
+                        _ => value.clone(),
+                    };
+                    (name, SymbolKind::Equate(value))
+                }
+                Directive::MemoryAllocation { name, size } => {
+                    let Some(region) = regions.next() else {
+                        continue;
+                    };
+                    let kind = SymbolKind::Memory {
+                        start: region.start,
+                        size: *size,
+                    };
+                    (name, kind)
+                }
+                Directive::SpinAsm { .. } => continue,
+            };
+            table.symbols.push(Symbol {
+                name: name.clone(),
+                kind,
+                span: program.directive_span(index),
+            });
+        }
+
+        let mut labels: Vec<(&String, &usize)> = program.labels.iter().collect();
+        labels.sort_by_key(|(name, index)| (**index, name.as_str()));
+        for (name, index) in labels {
+            let span = program
+                .statements
+                .iter()
+                .position(|statement| match statement {
+                    Statement::Label(label) | Statement::LabeledInstruction { label, .. } => {
+                        label == name
+                    }
+                    _ => false,
+                })
+                .and_then(|statement| program.statement_span(statement));
+            table.symbols.push(Symbol {
+                name: name.clone(),
+                kind: SymbolKind::Label { index: *index },
+                span,
+            });
+        }
+
+        table
+    }
+
+    /// Look up a symbol by name
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// Iterate over the symbols
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// Number of symbols
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Check whether the table has no symbols
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{codegen::Assembler, parser::Parser};
+
+    #[test]
+    fn test_symbol_table() {
+        let source = "\
+EQU gain, 0.5
+EQU level, gain
+MEM dly 100
+MEM ap 10
+rdax adcl, gain
+skp run, done
+wra dly, 0.0
+done:  wrax dacl, 0.0
+";
+        let program = Parser::new(source).parse().unwrap();
+        let (binary, symbols) = Assembler::new().assemble_with_symbols(&program).unwrap();
+        assert_eq!(binary.len(), 128);
+        assert_eq!(symbols.len(), 5);
+
+        let level = symbols.get("level").unwrap();
+        assert_eq!(level.kind, SymbolKind::Equate(Value::Float(0.5)));
+        assert_eq!(&source[level.span.clone().unwrap()], "EQU level, gain");
+        assert_eq!(
+            symbols.get("ap").unwrap().kind,
+            SymbolKind::Memory {
+                start: 101,
+                size: 10
+            }
+        );
+
+        let done = symbols.get("done").unwrap();
+        assert_eq!(done.kind, SymbolKind::Label { index: 3 });
+        assert!(source[done.span.clone().unwrap()].starts_with("done:"));
+        assert!(symbols.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_symbols_follow_optimization() {
+        let program = Parser::new("nop\nskp run, end\nrdax adcl, 1.0\nend: wrax dacl, 0.0")
+            .parse()
+            .unwrap();
+        let (_, symbols) = Assembler::new()
+            .with_optimization(true)
+            .assemble_with_symbols(&program)
+            .unwrap();
+        assert_eq!(
+            symbols.get("end").unwrap().kind,
+            SymbolKind::Label { index: 2 }
+        );
+    }
+}
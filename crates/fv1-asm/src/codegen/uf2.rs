@@ -0,0 +1,86 @@
+//! UF2
+//!
+//! Writes UF2 files for boards with a USB mass storage bootloader, which
+//! are flashed by copying the file onto the drive the board presents. Each
+//! 512-byte block carries 256 bytes of payload and the address to write it
+//! to. See <https://github.com/microsoft/uf2>.
+
+/// First magic number of a block
+const MAGIC_START0: u32 = 0x0A32_4655;
+/// Second magic number of a block
+const MAGIC_START1: u32 = 0x9E5D_5157;
+/// Magic number ending a block
+const MAGIC_END: u32 = 0x0AB1_6F30;
+/// Flag marking the file size field as a family ID
+const FLAG_FAMILY_ID: u32 = 0x0000_2000;
+
+/// Size of a block
+const BLOCK_SIZE: usize = 512;
+/// Payload bytes per block
+const PAYLOAD_SIZE: usize = 256;
+
+/// Format bytes as UF2 blocks writing them from `base_addr` onward on
+/// devices of family `family_id`
+pub(crate) fn uf2(bytes: &[u8], family_id: u32, base_addr: u32) -> Vec<u8> {
+    let chunks = bytes.chunks(PAYLOAD_SIZE);
+    let num_blocks = chunks.len() as u32;
+
+    let mut out = Vec::with_capacity(chunks.len() * BLOCK_SIZE);
+    for (block_no, chunk) in chunks.enumerate() {
+        let header = [
+            MAGIC_START0,
+            MAGIC_START1,
+            FLAG_FAMILY_ID,
+            base_addr + (block_no * PAYLOAD_SIZE) as u32,
+            chunk.len() as u32,
+            block_no as u32,
+            num_blocks,
+            family_id,
+        ];
+        let start = out.len();
+        for word in header {
+            out.extend_from_slice(&word.to_le_bytes());
+        }
+        out.extend_from_slice(chunk);
+        // Data area is 476 bytes, zero beyond the payload
+        out.resize(start + BLOCK_SIZE - 4, 0);
+        out.extend_from_slice(&MAGIC_END.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(block: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_uf2_blocks() {
+        let bytes: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        let out = uf2(&bytes, 0xE48B_FF56, 0x1000_0000);
+        assert_eq!(out.len(), 2 * BLOCK_SIZE);
+
+        let blocks: Vec<&[u8]> = out.chunks(BLOCK_SIZE).collect();
+        for (i, block) in blocks.iter().enumerate() {
+            assert_eq!(word(block, 0), MAGIC_START0);
+            assert_eq!(word(block, 4), MAGIC_START1);
+            assert_eq!(word(block, 8), FLAG_FAMILY_ID);
+            assert_eq!(word(block, 20), i as u32);
+            assert_eq!(word(block, 24), 2);
+            assert_eq!(word(block, 28), 0xE48B_FF56);
+            assert_eq!(word(block, 508), MAGIC_END);
+        }
+
+        assert_eq!(word(blocks[0], 12), 0x1000_0000);
+        assert_eq!(word(blocks[0], 16), 256);
+        assert_eq!(&blocks[0][32..288], &bytes[..256]);
+
+        assert_eq!(word(blocks[1], 12), 0x1000_0100);
+        assert_eq!(word(blocks[1], 16), 44);
+        assert_eq!(&blocks[1][32..76], &bytes[256..]);
+        assert!(blocks[1][76..508].iter().all(|&byte| byte == 0));
+    }
+}
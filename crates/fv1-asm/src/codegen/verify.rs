@@ -0,0 +1,128 @@
+//! Binary Verification
+//!
+//! Checks that a binary of unknown origin, such as an EEPROM dump, looks
+//! like an FV-1 program before it is flashed or disassembled: every word
+//! decodes, the image is not blank or erased, and the decoded program has
+//! no likely mistakes like skips past its end.
+
+use crate::{
+    analysis::{validate, Finding},
+    ast::{Program, Statement},
+    codegen::{decoder::decode_instruction, Binary},
+    instruction::Instruction,
+};
+
+/// A word that does not decode to an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndecodableWord {
+    pub index: usize,
+    pub word: u32,
+    /// Why decoding failed
+    pub reason: String,
+}
+
+/// Result of [`Binary::verify`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyReport {
+    /// Instructions before trailing padding
+    pub used: usize,
+    /// Every word is `0xFFFFFFFF`, as in erased EEPROM
+    pub erased: bool,
+    /// Words that do not decode, in order
+    pub undecodable: Vec<UndecodableWord>,
+    /// Likely mistakes in the decoded program, see [`validate`]
+    pub findings: Vec<Finding>,
+}
+
+impl VerifyReport {
+    /// Whether the binary looks like a working FV-1 program: it holds
+    /// instructions, every word decodes and no skip leaves the program
+    pub fn is_valid(&self) -> bool {
+        self.used > 0
+            && !self.erased
+            && self.undecodable.is_empty()
+            && !self
+                .findings
+                .iter()
+                .any(|finding| matches!(finding, Finding::SkipPastEnd { .. }))
+    }
+}
+
+/// Decode every word of a binary and check the result
+pub(crate) fn verify(binary: &Binary) -> VerifyReport {
+    let words = binary.without_padding();
+    let erased = !binary.is_empty()
+        && binary
+            .instructions()
+            .iter()
+            .all(|&word| word == 0xFFFF_FFFF);
+
+    let mut program = Program::new();
+    let mut undecodable = Vec::new();
+    for (index, &word) in words.iter().enumerate() {
+        let inst = decode_instruction(word).unwrap_or_else(|error| {
+            undecodable.push(UndecodableWord {
+                index,
+                word,
+                reason: error.to_string(),
+            });
+            Instruction::RAW(word)
+        });
+        program.add_statement(Statement::Instruction(inst));
+    }
+
+    VerifyReport {
+        used: words.len(),
+        erased,
+        undecodable,
+        findings: validate(&program),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{codegen::Assembler, parser::Parser};
+
+    fn binary(words: &[u32]) -> Binary {
+        let mut binary = Binary::new();
+        for &word in words {
+            binary.push(word);
+        }
+        binary
+    }
+
+    #[test]
+    fn test_verify_program() {
+        let program = Parser::new("rdax adcl, 1.0\nwrax dacl, 0.0")
+            .parse()
+            .unwrap();
+        let report = verify(&Assembler::new().assemble(&program).unwrap());
+        assert_eq!(report.used, 2);
+        assert!(report.undecodable.is_empty());
+        assert!(report.findings.is_empty());
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn test_verify_suspicious() {
+        assert!(!verify(&binary(&[0; 128])).is_valid());
+
+        let report = verify(&binary(&[0xFFFF_FFFF; 128]));
+        assert!(report.erased);
+        assert!(!report.is_valid());
+
+        // SKP RUN, 16 from the first of two instructions
+        let report = verify(&binary(&[0x8200_0011, 0x0000_0206]));
+        assert!(matches!(
+            report.findings[..],
+            [Finding::SkipPastEnd { index: 0, .. }, ..]
+        ));
+        assert!(!report.is_valid());
+
+        let report = verify(&binary(&[0x0000_0206, 0xFFFF_FFFF]));
+        assert_eq!(report.undecodable.len(), 1);
+        assert_eq!(report.undecodable[0].index, 1);
+        assert!(!report.is_valid());
+    }
+}
@@ -0,0 +1,78 @@
+//! FV-1 Coefficient Quantization
+//!
+//! Helpers for finding the values a coefficient will actually take once it
+//! is encoded into one of the FV-1 fixed-point formats.
+
+/// Find the representable S1.14 values around `value`
+///
+/// Returns `(below, nearest, above)` where `nearest` is the value the
+/// encoder will emit and `below`/`above` are its neighbors one step away.
+/// Values outside the format's range are clamped, and neighbors at the edge
+/// of the range are clamped to the range as well.
+pub fn nearest_s114(value: f32) -> (f32, f32, f32) {
+    nearest_fixed(value, 16, 14)
+}
+
+/// Find the representable S.10 values around `value`
+///
+/// See [`nearest_s114`] for the meaning of the returned tuple.
+pub fn nearest_s10(value: f32) -> (f32, f32, f32) {
+    nearest_fixed(value, 11, 10)
+}
+
+/// Quantize `value` to a `width`-bit two's complement format with
+/// `frac_bits` fractional bits, returning the result and its neighbors
+fn nearest_fixed(value: f32, width: u32, frac_bits: u32) -> (f32, f32, f32) {
+    let scale = (1u32 << frac_bits) as f32;
+    let min = -(1i32 << (width - 1));
+    let max = (1i32 << (width - 1)) - 1;
+
+    let nearest = if value.is_nan() {
+        0
+    } else {
+        ((value * scale).round() as i64).clamp(min as i64, max as i64) as i32
+    };
+    let below = (nearest - 1).max(min);
+    let above = (nearest + 1).min(max);
+
+    (
+        below as f32 / scale,
+        nearest as f32 / scale,
+        above as f32 / scale,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_s114_exact() {
+        let step = 1.0 / 16384.0;
+        assert_eq!(nearest_s114(0.5), (0.5 - step, 0.5, 0.5 + step));
+    }
+
+    #[test]
+    fn test_nearest_s114_rounds() {
+        let step = 1.0 / 16384.0;
+        let (below, nearest, above) = nearest_s114(0.1);
+        assert!((nearest - 0.1).abs() <= step / 2.0);
+        assert_eq!(below, nearest - step);
+        assert_eq!(above, nearest + step);
+    }
+
+    #[test]
+    fn test_nearest_s114_clamps() {
+        let max = 32767.0 / 16384.0;
+        assert_eq!(nearest_s114(2.0).1, max);
+        assert_eq!(nearest_s114(2.0).2, max);
+        assert_eq!(nearest_s114(-5.0), (-2.0, -2.0, -2.0 + 1.0 / 16384.0));
+    }
+
+    #[test]
+    fn test_nearest_s10() {
+        let step = 1.0 / 1024.0;
+        assert_eq!(nearest_s10(-0.25), (-0.25 - step, -0.25, -0.25 + step));
+        assert_eq!(nearest_s10(1.0).1, 1023.0 / 1024.0);
+    }
+}
@@ -3,6 +3,9 @@
 /// Maximum number of instructions in a program
 pub const MAX_INSTRUCTIONS: usize = 128;
 
+/// Number of programs in an EEPROM bank
+pub const PROGRAM_SLOTS: usize = 8;
+
 /// Delay RAM size in samples
 pub const DELAY_RAM_SIZE: usize = 32768;
 
@@ -1,8 +1,11 @@
 #![allow(unused_assignments)]
 
-use miette::Diagnostic;
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceCode, SourceSpan};
+use std::fmt;
 use thiserror::Error;
 
+use crate::register::{Lfo, Register};
+
 /// Errors that can occur during parsing
 #[derive(Error, Debug, Diagnostic)]
 pub enum ParseError {
@@ -50,6 +53,99 @@ pub enum ParseError {
         span: std::ops::Range<usize>,
     },
 
+    #[error("label {name} is defined more than once")]
+    #[diagnostic(code(parse::duplicate_label))]
+    DuplicateLabel {
+        name: String,
+        #[label("first defined here")]
+        first: std::ops::Range<usize>,
+        #[label("redefined here")]
+        span: std::ops::Range<usize>,
+    },
+
+    #[error("undefined symbol: {name}")]
+    #[diagnostic(code(parse::undefined_symbol))]
+    UndefinedSymbol {
+        name: String,
+        #[label("symbol used here")]
+        span: std::ops::Range<usize>,
+    },
+
+    #[error("cannot allocate {size} words of delay memory for {name} ({available} available)")]
+    #[diagnostic(code(parse::memory_overflow))]
+    MemoryOverflow {
+        name: String,
+        size: u16,
+        available: usize,
+        #[label("allocated here")]
+        span: std::ops::Range<usize>,
+    },
+
+    #[error("skip to {label} out of range: offset {offset} (must be 0 to 63)")]
+    #[diagnostic(code(parse::skip_out_of_range))]
+    SkipOutOfRange {
+        label: String,
+        offset: isize,
+        #[label("skip target here")]
+        span: std::ops::Range<usize>,
+    },
+
+    #[error("coefficient {value} out of range for {format} (must be at least {min} and less than {max})")]
+    #[diagnostic(code(parse::coefficient_out_of_range))]
+    CoefficientOutOfRange {
+        value: f32,
+        format: &'static str,
+        min: f32,
+        max: f32,
+        #[label("out of range for {format}")]
+        span: std::ops::Range<usize>,
+    },
+
+    #[error("{operand} {value} out of range (must be 0 to {max})")]
+    #[diagnostic(code(parse::operand_out_of_range))]
+    OperandOutOfRange {
+        operand: &'static str,
+        value: f32,
+        max: u32,
+        #[label("out of range {operand}")]
+        span: std::ops::Range<usize>,
+    },
+
+    #[error("macro {name} is already defined")]
+    #[diagnostic(code(parse::duplicate_macro))]
+    DuplicateMacro {
+        name: String,
+        #[label("redefined here")]
+        span: std::ops::Range<usize>,
+    },
+
+    #[error("macro {name} has no matching .endm")]
+    #[diagnostic(code(parse::unterminated_macro))]
+    UnterminatedMacro {
+        name: String,
+        #[label("macro defined here")]
+        span: std::ops::Range<usize>,
+    },
+
+    #[error("macro {name} expects {expected} arguments, found {found}")]
+    #[diagnostic(code(parse::macro_arguments))]
+    MacroArguments {
+        name: String,
+        expected: usize,
+        found: usize,
+        #[label("invoked here")]
+        span: std::ops::Range<usize>,
+    },
+
+    #[error("macro {name} nested more than {max} levels deep")]
+    #[diagnostic(code(parse::macro_depth))]
+    MacroDepthExceeded {
+        name: String,
+        max: usize,
+        #[label("expanded here")]
+        span: std::ops::Range<usize>,
+    },
+
     #[error("invalid token")]
     #[diagnostic(code(parse::invalid_token))]
     InvalidToken {
@@ -93,7 +189,173 @@ pub enum CodegenError {
     #[diagnostic(code(codegen::invalid_cho_mode))]
     InvalidChoMode { bits: u8 },
 
+    #[error("{mnemonic} instruction 0x{word:08X} sets reserved bits")]
+    #[diagnostic(code(codegen::reserved_bits))]
+    ReservedBits { mnemonic: &'static str, word: u32 },
+
+    #[error("{reg:?} has no FV-1 register address")]
+    #[diagnostic(code(codegen::register_not_addressable))]
+    RegisterNotAddressable { reg: Register },
+
+    #[error("{lfo:?} cannot be used with {instruction}")]
+    #[diagnostic(code(codegen::invalid_lfo_for_instruction))]
+    InvalidLfoForInstruction { instruction: &'static str, lfo: Lfo },
+
+    #[error("undefined label: {name}")]
+    #[diagnostic(code(codegen::undefined_label))]
+    UndefinedLabel { name: String },
+
+    #[error("skip to {label} out of range: offset {offset} (must be 0 to 63)")]
+    #[diagnostic(code(codegen::skip_out_of_range))]
+    SkipOutOfRange { label: String, offset: isize },
+
     #[error("invalid binary size: {size} bytes (expected {expected})")]
     #[diagnostic(code(codegen::invalid_binary_size))]
     InvalidBinarySize { size: usize, expected: usize },
+
+    #[error("I/O error: {0}")]
+    #[diagnostic(code(codegen::io))]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid Intel HEX on line {line}: {reason}")]
+    #[diagnostic(code(codegen::invalid_hex))]
+    InvalidHex { line: usize, reason: String },
+
+    #[error("invalid program slot {slot} (must be 0 to 7)")]
+    #[diagnostic(code(codegen::invalid_slot))]
+    InvalidSlot { slot: usize },
+
+    #[error("too many programs for one bank: {count} (max {max})")]
+    #[diagnostic(code(codegen::too_many_programs))]
+    TooManyPrograms { count: usize, max: usize },
+
+    #[error("{} errors in program", .errors.len())]
+    #[diagnostic(code(codegen::multiple))]
+    Multiple {
+        #[related]
+        errors: Vec<CodegenError>,
+    },
+
+    #[error("{error}")]
+    #[diagnostic(code(codegen::in_instruction))]
+    InInstruction {
+        error: Box<CodegenError>,
+        #[label("{error}")]
+        span: std::ops::Range<usize>,
+    },
+}
+
+impl CodegenError {
+    /// Get the underlying error, without its source location
+    pub fn without_span(&self) -> &CodegenError {
+        match self {
+            CodegenError::InInstruction { error, .. } => error.without_span(),
+            error => error,
+        }
+    }
+}
+
+/// Non-fatal problems found while assembling a program
+#[derive(Error, Debug, Diagnostic, Clone, PartialEq)]
+pub enum Warning {
+    #[error("coefficient quantized from {value} to {encoded} ({error_db:+.4} dB)")]
+    #[diagnostic(code(warning::quantized), severity(Warning))]
+    Quantized {
+        value: f32,
+        encoded: f32,
+        /// Gain of the encoded value relative to the requested value
+        error_db: f32,
+        #[label("encoded as {encoded}")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("register REG{reg} written but never read")]
+    #[diagnostic(code(warning::unread_register), severity(Warning))]
+    UnreadRegister {
+        reg: u8,
+        #[label("first written here")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("program uses {used}/{max} instructions")]
+    #[diagnostic(code(warning::nearly_full), severity(Warning))]
+    NearlyFull { used: usize, max: usize },
+
+    #[error("program in slot {slot} overwritten")]
+    #[diagnostic(code(warning::slot_overwritten), severity(Warning))]
+    SlotOverwritten { slot: usize },
+}
+
+/// An error together with the named source code its spans refer to, so
+/// that diagnostics render with the offending code highlighted
+#[derive(Debug)]
+pub struct SourceError<E> {
+    error: E,
+    source_code: Box<NamedSource<String>>,
+}
+
+impl<E> SourceError<E> {
+    /// Attach the source code `error` was produced from, under `name`
+    pub fn new(error: E, name: impl AsRef<str>, source: impl Into<String>) -> Self {
+        Self {
+            error,
+            source_code: Box::new(NamedSource::new(name, source.into())),
+        }
+    }
+
+    /// Get the underlying error
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Discard the source code, returning the underlying error
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for SourceError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<E: std::error::Error> std::error::Error for SourceError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl<E: Diagnostic> Diagnostic for SourceError<E> {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.code()
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        self.error.severity()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.help()
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.url()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(self.source_code.as_ref())
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.error.labels()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        self.error.related()
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.error.diagnostic_source()
+    }
 }
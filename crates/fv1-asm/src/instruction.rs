@@ -1,6 +1,7 @@
 use crate::register::{Lfo, Register};
 
 /// FV-1 Instruction Set
+#[allow(non_camel_case_types)] // CHO_SOF names the CHO mode it stands for
 #[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     // Accumulator operations
@@ -27,6 +28,18 @@ pub enum Instruction {
         coeff: f32,
     },
 
+    /// Write ACC to register, high-shelf: [REG] = ACC, ACC = ACC * C + PACC
+    WRHX {
+        reg: Register,
+        coeff: f32,
+    },
+
+    /// Write ACC to register, low-shelf: [REG] = ACC, ACC = (PACC - ACC) * C + PACC
+    WRLX {
+        reg: Register,
+        coeff: f32,
+    },
+
     /// Write ACC to delay RAM: [ADDR] = ACC * C, ACC = ACC * D
     WRA {
         addr: u16,
@@ -89,6 +102,9 @@ pub enum Instruction {
         mask: u32,
     },
 
+    /// Bitwise complement of ACC (XOR with all ones)
+    NOT,
+
     /// Shift left
     SHL,
 
@@ -134,12 +150,25 @@ pub enum Instruction {
     },
 
     // Delay RAM addressing
+    /// LFO-modulated delay read or LFO load. The parser and decoder give
+    /// SOF mode as [`Instruction::CHO_SOF`]; in this form its `addr` is the
+    /// raw offset field.
     CHO {
         mode: ChoMode,
         lfo: Lfo,
         flags: ChoFlags,
         addr: u16,
     },
+    /// CHO SOF: ACC = ACC * LFO + OFFSET, with an S.15 offset in place of
+    /// the delay address
+    CHO_SOF {
+        lfo: Lfo,
+        flags: ChoFlags,
+        offset: f32,
+    },
+
+    /// Raw 32-bit instruction word, emitted as-is
+    RAW(u32),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -158,13 +187,59 @@ pub enum ChoMode {
     RDAL, // Read delay and load LFO value
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct ChoFlags {
-    pub rptr2: bool, // Use second read pointer
-    pub na: bool,    // No add (crossfade control)
+    pub cos: bool,   // Use cosine output of SIN LFO (SIN when clear)
+    pub reg: bool,   // Register LFO value for later CHO instructions
     pub compc: bool, // Complement coefficient
     pub compa: bool, // Complement address
-    pub rptr2_select: bool,
+    pub rptr2: bool, // Use second read pointer
+    pub na: bool,    // No add (crossfade control)
+}
+
+impl ChoFlags {
+    /// COS flag bit
+    pub const COS: u8 = 0x01;
+    /// REG flag bit
+    pub const REG: u8 = 0x02;
+    /// COMPC flag bit
+    pub const COMPC: u8 = 0x04;
+    /// COMPA flag bit
+    pub const COMPA: u8 = 0x08;
+    /// RPTR2 flag bit
+    pub const RPTR2: u8 = 0x10;
+    /// NA flag bit
+    pub const NA: u8 = 0x20;
+
+    /// Build flags from the 6-bit hardware flag field
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            cos: bits & Self::COS != 0,
+            reg: bits & Self::REG != 0,
+            compc: bits & Self::COMPC != 0,
+            compa: bits & Self::COMPA != 0,
+            rptr2: bits & Self::RPTR2 != 0,
+            na: bits & Self::NA != 0,
+        }
+    }
+
+    /// Get the 6-bit hardware flag field
+    pub fn bits(&self) -> u8 {
+        let mut bits = 0;
+        for (set, bit) in [
+            (self.cos, Self::COS),
+            (self.reg, Self::REG),
+            (self.compc, Self::COMPC),
+            (self.compa, Self::COMPA),
+            (self.rptr2, Self::RPTR2),
+            (self.na, Self::NA),
+        ] {
+            if set {
+                bits |= bit;
+            }
+        }
+        bits
+    }
 }
 
 #[cfg(test)]
@@ -207,4 +282,16 @@ mod tests {
         let cond = SkipCondition::GEZ;
         assert_eq!(cond, SkipCondition::GEZ);
     }
+
+    #[test]
+    fn test_cho_flags_bits() {
+        let flags = ChoFlags {
+            reg: true,
+            compc: true,
+            ..Default::default()
+        };
+        assert_eq!(flags.bits(), 0x06);
+        assert_eq!(ChoFlags::from_bits(0x06), flags);
+        assert_eq!(ChoFlags::from_bits(0x3F).bits(), 0x3F);
+    }
 }
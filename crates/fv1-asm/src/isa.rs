@@ -0,0 +1,743 @@
+//! FV-1 Instruction Set Description
+//!
+//! A declarative description of every mnemonic the assembler accepts: its
+//! opcode, the bits it always sets, and where each operand lives in the
+//! instruction word. The parser, encoder, decoder and disassembler all work
+//! from this table, so adding or changing an instruction happens here and in
+//! the [`describe`]/[`build`] conversions below.
+
+use crate::{
+    instruction::{ChoFlags, ChoMode, Instruction, SkipCondition},
+    register::{Lfo, Register},
+};
+
+/// Version of the exported description format
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Signed fixed-point coefficient formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixedFormat {
+    /// S1.14: -2.0 to ~2.0 in 16 bits
+    S114,
+    /// S1.9: -2.0 to ~2.0 in 11 bits
+    S19,
+    /// S.10: -1.0 to ~1.0 in 11 bits
+    S10,
+    /// S4.6: -16.0 to ~16.0 in 11 bits
+    S46,
+    /// S.15: -1.0 to ~1.0 in 16 bits
+    S15,
+}
+
+impl FixedFormat {
+    /// Datasheet name of the format
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FixedFormat::S114 => "S1.14",
+            FixedFormat::S19 => "S1.9",
+            FixedFormat::S10 => "S.10",
+            FixedFormat::S46 => "S4.6",
+            FixedFormat::S15 => "S.15",
+        }
+    }
+
+    /// Width of the field in bits, including the sign
+    pub fn width(&self) -> u32 {
+        match self {
+            FixedFormat::S114 | FixedFormat::S15 => 16,
+            FixedFormat::S19 | FixedFormat::S10 | FixedFormat::S46 => 11,
+        }
+    }
+
+    /// Number of fractional bits
+    pub fn frac_bits(&self) -> u32 {
+        match self {
+            FixedFormat::S114 => 14,
+            FixedFormat::S19 => 9,
+            FixedFormat::S10 => 10,
+            FixedFormat::S46 => 6,
+            FixedFormat::S15 => 15,
+        }
+    }
+
+    /// Values the format can represent, from the most negative value up to
+    /// (but excluding) the positive limit
+    pub fn range(&self) -> std::ops::Range<f32> {
+        match self {
+            FixedFormat::S114 | FixedFormat::S19 => -2.0..2.0,
+            FixedFormat::S10 | FixedFormat::S15 => -1.0..1.0,
+            FixedFormat::S46 => -16.0..16.0,
+        }
+    }
+}
+
+/// Kind of value an operand field holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    /// 6-bit register address
+    Register,
+    /// Delay RAM address
+    Address,
+    /// Signed fixed-point coefficient
+    Coefficient(FixedFormat),
+    /// 24-bit bit mask
+    Mask,
+    /// SKP condition flags (RUN, ZRC, ZRO, GEZ, NEG)
+    SkipCondition,
+    /// Number of instructions to skip
+    SkipOffset,
+    /// Any LFO (SIN0, SIN1, RMP0, RMP1)
+    Lfo,
+    /// SIN LFO selector (SIN0, SIN1)
+    SinLfo,
+    /// RMP LFO selector (RMP0, RMP1)
+    RmpLfo,
+    /// WLDS frequency
+    Frequency,
+    /// WLDS amplitude
+    Amplitude,
+    /// CHO mode (RDA, SOF, RDAL)
+    ChoMode,
+    /// CHO flags (COS, REG, COMPC, COMPA, RPTR2, NA)
+    ChoFlags,
+}
+
+impl OperandKind {
+    /// Short lowercase name of the operand kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperandKind::Register => "register",
+            OperandKind::Address => "address",
+            OperandKind::Coefficient(_) => "coefficient",
+            OperandKind::Mask => "mask",
+            OperandKind::SkipCondition => "skip_condition",
+            OperandKind::SkipOffset => "skip_offset",
+            OperandKind::Lfo => "lfo",
+            OperandKind::SinLfo => "sin_lfo",
+            OperandKind::RmpLfo => "rmp_lfo",
+            OperandKind::Frequency => "frequency",
+            OperandKind::Amplitude => "amplitude",
+            OperandKind::ChoMode => "cho_mode",
+            OperandKind::ChoFlags => "cho_flags",
+        }
+    }
+}
+
+/// An operand and the bits it occupies in the instruction word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperandSpec {
+    /// Operand name
+    pub name: &'static str,
+    /// Kind of value the operand holds
+    pub kind: OperandKind,
+    /// Lowest bit of the field
+    pub lsb: u32,
+    /// Width of the field in bits
+    pub width: u32,
+}
+
+impl OperandSpec {
+    const fn new(name: &'static str, kind: OperandKind, lsb: u32, width: u32) -> Self {
+        Self {
+            name,
+            kind,
+            lsb,
+            width,
+        }
+    }
+
+    /// Bits of the instruction word the field occupies
+    pub fn mask(&self) -> u32 {
+        (((1u64 << self.width) - 1) << self.lsb) as u32
+    }
+}
+
+/// Description of one mnemonic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionSpec {
+    /// Assembly mnemonic
+    pub mnemonic: &'static str,
+    /// Opcode in the low 5 bits
+    pub opcode: u32,
+    /// Bits always set besides the opcode
+    pub fixed: u32,
+    /// Operands in source order
+    pub operands: &'static [OperandSpec],
+    /// Mnemonic this one is shorthand for, if any
+    pub alias_of: Option<&'static str>,
+    /// One-line description
+    pub description: &'static str,
+}
+
+impl InstructionSpec {
+    /// Bits of the instruction word covered by operand fields
+    pub fn operand_mask(&self) -> u32 {
+        self.operands.iter().fold(0, |mask, op| mask | op.mask())
+    }
+}
+
+/// Mask of the opcode field
+pub const OPCODE_MASK: u32 = 0x1F;
+
+const REGISTER: OperandSpec = OperandSpec::new("reg", OperandKind::Register, 5, 6);
+const COEFF_S114: OperandSpec =
+    OperandSpec::new("coeff", OperandKind::Coefficient(FixedFormat::S114), 16, 16);
+const DELAY_ADDR: OperandSpec = OperandSpec::new("addr", OperandKind::Address, 5, 16);
+const COEFF_S19: OperandSpec =
+    OperandSpec::new("coeff", OperandKind::Coefficient(FixedFormat::S19), 21, 11);
+const MASK: OperandSpec = OperandSpec::new("mask", OperandKind::Mask, 8, 24);
+
+const fn spec(
+    mnemonic: &'static str,
+    opcode: u32,
+    operands: &'static [OperandSpec],
+    description: &'static str,
+) -> InstructionSpec {
+    InstructionSpec {
+        mnemonic,
+        opcode,
+        fixed: 0,
+        operands,
+        alias_of: None,
+        description,
+    }
+}
+
+const fn alias(
+    mnemonic: &'static str,
+    alias_of: &'static str,
+    opcode: u32,
+    fixed: u32,
+    operands: &'static [OperandSpec],
+    description: &'static str,
+) -> InstructionSpec {
+    InstructionSpec {
+        mnemonic,
+        opcode,
+        fixed,
+        operands,
+        alias_of: Some(alias_of),
+        description,
+    }
+}
+
+/// Every mnemonic the assembler accepts, in opcode order
+pub static INSTRUCTIONS: &[InstructionSpec] = &[
+    spec(
+        "RDA",
+        0x00,
+        &[DELAY_ADDR, COEFF_S19],
+        "ACC = ACC + [ADDR] * C",
+    ),
+    InstructionSpec {
+        mnemonic: "RMPA",
+        opcode: 0x01,
+        // The address field holds the ADDR_PTR register address
+        fixed: 0x18 << 5,
+        operands: &[COEFF_S19],
+        alias_of: None,
+        description: "ACC = ACC + [ADDR_PTR] * C",
+    },
+    spec(
+        "WRA",
+        0x02,
+        &[DELAY_ADDR, COEFF_S19],
+        "[ADDR] = ACC, ACC = ACC * C",
+    ),
+    spec(
+        "WRAP",
+        0x03,
+        &[DELAY_ADDR, COEFF_S19],
+        "[ADDR] = ACC, ACC = ACC * C + LR",
+    ),
+    spec(
+        "RDAX",
+        0x04,
+        &[REGISTER, COEFF_S114],
+        "ACC = ACC + [REG] * C",
+    ),
+    spec(
+        "RDFX",
+        0x05,
+        &[REGISTER, COEFF_S114],
+        "ACC = (ACC - [REG]) * C + [REG]",
+    ),
+    alias("LDAX", "RDFX", 0x05, 0, &[REGISTER], "ACC = [REG]"),
+    spec(
+        "WRAX",
+        0x06,
+        &[REGISTER, COEFF_S114],
+        "[REG] = ACC, ACC = ACC * C",
+    ),
+    spec(
+        "WRHX",
+        0x07,
+        &[REGISTER, COEFF_S114],
+        "[REG] = ACC, ACC = ACC * C + PACC",
+    ),
+    spec(
+        "WRLX",
+        0x08,
+        &[REGISTER, COEFF_S114],
+        "[REG] = ACC, ACC = (PACC - ACC) * C + PACC",
+    ),
+    alias("ABSA", "MAXX", 0x09, 0, &[], "ACC = |ACC|"),
+    spec("MULX", 0x0A, &[REGISTER], "ACC = ACC * [REG]"),
+    spec(
+        "LOG",
+        0x0B,
+        &[
+            COEFF_S114,
+            OperandSpec::new("offset", OperandKind::Coefficient(FixedFormat::S46), 5, 11),
+        ],
+        "ACC = C * LOG2(|ACC|) / 16 + D",
+    ),
+    spec(
+        "EXP",
+        0x0C,
+        &[
+            COEFF_S114,
+            OperandSpec::new("offset", OperandKind::Coefficient(FixedFormat::S10), 5, 11),
+        ],
+        "ACC = C * EXP2(ACC * 16) + D",
+    ),
+    spec(
+        "SOF",
+        0x0D,
+        &[
+            COEFF_S114,
+            OperandSpec::new("offset", OperandKind::Coefficient(FixedFormat::S10), 5, 11),
+        ],
+        "ACC = ACC * C + D",
+    ),
+    spec("AND", 0x0E, &[MASK], "ACC = ACC & MASK"),
+    alias("CLR", "AND", 0x0E, 0, &[], "ACC = 0"),
+    spec("OR", 0x0F, &[MASK], "ACC = ACC | MASK"),
+    spec("XOR", 0x10, &[MASK], "ACC = ACC ^ MASK"),
+    alias("NOT", "XOR", 0x10, 0xFFFF_FF00, &[], "ACC = !ACC"),
+    spec(
+        "SKP",
+        0x11,
+        &[
+            OperandSpec::new("condition", OperandKind::SkipCondition, 27, 5),
+            OperandSpec::new("offset", OperandKind::SkipOffset, 21, 6),
+        ],
+        "Skip the next OFFSET instructions if CONDITION holds",
+    ),
+    alias("NOP", "SKP", 0x11, 0, &[], "No operation"),
+    spec(
+        "WLDS",
+        0x12,
+        &[
+            OperandSpec::new("lfo", OperandKind::SinLfo, 29, 1),
+            OperandSpec::new("freq", OperandKind::Frequency, 20, 9),
+            OperandSpec::new("amplitude", OperandKind::Amplitude, 5, 15),
+        ],
+        "Load SIN LFO frequency and amplitude",
+    ),
+    InstructionSpec {
+        mnemonic: "JAM",
+        opcode: 0x13,
+        fixed: 0x80,
+        operands: &[OperandSpec::new("lfo", OperandKind::RmpLfo, 6, 1)],
+        alias_of: None,
+        description: "Reset RMP LFO",
+    },
+    spec(
+        "CHO",
+        0x14,
+        &[
+            OperandSpec::new("mode", OperandKind::ChoMode, 30, 2),
+            OperandSpec::new("lfo", OperandKind::Lfo, 21, 2),
+            OperandSpec::new("flags", OperandKind::ChoFlags, 24, 6),
+            DELAY_ADDR,
+        ],
+        "Delay RAM access or scaling modulated by an LFO",
+    ),
+    spec(
+        "RDFX2",
+        0x15,
+        &[REGISTER, COEFF_S114],
+        "Double-precision RDFX (extension, not in SpinASM)",
+    ),
+    spec(
+        "SHL",
+        0x16,
+        &[],
+        "ACC = ACC << 1 (extension, not in SpinASM)",
+    ),
+    spec(
+        "SHR",
+        0x17,
+        &[],
+        "ACC = ACC >> 1 (extension, not in SpinASM)",
+    ),
+];
+
+/// `CHO` in SOF mode, whose delay address field holds an S.15 offset.
+/// It is not in [`INSTRUCTIONS`]; [`variant`] selects it from the mode.
+pub static CHO_SOF: InstructionSpec = InstructionSpec {
+    mnemonic: "CHO",
+    opcode: 0x14,
+    fixed: 0,
+    operands: &[
+        OperandSpec::new("mode", OperandKind::ChoMode, 30, 2),
+        OperandSpec::new("lfo", OperandKind::Lfo, 21, 2),
+        OperandSpec::new("flags", OperandKind::ChoFlags, 24, 6),
+        OperandSpec::new("offset", OperandKind::Coefficient(FixedFormat::S15), 5, 16),
+    ],
+    alias_of: None,
+    description: "ACC = ACC * LFO + OFFSET",
+};
+
+/// Get the form of an instruction that its first operand selects, for
+/// instructions whose later fields depend on it
+pub fn variant(spec: &'static InstructionSpec, first: &Operand) -> &'static InstructionSpec {
+    match (spec.mnemonic, first) {
+        ("CHO", Operand::ChoMode(ChoMode::SOF)) => &CHO_SOF,
+        _ => spec,
+    }
+}
+
+/// Look up the description of a mnemonic, ignoring case
+pub fn lookup(mnemonic: &str) -> Option<&'static InstructionSpec> {
+    INSTRUCTIONS
+        .iter()
+        .find(|spec| spec.mnemonic.eq_ignore_ascii_case(mnemonic))
+}
+
+/// Value of an instruction operand
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Register(Register),
+    Address(u16),
+    Coefficient(f32),
+    Mask(u32),
+    SkipCondition(SkipCondition),
+    SkipOffset(i8),
+    Lfo(Lfo),
+    /// WLDS frequency or amplitude
+    Integer(u16),
+    ChoMode(ChoMode),
+    ChoFlags(ChoFlags),
+}
+
+/// Get the description of an instruction and its operands in source order
+///
+/// Returns `None` for [`Instruction::RAW`], which has no mnemonic.
+pub fn describe(inst: &Instruction) -> Option<(&'static InstructionSpec, Vec<Operand>)> {
+    use Operand as O;
+
+    let (mnemonic, operands) = match *inst {
+        Instruction::RDA { addr, coeff } => ("RDA", vec![O::Address(addr), O::Coefficient(coeff)]),
+        Instruction::RMPA { coeff } => ("RMPA", vec![O::Coefficient(coeff)]),
+        Instruction::WRA { addr, coeff } => ("WRA", vec![O::Address(addr), O::Coefficient(coeff)]),
+        Instruction::WRAP { addr, coeff } => {
+            ("WRAP", vec![O::Address(addr), O::Coefficient(coeff)])
+        }
+        Instruction::RDAX { reg, coeff } => ("RDAX", vec![O::Register(reg), O::Coefficient(coeff)]),
+        Instruction::RDFX { reg, coeff } => ("RDFX", vec![O::Register(reg), O::Coefficient(coeff)]),
+        Instruction::LDAX { reg } => ("LDAX", vec![O::Register(reg)]),
+        Instruction::WRAX { reg, coeff } => ("WRAX", vec![O::Register(reg), O::Coefficient(coeff)]),
+        Instruction::WRHX { reg, coeff } => ("WRHX", vec![O::Register(reg), O::Coefficient(coeff)]),
+        Instruction::WRLX { reg, coeff } => ("WRLX", vec![O::Register(reg), O::Coefficient(coeff)]),
+        Instruction::ABSA => ("ABSA", vec![]),
+        Instruction::MULX { reg } => ("MULX", vec![O::Register(reg)]),
+        Instruction::LOG { coeff, offset } => {
+            ("LOG", vec![O::Coefficient(coeff), O::Coefficient(offset)])
+        }
+        Instruction::EXP { coeff, offset } => {
+            ("EXP", vec![O::Coefficient(coeff), O::Coefficient(offset)])
+        }
+        Instruction::SOF { coeff, offset } => {
+            ("SOF", vec![O::Coefficient(coeff), O::Coefficient(offset)])
+        }
+        Instruction::AND { mask } => ("AND", vec![O::Mask(mask)]),
+        Instruction::CLR => ("CLR", vec![]),
+        Instruction::OR { mask } => ("OR", vec![O::Mask(mask)]),
+        Instruction::XOR { mask } => ("XOR", vec![O::Mask(mask)]),
+        Instruction::NOT => ("NOT", vec![]),
+        Instruction::SKP { condition, offset } => (
+            "SKP",
+            vec![O::SkipCondition(condition), O::SkipOffset(offset)],
+        ),
+        Instruction::NOP => ("NOP", vec![]),
+        Instruction::WLDS {
+            lfo,
+            freq,
+            amplitude,
+        } => (
+            "WLDS",
+            vec![O::Lfo(lfo), O::Integer(freq), O::Integer(amplitude)],
+        ),
+        Instruction::JAM { lfo } => ("JAM", vec![O::Lfo(lfo)]),
+        Instruction::CHO {
+            mode,
+            lfo,
+            flags,
+            addr,
+        } => (
+            "CHO",
+            vec![
+                O::ChoMode(mode),
+                O::Lfo(lfo),
+                O::ChoFlags(flags),
+                O::Address(addr),
+            ],
+        ),
+        Instruction::CHO_SOF { lfo, flags, offset } => {
+            let operands = vec![
+                O::ChoMode(ChoMode::SOF),
+                O::Lfo(lfo),
+                O::ChoFlags(flags),
+                O::Coefficient(offset),
+            ];
+            return Some((&CHO_SOF, operands));
+        }
+        Instruction::RDFX2 { reg, coeff } => {
+            ("RDFX2", vec![O::Register(reg), O::Coefficient(coeff)])
+        }
+        Instruction::SHL => ("SHL", vec![]),
+        Instruction::SHR => ("SHR", vec![]),
+        Instruction::RAW(_) => return None,
+    };
+
+    let spec = lookup(mnemonic).expect("every mnemonic is in the instruction table");
+    Some((spec, operands))
+}
+
+/// Build an instruction from its description and operands in source order
+///
+/// Returns `None` if the operands do not match the description.
+pub fn build(spec: &InstructionSpec, operands: &[Operand]) -> Option<Instruction> {
+    use Operand as O;
+
+    let inst = match (spec.mnemonic, operands) {
+        ("RDA", &[O::Address(addr), O::Coefficient(coeff)]) => Instruction::RDA { addr, coeff },
+        ("RMPA", &[O::Coefficient(coeff)]) => Instruction::RMPA { coeff },
+        ("WRA", &[O::Address(addr), O::Coefficient(coeff)]) => Instruction::WRA { addr, coeff },
+        ("WRAP", &[O::Address(addr), O::Coefficient(coeff)]) => Instruction::WRAP { addr, coeff },
+        ("RDAX", &[O::Register(reg), O::Coefficient(coeff)]) => Instruction::RDAX { reg, coeff },
+        ("RDFX", &[O::Register(reg), O::Coefficient(coeff)]) => Instruction::RDFX { reg, coeff },
+        ("LDAX", &[O::Register(reg)]) => Instruction::LDAX { reg },
+        ("WRAX", &[O::Register(reg), O::Coefficient(coeff)]) => Instruction::WRAX { reg, coeff },
+        ("WRHX", &[O::Register(reg), O::Coefficient(coeff)]) => Instruction::WRHX { reg, coeff },
+        ("WRLX", &[O::Register(reg), O::Coefficient(coeff)]) => Instruction::WRLX { reg, coeff },
+        ("ABSA", &[]) => Instruction::ABSA,
+        ("MULX", &[O::Register(reg)]) => Instruction::MULX { reg },
+        ("LOG", &[O::Coefficient(coeff), O::Coefficient(offset)]) => {
+            Instruction::LOG { coeff, offset }
+        }
+        ("EXP", &[O::Coefficient(coeff), O::Coefficient(offset)]) => {
+            Instruction::EXP { coeff, offset }
+        }
+        ("SOF", &[O::Coefficient(coeff), O::Coefficient(offset)]) => {
+            Instruction::SOF { coeff, offset }
+        }
+        ("AND", &[O::Mask(mask)]) => Instruction::AND { mask },
+        ("CLR", &[]) => Instruction::CLR,
+        ("OR", &[O::Mask(mask)]) => Instruction::OR { mask },
+        ("XOR", &[O::Mask(mask)]) => Instruction::XOR { mask },
+        ("NOT", &[]) => Instruction::NOT,
+        ("SKP", &[O::SkipCondition(condition), O::SkipOffset(offset)]) => {
+            Instruction::SKP { condition, offset }
+        }
+        ("NOP", &[]) => Instruction::NOP,
+        ("WLDS", &[O::Lfo(lfo), O::Integer(freq), O::Integer(amplitude)]) => Instruction::WLDS {
+            lfo,
+            freq,
+            amplitude,
+        },
+        ("JAM", &[O::Lfo(lfo)]) => Instruction::JAM { lfo },
+        ("CHO", &[O::ChoMode(mode), O::Lfo(lfo), O::ChoFlags(flags), O::Address(addr)]) => {
+            Instruction::CHO {
+                mode,
+                lfo,
+                flags,
+                addr,
+            }
+        }
+        (
+            "CHO",
+            &[O::ChoMode(ChoMode::SOF), O::Lfo(lfo), O::ChoFlags(flags), O::Coefficient(offset)],
+        ) => Instruction::CHO_SOF { lfo, flags, offset },
+        ("RDFX2", &[O::Register(reg), O::Coefficient(coeff)]) => Instruction::RDFX2 { reg, coeff },
+        ("SHL", &[]) => Instruction::SHL,
+        ("SHR", &[]) => Instruction::SHR,
+        _ => return None,
+    };
+    Some(inst)
+}
+
+/// Export the instruction set description as JSON
+///
+/// Each instruction is written on its own line so diffs of the export stay
+/// readable.
+pub fn to_json() -> String {
+    let mut json = String::new();
+    json.push_str(&format!("{{\n  \"version\": {},\n", FORMAT_VERSION));
+    json.push_str("  \"instructions\": [\n");
+
+    for (i, spec) in INSTRUCTIONS.iter().enumerate() {
+        let operands: Vec<String> = spec.operands.iter().map(operand_json).collect();
+        let alias_of = match spec.alias_of {
+            Some(name) => format!("\"{}\"", name),
+            None => "null".to_string(),
+        };
+        json.push_str(&format!(
+            "    {{\"mnemonic\": \"{}\", \"opcode\": {}, \"fixed\": {}, \"alias_of\": {}, \"description\": \"{}\", \"operands\": [{}]}}",
+            spec.mnemonic,
+            spec.opcode,
+            spec.fixed,
+            alias_of,
+            spec.description,
+            operands.join(", ")
+        ));
+        if i + 1 < INSTRUCTIONS.len() {
+            json.push(',');
+        }
+        json.push('\n');
+    }
+
+    json.push_str("  ]\n}\n");
+    json
+}
+
+fn operand_json(op: &OperandSpec) -> String {
+    let format = match op.kind {
+        OperandKind::Coefficient(format) => format!("\"{}\"", format.as_str()),
+        _ => "null".to_string(),
+    };
+    format!(
+        "{{\"name\": \"{}\", \"kind\": \"{}\", \"format\": {}, \"lsb\": {}, \"width\": {}}}",
+        op.name,
+        op.kind.as_str(),
+        format,
+        op.lsb,
+        op.width
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::encode_instruction;
+
+    /// An instruction for each mnemonic, with operands that set as many
+    /// bits of their fields as possible
+    fn sample(mnemonic: &str) -> Instruction {
+        let reg = Register::REG(31);
+        let coeff = -0.5;
+        let addr = 0x7FFF;
+        match mnemonic {
+            "RDA" => Instruction::RDA { addr, coeff },
+            "RMPA" => Instruction::RMPA { coeff },
+            "WRA" => Instruction::WRA { addr, coeff },
+            "WRAP" => Instruction::WRAP { addr, coeff },
+            "RDAX" => Instruction::RDAX { reg, coeff },
+            "RDFX" => Instruction::RDFX { reg, coeff },
+            "LDAX" => Instruction::LDAX { reg },
+            "WRAX" => Instruction::WRAX { reg, coeff },
+            "WRHX" => Instruction::WRHX { reg, coeff },
+            "WRLX" => Instruction::WRLX { reg, coeff },
+            "ABSA" => Instruction::ABSA,
+            "MULX" => Instruction::MULX { reg },
+            "LOG" => Instruction::LOG {
+                coeff,
+                offset: coeff,
+            },
+            "EXP" => Instruction::EXP {
+                coeff,
+                offset: coeff,
+            },
+            "SOF" => Instruction::SOF {
+                coeff,
+                offset: coeff,
+            },
+            "AND" => Instruction::AND { mask: 0xFFFFFF },
+            "CLR" => Instruction::CLR,
+            "OR" => Instruction::OR { mask: 0xFFFFFF },
+            "XOR" => Instruction::XOR { mask: 0xFFFFFF },
+            "NOT" => Instruction::NOT,
+            "SKP" => Instruction::SKP {
+                condition: SkipCondition::RUN,
+                offset: 63,
+            },
+            "NOP" => Instruction::NOP,
+            "WLDS" => Instruction::WLDS {
+                lfo: Lfo::SIN1,
+                freq: 0x1FF,
+                amplitude: 0x7FFF,
+            },
+            "JAM" => Instruction::JAM { lfo: Lfo::RMP1 },
+            "CHO" => Instruction::CHO {
+                mode: ChoMode::RDAL,
+                lfo: Lfo::RMP1,
+                flags: ChoFlags::from_bits(0x3F),
+                addr,
+            },
+            "RDFX2" => Instruction::RDFX2 { reg, coeff },
+            "SHL" => Instruction::SHL,
+            "SHR" => Instruction::SHR,
+            _ => panic!("no sample for {}", mnemonic),
+        }
+    }
+
+    #[test]
+    fn test_table_matches_encoder() {
+        for spec in INSTRUCTIONS {
+            let word = encode_instruction(&sample(spec.mnemonic)).unwrap();
+            assert_eq!(word & OPCODE_MASK, spec.opcode, "{}", spec.mnemonic);
+            assert_eq!(
+                word & !spec.operand_mask(),
+                spec.opcode | spec.fixed,
+                "{} sets bits outside its operand fields",
+                spec.mnemonic
+            );
+            for op in spec.operands {
+                assert_ne!(word & op.mask(), 0, "{} {}", spec.mnemonic, op.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fields_do_not_overlap() {
+        for spec in INSTRUCTIONS {
+            let mut used = OPCODE_MASK | spec.fixed;
+            for op in spec.operands {
+                assert!(op.lsb + op.width <= 32, "{} {}", spec.mnemonic, op.name);
+                assert_eq!(used & op.mask(), 0, "{} {}", spec.mnemonic, op.name);
+                used |= op.mask();
+            }
+        }
+    }
+
+    #[test]
+    fn test_describe_and_build_roundtrip() {
+        for spec in INSTRUCTIONS {
+            let inst = sample(spec.mnemonic);
+            let (described, operands) = describe(&inst).unwrap();
+            assert_eq!(described, spec);
+            assert_eq!(operands.len(), spec.operands.len(), "{}", spec.mnemonic);
+            assert_eq!(build(spec, &operands), Some(inst));
+        }
+        assert!(describe(&Instruction::RAW(0)).is_none());
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup("sof").unwrap().opcode, 0x0D);
+        assert_eq!(lookup("clr").unwrap().alias_of, Some("AND"));
+        assert!(lookup("maxx").is_none());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = to_json();
+        assert!(json.starts_with("{\n  \"version\": 1,\n"));
+        assert!(json.contains(
+            "{\"name\": \"coeff\", \"kind\": \"coefficient\", \"format\": \"S1.9\", \"lsb\": 21, \"width\": 11}"
+        ));
+        assert_eq!(json.matches("\"mnemonic\"").count(), INSTRUCTIONS.len());
+    }
+}
@@ -3,7 +3,6 @@ use logos::Logos;
 /// Token types for FV-1 assembly language
 #[derive(Logos, Debug, Clone, PartialEq)]
 #[logos(skip r"[ \t\r\n\f]+")] // Skip whitespace
-#[logos(skip r";[^\n]*")] // Skip comments starting with semicolon
 #[allow(non_camel_case_types)] // Allow register names like ADDR_PTR, SIN0_RATE
 pub enum Token {
     // Instructions (case-insensitive)
@@ -13,6 +12,10 @@ pub enum Token {
     RDA,
     #[token("wrax", ignore(ascii_case))]
     WRAX,
+    #[token("wrhx", ignore(ascii_case))]
+    WRHX,
+    #[token("wrlx", ignore(ascii_case))]
+    WRLX,
     #[token("wra", ignore(ascii_case))]
     WRA,
     #[token("wrap", ignore(ascii_case))]
@@ -37,6 +40,8 @@ pub enum Token {
     OR,
     #[token("xor", ignore(ascii_case))]
     XOR,
+    #[token("not", ignore(ascii_case))]
+    NOT,
     #[token("shl", ignore(ascii_case))]
     SHL,
     #[token("shr", ignore(ascii_case))]
@@ -45,6 +50,8 @@ pub enum Token {
     CLR,
     #[token("nop", ignore(ascii_case))]
     NOP,
+    #[token(".dw", ignore(ascii_case))]
+    DW,
     #[token("exp", ignore(ascii_case))]
     EXP,
     #[token("log", ignore(ascii_case))]
@@ -127,6 +134,12 @@ pub enum Token {
     RDAL,
 
     // CHO flags
+    #[token("sin", ignore(ascii_case))]
+    SIN,
+    #[token("cos", ignore(ascii_case))]
+    COS,
+    #[token("reg", ignore(ascii_case))]
+    REG_FLAG,
     #[token("rptr2", ignore(ascii_case))]
     RPTR2,
     #[token("na", ignore(ascii_case))]
@@ -137,20 +150,23 @@ pub enum Token {
     COMPA,
 
     // Numeric literals
-    // Float: Must come before integer to match decimal numbers correctly
-    #[regex(r"-?[0-9]+\.[0-9]+([eE][+-]?[0-9]+)?", parse_float)]
+    // Float: Must come before integer to match decimal numbers correctly.
+    // Either side of the decimal point may be empty (`1.`, `.5`)
+    #[regex(r"-?([0-9]+\.[0-9]*|\.[0-9]+)([eE][+-]?[0-9]+)?", parse_float)]
     #[regex(r"-?[0-9]+[eE][+-]?[0-9]+", parse_float)]
     Float(f32),
 
-    // Hex integer: 0x prefix or $ prefix
-    #[regex(r"0x[0-9a-fA-F]+", parse_hex)]
-    #[regex(r"\$[0-9a-fA-F]+", parse_hex_dollar)]
-    // Binary integer: % prefix
-    #[regex(r"%[01]+", parse_binary)]
     // Decimal integer
     #[regex(r"-?[0-9]+", priority = 1, callback = parse_int)]
     Integer(i64),
 
+    // Bit pattern: hex with 0x or $ prefix, or binary with % prefix, digits
+    // optionally grouped with underscores
+    #[regex(r"0x[0-9a-fA-F][0-9a-fA-F_]*", parse_hex)]
+    #[regex(r"\$[0-9a-fA-F][0-9a-fA-F_]*", parse_hex_dollar)]
+    #[regex(r"%[01][01_]*", parse_binary)]
+    Bits(i64),
+
     // Identifiers (labels, equates) - lower priority than keywords
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", priority = 1, callback = |lex| lex.slice().to_string())]
     Identifier(String),
@@ -164,6 +180,8 @@ pub enum Token {
     Equals,
     #[token("|")]
     Pipe,
+    #[token("/")]
+    Slash,
 
     // Directives
     #[token("equ", ignore(ascii_case))]
@@ -172,10 +190,21 @@ pub enum Token {
     MEM,
     #[token("spinasm", ignore(ascii_case))]
     SPINASM,
+    #[token(".macro", ignore(ascii_case))]
+    MACRO,
+    #[token(".endm", ignore(ascii_case))]
+    ENDM,
 
     // Special
     #[token("#")]
     Hash,
+    #[token("^")]
+    Caret,
+
+    // Comments: text after the semicolon, only yielded when the lexer
+    // keeps comments
+    #[regex(r";[^\n]*", |lex| lex.slice()[1..].trim_end().to_string())]
+    Comment(String),
 }
 
 // Helper functions for parsing token values
@@ -200,41 +229,56 @@ fn parse_int(lex: &mut logos::Lexer<Token>) -> Option<i64> {
 }
 
 fn parse_hex(lex: &mut logos::Lexer<Token>) -> Option<i64> {
-    let slice = lex.slice();
-    i64::from_str_radix(&slice[2..], 16).ok()
+    parse_digits(&lex.slice()[2..], 16)
 }
 
 fn parse_hex_dollar(lex: &mut logos::Lexer<Token>) -> Option<i64> {
-    let slice = lex.slice();
-    i64::from_str_radix(&slice[1..], 16).ok()
+    parse_digits(&lex.slice()[1..], 16)
 }
 
 fn parse_binary(lex: &mut logos::Lexer<Token>) -> Option<i64> {
-    let slice = lex.slice();
-    i64::from_str_radix(&slice[1..], 2).ok()
+    parse_digits(&lex.slice()[1..], 2)
+}
+
+/// Parse digits in the given radix, ignoring `_` separators
+fn parse_digits(digits: &str, radix: u32) -> Option<i64> {
+    let digits: String = digits.chars().filter(|&c| c != '_').collect();
+    i64::from_str_radix(&digits, radix).ok()
 }
 
 /// Lexer for FV-1 assembly source code
 pub struct Lexer<'source> {
     inner: logos::Lexer<'source, Token>,
+    comments: bool,
 }
 
 impl<'source> Lexer<'source> {
-    /// Create a new lexer for the given source code
+    /// Create a new lexer for the given source code, skipping comments
     pub fn new(source: &'source str) -> Self {
         Self {
             inner: Token::lexer(source),
+            comments: false,
         }
     }
+
+    /// Yield comments as [`Token::Comment`] instead of skipping them
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
 }
 
 impl<'source> Iterator for Lexer<'source> {
     type Item = (Result<Token, ()>, std::ops::Range<usize>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let token = self.inner.next()?;
-        let span = self.inner.span();
-        Some((token, span))
+        loop {
+            let token = self.inner.next()?;
+            if !self.comments && matches!(token, Ok(Token::Comment(_))) {
+                continue;
+            }
+            return Some((token, self.inner.span()));
+        }
     }
 }
 
@@ -283,6 +327,18 @@ mod tests {
 
         // Should skip comments and only get instruction tokens
         assert_eq!(tokens.len(), 8); // RDAX ADCL , 0.5 SOF 0 , 0
+
+        let comments: Vec<_> = Lexer::new(source)
+            .with_comments(true)
+            .filter_map(|(tok, _)| match tok {
+                Ok(Token::Comment(text)) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            comments,
+            vec![" read left input", " full line comment", " clear"]
+        );
     }
 
     #[test]
@@ -313,7 +369,7 @@ mod tests {
 
     #[test]
     fn test_numeric_literals() {
-        let source = "1.5 -0.5 42 -10 0x1A $FF %1010";
+        let source = "1.5 -0.5 42 -10 0x1A $FF %1010 %0111_0000 1. .5 -.25 0x7F_FF $80_00";
         let tokens: Vec<_> = Lexer::new(source)
             .map(|(tok, _)| tok)
             .collect::<Result<Vec<_>, _>>()
@@ -323,9 +379,15 @@ mod tests {
         assert_eq!(tokens[1], Token::Float(-0.5));
         assert_eq!(tokens[2], Token::Integer(42));
         assert_eq!(tokens[3], Token::Integer(-10));
-        assert_eq!(tokens[4], Token::Integer(0x1A));
-        assert_eq!(tokens[5], Token::Integer(0xFF));
-        assert_eq!(tokens[6], Token::Integer(0b1010));
+        assert_eq!(tokens[4], Token::Bits(0x1A));
+        assert_eq!(tokens[5], Token::Bits(0xFF));
+        assert_eq!(tokens[6], Token::Bits(0b1010));
+        assert_eq!(tokens[7], Token::Bits(0b0111_0000));
+        assert_eq!(tokens[8], Token::Float(1.0));
+        assert_eq!(tokens[9], Token::Float(0.5));
+        assert_eq!(tokens[10], Token::Float(-0.25));
+        assert_eq!(tokens[11], Token::Bits(0x7FFF));
+        assert_eq!(tokens[12], Token::Bits(0x8000));
     }
 
     #[test]
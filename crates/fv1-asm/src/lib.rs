@@ -1,17 +1,30 @@
+pub mod analysis;
 pub mod ast;
 pub mod codegen;
+pub mod coeff;
 pub mod constants;
 pub mod error;
 pub mod instruction;
+pub mod isa;
 pub mod lexer;
+pub mod macros;
+pub mod migrate;
 pub mod parser;
 pub mod register;
+pub mod signals;
 
 // Re-export commonly used types
+pub use analysis::{
+    control_flow_graph, latency_report, path_report, register_roles, resource_report,
+    ControlFlowGraph, Finding, LatencyReport, PathReport, RegisterRole, ResourceReport,
+};
 pub use ast::{Directive, Program, Statement, Value};
-pub use codegen::{Assembler, Binary, Disassembler};
+pub use codegen::{
+    Assembler, Bank, Binary, BudgetReport, Disassembler, EepromImage, PaddingKind, RoundingMode,
+    SymbolTable,
+};
 pub use constants::*;
-pub use error::{CodegenError, ParseError};
+pub use error::{CodegenError, ParseError, SourceError, Warning};
 pub use instruction::{ChoFlags, ChoMode, Instruction, SkipCondition};
 pub use lexer::{Lexer, Token};
 pub use parser::Parser;
@@ -0,0 +1,412 @@
+//! Macro Expansion
+//!
+//! Text macros are defined between `.macro name param, ...` and `.endm`
+//! and expanded as the parser pulls tokens, one source line at a time. A
+//! line whose first token (after an optional label) names a macro is
+//! replaced by the macro body, with each parameter replaced by the tokens
+//! of the matching argument. Bodies may invoke other macros up to
+//! [`MAX_MACRO_DEPTH`] levels deep.
+//!
+//! Expanded tokens carry the span of the invocation, so diagnostics for
+//! instructions produced by a macro point at the line that expanded it.
+//! Since the parser can then no longer read an expanded mnemonic from the
+//! source, mnemonics are passed on as [`Token::Identifier`]s.
+
+use crate::{error::ParseError, isa, lexer::Token};
+use std::{
+    collections::{HashMap, VecDeque},
+    iter::Peekable,
+    ops::Range,
+};
+
+/// Deepest allowed nesting of macro invocations inside macro bodies
+pub const MAX_MACRO_DEPTH: usize = 16;
+
+/// Token with its source span, as produced by the lexer
+pub(crate) type Spanned = (Result<Token, ()>, Range<usize>);
+
+/// A macro definition
+struct Macro {
+    params: Vec<String>,
+    /// Body lines, without comments
+    body: Vec<Vec<Spanned>>,
+}
+
+/// Lazily expands macros in a token stream, reading one line ahead of the
+/// tokens it yields
+pub(crate) struct MacroExpander<'source, I: Iterator<Item = Spanned>> {
+    source: &'source str,
+    lines: Lines<'source, I>,
+    macros: HashMap<String, Macro>,
+    /// Tokens of the current line, after expansion
+    pending: VecDeque<Spanned>,
+}
+
+impl<'source, I: Iterator<Item = Spanned>> MacroExpander<'source, I> {
+    pub(crate) fn new(source: &'source str, tokens: I) -> Self {
+        Self {
+            source,
+            lines: Lines {
+                source,
+                tokens: tokens.peekable(),
+            },
+            macros: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Handle the next source line, queueing its tokens
+    fn next_line(&mut self, line: Vec<Spanned>) -> Result<(), ParseError> {
+        match line.first() {
+            Some((Ok(Token::MACRO), span)) => {
+                let span = span.clone();
+                let (name, definition) = define(line, &mut self.lines)?;
+                if self.macros.contains_key(&name) {
+                    return Err(ParseError::DuplicateMacro { name, span });
+                }
+                self.macros.insert(name, definition);
+                Ok(())
+            }
+            Some((Ok(Token::ENDM), span)) => Err(ParseError::UnexpectedToken {
+                expected: "statement".to_string(),
+                found: "ENDM".to_string(),
+                span: span.clone(),
+            }),
+            _ => expand_line(self.source, line, &self.macros, 0, None, &mut self.pending),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Spanned>> Iterator for MacroExpander<'_, I> {
+    type Item = Result<Spanned, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Some(Ok(token));
+            }
+            let line = self.lines.next()?;
+            if let Err(error) = self.next_line(line) {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+/// Groups tokens into source lines
+struct Lines<'source, I: Iterator<Item = Spanned>> {
+    source: &'source str,
+    tokens: Peekable<I>,
+}
+
+impl<I: Iterator<Item = Spanned>> Iterator for Lines<'_, I> {
+    type Item = Vec<Spanned>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.tokens.next()?;
+        let mut end = first.1.end;
+        let mut line = vec![first];
+        while let Some(token) = self
+            .tokens
+            .next_if(|(_, span)| !self.source[end..span.start].contains('\n'))
+        {
+            end = token.1.end;
+            line.push(token);
+        }
+        Some(line)
+    }
+}
+
+/// Parse a macro definition starting at its `.macro` line, consuming lines
+/// up to and including `.endm`
+fn define(
+    header: Vec<Spanned>,
+    lines: &mut impl Iterator<Item = Vec<Spanned>>,
+) -> Result<(String, Macro), ParseError> {
+    let mut header = without_comments(header).into_iter();
+    let (_, macro_span) = header.next().expect("header starts with .macro");
+
+    let name = match header.next() {
+        Some((Ok(Token::Identifier(name)), _)) => name,
+        Some((token, span)) => return Err(unexpected("macro name", &token, span)),
+        None => return Err(ParseError::UnexpectedEof),
+    };
+
+    let mut params = Vec::new();
+    for (i, (token, span)) in header.enumerate() {
+        match token {
+            Ok(Token::Comma) if i % 2 == 1 => {}
+            Ok(Token::Identifier(param)) if i % 2 == 0 => params.push(param),
+            token => return Err(unexpected("macro parameter", &token, span)),
+        }
+    }
+
+    let mut body = Vec::new();
+    for line in lines.by_ref() {
+        match line.first() {
+            Some((Ok(Token::ENDM), _)) => {
+                if let Some((token, span)) = without_comments(line).into_iter().nth(1) {
+                    return Err(unexpected("end of line", &token, span));
+                }
+                return Ok((name, Macro { params, body }));
+            }
+            Some((Ok(Token::MACRO), span)) => {
+                return Err(unexpected(".endm", &Ok(Token::MACRO), span.clone()));
+            }
+            _ => body.push(without_comments(line)),
+        }
+    }
+
+    Err(ParseError::UnterminatedMacro {
+        name,
+        span: macro_span,
+    })
+}
+
+/// Append a line to `output`, expanding it if it invokes a macro
+///
+/// `site` is the span of the outermost invocation when the line comes from
+/// a macro body.
+fn expand_line(
+    source: &str,
+    line: Vec<Spanned>,
+    macros: &HashMap<String, Macro>,
+    depth: usize,
+    site: Option<&Range<usize>>,
+    output: &mut impl Extend<Spanned>,
+) -> Result<(), ParseError> {
+    // Skip a leading label
+    let start = match (line.first(), line.get(1)) {
+        (Some((Ok(Token::Identifier(_)), _)), Some((Ok(Token::Colon), _))) => 2,
+        _ => 0,
+    };
+
+    let relocate = |(i, (token, span)): (usize, Spanned)| match site {
+        Some(site) => {
+            let text = &source[span];
+            let token = match token {
+                Ok(_) if i == start && isa::lookup(text).is_some() => {
+                    Ok(Token::Identifier(text.to_string()))
+                }
+                token => token,
+            };
+            (token, site.clone())
+        }
+        None => (token, span),
+    };
+
+    let invoked = match (line.get(start), line.get(start + 1)) {
+        (Some((Ok(Token::Identifier(name)), _)), next)
+            if macros.contains_key(name) && !matches!(next, Some((Ok(Token::Colon), _))) =>
+        {
+            name.clone()
+        }
+        _ => {
+            output.extend(line.into_iter().enumerate().map(relocate));
+            return Ok(());
+        }
+    };
+
+    let (code, comments): (Vec<_>, Vec<_>) = line
+        .into_iter()
+        .partition(|(token, _)| !matches!(token, Ok(Token::Comment(_))));
+    let span = site
+        .cloned()
+        .unwrap_or_else(|| code[start].1.start..code[code.len() - 1].1.end);
+
+    if depth >= MAX_MACRO_DEPTH {
+        return Err(ParseError::MacroDepthExceeded {
+            name: invoked,
+            max: MAX_MACRO_DEPTH,
+            span,
+        });
+    }
+
+    let mut code = code.into_iter();
+    output.extend(code.by_ref().take(start).enumerate().map(relocate));
+    let args = split_arguments(code.skip(1).collect())?;
+
+    let definition = &macros[&invoked];
+    if args.len() != definition.params.len() {
+        return Err(ParseError::MacroArguments {
+            name: invoked,
+            expected: definition.params.len(),
+            found: args.len(),
+            span,
+        });
+    }
+
+    for body_line in &definition.body {
+        let mut expanded = Vec::with_capacity(body_line.len());
+        for token in body_line {
+            let param = match &token.0 {
+                Ok(Token::Identifier(name)) => definition.params.iter().position(|p| p == name),
+                _ => None,
+            };
+            match param {
+                Some(i) => expanded.extend(args[i].iter().cloned()),
+                None => expanded.push(token.clone()),
+            }
+        }
+        expand_line(source, expanded, macros, depth + 1, Some(&span), output)?;
+    }
+
+    // Trailing comments stay after the expansion
+    output.extend(
+        comments
+            .into_iter()
+            .map(|(token, span)| (token, site.cloned().unwrap_or(span))),
+    );
+    Ok(())
+}
+
+/// Split invocation arguments on commas
+fn split_arguments(tokens: Vec<Spanned>) -> Result<Vec<Vec<Spanned>>, ParseError> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut args = vec![Vec::new()];
+    for (token, span) in tokens {
+        let empty = args.last().is_some_and(Vec::is_empty);
+        match token {
+            Ok(Token::Comma) if empty => return Err(unexpected("macro argument", &token, span)),
+            Ok(Token::Comma) => args.push(Vec::new()),
+            token => args
+                .last_mut()
+                .expect("args is never empty")
+                .push((token, span)),
+        }
+    }
+
+    if args.last().is_some_and(Vec::is_empty) {
+        return Err(ParseError::UnexpectedEof);
+    }
+    Ok(args)
+}
+
+fn without_comments(line: Vec<Spanned>) -> Vec<Spanned> {
+    line.into_iter()
+        .filter(|(token, _)| !matches!(token, Ok(Token::Comment(_))))
+        .collect()
+}
+
+fn unexpected(expected: &str, token: &Result<Token, ()>, span: Range<usize>) -> ParseError {
+    ParseError::UnexpectedToken {
+        expected: expected.to_string(),
+        found: match token {
+            Ok(token) => format!("{:?}", token),
+            Err(()) => "invalid token".to_string(),
+        },
+        span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{instruction::Instruction, parser::Parser, register::Register};
+
+    const LOWPASS: &str = "\
+.macro lowpass state, k
+    rdax state, -1.0
+    sof k, 0.0
+    rdax state, 1.0
+    wrax state, 1.0
+.endm
+";
+
+    #[test]
+    fn test_expand_macro() {
+        let source = format!(
+            "{}rdax adcl, 1.0\nlowpass reg0, 0.25\nwrax dacl, 0.0\n",
+            LOWPASS
+        );
+        let program = Parser::new(&source).parse().unwrap();
+        let instructions = program.instructions();
+
+        assert_eq!(instructions.len(), 6);
+        assert_eq!(
+            *instructions[1],
+            Instruction::RDAX {
+                reg: Register::REG(0),
+                coeff: -1.0
+            }
+        );
+        assert_eq!(
+            *instructions[2],
+            Instruction::SOF {
+                coeff: 0.25,
+                offset: 0.0
+            }
+        );
+
+        // Expanded instructions point at the invocation
+        for index in 1..5 {
+            let span = program.instruction_span(index).unwrap();
+            assert_eq!(&source[span], "lowpass reg0, 0.25");
+        }
+    }
+
+    #[test]
+    fn test_nested_macro_and_label() {
+        let source = format!(
+            "{}.macro stereo_lp k\nlowpass reg0, k\nlowpass reg1, k\n.endm\ntop: stereo_lp 1/2\n",
+            LOWPASS
+        );
+        let program = Parser::new(&source).parse().unwrap();
+
+        assert_eq!(program.instructions().len(), 8);
+        assert_eq!(program.labels.get("top"), Some(&0));
+        assert_eq!(
+            *program.instructions()[5],
+            Instruction::SOF {
+                coeff: 0.5,
+                offset: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_error_points_at_invocation() {
+        let source = format!("{}lowpass reg0, 3.0\n", LOWPASS);
+        let err = Parser::new(&source).parse().unwrap_err();
+        match err {
+            ParseError::CoefficientOutOfRange { span, .. } => {
+                assert_eq!(&source[span], "lowpass reg0, 3.0")
+            }
+            _ => panic!("Expected CoefficientOutOfRange, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_macro_errors() {
+        let source = format!("{}lowpass reg0\n", LOWPASS);
+        assert!(matches!(
+            Parser::new(&source).parse(),
+            Err(ParseError::MacroArguments {
+                expected: 2,
+                found: 1,
+                ..
+            })
+        ));
+
+        let source = ".macro forever\nforever\n.endm\nforever\n";
+        assert!(matches!(
+            Parser::new(source).parse(),
+            Err(ParseError::MacroDepthExceeded { .. })
+        ));
+
+        let source = ".macro open\nclr\n";
+        assert!(matches!(
+            Parser::new(source).parse(),
+            Err(ParseError::UnterminatedMacro { .. })
+        ));
+
+        let source = format!("{}{}", LOWPASS, LOWPASS);
+        assert!(matches!(
+            Parser::new(&source).parse(),
+            Err(ParseError::DuplicateMacro { .. })
+        ));
+    }
+}
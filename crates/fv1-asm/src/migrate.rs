@@ -0,0 +1,278 @@
+//! Migrating Old Programs
+//!
+//! Before pots had registers of their own, POT0-POT2 were written as
+//! REG16-REG18 (`Register::REG(16)` in the DSL). Those now name general
+//! purpose registers, so programs written the old way still assemble but
+//! read a register nothing writes instead of a pot. These rewrites turn
+//! such registers back into pots.
+//!
+//! Whether REG16-REG18 stands for a pot can only be guessed, so rewrites
+//! are made only where the guess is safe. In DSL source a register is
+//! rewritten only if every mention of it is read directly by an
+//! instruction, since one bound to a variable or passed to a block may be
+//! written there. In assembly a register is never written when it stands
+//! for a pot, but one that is only read may still be meant as a register,
+//! so registers are rewritten only when asked. Candidates left alone are
+//! reported so they can be checked by hand.
+
+use crate::{
+    analysis::roles::{register_access, Access},
+    error::ParseError,
+    lexer::{Lexer, Token},
+    parser::Parser,
+    register::Register,
+};
+use std::{collections::HashSet, fmt, ops::Range};
+
+/// Registers that stood for the pots, by pot number
+const POT_REGISTERS: Range<u8> = 16..19;
+
+/// Functions and methods of the DSL that only read their register
+const DSL_READS: [&str; 5] = ["rdax", "rdfx", "rdfx2", "ldax", "mulx"];
+
+/// A rewrite made by a migration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Line of the rewritten text, from 1
+    pub line: usize,
+    pub from: String,
+    pub to: String,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {} -> {}", self.line, self.from, self.to)
+    }
+}
+
+/// Source after migration, with the changes made to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub source: String,
+    pub changes: Vec<Change>,
+    /// Rewrites that may apply but were not made, to check by hand
+    pub unsure: Vec<Change>,
+}
+
+impl Migration {
+    /// Apply replacements of byte ranges of `source`, given in order, and
+    /// report the `unsure` ones without applying them
+    fn apply(
+        source: &str,
+        edits: Vec<(Range<usize>, String)>,
+        unsure: Vec<(Range<usize>, String)>,
+    ) -> Self {
+        let change = |range: Range<usize>, to: String| Change {
+            line: source[..range.start].matches('\n').count() + 1,
+            from: source[range].to_string(),
+            to,
+        };
+
+        let mut migrated = String::with_capacity(source.len());
+        let mut changes = Vec::new();
+        let mut end = 0;
+        for (range, replacement) in edits {
+            migrated.push_str(&source[end..range.start]);
+            migrated.push_str(&replacement);
+            end = range.end;
+            changes.push(change(range, replacement));
+        }
+        migrated.push_str(&source[end..]);
+
+        Self {
+            source: migrated,
+            changes,
+            unsure: unsure
+                .into_iter()
+                .map(|(range, to)| change(range, to))
+                .collect(),
+        }
+    }
+}
+
+/// Find REG16-REG18 in assembly source that the program reads without
+/// ever writing, and with `assume_pots` rewrite them to POT0-POT2.
+/// Otherwise they are only reported as unsure.
+pub fn migrate_asm(source: &str, assume_pots: bool) -> Result<Migration, ParseError> {
+    let program = Parser::new(source).parse()?;
+    let written: HashSet<Register> = program
+        .instructions()
+        .into_iter()
+        .filter_map(register_access)
+        .filter_map(|(reg, access)| (access == Access::Write).then_some(reg))
+        .collect();
+
+    let candidates = Lexer::new(source)
+        .filter_map(|(token, span)| match token {
+            Ok(Token::REG(n))
+                if POT_REGISTERS.contains(&n) && !written.contains(&Register::REG(n)) =>
+            {
+                let pot = if source[span.clone()].starts_with('R') {
+                    "POT"
+                } else {
+                    "pot"
+                };
+                Some((span, format!("{}{}", pot, n - POT_REGISTERS.start)))
+            }
+            _ => None,
+        })
+        .collect();
+
+    Ok(if assume_pots {
+        Migration::apply(source, candidates, Vec::new())
+    } else {
+        Migration::apply(source, Vec::new(), candidates)
+    })
+}
+
+/// Rewrite `Register::REG(16)` to `Register::POT0`, and so on, in Rust
+/// source using the DSL, where every mention of the register is read
+/// directly by an instruction. Registers mentioned any other way are
+/// reported as unsure.
+pub fn migrate_dsl(source: &str) -> Migration {
+    // Each `REG(n)` naming a pot register, with n
+    let uses: Vec<(Range<usize>, u8)> = source
+        .match_indices("REG(")
+        .filter(|(start, _)| !source[..*start].ends_with(is_ident_char))
+        .filter_map(|(start, _)| {
+            let rest = &source[start + 4..];
+            let close = rest.find(')')?;
+            let n: u8 = rest[..close].trim().parse().ok()?;
+            POT_REGISTERS
+                .contains(&n)
+                .then_some((start..start + 4 + close + 1, n))
+        })
+        .collect();
+
+    // Registers mentioned other than as an instruction's read operand
+    let other: HashSet<u8> = uses
+        .iter()
+        .filter(|(range, _)| !is_direct_read(&source[..range.start]))
+        .map(|(_, n)| *n)
+        .collect();
+
+    let (edits, unsure) = uses
+        .into_iter()
+        .map(|(range, n)| (range, format!("POT{}", n - POT_REGISTERS.start), n))
+        .partition::<Vec<_>, _>(|(_, _, n)| !other.contains(n));
+    let strip = |edits: Vec<(Range<usize>, String, u8)>| {
+        edits
+            .into_iter()
+            .map(|(range, to, _)| (range, to))
+            .collect()
+    };
+    Migration::apply(source, strip(edits), strip(unsure))
+}
+
+/// Check whether source ending just before a register is a call of a DSL
+/// instruction that only reads it, such as `rdax(Register::`
+fn is_direct_read(before: &str) -> bool {
+    let before = before.strip_suffix("Register::").unwrap_or(before);
+    let Some(call) = before.trim_end().strip_suffix('(') else {
+        return false;
+    };
+    let call = call.trim_end();
+    DSL_READS.iter().any(|read| {
+        call.strip_suffix(read)
+            .is_some_and(|prefix| !prefix.ends_with(is_ident_char))
+    })
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(changes: &[Change]) -> Vec<String> {
+        changes.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_migrate_asm() {
+        let source = "equ vol, reg16\nrdax adcl, 1.0\nmulx vol\nmulx REG17\n\
+                      wrax reg18, 0.0\nrdax reg18, 1.0\nwrax dacl, 0.0\n";
+
+        // Only reported unless pots are assumed
+        let migration = migrate_asm(source, false).unwrap();
+        assert_eq!(migration.source, source);
+        assert_eq!(migration.changes, vec![]);
+        assert_eq!(
+            lines(&migration.unsure),
+            vec!["line 1: reg16 -> pot0", "line 4: REG17 -> POT1"]
+        );
+
+        let migration = migrate_asm(source, true).unwrap();
+        assert_eq!(
+            migration.source,
+            "equ vol, pot0\nrdax adcl, 1.0\nmulx vol\nmulx POT1\n\
+             wrax reg18, 0.0\nrdax reg18, 1.0\nwrax dacl, 0.0\n"
+        );
+        assert_eq!(
+            lines(&migration.changes),
+            vec!["line 1: reg16 -> pot0", "line 4: REG17 -> POT1"]
+        );
+        assert_eq!(migration.unsure, vec![]);
+
+        let current = migrate_asm("mulx pot0\n", true).unwrap();
+        assert_eq!((current.changes, current.unsure), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_migrate_dsl() {
+        let source = "\
+.inst(mulx(Register::REG(16))) // POT0
+.inst(rdax(REG( 17 ), 0.5))
+.inst(wrax(Register::REG(18), 0.0))
+.inst(rdax(Register::REG(18), 1.0))
+.inst(rdax(Register::REG(15), 1.0))
+.inst(rdax(MY_REG(16), 1.0))
+";
+        let migration = migrate_dsl(source);
+
+        assert_eq!(
+            migration.source,
+            "\
+.inst(mulx(Register::POT0)) // POT0
+.inst(rdax(POT1, 0.5))
+.inst(wrax(Register::REG(18), 0.0))
+.inst(rdax(Register::REG(18), 1.0))
+.inst(rdax(Register::REG(15), 1.0))
+.inst(rdax(MY_REG(16), 1.0))
+"
+        );
+        assert_eq!(
+            lines(&migration.changes),
+            vec!["line 1: REG(16) -> POT0", "line 2: REG( 17 ) -> POT1"]
+        );
+        assert_eq!(migration.unsure.len(), 2);
+    }
+
+    #[test]
+    fn test_migrate_dsl_leaves_indirect_uses() {
+        // Written through a variable
+        let source = "\
+let r = Register::REG(16);
+b.inst(rdax(Register::REG(16), 1.0));
+b.inst(wrax(r, 0.0));
+";
+        let migration = migrate_dsl(source);
+        assert_eq!(migration.source, source);
+        assert_eq!(migration.changes, vec![]);
+        assert_eq!(
+            lines(&migration.unsure),
+            vec!["line 1: REG(16) -> POT0", "line 2: REG(16) -> POT0"]
+        );
+
+        // Written by a block as its state
+        let source = "\
+b.insts(blocks::lowpass(Register::ACC, Register::POT0, Register::REG(17)));
+b.inst(rdax(Register::REG(17), 1.0));
+";
+        let migration = migrate_dsl(source);
+        assert_eq!(migration.source, source);
+        assert_eq!(migration.unsure.len(), 2);
+    }
+}
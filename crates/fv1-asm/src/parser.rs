@@ -1,54 +1,204 @@
 use crate::{
     ast::*,
-    error::ParseError,
+    codegen::decoder::decode_register,
+    constants::DELAY_RAM_SIZE,
+    error::{ParseError, SourceError},
     instruction::*,
+    isa::{self, FixedFormat, Operand, OperandKind, OperandSpec},
     lexer::{Lexer, Token},
+    macros::{MacroExpander, Spanned},
     register::*,
 };
+use std::collections::{HashMap, VecDeque};
+
+/// Tokens the parser can look ahead at
+const LOOKAHEAD: usize = 2;
 
 /// Parser for FV-1 assembly source code
+///
+/// Tokens are pulled from the lexer as parsing proceeds, keeping only
+/// [`LOOKAHEAD`] tokens buffered.
 pub struct Parser<'source> {
-    tokens: Vec<(Result<Token, ()>, std::ops::Range<usize>)>,
-    pos: usize,
-    /// Source code (kept for future error reporting improvements)
-    #[allow(dead_code)]
+    /// Remaining tokens, after macro expansion
+    tokens: MacroExpander<'source, Lexer<'source>>,
+    /// Upcoming tokens
+    lookahead: VecDeque<Spanned>,
+    /// Error met while pulling tokens, reported once the tokens before it
+    /// are consumed
+    token_error: Option<ParseError>,
+    /// End of the most recently consumed token
+    last_end: Option<usize>,
+    /// Label target of the SKP instruction currently being parsed, with the
+    /// span of the label operand
+    skip_target: Option<(String, std::ops::Range<usize>)>,
+    /// Labels defined so far, with the span of each definition
+    label_spans: Vec<(String, std::ops::Range<usize>)>,
+    /// Equates defined so far, with identifier values already resolved
+    equates: HashMap<String, Value>,
+    /// Equates defined from outside the source, overriding its own
+    defines: Vec<(String, Value)>,
+    /// Delay memory regions allocated so far, as (start, size)
+    memory: HashMap<String, (u16, u16)>,
+    /// Next free delay memory address
+    next_addr: usize,
+    /// Accept SpinASM's quirks
+    spinasm_compat: bool,
+    /// Source code
     source: &'source str,
 }
 
 impl<'source> Parser<'source> {
     /// Create a new parser for the given source code
     pub fn new(source: &'source str) -> Self {
-        let tokens: Vec<_> = Lexer::new(source).collect();
         Self {
-            tokens,
-            pos: 0,
+            tokens: MacroExpander::new(source, Lexer::new(source)),
+            lookahead: VecDeque::with_capacity(LOOKAHEAD),
+            token_error: None,
+            last_end: None,
+            skip_target: None,
+            label_spans: Vec::new(),
+            equates: HashMap::new(),
+            defines: Vec::new(),
+            memory: HashMap::new(),
+            next_addr: 0,
+            spinasm_compat: false,
             source,
         }
     }
 
+    /// Keep comments in the parsed program, as [`Statement::Comment`] for
+    /// comments on their own line and in [`Program::trailing_comments`] or
+    /// [`Program::directive_comments`] for comments ending a line
+    pub fn with_comments(mut self, comments: bool) -> Self {
+        let lexer = Lexer::new(self.source).with_comments(comments);
+        self.tokens = MacroExpander::new(self.source, lexer);
+        self
+    }
+
+    /// Accept source the way SpinASM does
+    ///
+    /// Symbols are case-insensitive, `EQU` takes its value with or without
+    /// a comma, registers and LFOs may be given by number, `CHO RDAL`
+    /// takes just an LFO, and decimal integer coefficients other than 0,
+    /// ±1 and ±2 are raw fixed-point values.
+    pub fn spinasm_compat(mut self, enable: bool) -> Self {
+        self.spinasm_compat = enable;
+        self
+    }
+
+    /// Define an equate before parsing, as build scripts do to inject
+    /// constants. It replaces the value of any `EQU` of the same name in the
+    /// source, and is added to the program's directives if there is none.
+    pub fn with_define(mut self, name: impl Into<String>, value: Value) -> Self {
+        let name = name.into();
+        self.defines.retain(|(defined, _)| *defined != name);
+        self.defines.push((name, value));
+        self
+    }
+
     /// Parse the source code into a Program
     pub fn parse(&mut self) -> Result<Program, ParseError> {
+        self.fill_lookahead();
+
         let mut program = Program::new();
+        let mut skip_spans = Vec::new();
+        // Most recently parsed line, for attaching trailing comments
+        let mut last_line = None;
+
+        for (name, value) in self.defines.clone() {
+            self.define_equate(&name, &value);
+        }
 
         while !self.is_at_end() {
+            if let Some((Ok(Token::Comment(text)), span)) = self.peek() {
+                let (text, span) = (text.clone(), span.clone());
+                let trailing = self
+                    .last_end
+                    .is_some_and(|end| !self.source[end..span.start].contains('\n'));
+                self.advance();
+                match last_line {
+                    Some(Line::Directive(index)) if trailing => {
+                        program.directive_comments.insert(index, text);
+                    }
+                    Some(Line::Statement(index)) if trailing => {
+                        program.trailing_comments.insert(index, text);
+                    }
+                    _ => {
+                        program.add_statement_with_span(Statement::Comment(text), span);
+                        last_line = None;
+                    }
+                }
+                continue;
+            }
+
             // Try to parse directive or statement
             if self.check_directive() {
-                program.directives.push(self.parse_directive()?);
+                let start = self.current_offset();
+                let mut directive = self.parse_directive()?;
+                let span = start..self.previous_end();
+                match &mut directive {
+                    Directive::Equate { name, value } => match self.defined(name) {
+                        Some(defined) => *value = defined,
+                        None => self.define_equate(name, value),
+                    },
+                    Directive::MemoryAllocation { name, size } => {
+                        self.allocate_memory(name, *size, span.clone())?;
+                    }
+                    _ => {}
+                }
+                program.add_directive_with_span(directive, span);
+                last_line = Some(Line::Directive(program.directives.len() - 1));
             } else {
+                let start = self.current_offset();
                 let stmt = self.parse_statement()?;
-                program.add_statement(stmt);
+                let end = self.previous_end();
+                program.add_statement_with_span(stmt, start..end);
+                last_line = Some(Line::Statement(program.statements.len() - 1));
+
+                if let Some((label, span)) = self.skip_target.take() {
+                    let index = program.instructions().len() - 1;
+                    program.skip_targets.insert(index, label);
+                    skip_spans.push((index, span));
+                }
+            }
+        }
+
+        if let Some(error) = self.token_error.take() {
+            return Err(error);
+        }
+        check_duplicate_labels(&self.label_spans)?;
+        resolve_skip_targets(&mut program, skip_spans)?;
+
+        // Defines the source doesn't mention still name their values
+        for (name, value) in &self.defines {
+            let mentioned = program.directives.iter().any(
+                |directive| matches!(directive, Directive::Equate { name: equate, .. } if equate == name),
+            );
+            if !mentioned {
+                program.add_directive(Directive::Equate {
+                    name: name.clone(),
+                    value: value.clone(),
+                });
             }
         }
 
         Ok(program)
     }
 
+    /// Parse the source code, naming it `name` in any error so that the
+    /// diagnostic renders with the source highlighted
+    pub fn parse_named(&mut self, name: &str) -> Result<Program, SourceError<ParseError>> {
+        self.parse()
+            .map_err(|error| SourceError::new(error, name, self.source))
+    }
+
     /// Parse a statement (label, instruction, or labeled instruction)
     fn parse_statement(&mut self) -> Result<Statement, ParseError> {
         // Check for label followed by colon
-        if let Some((Ok(Token::Identifier(name)), _)) = self.peek() {
+        if let Some((Ok(Token::Identifier(name)), span)) = self.peek() {
             if matches!(self.peek_next(), Some((Ok(Token::Colon), _))) {
                 let label = name.clone();
+                self.label_spans.push((label.clone(), span.clone()));
                 self.advance(); // consume identifier
                 self.advance(); // consume colon
 
@@ -67,143 +217,178 @@ impl<'source> Parser<'source> {
         Ok(Statement::Instruction(instruction))
     }
 
-    /// Parse an instruction
+    /// Parse an instruction and its operands, as described by the
+    /// instruction table
     fn parse_instruction(&mut self) -> Result<Instruction, ParseError> {
         let (token, span) = self.advance_checked()?;
 
-        match token {
-            Token::RDAX => {
-                let reg = self.parse_register()?;
-                self.expect(Token::Comma)?;
-                let coeff = self.parse_number()?;
-                Ok(Instruction::RDAX { reg, coeff })
-            }
-            Token::RDA => {
-                let addr = self.parse_number()? as u16;
-                self.expect(Token::Comma)?;
-                let coeff = self.parse_number()?;
-                Ok(Instruction::RDA { addr, coeff })
-            }
-            Token::WRAX => {
-                let reg = self.parse_register()?;
-                self.expect(Token::Comma)?;
-                let coeff = self.parse_number()?;
-                Ok(Instruction::WRAX { reg, coeff })
-            }
-            Token::WRA => {
-                let addr = self.parse_number()? as u16;
-                self.expect(Token::Comma)?;
-                let coeff = self.parse_number()?;
-                Ok(Instruction::WRA { addr, coeff })
-            }
-            Token::WRAP => {
-                let addr = self.parse_number()? as u16;
-                self.expect(Token::Comma)?;
-                let coeff = self.parse_number()?;
-                Ok(Instruction::WRAP { addr, coeff })
-            }
-            Token::RMPA => {
-                let coeff = self.parse_number()?;
-                Ok(Instruction::RMPA { coeff })
-            }
-            Token::MULX => {
-                let reg = self.parse_register()?;
-                Ok(Instruction::MULX { reg })
-            }
-            Token::RDFX => {
-                let reg = self.parse_register()?;
-                self.expect(Token::Comma)?;
-                let coeff = self.parse_number()?;
-                Ok(Instruction::RDFX { reg, coeff })
-            }
-            Token::RDFX2 => {
-                let reg = self.parse_register()?;
-                self.expect(Token::Comma)?;
-                let coeff = self.parse_number()?;
-                Ok(Instruction::RDFX2 { reg, coeff })
-            }
-            Token::LDAX => {
-                let reg = self.parse_register()?;
-                Ok(Instruction::LDAX { reg })
-            }
-            Token::SOF => {
-                let coeff = self.parse_number()?;
+        if token == Token::DW {
+            let (token, span) = self.advance_checked()?;
+            return match token {
+                Token::Integer(i) | Token::Bits(i) if (0..=u32::MAX as i64).contains(&i) => {
+                    Ok(Instruction::RAW(i as u32))
+                }
+                _ => Err(ParseError::ExpectedNumber { span }),
+            };
+        }
+
+        let Some(spec) = isa::lookup(self.mnemonic(&token, &span)) else {
+            return Err(ParseError::UnexpectedToken {
+                expected: "instruction".to_string(),
+                found: format!("{:?}", token),
+                span,
+            });
+        };
+
+        let mut spec = spec;
+        let mut operands = Vec::with_capacity(spec.operands.len());
+        for i in 0..spec.operands.len() {
+            let field = &spec.operands[i];
+            if i > 0 {
+                if self.spinasm_compat && self.is_short_rdal(&operands) {
+                    // SpinASM's `CHO RDAL, lfo` reads the LFO into ACC
+                    operands.push(Operand::ChoFlags(ChoFlags::from_bits(ChoFlags::REG)));
+                    operands.push(Operand::Address(0));
+                    break;
+                }
                 self.expect(Token::Comma)?;
-                let offset = self.parse_number()?;
-                Ok(Instruction::SOF { coeff, offset })
-            }
-            Token::AND => {
-                let mask = self.parse_number()? as u32;
-                Ok(Instruction::AND { mask })
-            }
-            Token::OR => {
-                let mask = self.parse_number()? as u32;
-                Ok(Instruction::OR { mask })
             }
-            Token::XOR => {
-                let mask = self.parse_number()? as u32;
-                Ok(Instruction::XOR { mask })
-            }
-            Token::EXP => {
-                let coeff = self.parse_number()?;
-                self.expect(Token::Comma)?;
-                let offset = self.parse_number()?;
-                Ok(Instruction::EXP { coeff, offset })
+            operands.push(self.parse_operand(field)?);
+            if i == 0 {
+                spec = isa::variant(spec, &operands[0]);
             }
-            Token::LOG => {
-                let coeff = self.parse_number()?;
-                self.expect(Token::Comma)?;
-                let offset = self.parse_number()?;
-                Ok(Instruction::LOG { coeff, offset })
+        }
+        Ok(isa::build(spec, &operands).expect("parsed operands match their fields"))
+    }
+
+    /// Check for the end of a `CHO RDAL, lfo` line
+    fn is_short_rdal(&self, operands: &[Operand]) -> bool {
+        matches!(operands, [Operand::ChoMode(ChoMode::RDAL), Operand::Lfo(_)])
+            && !matches!(self.peek(), Some((Ok(Token::Comma), _)))
+    }
+
+    /// Parse an operand for the given field, checking that its value fits
+    fn parse_operand(&mut self, field: &OperandSpec) -> Result<Operand, ParseError> {
+        let start = self.current_offset();
+        Ok(match field.kind {
+            OperandKind::Register => Operand::Register(self.parse_register()?),
+            OperandKind::Address => Operand::Address(self.parse_address(start)?),
+            OperandKind::Coefficient(format) => {
+                let value = self.parse_coefficient(field, format, start)?;
+                let range = format.range();
+                if !range.contains(&value) {
+                    return Err(ParseError::CoefficientOutOfRange {
+                        value,
+                        format: format.as_str(),
+                        min: range.start,
+                        max: range.end,
+                        span: start..self.previous_end(),
+                    });
+                }
+                Operand::Coefficient(value)
             }
-            Token::SKP => {
-                let condition = self.parse_skip_condition()?;
-                self.expect(Token::Comma)?;
-                let offset = self.parse_number()? as i8;
-                Ok(Instruction::SKP { condition, offset })
+            OperandKind::Mask => Operand::Mask(self.parse_field_integer(field, start)?),
+            OperandKind::SkipCondition => Operand::SkipCondition(self.parse_skip_condition()?),
+            OperandKind::SkipOffset => Operand::SkipOffset(self.parse_skip_offset(field, start)?),
+            OperandKind::Lfo | OperandKind::SinLfo | OperandKind::RmpLfo => {
+                Operand::Lfo(self.parse_lfo()?)
             }
-            Token::WLDS => {
-                let lfo = self.parse_lfo()?;
-                self.expect(Token::Comma)?;
-                let freq = self.parse_number()? as u16;
-                self.expect(Token::Comma)?;
-                let amplitude = self.parse_number()? as u16;
-                Ok(Instruction::WLDS {
-                    lfo,
-                    freq,
-                    amplitude,
-                })
+            OperandKind::Frequency | OperandKind::Amplitude => {
+                Operand::Integer(self.parse_field_integer(field, start)? as u16)
             }
-            Token::JAM => {
-                let lfo = self.parse_lfo()?;
-                Ok(Instruction::JAM { lfo })
+            OperandKind::ChoMode => Operand::ChoMode(self.parse_cho_mode()?),
+            OperandKind::ChoFlags => Operand::ChoFlags(self.parse_cho_flags()?),
+        })
+    }
+
+    /// Parse a coefficient, reading integers the way SpinASM does
+    ///
+    /// Hex and binary literals are raw bit patterns of the field. Decimal
+    /// integers, directly or through an equate, are values when the format
+    /// can represent them and raw signed fixed-point values otherwise, so
+    /// `SOF -32768, 0` is `SOF -2.0, 0`.
+    fn parse_coefficient(
+        &mut self,
+        field: &OperandSpec,
+        format: FixedFormat,
+        start: usize,
+    ) -> Result<f32, ParseError> {
+        let scale = (1u32 << format.frac_bits()) as f32;
+        let half = 1i64 << (field.width - 1);
+        let out_of_range = |value: i64, end: usize| ParseError::CoefficientOutOfRange {
+            value: value as f32,
+            format: format.as_str(),
+            min: format.range().start,
+            max: format.range().end,
+            span: start..end,
+        };
+
+        // Fractions such as `1/3` are always values
+        if matches!(self.peek_next(), Some((Ok(Token::Slash), _))) {
+            return self.parse_number();
+        }
+        let integer = match self.peek() {
+            Some((Ok(Token::Bits(bits)), _)) => {
+                let bits = *bits;
+                self.advance();
+                if !(0..2 * half).contains(&bits) {
+                    return Err(out_of_range(bits, self.previous_end()));
+                }
+                // Sign-extend the field
+                let raw = if bits >= half { bits - 2 * half } else { bits };
+                return Ok(raw as f32 / scale);
             }
-            Token::CHO => {
-                let mode = self.parse_cho_mode()?;
-                self.expect(Token::Comma)?;
-                let lfo = self.parse_lfo()?;
-                self.expect(Token::Comma)?;
-                let flags = self.parse_cho_flags()?;
-                self.expect(Token::Comma)?;
-                let addr = self.parse_number()? as u16;
-                Ok(Instruction::CHO {
-                    mode,
-                    lfo,
-                    flags,
-                    addr,
-                })
+            Some((Ok(Token::Integer(i)), _)) => *i,
+            Some((Ok(Token::Identifier(name)), span)) => {
+                match self.lookup_equate(name.clone(), span.clone())? {
+                    Value::Integer(i) => i,
+                    _ => return self.parse_number(),
+                }
             }
-            Token::ABSA => Ok(Instruction::ABSA),
-            Token::SHL => Ok(Instruction::SHL),
-            Token::SHR => Ok(Instruction::SHR),
-            Token::CLR => Ok(Instruction::CLR),
-            Token::NOP => Ok(Instruction::NOP),
-            _ => Err(ParseError::UnexpectedToken {
-                expected: "instruction".to_string(),
-                found: format!("{:?}", token),
-                span,
-            }),
+            _ => return self.parse_number(),
+        };
+        self.advance();
+
+        // SpinASM reads small integers as values and others as raw
+        // fixed-point bits. Otherwise a decimal integer is always a value,
+        // held to the same range as a float.
+        let value = !self.spinasm_compat || (-2..=2).contains(&integer);
+        if value && format.range().contains(&(integer as f32)) {
+            Ok(integer as f32)
+        } else if self.spinasm_compat && (-half..half).contains(&integer) {
+            Ok(integer as f32 / scale)
+        } else {
+            Err(out_of_range(integer, self.previous_end()))
+        }
+    }
+
+    /// Parse a number that must fit in a field of `max + 1` values
+    fn parse_bounded(
+        &mut self,
+        operand: &'static str,
+        max: u32,
+        start: usize,
+    ) -> Result<u32, ParseError> {
+        let value = self.parse_number()?;
+        if !(0.0..=max as f32).contains(&value) {
+            return Err(ParseError::OperandOutOfRange {
+                operand,
+                value,
+                max,
+                span: start..self.previous_end(),
+            });
         }
+        Ok(value as u32)
+    }
+
+    /// Parse an unsigned integer that must fit in the field's bits
+    fn parse_field_integer(
+        &mut self,
+        field: &OperandSpec,
+        start: usize,
+    ) -> Result<u32, ParseError> {
+        let max = ((1u64 << field.width) - 1) as u32;
+        self.parse_bounded(field.name, max, start)
     }
 
     /// Parse a register
@@ -219,7 +404,9 @@ impl<'source> Parser<'source> {
             Token::ADDR_PTR => Ok(Register::ADDR_PTR),
             Token::LR => Ok(Register::LR),
             Token::REG(n) => Ok(Register::REG(n)),
-            Token::POT(n) => Ok(Register::REG(n + 16)), // POT0-2 map to REG16-18
+            Token::POT(0) => Ok(Register::POT0),
+            Token::POT(1) => Ok(Register::POT1),
+            Token::POT(2) => Ok(Register::POT2),
             Token::SIN0_RATE => Ok(Register::SIN0_RATE),
             Token::SIN0_RANGE => Ok(Register::SIN0_RANGE),
             Token::SIN1_RATE => Ok(Register::SIN1_RATE),
@@ -228,21 +415,93 @@ impl<'source> Parser<'source> {
             Token::RMP0_RANGE => Ok(Register::RMP0_RANGE),
             Token::RMP1_RATE => Ok(Register::RMP1_RATE),
             Token::RMP1_RANGE => Ok(Register::RMP1_RANGE),
+            Token::Identifier(name) => match self.lookup_equate(name, span.clone())? {
+                Value::Register(reg) => Ok(reg),
+                Value::Integer(i) if self.spinasm_compat => Self::register_number(i, span),
+                _ => Err(ParseError::ExpectedRegister { span }),
+            },
+            Token::Integer(i) | Token::Bits(i) if self.spinasm_compat => {
+                Self::register_number(i, span)
+            }
             _ => Err(ParseError::ExpectedRegister { span }),
         }
     }
 
-    /// Parse a numeric value (float or integer)
+    /// Look up a register by its address, as SpinASM allows
+    fn register_number(number: i64, span: std::ops::Range<usize>) -> Result<Register, ParseError> {
+        u32::try_from(number)
+            .ok()
+            .and_then(|bits| decode_register(bits).ok())
+            .ok_or(ParseError::ExpectedRegister { span })
+    }
+
+    /// Parse a numeric value (float or integer), optionally written as a
+    /// fraction such as `1/3`
     fn parse_number(&mut self) -> Result<f32, ParseError> {
+        let value = self.parse_number_term()?;
+        if matches!(self.peek(), Some((Ok(Token::Slash), _))) {
+            self.advance();
+            return Ok(value / self.parse_number_term()?);
+        }
+        Ok(value)
+    }
+
+    /// Parse a single numeric literal or equate
+    fn parse_number_term(&mut self) -> Result<f32, ParseError> {
         let (token, span) = self.advance_checked()?;
 
         match token {
             Token::Float(f) => Ok(f),
-            Token::Integer(i) => Ok(i as f32),
+            Token::Integer(i) | Token::Bits(i) => Ok(i as f32),
+            Token::Identifier(name) => match self.lookup_equate(name, span.clone())? {
+                Value::Float(f) => Ok(f),
+                Value::Integer(i) => Ok(i as f32),
+                _ => Err(ParseError::ExpectedNumber { span }),
+            },
             _ => Err(ParseError::ExpectedNumber { span }),
         }
     }
 
+    /// Parse a delay memory address: a number, or a `MEM` region name
+    /// referring to its start, with `#` for its end or `^` for its midpoint
+    fn parse_address(&mut self, offset: usize) -> Result<u16, ParseError> {
+        let (start, size) = match self.peek() {
+            Some((Ok(Token::Identifier(name)), _)) if self.memory.contains_key(name) => {
+                self.memory[name]
+            }
+            _ => {
+                let max = (DELAY_RAM_SIZE - 1) as u32;
+                return Ok(self.parse_bounded("address", max, offset)? as u16);
+            }
+        };
+        self.advance();
+
+        match self.peek() {
+            Some((Ok(Token::Hash), _)) => {
+                self.advance();
+                Ok(start + size)
+            }
+            Some((Ok(Token::Caret), _)) => {
+                self.advance();
+                Ok(start + size / 2)
+            }
+            _ => Ok(start),
+        }
+    }
+
+    /// Parse a SKP offset, either a number or a label resolved once the whole
+    /// program has been parsed
+    fn parse_skip_offset(&mut self, field: &OperandSpec, start: usize) -> Result<i8, ParseError> {
+        if let Some((Ok(Token::Identifier(name)), span)) = self.peek() {
+            if !self.equates.contains_key(name) {
+                self.skip_target = Some((name.clone(), span.clone()));
+                self.advance();
+                return Ok(0);
+            }
+        }
+        Ok(self.parse_field_integer(field, start)? as i8)
+    }
+
     /// Parse an LFO
     fn parse_lfo(&mut self) -> Result<Lfo, ParseError> {
         let (token, span) = self.advance_checked()?;
@@ -252,6 +511,9 @@ impl<'source> Parser<'source> {
             Token::SIN1 => Ok(Lfo::SIN1),
             Token::RMP0 => Ok(Lfo::RMP0),
             Token::RMP1 => Ok(Lfo::RMP1),
+            Token::Integer(i) | Token::Bits(i) if self.spinasm_compat && (0..=3).contains(&i) => {
+                Ok([Lfo::SIN0, Lfo::SIN1, Lfo::RMP0, Lfo::RMP1][i as usize])
+            }
             _ => Err(ParseError::UnexpectedToken {
                 expected: "LFO (sin0, sin1, rmp0, rmp1)".to_string(),
                 found: format!("{:?}", token),
@@ -294,28 +556,39 @@ impl<'source> Parser<'source> {
         }
     }
 
-    /// Parse CHO flags
-    /// Note: This is a simplified implementation that returns default flags.
-    /// Full flag parsing will be implemented when CHO instruction support is needed.
+    /// Parse CHO flags: flag names or integers combined with `|`
     fn parse_cho_flags(&mut self) -> Result<ChoFlags, ParseError> {
-        // For now, parse an integer representing flags as a bitmask
-        let (token, _span) = self.advance_checked()?;
+        let mut bits = self.parse_cho_flag()?;
+        while matches!(self.peek(), Some((Ok(Token::Pipe), _))) {
+            self.advance();
+            bits |= self.parse_cho_flag()?;
+        }
+        Ok(ChoFlags::from_bits(bits))
+    }
 
-        // Parse as integer and convert to flags (simplified)
-        let _flags_value = match token {
-            Token::Integer(i) => i,
-            _ => 0,
-        };
+    /// Parse a single CHO flag term
+    fn parse_cho_flag(&mut self) -> Result<u8, ParseError> {
+        let (token, span) = self.advance_checked()?;
 
-        // TODO: Parse actual flag combinations (rptr2, na, compc, compa)
-        // For now, return default flags
-        Ok(ChoFlags {
-            rptr2: false,
-            na: false,
-            compc: false,
-            compa: false,
-            rptr2_select: false,
-        })
+        match token {
+            Token::SIN => Ok(0),
+            Token::COS => Ok(ChoFlags::COS),
+            Token::REG_FLAG => Ok(ChoFlags::REG),
+            Token::COMPC => Ok(ChoFlags::COMPC),
+            Token::COMPA => Ok(ChoFlags::COMPA),
+            Token::RPTR2 => Ok(ChoFlags::RPTR2),
+            Token::NA => Ok(ChoFlags::NA),
+            Token::Integer(i) | Token::Bits(i) if (0..=0x3F).contains(&i) => Ok(i as u8),
+            Token::Identifier(name) => match self.lookup_equate(name, span.clone())? {
+                Value::Integer(i) if (0..=0x3F).contains(&i) => Ok(i as u8),
+                _ => Err(ParseError::ExpectedNumber { span }),
+            },
+            _ => Err(ParseError::UnexpectedToken {
+                expected: "CHO flag (sin, cos, reg, compc, compa, rptr2, na)".to_string(),
+                found: format!("{:?}", token),
+                span,
+            }),
+        }
     }
 
     /// Parse a directive
@@ -325,7 +598,10 @@ impl<'source> Parser<'source> {
         match token {
             Token::EQU => {
                 let name = self.parse_identifier()?;
-                self.expect(Token::Comma)?;
+                let comma = matches!(self.peek(), Some((Ok(Token::Comma), _)));
+                if comma || !self.spinasm_compat {
+                    self.expect(Token::Comma)?;
+                }
                 let value = self.parse_value()?;
                 Ok(Directive::Equate { name, value })
             }
@@ -362,16 +638,86 @@ impl<'source> Parser<'source> {
 
     /// Parse a value (for directives)
     fn parse_value(&mut self) -> Result<Value, ParseError> {
-        let (token, span) = self.advance_checked()?;
+        match self.peek() {
+            Some((Ok(Token::Float(_) | Token::Integer(_) | Token::Bits(_)), _)) | None => {}
+            Some((Ok(Token::Identifier(_)), _)) => {}
+            Some((_, span)) => {
+                // Equates may also alias a register
+                let span = span.clone();
+                return self
+                    .parse_register()
+                    .map(Value::Register)
+                    .map_err(|_| ParseError::ExpectedNumber { span });
+            }
+        }
 
+        let (token, span) = self.advance_checked()?;
         match token {
             Token::Float(f) => Ok(Value::Float(f)),
-            Token::Integer(i) => Ok(Value::Integer(i)),
+            Token::Integer(i) | Token::Bits(i) => Ok(Value::Integer(i)),
             Token::Identifier(s) => Ok(Value::Identifier(s)),
             _ => Err(ParseError::ExpectedNumber { span }),
         }
     }
 
+    /// Value given to `name` from outside the source, if any
+    fn defined(&self, name: &str) -> Option<Value> {
+        self.defines
+            .iter()
+            .find(|(defined, _)| defined == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Record an equate, resolving a value that names an earlier equate
+    fn define_equate(&mut self, name: &str, value: &Value) {
+        let value = match value {
+            Value::Identifier(other) => self
+                .equates
+                .get(other)
+                .cloned()
+                .unwrap_or_else(|| value.clone()),
+            value => value.clone(),
+        };
+        self.equates.insert(name.to_string(), value);
+    }
+
+    /// Allocate a delay memory region after the previous ones
+    ///
+    /// Like SpinASM, each region reserves `size + 1` words so that `name#`
+    /// still lies inside it.
+    fn allocate_memory(
+        &mut self,
+        name: &str,
+        size: u16,
+        span: std::ops::Range<usize>,
+    ) -> Result<(), ParseError> {
+        let available = DELAY_RAM_SIZE.saturating_sub(self.next_addr);
+        if size as usize >= available {
+            return Err(ParseError::MemoryOverflow {
+                name: name.to_string(),
+                size,
+                available,
+                span,
+            });
+        }
+        self.memory
+            .insert(name.to_string(), (self.next_addr as u16, size));
+        self.next_addr += size as usize + 1;
+        Ok(())
+    }
+
+    /// Look up the value of an equate used as an operand
+    fn lookup_equate(
+        &self,
+        name: String,
+        span: std::ops::Range<usize>,
+    ) -> Result<Value, ParseError> {
+        match self.equates.get(&name) {
+            Some(Value::Identifier(_)) | None => Err(ParseError::UndefinedSymbol { name, span }),
+            Some(value) => Ok(value.clone()),
+        }
+    }
+
     // Helper methods
 
     /// Check if current token is a directive
@@ -382,72 +728,88 @@ impl<'source> Parser<'source> {
         )
     }
 
-    /// Check if current token is an instruction
+    /// Check if current token is an instruction mnemonic
     fn is_instruction(&self) -> bool {
-        matches!(
-            self.peek(),
-            Some((
-                Ok(Token::RDAX
-                    | Token::RDA
-                    | Token::WRAX
-                    | Token::WRA
-                    | Token::WRAP
-                    | Token::RMPA
-                    | Token::MULX
-                    | Token::RDFX
-                    | Token::RDFX2
-                    | Token::LDAX
-                    | Token::SOF
-                    | Token::AND
-                    | Token::OR
-                    | Token::XOR
-                    | Token::EXP
-                    | Token::LOG
-                    | Token::SKP
-                    | Token::WLDS
-                    | Token::JAM
-                    | Token::CHO
-                    | Token::ABSA
-                    | Token::SHL
-                    | Token::SHR
-                    | Token::CLR
-                    | Token::NOP),
-                _
-            ))
-        )
+        match self.peek() {
+            Some((Ok(Token::DW), _)) => true,
+            Some((Ok(token), span)) => isa::lookup(self.mnemonic(token, span)).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Text of a token that may be an instruction mnemonic
+    ///
+    /// Mnemonics expanded from a macro arrive as identifiers, since their
+    /// span points at the macro invocation.
+    fn mnemonic<'a>(&'a self, token: &'a Token, span: &std::ops::Range<usize>) -> &'a str {
+        match token {
+            Token::Identifier(name) => name,
+            _ => &self.source[span.clone()],
+        }
+    }
+
+    /// Byte offset where the current token starts
+    fn current_offset(&self) -> usize {
+        self.peek()
+            .map(|(_, span)| span.start)
+            .unwrap_or(self.source.len())
+    }
+
+    /// Byte offset where the most recently consumed token ends
+    fn previous_end(&self) -> usize {
+        self.last_end.unwrap_or(0)
+    }
+
+    /// Pull tokens from the lexer until the lookahead buffer is full
+    fn fill_lookahead(&mut self) {
+        while self.lookahead.len() < LOOKAHEAD && self.token_error.is_none() {
+            match self.tokens.next() {
+                Some(Ok((mut token, span))) => {
+                    // SpinASM symbols are case-insensitive
+                    if let (true, Ok(Token::Identifier(name))) = (self.spinasm_compat, &mut token) {
+                        name.make_ascii_uppercase();
+                    }
+                    self.lookahead.push_back((token, span));
+                }
+                Some(Err(error)) => self.token_error = Some(error),
+                None => break,
+            }
+        }
     }
 
     /// Check if at end of token stream
     fn is_at_end(&self) -> bool {
-        self.pos >= self.tokens.len()
+        self.lookahead.is_empty()
     }
 
     /// Peek at current token without consuming
-    fn peek(&self) -> Option<&(Result<Token, ()>, std::ops::Range<usize>)> {
-        self.tokens.get(self.pos)
+    fn peek(&self) -> Option<&Spanned> {
+        self.lookahead.front()
     }
 
     /// Peek at next token without consuming
-    fn peek_next(&self) -> Option<&(Result<Token, ()>, std::ops::Range<usize>)> {
-        self.tokens.get(self.pos + 1)
+    fn peek_next(&self) -> Option<&Spanned> {
+        self.lookahead.get(1)
     }
 
     /// Advance to next token
-    fn advance(&mut self) -> Option<&(Result<Token, ()>, std::ops::Range<usize>)> {
-        let token = self.tokens.get(self.pos);
-        self.pos += 1;
+    fn advance(&mut self) -> Option<Spanned> {
+        let token = self.lookahead.pop_front();
+        if let Some((_, span)) = &token {
+            self.last_end = Some(span.end);
+        }
+        self.fill_lookahead();
         token
     }
 
     /// Advance and return token, or error if at end
     fn advance_checked(&mut self) -> Result<(Token, std::ops::Range<usize>), ParseError> {
-        if self.is_at_end() {
-            return Err(ParseError::UnexpectedEof);
-        }
-        let (token_result, span) = self.advance().unwrap();
+        let Some((token_result, span)) = self.advance() else {
+            return Err(self.token_error.take().unwrap_or(ParseError::UnexpectedEof));
+        };
         match token_result {
-            Ok(token) => Ok((token.clone(), span.clone())),
-            Err(_) => Err(ParseError::InvalidToken { span: span.clone() }),
+            Ok(token) => Ok((token, span)),
+            Err(_) => Err(ParseError::InvalidToken { span }),
         }
     }
 
@@ -467,6 +829,61 @@ impl<'source> Parser<'source> {
     }
 }
 
+/// A parsed line that a trailing comment can be attached to
+#[derive(Debug, Clone, Copy)]
+enum Line {
+    /// Index into the program's directives
+    Directive(usize),
+    /// Index into the program's statements
+    Statement(usize),
+}
+
+/// Check that no label is defined twice
+fn check_duplicate_labels(labels: &[(String, std::ops::Range<usize>)]) -> Result<(), ParseError> {
+    let mut seen: HashMap<&str, &std::ops::Range<usize>> = HashMap::new();
+    for (name, span) in labels {
+        if let Some(first) = seen.insert(name, span) {
+            return Err(ParseError::DuplicateLabel {
+                name: name.clone(),
+                first: first.clone(),
+                span: span.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Resolve the label targets of SKP instructions once every label is known,
+/// filling in their offsets
+fn resolve_skip_targets(
+    program: &mut Program,
+    skip_spans: Vec<(usize, std::ops::Range<usize>)>,
+) -> Result<(), ParseError> {
+    for (index, span) in skip_spans {
+        let label = program.skip_targets[&index].clone();
+        let target = program
+            .resolve_label(&label)
+            .ok_or_else(|| ParseError::UndefinedLabel {
+                name: label.clone(),
+                span: span.clone(),
+            })?;
+
+        // Offsets count the instructions skipped after the SKP itself
+        let distance = target as isize - index as isize - 1;
+        if !(0..=63).contains(&distance) {
+            return Err(ParseError::SkipOutOfRange {
+                label,
+                offset: distance,
+                span,
+            });
+        }
+        if let Some(Instruction::SKP { offset, .. }) = program.instruction_mut(index) {
+            *offset = distance as i8;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -497,6 +914,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_fraction() {
+        let program = Parser::new("sof 1/3, -1/2").parse().unwrap();
+
+        assert_eq!(
+            *program.instructions()[0],
+            Instruction::SOF {
+                coeff: 1.0 / 3.0,
+                offset: -0.5
+            }
+        );
+    }
+
     #[test]
     fn test_parse_sof() {
         let source = "sof 0.5, 0.0";
@@ -513,6 +943,428 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_wrlx() {
+        let source = "wrlx reg4, -1.0";
+        let mut parser = Parser::new(source);
+        let program = parser.parse().unwrap();
+
+        match program.instructions()[0] {
+            Instruction::WRLX { reg, coeff } => {
+                assert_eq!(*reg, Register::REG(4));
+                assert_eq!(*coeff, -1.0);
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+    }
+
+    #[test]
+    fn test_parse_wrhx() {
+        let source = "wrhx reg5, -0.5";
+        let mut parser = Parser::new(source);
+        let program = parser.parse().unwrap();
+
+        match program.instructions()[0] {
+            Instruction::WRHX { reg, coeff } => {
+                assert_eq!(*reg, Register::REG(5));
+                assert_eq!(*coeff, -0.5);
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+    }
+
+    #[test]
+    fn test_parse_not() {
+        let source = "rdax adcl, 1.0\nnot";
+        let mut parser = Parser::new(source);
+        let program = parser.parse().unwrap();
+
+        assert_eq!(program.instructions().len(), 2);
+        assert!(matches!(program.instructions()[1], Instruction::NOT));
+    }
+
+    #[test]
+    fn test_parse_skp_label() {
+        let source = "SKP ZRO, end\nCLR\nend:\n";
+        let mut parser = Parser::new(source);
+        let program = parser.parse().unwrap();
+
+        assert_eq!(program.skip_target(0), Some("end"));
+        assert_eq!(program.skip_target(1), None);
+        assert_eq!(program.resolve_label("end"), Some(2));
+        assert!(matches!(
+            program.instructions()[0],
+            Instruction::SKP { offset: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_skp_undefined_label() {
+        let result = Parser::new("SKP ZRO, nowhere\nCLR\n").parse();
+        assert!(matches!(
+            result,
+            Err(ParseError::UndefinedLabel { name, span }) if name == "nowhere" && span == (9..16)
+        ));
+    }
+
+    #[test]
+    fn test_parse_duplicate_label() {
+        let source = "loop: clr
+sof 0.5, 0.0
+loop: wrax dacl, 0.0";
+        let err = Parser::new(source).parse().unwrap_err();
+        match err {
+            ParseError::DuplicateLabel { name, first, span } => {
+                assert_eq!(name, "loop");
+                assert_eq!(first, 0..4);
+                assert_eq!(span, 23..27);
+            }
+            _ => panic!("Expected DuplicateLabel, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_parse_skp_out_of_range() {
+        let result = Parser::new("top: CLR\nSKP GEZ, top\n").parse();
+        assert!(matches!(
+            result,
+            Err(ParseError::SkipOutOfRange { offset: -2, .. })
+        ));
+
+        let far = format!("SKP RUN, end\n{}end: CLR\n", "NOP\n".repeat(64));
+        assert!(matches!(
+            Parser::new(&far).parse(),
+            Err(ParseError::SkipOutOfRange { offset: 64, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_raw_word() {
+        let source = ".dw 0x8000000F\n.DW 17";
+        let mut parser = Parser::new(source);
+        let program = parser.parse().unwrap();
+        let instructions = program.instructions();
+
+        assert_eq!(*instructions[0], Instruction::RAW(0x8000000F));
+        assert_eq!(*instructions[1], Instruction::RAW(17));
+        assert!(Parser::new(".dw -1").parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_pot_registers() {
+        let source = "mulx pot0\nrdax pot2, 1.0";
+        let mut parser = Parser::new(source);
+        let program = parser.parse().unwrap();
+        let instructions = program.instructions();
+
+        assert_eq!(
+            *instructions[0],
+            Instruction::MULX {
+                reg: Register::POT0
+            }
+        );
+        assert_eq!(
+            *instructions[1],
+            Instruction::RDAX {
+                reg: Register::POT2,
+                coeff: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_spinasm_compat() {
+        let source = "\
+equ Gain 0.5
+mem Delay 100
+Start: rdax 20, gain
+log 0.5, 3
+cho rdal, 1
+wra delay#, 0
+skp run, FINISH
+finish: clr
+";
+        assert!(Parser::new(source).parse().is_err());
+
+        let program = Parser::new(source).spinasm_compat(true).parse().unwrap();
+        let instructions = program.instructions();
+        assert_eq!(program.labels.get("START"), Some(&0));
+        assert_eq!(
+            *instructions[0],
+            Instruction::RDAX {
+                reg: Register::ADCL,
+                coeff: 0.5
+            }
+        );
+        assert_eq!(
+            *instructions[1],
+            Instruction::LOG {
+                coeff: 0.5,
+                offset: 3.0 / 64.0
+            }
+        );
+        assert_eq!(
+            *instructions[2],
+            Instruction::CHO {
+                mode: ChoMode::RDAL,
+                lfo: Lfo::SIN1,
+                flags: ChoFlags::from_bits(ChoFlags::REG),
+                addr: 0
+            }
+        );
+        assert_eq!(
+            *instructions[3],
+            Instruction::WRA {
+                addr: 100,
+                coeff: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_pot_register_operands() {
+        let source = "equ vol, POT1\nrdfx pot0, 0.01\nldax vol\nwrax POT2, 0.0";
+        let program = Parser::new(source).parse().unwrap();
+        let instructions = program.instructions();
+
+        assert_eq!(
+            *instructions[0],
+            Instruction::RDFX {
+                reg: Register::POT0,
+                coeff: 0.01
+            }
+        );
+        assert_eq!(
+            *instructions[1],
+            Instruction::LDAX {
+                reg: Register::POT1
+            }
+        );
+        assert_eq!(
+            *instructions[2],
+            Instruction::WRAX {
+                reg: Register::POT2,
+                coeff: 0.0
+            }
+        );
+
+        // Only POT0-POT2 exist
+        assert!(Parser::new("mulx pot3").parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_cho_flags() {
+        let source = "cho rdal, sin1, cos|reg|compc, 0\ncho rda, rmp0, 0x26, 100";
+        let mut parser = Parser::new(source);
+        let program = parser.parse().unwrap();
+        let instructions = program.instructions();
+
+        match instructions[0] {
+            Instruction::CHO { flags, .. } => assert_eq!(flags.bits(), 0x07),
+            _ => panic!("Expected CHO instruction"),
+        }
+        match instructions[1] {
+            Instruction::CHO { flags, addr, .. } => {
+                assert!(flags.reg && flags.compc && flags.na);
+                assert_eq!(*addr, 100);
+            }
+            _ => panic!("Expected CHO instruction"),
+        }
+    }
+
+    #[test]
+    fn test_parse_cho_sof_offset() {
+        let program = Parser::new("cho sof, sin0, reg|compc, -0.25\ncho sof, rmp0, na, 0x4000")
+            .parse()
+            .unwrap();
+        let instructions = program.instructions();
+        assert_eq!(
+            *instructions[0],
+            Instruction::CHO_SOF {
+                lfo: Lfo::SIN0,
+                flags: ChoFlags::from_bits(ChoFlags::REG | ChoFlags::COMPC),
+                offset: -0.25,
+            }
+        );
+        // Hex literals are raw S.15 field bits
+        assert!(matches!(
+            instructions[1],
+            Instruction::CHO_SOF { offset, .. } if *offset == 0.5
+        ));
+
+        // Offsets are limited to the S.15 range
+        let err = Parser::new("cho sof, sin0, 0, 1.5").parse().unwrap_err();
+        assert!(matches!(err, ParseError::CoefficientOutOfRange { .. }));
+    }
+
+    #[test]
+    fn test_parse_cho_flag_expressions() {
+        let source = "MEM delay 1000\nEQU flags, 0x20\n\
+                      CHO RDA, SIN0, SIN|REG|COMPC, delay\n\
+                      CHO RDA, SIN0, flags | REG | 0x10, delay#";
+        let program = Parser::new(source).parse().unwrap();
+        let instructions = program.instructions();
+
+        match instructions[0] {
+            Instruction::CHO { flags, addr, .. } => {
+                assert_eq!(flags.bits(), ChoFlags::REG | ChoFlags::COMPC);
+                assert_eq!(*addr, 0);
+            }
+            _ => panic!("Expected CHO instruction"),
+        }
+        match instructions[1] {
+            Instruction::CHO { flags, addr, .. } => {
+                assert_eq!(flags.bits(), ChoFlags::NA | ChoFlags::REG | ChoFlags::RPTR2);
+                assert_eq!(*addr, 1000);
+            }
+            _ => panic!("Expected CHO instruction"),
+        }
+
+        let err = Parser::new("cho rda, sin0, reg|, 0").parse().unwrap_err();
+        assert!(matches!(err, ParseError::UnexpectedToken { .. }));
+    }
+
+    #[test]
+    fn test_parse_coefficient_out_of_range() {
+        let source = "equ big, 1.5\nsof 1.0, 0.0\nsof 0.5, 2.0\nrdax adcl, big";
+        let err = Parser::new(source).parse().unwrap_err();
+        match err {
+            ParseError::CoefficientOutOfRange {
+                value,
+                format,
+                span,
+                ..
+            } => {
+                assert_eq!(value, 2.0);
+                assert_eq!(format, "S.10");
+                assert_eq!(&source[span], "2.0");
+            }
+            _ => panic!("Expected CoefficientOutOfRange, got {:?}", err),
+        }
+
+        let source = "equ big, 2.5\nrdax adcl, big";
+        let err = Parser::new(source).parse().unwrap_err();
+        match err {
+            ParseError::CoefficientOutOfRange { span, .. } => assert_eq!(&source[span], "big"),
+            _ => panic!("Expected CoefficientOutOfRange, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_parse_integer_coefficients() {
+        let coefficients = |source: &str, spinasm: bool| -> Vec<(f32, f32)> {
+            let program = Parser::new(source).spinasm_compat(spinasm).parse().unwrap();
+            program
+                .instructions()
+                .iter()
+                .map(|instruction| match instruction {
+                    Instruction::SOF { coeff, offset } => (*coeff, *offset),
+                    Instruction::RDAX { coeff, .. } => (*coeff, 0.0),
+                    _ => panic!("Unexpected instruction {:?}", instruction),
+                })
+                .collect()
+        };
+
+        // Decimal integers are values, like floats
+        assert_eq!(
+            coefficients("sof 1, -1\nrdax adcl, -2\nsof 1., .5", false),
+            vec![(1.0, -1.0), (-2.0, 0.0), (1.0, 0.5)]
+        );
+        // SpinASM reads them as raw fixed-point unless they are small
+        assert_eq!(
+            coefficients("sof -32768, 0\nsof 1, -1\nsof 8192, 512", true),
+            vec![(-2.0, 0.0), (1.0, -1.0), (0.5, 0.5)]
+        );
+        // Hex and binary are bit patterns of the field
+        assert_eq!(
+            coefficients("rdax adcl, $4000\nrdax adcl, 0xC000\nsof %01_0000_0000_0000_00, 0x7FF\nsof 0, 0x3FF", false),
+            vec![(1.0, 0.0), (-1.0, 0.0), (1.0, -1.0 / 1024.0), (0.0, 1023.0 / 1024.0)]
+        );
+        // Integer equates follow the decimal rule
+        assert_eq!(
+            coefficients("equ half, 8192\nrdax adcl, half", true),
+            vec![(0.5, 0.0)]
+        );
+
+        // Out of range decimal integers are rejected just as floats are
+        for source in [
+            "rdax adcl, 2",
+            "sof -1, 1",
+            "sof 0.5, 1.0",
+            "sof 8192, 0",
+            "equ half, 8192\nrdax adcl, half",
+            "rdax adcl, 0x10000",
+            "sof 0, 0x800",
+        ] {
+            let err = Parser::new(source).parse().unwrap_err();
+            assert!(
+                matches!(err, ParseError::CoefficientOutOfRange { .. }),
+                "{}: {:?}",
+                source,
+                err
+            );
+        }
+        let err = Parser::new("sof 32768, 0")
+            .spinasm_compat(true)
+            .parse()
+            .unwrap_err();
+        assert!(matches!(err, ParseError::CoefficientOutOfRange { .. }));
+    }
+
+    #[test]
+    fn test_parse_operand_out_of_range() {
+        let cases = [
+            ("rda 32768, 0.5", "address", "32768"),
+            ("wra -1, 0.5", "address", "-1"),
+            ("and 0x1000000", "mask", "0x1000000"),
+            ("skp run, 64", "offset", "64"),
+            ("wlds sin0, 512, 100", "freq", "512"),
+        ];
+        for (source, expected, literal) in cases {
+            let err = Parser::new(source).parse().unwrap_err();
+            match err {
+                ParseError::OperandOutOfRange { operand, span, .. } => {
+                    assert_eq!(operand, expected, "{}", source);
+                    assert_eq!(&source[span], literal, "{}", source);
+                }
+                _ => panic!("Expected OperandOutOfRange for {}, got {:?}", source, err),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_comments() {
+        let source = "; header\nclr ; clear\nmem dly 100 ; delay\n; footer";
+
+        let program = Parser::new(source).parse().unwrap();
+        assert_eq!(program.statements.len(), 1);
+        assert!(program.trailing_comments.is_empty());
+
+        let program = Parser::new(source).with_comments(true).parse().unwrap();
+        assert!(matches!(&program.statements[0], Statement::Comment(c) if c == " header"));
+        assert!(matches!(&program.statements[2], Statement::Comment(c) if c == " footer"));
+        assert_eq!(program.trailing_comments.get(&1).unwrap(), " clear");
+        assert_eq!(program.directive_comments.get(&0).unwrap(), " delay");
+        assert_eq!(program.instructions().len(), 1);
+        assert_eq!(&source[program.instruction_span(0).unwrap()], "clr");
+    }
+
+    #[test]
+    fn test_source_spans() {
+        let source = "equ k, 0.5\nmem dly 100\nloop:  sof k, 0.0\nwra dly, 0.0";
+        let program = Parser::new(source).parse().unwrap();
+
+        assert_eq!(&source[program.directive_span(0).unwrap()], "equ k, 0.5");
+        assert_eq!(&source[program.directive_span(1).unwrap()], "mem dly 100");
+        assert_eq!(
+            &source[program.statement_span(0).unwrap()],
+            "loop:  sof k, 0.0"
+        );
+        assert_eq!(&source[program.statement_span(1).unwrap()], "wra dly, 0.0");
+        assert_eq!(program.statement_span(2), None);
+        assert_eq!(program.directive_span(2), None);
+    }
+
     #[test]
     fn test_parse_label() {
         let source = "start: clr";
@@ -598,6 +1450,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_directive_equ_register() {
+        let source = "equ feedback, reg3";
+        let mut parser = Parser::new(source);
+        let program = parser.parse().unwrap();
+
+        assert!(matches!(
+            &program.directives[0],
+            Directive::Equate {
+                value: Value::Register(Register::REG(3)),
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn test_parse_directive_mem() {
         let source = "mem delay 4096";
@@ -613,4 +1480,109 @@ mod tests {
             _ => panic!("Wrong directive"),
         }
     }
+
+    #[test]
+    fn test_equate_operands() {
+        let source = "equ gain, 0.5\nequ input, adcl\nequ tap, 4000\nequ level, gain\n\
+                      rdax input, gain\nrda tap, level\n";
+        let program = Parser::new(source).parse().unwrap();
+
+        let instructions = program.instructions();
+        assert_eq!(
+            *instructions[0],
+            Instruction::RDAX {
+                reg: Register::ADCL,
+                coeff: 0.5
+            }
+        );
+        assert_eq!(
+            *instructions[1],
+            Instruction::RDA {
+                addr: 4000,
+                coeff: 0.5
+            }
+        );
+    }
+
+    #[test]
+    fn test_equate_skip_offset() {
+        let program = Parser::new("equ two, 2\nskp run, two\n").parse().unwrap();
+
+        assert!(program.skip_target(0).is_none());
+        assert!(matches!(
+            program.instructions()[0],
+            Instruction::SKP { offset: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_defines() {
+        let source = "equ tap, 1000\nequ level, gain\nrda tap, level\n";
+        let program = Parser::new(source)
+            .with_define("tap", Value::Integer(2000))
+            .with_define("gain", Value::Float(0.25))
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            *program.instructions()[0],
+            Instruction::RDA {
+                addr: 2000,
+                coeff: 0.25
+            }
+        );
+        assert!(matches!(
+            &program.directives[..],
+            [
+                Directive::Equate { name: tap, value: Value::Integer(2000) },
+                Directive::Equate { .. },
+                Directive::Equate { name: gain, value: Value::Float(_) },
+            ] if tap == "tap" && gain == "gain"
+        ));
+    }
+
+    #[test]
+    fn test_undefined_symbol() {
+        let result = Parser::new("rdax adcl, gain\n").parse();
+        assert!(matches!(
+            result,
+            Err(ParseError::UndefinedSymbol { name, span }) if name == "gain" && span == (11..15)
+        ));
+    }
+
+    #[test]
+    fn test_equate_kind_mismatch() {
+        let result = Parser::new("equ gain, 0.5\nrdax gain, 1.0\n").parse();
+        assert!(matches!(result, Err(ParseError::ExpectedRegister { .. })));
+    }
+
+    #[test]
+    fn test_memory_operands() {
+        let source = "mem first 100\nmem delay 4000\n\
+                      wra delay, 0.0\nrda delay#, 0.5\nrda delay^, 0.5\ncho rda, sin0, sin, first#\n";
+        let program = Parser::new(source).parse().unwrap();
+
+        let addresses: Vec<u16> = program
+            .instructions()
+            .iter()
+            .map(|inst| match inst {
+                Instruction::WRA { addr, .. }
+                | Instruction::RDA { addr, .. }
+                | Instruction::CHO { addr, .. } => *addr,
+                _ => panic!("unexpected instruction"),
+            })
+            .collect();
+        assert_eq!(addresses, vec![101, 4101, 2101, 100]);
+    }
+
+    #[test]
+    fn test_memory_overflow() {
+        assert!(Parser::new("mem all 32767\n").parse().is_ok());
+
+        let result = Parser::new("mem a 20000\nmem b 12768\n").parse();
+        assert!(matches!(
+            result,
+            Err(ParseError::MemoryOverflow { name, available: 12767, .. }) if name == "b"
+        ));
+    }
 }
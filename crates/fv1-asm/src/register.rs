@@ -13,6 +13,11 @@ pub enum Register {
     DACL, // Left DAC output
     DACR, // Right DAC output
 
+    // Potentiometer inputs
+    POT0,
+    POT1,
+    POT2,
+
     // General purpose registers (32 total)
     REG(u8), // REG0-REG31
 
@@ -59,8 +64,18 @@ pub enum Control {
     POT2,
 }
 
+impl From<Control> for Register {
+    fn from(control: Control) -> Self {
+        match control {
+            Control::POT0 => Register::POT0,
+            Control::POT1 => Register::POT1,
+            Control::POT2 => Register::POT2,
+        }
+    }
+}
+
 /// LFO oscillators
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Lfo {
     SIN0,
     SIN1,
@@ -100,4 +115,10 @@ mod tests {
         let lfo = Lfo::SIN0;
         assert_eq!(lfo, Lfo::SIN0);
     }
+
+    #[test]
+    fn test_control_to_register() {
+        assert_eq!(Register::from(Control::POT0), Register::POT0);
+        assert_eq!(Register::from(Control::POT2), Register::POT2);
+    }
 }
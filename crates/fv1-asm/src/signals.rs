@@ -0,0 +1,245 @@
+//! Test Signal Generators
+//!
+//! Deterministic audio sources at the FV-1 sample rate for rendering and
+//! testing programs. Noise is driven by a seeded generator and tones use a
+//! polynomial sine rather than the platform's math library, so the same
+//! arguments produce bit-identical samples on every platform.
+
+use crate::constants::SAMPLE_RATE;
+
+/// Seedable pseudo-random number generator (xorshift64*)
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a generator from a seed
+    pub fn new(seed: u64) -> Self {
+        // The state must never be zero
+        Self {
+            state: seed ^ 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// Next 32 random bits
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as u32
+    }
+
+    /// Next sample, uniform in -1.0..1.0
+    pub fn next_sample(&mut self) -> f32 {
+        (self.next_u32() as i32) as f32 / 2147483648.0
+    }
+}
+
+/// Sine of `turns` full cycles, without relying on the platform's `sin`
+fn sin_turns(turns: f64) -> f64 {
+    // Reduce to -0.25..=0.25 turns, where the series converges quickly
+    let mut x = turns - turns.floor();
+    if x > 0.5 {
+        x -= 1.0;
+    }
+    if x > 0.25 {
+        x = 0.5 - x;
+    } else if x < -0.25 {
+        x = -0.5 - x;
+    }
+
+    let x = x * std::f64::consts::TAU;
+    let x2 = x * x;
+    // Taylor series to x^13, accurate to ~1e-10 over the reduced range
+    let mut term = x;
+    let mut sum = x;
+    for n in 1..7 {
+        term *= -x2 / ((2 * n) * (2 * n + 1)) as f64;
+        sum += term;
+    }
+    sum
+}
+
+/// Single full-scale sample followed by silence
+pub fn impulse(len: usize) -> Vec<f32> {
+    let mut samples = vec![0.0; len];
+    if let Some(first) = samples.first_mut() {
+        *first = 1.0;
+    }
+    samples
+}
+
+/// Sine wave at `freq` Hz
+pub fn sine(len: usize, freq: f32, amplitude: f32) -> Vec<f32> {
+    let step = freq as f64 / SAMPLE_RATE as f64;
+    (0..len)
+        .map(|n| (sin_turns(n as f64 * step) * amplitude as f64) as f32)
+        .collect()
+}
+
+/// Exponential sine sweep from `start` to `end` Hz
+pub fn sweep(len: usize, start: f32, end: f32, amplitude: f32) -> Vec<f32> {
+    // Grow the frequency by a constant ratio each sample
+    let ratio = (end as f64 / start as f64).powf(1.0 / len.max(1) as f64);
+    let mut freq = start as f64;
+    let mut phase = 0.0;
+
+    (0..len)
+        .map(|_| {
+            let sample = (sin_turns(phase) * amplitude as f64) as f32;
+            phase += freq / SAMPLE_RATE as f64;
+            phase -= phase.floor();
+            freq *= ratio;
+            sample
+        })
+        .collect()
+}
+
+/// White noise, uniform in -1.0..1.0
+pub fn white_noise(len: usize, seed: u64) -> Vec<f32> {
+    let mut rng = Rng::new(seed);
+    (0..len).map(|_| rng.next_sample()).collect()
+}
+
+/// Pink (1/f) noise in -1.0..1.0, using the Voss-McCartney algorithm
+pub fn pink_noise(len: usize, seed: u64) -> Vec<f32> {
+    const ROWS: usize = 16;
+
+    let mut rng = Rng::new(seed);
+    let mut rows = [0.0f32; ROWS];
+    for row in &mut rows {
+        *row = rng.next_sample();
+    }
+    let mut sum: f32 = rows.iter().sum();
+
+    (0..len)
+        .map(|n| {
+            // Row k is refreshed every 2^k samples
+            let row = (n + 1).trailing_zeros() as usize;
+            if row < ROWS {
+                let value = rng.next_sample();
+                sum += value - rows[row];
+                rows[row] = value;
+            }
+            (sum + rng.next_sample()) / (ROWS + 1) as f32
+        })
+        .collect()
+}
+
+/// Synthesized drum loop: kick on beats 1 and 3, snare on 2 and 4 and a hi-hat
+/// on every eighth note
+pub fn drum_loop(len: usize, bpm: f32, seed: u64) -> Vec<f32> {
+    let mut rng = Rng::new(seed);
+    let eighth = ((SAMPLE_RATE * 30.0 / bpm) as usize).max(1);
+
+    let mut kick_env = 0.0f32;
+    let mut kick_phase = 0.0f64;
+    let mut snare_env = 0.0f32;
+    let mut snare_phase = 0.0f64;
+    let mut hat_env = 0.0f32;
+    let mut last_noise = 0.0f32;
+
+    (0..len)
+        .map(|n| {
+            if n % eighth == 0 {
+                match (n / eighth) % 8 {
+                    0 | 4 => {
+                        kick_env = 1.0;
+                        kick_phase = 0.0;
+                    }
+                    2 | 6 => snare_env = 1.0,
+                    _ => {}
+                }
+                hat_env = 1.0;
+            }
+
+            let noise = rng.next_sample();
+            // Kick pitch falls from 150 Hz to 50 Hz as it decays
+            let kick_freq = 50.0 + 100.0 * kick_env as f64;
+            let kick = sin_turns(kick_phase) as f32 * kick_env;
+            kick_phase += kick_freq / SAMPLE_RATE as f64;
+            let snare = (noise * 0.7 + sin_turns(snare_phase) as f32 * 0.3) * snare_env;
+            snare_phase += 200.0 / SAMPLE_RATE as f64;
+            // First difference of the noise emphasizes the highs
+            let hat = (noise - last_noise) * 0.5 * hat_env;
+            last_noise = noise;
+
+            kick_env *= 0.9997;
+            snare_env *= 0.9992;
+            hat_env *= 0.997;
+
+            ((kick * 0.6 + snare * 0.3 + hat * 0.1) * 0.9).clamp(-1.0, 1.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_sin_turns() {
+        for i in -40..=40 {
+            let turns = i as f64 / 16.0 + 0.01;
+            let expected = (turns * std::f64::consts::TAU).sin();
+            assert!((sin_turns(turns) - expected).abs() < 1e-9, "{}", turns);
+        }
+    }
+
+    #[test]
+    fn test_impulse_and_sine() {
+        assert_eq!(impulse(3), vec![1.0, 0.0, 0.0]);
+
+        // 1024 Hz is exactly 32 samples per cycle
+        let tone = sine(64, 1024.0, 0.5);
+        assert_eq!(tone[0], 0.0);
+        assert!((tone[8] - 0.5).abs() < 1e-6);
+        assert!((tone[24] + 0.5).abs() < 1e-6);
+        assert!((rms(&tone) - 0.5 / 2f32.sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_sweep_rises() {
+        let samples = sweep(32768, 20.0, 8000.0, 1.0);
+        let crossings = |s: &[f32]| s.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        assert!(crossings(&samples[..4096]) < crossings(&samples[28672..]));
+    }
+
+    #[test]
+    fn test_noise_is_seeded() {
+        assert_eq!(white_noise(256, 7), white_noise(256, 7));
+        assert_ne!(white_noise(256, 7), white_noise(256, 8));
+        assert_eq!(pink_noise(256, 7), pink_noise(256, 7));
+
+        for samples in [white_noise(8192, 1), pink_noise(8192, 1)] {
+            assert!(samples.iter().all(|s| (-1.0..1.0).contains(s)));
+            assert!(rms(&samples) > 0.05);
+        }
+    }
+
+    #[test]
+    fn test_pink_noise_favors_lows() {
+        // Successive pink samples are correlated, white samples are not
+        let correlation = |s: &[f32]| {
+            s.windows(2).map(|w| w[0] * w[1]).sum::<f32>() / s.iter().map(|x| x * x).sum::<f32>()
+        };
+        assert!(correlation(&pink_noise(8192, 3)) > 0.5);
+        assert!(correlation(&white_noise(8192, 3)).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_drum_loop() {
+        let samples = drum_loop(32768, 120.0, 1);
+        assert_eq!(samples, drum_loop(32768, 120.0, 1));
+        assert!(samples.iter().all(|s| (-1.0..=1.0).contains(s)));
+
+        // The kick lands at the start of each bar
+        let beat = 16384;
+        assert!(rms(&samples[..2048]) > rms(&samples[beat - 2048..beat]));
+    }
+}
@@ -24,8 +24,8 @@ fn test_complete_assembler_workflow() {
 
     // Check that instructions are encoded
     let instructions = binary.instructions();
-    assert_eq!(instructions[0] >> 27, 0b00000); // RDAX opcode
-    assert_eq!(instructions[1] >> 27, 0b00110); // WRAX opcode
+    assert_eq!(instructions[0] & 0x1F, 0b00100); // RDAX opcode
+    assert_eq!(instructions[1] & 0x1F, 0b00110); // WRAX opcode
 
     // Test binary output formats
     let bytes = binary.to_bytes();
@@ -1,7 +1,5 @@
 // Property tests for FV-1 assembler/disassembler
 
-#![allow(clippy::identity_op)] // Allow 0 << 27 for readability in opcode definitions
-
 use fv1_asm::{Assembler, Binary, Disassembler, Parser};
 use proptest::prelude::*;
 
@@ -44,59 +42,60 @@ proptest! {
 // Generate a valid FV-1 instruction word
 fn valid_instruction_word() -> impl Strategy<Value = u32> {
     prop_oneof![
-        // RDAX: opcode 0b00000, reg (6 bits), coeff (15 bits)
-        (valid_register(), valid_s114()).prop_map(|(reg, coeff)| {
-            (0b00000_u32 << 27) | ((reg & 0x3F) << 21) | ((coeff & 0x7FFF) << 6)
-        }),
-        // WRAX: opcode 0b00110, reg (6 bits), coeff (15 bits)
-        (valid_register(), valid_s114()).prop_map(|(reg, coeff)| {
-            (0b00110_u32 << 27) | ((reg & 0x3F) << 21) | ((coeff & 0x7FFF) << 6)
-        }),
-        // SOF: opcode 0b01101, coeff (16 bits), offset (11 bits)
-        (valid_s114(), valid_s10()).prop_map(|(coeff, offset)| {
-            (0b01101_u32 << 27) | ((coeff & 0xFFFF) << 11) | (offset & 0x7FF)
-        }),
-        // MULX: opcode 0b01010, reg (6 bits)
-        valid_register().prop_map(|reg| { (0b01010_u32 << 27) | ((reg & 0x3F) << 21) }),
-        // CLR: opcode 0b01110
-        Just(0b01110_u32 << 27),
+        // RDAX: coeff (16 bits), reg (6 bits), opcode 0b00100
+        (valid_register(), valid_s114())
+            .prop_map(|(reg, coeff)| { (coeff << 16) | ((reg & 0x3F) << 5) | 0b00100 }),
+        // WRAX: coeff (16 bits), reg (6 bits), opcode 0b00110
+        (valid_register(), valid_s114())
+            .prop_map(|(reg, coeff)| { (coeff << 16) | ((reg & 0x3F) << 5) | 0b00110 }),
+        // SOF: coeff (16 bits), offset (11 bits), opcode 0b01101
+        (valid_s114(), valid_s10())
+            .prop_map(|(coeff, offset)| { (coeff << 16) | (offset << 5) | 0b01101 }),
+        // MULX: reg (6 bits), opcode 0b01010
+        valid_register().prop_map(|reg| { ((reg & 0x3F) << 5) | 0b01010 }),
+        // CLR: AND with zero mask
+        Just(0b01110_u32),
         // NOP
         Just(0x00000000),
-        // AND: opcode 0b01111, mask (24 bits)
-        (0u32..0x1000000).prop_map(|mask| { (0b01111_u32 << 27) | (mask & 0xFFFFFF) }),
-        // OR: opcode 0b10000, mask (24 bits)
-        (0u32..0x1000000).prop_map(|mask| { (0b10000_u32 << 27) | (mask & 0xFFFFFF) }),
-        // XOR: opcode 0b10001, mask (24 bits)
-        (0u32..0x1000000).prop_map(|mask| { (0b10001_u32 << 27) | (mask & 0xFFFFFF) }),
+        // AND: mask (24 bits), opcode 0b01110
+        (0u32..0x1000000).prop_map(|mask| { (mask << 8) | 0b01110 }),
+        // OR: mask (24 bits), opcode 0b01111
+        (0u32..0x1000000).prop_map(|mask| { (mask << 8) | 0b01111 }),
+        // XOR: mask (24 bits), opcode 0b10000
+        (0u32..0x1000000).prop_map(|mask| { (mask << 8) | 0b10000 }),
     ]
 }
 
-// Generate a valid register code
+// Generate a valid register address
 fn valid_register() -> impl Strategy<Value = u32> {
     prop_oneof![
+        // SIN0_RATE through RMP1_RANGE
+        (0x00u32..0x08),
+        // POT0-POT2
+        (0x10u32..0x13),
         // ADCL, ADCR, DACL, DACR, ADDR_PTR, LR
-        Just(0u32), // ADCL
-        Just(1u32), // ADCR
-        Just(2u32), // DACL
-        Just(3u32), // DACR
-        Just(4u32), // ADDR_PTR
-        Just(5u32), // LR
-        // REG0-REG31 (encoded as 16-47)
-        (16u32..48),
+        Just(0x14u32), // ADCL
+        Just(0x15u32), // ADCR
+        Just(0x16u32), // DACL
+        Just(0x17u32), // DACR
+        Just(0x18u32), // ADDR_PTR
+        Just(0x19u32), // LR
+        // REG0-REG31 (addresses 0x20-0x3F)
+        (0x20u32..0x40),
     ]
 }
 
-// Generate a valid S1.14 coefficient (15-bit signed value)
+// Generate a valid S1.14 coefficient (16-bit signed value)
 fn valid_s114() -> impl Strategy<Value = u32> {
-    // S1.14 format: 15-bit signed, range [-16384, 16383]
+    // S1.14 format: 16-bit signed, range [-32768, 32767]
     // We want values that can roundtrip, so we'll generate actual S1.14 encoded values
-    (-16384i32..16384).prop_map(|val| (val & 0x7FFF) as u32)
+    (-32768i32..32768).prop_map(|val| (val & 0xFFFF) as u32)
 }
 
 // Generate a valid S.10 coefficient (11-bit signed value)
 fn valid_s10() -> impl Strategy<Value = u32> {
-    // S.10 format: 11-bit signed, range [-512, 511]
-    (-512i32..512).prop_map(|val| (val & 0x7FF) as u32)
+    // S.10 format: 11-bit signed, range [-1024, 1023]
+    (-1024i32..1024).prop_map(|val| (val & 0x7FF) as u32)
 }
 
 #[cfg(test)]
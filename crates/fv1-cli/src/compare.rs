@@ -0,0 +1,171 @@
+//! Patch Comparison
+//!
+//! Compares two revisions of a program: a line diff of their canonical
+//! source, so formatting and comments don't show up as changes, followed by
+//! the change in the resources each revision uses.
+
+use fv1_asm::{codegen::format_program, register_roles, Directive, Program, Register};
+
+/// One line of a diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    /// Line present in both revisions
+    Same(&'a str),
+    /// Line only in the old revision
+    Removed(&'a str),
+    /// Line only in the new revision
+    Added(&'a str),
+}
+
+/// Diff two sequences of lines, keeping the longest common subsequence
+pub fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // lcs[i][j] is the length of the common subsequence of old[i..], new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Resources a program uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resources {
+    /// Number of instructions
+    pub instructions: usize,
+    /// Number of REG0-REG31 used
+    pub registers: usize,
+    /// Words of delay memory allocated with MEM
+    pub delay_memory: usize,
+}
+
+impl Resources {
+    /// Measure the resources used by a program
+    pub fn of(program: &Program) -> Self {
+        let registers = register_roles(program)
+            .keys()
+            .filter(|reg| matches!(reg, Register::REG(_)))
+            .count();
+        // SpinASM reserves one extra word per region
+        let delay_memory = program
+            .directives
+            .iter()
+            .map(|directive| match directive {
+                Directive::MemoryAllocation { size, .. } => *size as usize + 1,
+                _ => 0,
+            })
+            .sum();
+
+        Self {
+            instructions: program.instructions().len(),
+            registers,
+            delay_memory,
+        }
+    }
+}
+
+/// Format a before/after count
+fn format_delta(old: usize, new: usize) -> String {
+    if old == new {
+        format!("{} (unchanged)", new)
+    } else {
+        format!("{} -> {} ({:+})", old, new, new as isize - old as isize)
+    }
+}
+
+/// Report the differences between two revisions of a program
+pub fn compare_report(old_name: &str, old: &Program, new_name: &str, new: &Program) -> String {
+    let old_source = format_program(old);
+    let new_source = format_program(new);
+    let old_lines: Vec<_> = old_source.lines().collect();
+    let new_lines: Vec<_> = new_source.lines().collect();
+
+    let mut report = String::new();
+    report.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+    for line in diff_lines(&old_lines, &new_lines) {
+        match line {
+            DiffLine::Same(line) => report.push_str(&format!("  {}\n", line)),
+            DiffLine::Removed(line) => report.push_str(&format!("- {}\n", line)),
+            DiffLine::Added(line) => report.push_str(&format!("+ {}\n", line)),
+        }
+    }
+
+    let old = Resources::of(old);
+    let new = Resources::of(new);
+    report.push('\n');
+    report.push_str(&format!(
+        "instructions: {}\n",
+        format_delta(old.instructions, new.instructions)
+    ));
+    report.push_str(&format!(
+        "registers: {}\n",
+        format_delta(old.registers, new.registers)
+    ));
+    report.push_str(&format!(
+        "delay memory: {} words\n",
+        format_delta(old.delay_memory, new.delay_memory)
+    ));
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fv1_asm::Parser;
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines(&["a", "b", "c"], &["a", "x", "c", "d"]);
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("a"),
+                DiffLine::Removed("b"),
+                DiffLine::Added("x"),
+                DiffLine::Same("c"),
+                DiffLine::Added("d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_report() {
+        let old = Parser::new("rdax adcl, 1.0\nwrax dacl, 0.0")
+            .parse()
+            .unwrap();
+        let new = Parser::new(
+            "mem dly 100\n; now with a delay\nRDAX ADCL, 1.0\nwra dly, 0.0\nrda dly#, 1.0\nwrax reg0, 1.0\nwrax dacl, 0.0",
+        )
+        .parse()
+        .unwrap();
+
+        let report = compare_report("old.asm", &old, "new.asm", &new);
+        assert!(report.starts_with("--- old.asm\n+++ new.asm\n+ MEM dly 100\n"));
+        assert!(report.contains("  RDAX ADCL, 1\n+ WRA dly, 0\n"));
+        assert!(report.contains("instructions: 2 -> 5 (+3)\n"));
+        assert!(report.contains("registers: 0 -> 1 (+1)\n"));
+        assert!(report.contains("delay memory: 0 -> 101 (+101) words\n"));
+    }
+}
@@ -0,0 +1,274 @@
+//! EEPROM Programming
+//!
+//! Writes program images to the 24LC32A EEPROM an FV-1 boots from, through
+//! a serial bridge such as an Arduino wired to the EEPROM's I2C pins. The
+//! bridge speaks a line-based ASCII protocol, with addresses and data in
+//! hexadecimal:
+//!
+//! - `I` identifies the bridge, which replies with a line starting `FV1`
+//! - `W AAAA DD..DD` writes up to one 32-byte page at address `AAAA` and
+//!   replies `OK` once the write cycle is done
+//! - `R AAAA NN` reads `NN` bytes from address `AAAA` and replies with them
+//!
+//! Any reply starting `ERR` reports a failure.
+
+use miette::{miette, IntoDiagnostic, Result, WrapErr};
+use std::io::{ErrorKind, Read, Write};
+
+/// Page size of the 24LC32A. A write must not cross a page boundary.
+pub const PAGE_SIZE: usize = 32;
+
+/// Times to send the identify command before giving up, since many
+/// bridges reset when the port opens
+const HANDSHAKE_ATTEMPTS: usize = 5;
+
+/// Longest reply accepted from the bridge
+const MAX_LINE: usize = 256;
+
+/// A serial EEPROM programmer
+pub struct Programmer<P> {
+    port: P,
+}
+
+impl<P: Read + Write> Programmer<P> {
+    /// Talk to a bridge over `port`
+    pub fn new(port: P) -> Self {
+        Self { port }
+    }
+
+    /// Wait for the bridge to answer, returning its identification
+    pub fn handshake(&mut self) -> Result<String> {
+        for _ in 0..HANDSHAKE_ATTEMPTS {
+            self.send("I")?;
+            match self.read_line() {
+                Ok(line) if line.starts_with("FV1") => return Ok(line),
+                Ok(line) => return Err(miette!("Unexpected reply from bridge: {}", line)),
+                Err(error) if error.kind() == ErrorKind::TimedOut => continue,
+                Err(error) => return Err(error).into_diagnostic(),
+            }
+        }
+        Err(miette!(
+            help = "check the port name and that the bridge sketch is running",
+            "No reply from the programmer"
+        ))
+    }
+
+    /// Write `data` starting at `address`, one page at a time
+    pub fn write(&mut self, address: usize, data: &[u8]) -> Result<()> {
+        for (offset, page) in pages(address, data.len()) {
+            let start = offset - address;
+            let bytes = &data[start..start + page];
+            self.send(&format!("W {:04X} {}", offset, to_hex(bytes)))?;
+            let reply = self.reply()?;
+            if reply != "OK" {
+                return Err(miette!(
+                    "Unexpected reply writing 0x{:04X}: {}",
+                    offset,
+                    reply
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read `len` bytes starting at `address`
+    pub fn read(&mut self, address: usize, len: usize) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(len);
+        for (offset, page) in pages(address, len) {
+            self.send(&format!("R {:04X} {:02X}", offset, page))?;
+            let reply = self.reply()?;
+            let bytes = from_hex(&reply)
+                .filter(|bytes| bytes.len() == page)
+                .ok_or_else(|| miette!("Malformed reply reading 0x{:04X}: {}", offset, reply))?;
+            data.extend(bytes);
+        }
+        Ok(data)
+    }
+
+    /// Read back `data` from `address` and check that it matches
+    pub fn verify(&mut self, address: usize, data: &[u8]) -> Result<()> {
+        let read = self.read(address, data.len())?;
+        match read.iter().zip(data).position(|(read, data)| read != data) {
+            Some(index) => Err(miette!(
+                "Verification failed at 0x{:04X}: wrote {:02X}, read {:02X}",
+                address + index,
+                data[index],
+                read[index]
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<()> {
+        self.port
+            .write_all(format!("{}\n", command).as_bytes())
+            .and_then(|()| self.port.flush())
+            .into_diagnostic()
+            .wrap_err("Failed to write to the programmer")
+    }
+
+    /// Read a reply, turning `ERR` replies into errors
+    fn reply(&mut self) -> Result<String> {
+        let line = self
+            .read_line()
+            .into_diagnostic()
+            .wrap_err("Failed to read from the programmer")?;
+        match line.strip_prefix("ERR") {
+            Some(message) => Err(miette!("Programmer error: {}", message.trim())),
+            None => Ok(line),
+        }
+    }
+
+    fn read_line(&mut self) -> std::io::Result<String> {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        loop {
+            match self.port.read(&mut byte)? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                _ if byte[0] == b'\n' => break,
+                _ if line.len() == MAX_LINE => return Err(ErrorKind::InvalidData.into()),
+                _ => line.push(byte[0]),
+            }
+        }
+        Ok(String::from_utf8_lossy(&line).trim().to_string())
+    }
+}
+
+/// Split `len` bytes from `address` into runs that stay within a page,
+/// as (address, length) pairs
+fn pages(address: usize, len: usize) -> impl Iterator<Item = (usize, usize)> {
+    let end = address + len;
+    let mut offset = address;
+    std::iter::from_fn(move || {
+        (offset < end).then(|| {
+            let page = (PAGE_SIZE - offset % PAGE_SIZE).min(end - offset);
+            let run = (offset, page);
+            offset += page;
+            run
+        })
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A bridge with an EEPROM behind it, answering commands as they arrive
+    struct Bridge {
+        eeprom: Vec<u8>,
+        command: Vec<u8>,
+        replies: Vec<u8>,
+    }
+
+    impl Bridge {
+        fn new() -> Self {
+            Self {
+                eeprom: vec![0xFF; 4096],
+                command: Vec::new(),
+                replies: Vec::new(),
+            }
+        }
+
+        fn execute(&mut self, command: &str) -> String {
+            let parts: Vec<&str> = command.split(' ').collect();
+            match parts[..] {
+                ["I"] => "FV1 24LC32A".to_string(),
+                ["W", address, data] => {
+                    let address = usize::from_str_radix(address, 16).unwrap();
+                    let data = from_hex(data).unwrap();
+                    assert!(address / PAGE_SIZE == (address + data.len() - 1) / PAGE_SIZE);
+                    self.eeprom[address..address + data.len()].copy_from_slice(&data);
+                    "OK".to_string()
+                }
+                ["R", address, len] => {
+                    let address = usize::from_str_radix(address, 16).unwrap();
+                    let len = usize::from_str_radix(len, 16).unwrap();
+                    to_hex(&self.eeprom[address..address + len])
+                }
+                _ => "ERR unknown command".to_string(),
+            }
+        }
+    }
+
+    impl Write for Bridge {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            for &byte in buf {
+                if byte == b'\n' {
+                    let command = String::from_utf8(std::mem::take(&mut self.command)).unwrap();
+                    let reply = self.execute(&command);
+                    self.replies.extend(format!("{}\r\n", reply).bytes());
+                } else {
+                    self.command.push(byte);
+                }
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for Bridge {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.replies.is_empty() {
+                return Err(ErrorKind::TimedOut.into());
+            }
+            let len = buf.len().min(self.replies.len());
+            buf[..len].copy_from_slice(&self.replies[..len]);
+            self.replies.drain(..len);
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_pages() {
+        assert_eq!(pages(0, 64).collect::<Vec<_>>(), vec![(0, 32), (32, 32)]);
+        assert_eq!(
+            pages(30, 40).collect::<Vec<_>>(),
+            vec![(30, 2), (32, 32), (64, 6)]
+        );
+        assert_eq!(pages(512, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_write_and_verify() {
+        let mut programmer = Programmer::new(Bridge::new());
+        assert_eq!(programmer.handshake().unwrap(), "FV1 24LC32A");
+
+        let data: Vec<u8> = (0..=255).cycle().take(512).collect();
+        programmer.write(1024, &data).unwrap();
+        programmer.verify(1024, &data).unwrap();
+        assert_eq!(&programmer.port.eeprom[1024..1536], &data[..]);
+        assert_eq!(programmer.port.eeprom[1023], 0xFF);
+
+        programmer.port.eeprom[1030] = 0;
+        let error = programmer.verify(1024, &data).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Verification failed at 0x0406: wrote 06, read 00"
+        );
+    }
+
+    #[test]
+    fn test_error_reply() {
+        let mut programmer = Programmer::new(Bridge::new());
+        programmer.send("X").unwrap();
+        let error = programmer.reply().unwrap_err();
+        assert_eq!(error.to_string(), "Programmer error: unknown command");
+    }
+}
@@ -0,0 +1,314 @@
+//! Output Format Registry
+//!
+//! Exporters for assembled programs, looked up by name. The built-in
+//! formats are registered by [`FormatRegistry::builtin`]; additional formats
+//! can be added with [`FormatRegistry::register`].
+
+use fv1_asm::Binary;
+
+/// Settings shared by all exporters
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Symbol name for formats that embed one (e.g. the C array name)
+    pub name: String,
+    /// Labels of POT0-POT2 for formats that describe the controls
+    pub pot_labels: Vec<String>,
+}
+
+/// Converts an assembled program into an output file
+pub trait Exporter {
+    /// Name used to select the format (`--format <name>`)
+    fn name(&self) -> &str;
+
+    /// File extension for output files, without the leading dot
+    fn extension(&self) -> &str;
+
+    /// One-line description of the format
+    fn description(&self) -> &str;
+
+    /// Produce the file contents for `binary`
+    fn export(&self, binary: &Binary, options: &ExportOptions) -> Vec<u8>;
+
+    /// Whether the contents are binary rather than text, so unfit for a
+    /// terminal
+    fn is_binary(&self) -> bool {
+        false
+    }
+}
+
+/// Raw binary format (.bin)
+struct Bin;
+
+impl Exporter for Bin {
+    fn name(&self) -> &str {
+        "bin"
+    }
+
+    fn extension(&self) -> &str {
+        "bin"
+    }
+
+    fn description(&self) -> &str {
+        "Raw binary"
+    }
+
+    fn export(&self, binary: &Binary, _options: &ExportOptions) -> Vec<u8> {
+        binary.to_bytes()
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+}
+
+/// Intel HEX format (.hex)
+struct Hex;
+
+impl Exporter for Hex {
+    fn name(&self) -> &str {
+        "hex"
+    }
+
+    fn extension(&self) -> &str {
+        "hex"
+    }
+
+    fn description(&self) -> &str {
+        "Intel HEX"
+    }
+
+    fn export(&self, binary: &Binary, _options: &ExportOptions) -> Vec<u8> {
+        binary.to_hex().into_bytes()
+    }
+}
+
+/// Motorola S-record format (.s19)
+struct Srec;
+
+impl Exporter for Srec {
+    fn name(&self) -> &str {
+        "srec"
+    }
+
+    fn extension(&self) -> &str {
+        "s19"
+    }
+
+    fn description(&self) -> &str {
+        "Motorola S-records"
+    }
+
+    fn export(&self, binary: &Binary, _options: &ExportOptions) -> Vec<u8> {
+        binary.to_srec().into_bytes()
+    }
+}
+
+/// C array format (.c)
+struct C;
+
+impl Exporter for C {
+    fn name(&self) -> &str {
+        "c"
+    }
+
+    fn extension(&self) -> &str {
+        "c"
+    }
+
+    fn description(&self) -> &str {
+        "C array"
+    }
+
+    fn export(&self, binary: &Binary, options: &ExportOptions) -> Vec<u8> {
+        binary.to_c_array(&options.name).into_bytes()
+    }
+}
+
+/// C header format (.h)
+struct CHeader;
+
+impl Exporter for CHeader {
+    fn name(&self) -> &str {
+        "h"
+    }
+
+    fn extension(&self) -> &str {
+        "h"
+    }
+
+    fn description(&self) -> &str {
+        "C header with program metadata"
+    }
+
+    fn export(&self, binary: &Binary, options: &ExportOptions) -> Vec<u8> {
+        let labels: Vec<&str> = options.pot_labels.iter().map(String::as_str).collect();
+        binary.to_c_header(&options.name, &labels).into_bytes()
+    }
+}
+
+/// Rust constant format (.rs)
+struct Rust;
+
+impl Exporter for Rust {
+    fn name(&self) -> &str {
+        "rust"
+    }
+
+    fn extension(&self) -> &str {
+        "rs"
+    }
+
+    fn description(&self) -> &str {
+        "Rust array"
+    }
+
+    fn export(&self, binary: &Binary, options: &ExportOptions) -> Vec<u8> {
+        binary.to_rust_array(&options.name).into_bytes()
+    }
+}
+
+/// Arduino sketch format (.ino)
+struct Arduino;
+
+impl Exporter for Arduino {
+    fn name(&self) -> &str {
+        "arduino"
+    }
+
+    fn extension(&self) -> &str {
+        "ino"
+    }
+
+    fn description(&self) -> &str {
+        "Arduino sketch writing a 24LC32A EEPROM"
+    }
+
+    fn export(&self, binary: &Binary, options: &ExportOptions) -> Vec<u8> {
+        binary.to_arduino_sketch(&options.name).into_bytes()
+    }
+}
+
+/// Output formats keyed by name
+pub struct FormatRegistry {
+    formats: Vec<Box<dyn Exporter>>,
+}
+
+impl FormatRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self {
+            formats: Vec::new(),
+        }
+    }
+
+    /// Create a registry with the built-in formats
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(Bin);
+        registry.register(Hex);
+        registry.register(Srec);
+        registry.register(C);
+        registry.register(CHeader);
+        registry.register(Rust);
+        registry.register(Arduino);
+        registry
+    }
+
+    /// Add a format, replacing any existing format with the same name
+    pub fn register(&mut self, format: impl Exporter + 'static) -> &mut Self {
+        self.formats.retain(|f| f.name() != format.name());
+        self.formats.push(Box::new(format));
+        self
+    }
+
+    /// Look up a format by name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&dyn Exporter> {
+        self.formats
+            .iter()
+            .find(|f| f.name().eq_ignore_ascii_case(name))
+            .map(|f| f.as_ref())
+    }
+
+    /// Names of all registered formats, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.formats.iter().map(|f| f.name()).collect()
+    }
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Words;
+
+    impl Exporter for Words {
+        fn name(&self) -> &str {
+            "words"
+        }
+
+        fn extension(&self) -> &str {
+            "txt"
+        }
+
+        fn description(&self) -> &str {
+            "One hex word per line"
+        }
+
+        fn export(&self, binary: &Binary, _options: &ExportOptions) -> Vec<u8> {
+            binary
+                .instructions()
+                .iter()
+                .map(|word| format!("{:08X}\n", word))
+                .collect::<String>()
+                .into_bytes()
+        }
+    }
+
+    fn binary() -> Binary {
+        let mut binary = Binary::new();
+        binary.push(0x11);
+        binary
+    }
+
+    fn options() -> ExportOptions {
+        ExportOptions {
+            name: "prog".to_string(),
+            pot_labels: vec!["Mix".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_builtin_formats() {
+        let registry = FormatRegistry::builtin();
+        assert_eq!(
+            registry.names(),
+            vec!["bin", "hex", "srec", "c", "h", "rust", "arduino"]
+        );
+
+        let c = registry.get("C").unwrap();
+        assert_eq!(c.extension(), "c");
+        let source = String::from_utf8(c.export(&binary(), &options())).unwrap();
+        assert!(source.contains("prog"));
+
+        let header = registry.get("h").unwrap();
+        let source = String::from_utf8(header.export(&binary(), &options())).unwrap();
+        assert!(source.contains("#define PROG_POT0_LABEL \"Mix\"\n"));
+    }
+
+    #[test]
+    fn test_register_format() {
+        let mut registry = FormatRegistry::builtin();
+        registry.register(Words);
+
+        let words = registry.get("words").unwrap();
+        let text = String::from_utf8(words.export(&binary(), &options())).unwrap();
+        assert_eq!(text, "00000011\n");
+        assert!(registry.get("missing").is_none());
+    }
+}
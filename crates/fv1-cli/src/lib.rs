@@ -0,0 +1,1303 @@
+//! FV-1 Command Line Interface
+//!
+//! The `fv1` command implementations, usable as a library so that other
+//! front ends can drive the same code paths as the binary.
+
+pub mod compare;
+pub mod flash;
+pub mod formats;
+pub mod repl;
+pub mod templates;
+
+use clap::{Parser, Subcommand};
+pub use formats::{ExportOptions, Exporter, FormatRegistry};
+use fv1_asm::migrate::{migrate_asm, migrate_dsl};
+use fv1_asm::{
+    codegen::SymbolKind,
+    codegen::{
+        diff_binaries, diff_words_exact, format_dsl, format_explanation, format_hexdump,
+        format_listing,
+    },
+    Assembler, Bank, Binary, CodegenError, Directive, Disassembler, EepromImage,
+    Parser as FV1Parser, Program, SourceError, SymbolTable, Value, DELAY_RAM_SIZE,
+    MAX_INSTRUCTIONS, NUM_REGISTERS, PROGRAM_SLOTS,
+};
+use miette::{miette, Context, IntoDiagnostic, Result};
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+pub use templates::Template;
+
+/// Command line arguments of the `fv1` binary
+#[derive(Parser, Debug)]
+#[command(name = "fv1")]
+#[command(about = "FV-1 DSP Assembler", long_about = None)]
+#[command(author, version)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Assemble .asm files to binary
+    Assemble {
+        /// Input assembly files or glob patterns, or `-` for stdin
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Output file, or `-` for stdout (defaults to input filename with
+        /// new extension, or stdout when reading stdin)
+        #[arg(short, long, value_name = "FILE", conflicts_with = "out_dir")]
+        output: Option<PathBuf>,
+
+        /// Directory to write outputs to, named after their inputs
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+
+        /// Output format (bin, hex, srec, c, h, rust, arduino)
+        #[arg(short, long, default_value = "bin")]
+        format: String,
+
+        /// Name for the program array in c, h, rust and arduino output
+        #[arg(short = 'n', long, default_value = "fv1_program")]
+        name: String,
+
+        /// Label of POT0, POT1 and POT2 in turn for h output (repeatable)
+        #[arg(long = "pot-label", value_name = "LABEL", num_args = 1, action = clap::ArgAction::Append)]
+        pot_labels: Vec<String>,
+
+        /// Define an equate, overriding any EQU of the same name in the
+        /// source (repeatable). A bare NAME is defined as 1.
+        #[arg(short = 'D', long = "define", value_name = "NAME=VALUE", value_parser = parse_define)]
+        defines: Vec<(String, Value)>,
+
+        /// Apply peephole optimizations and report what changed
+        #[arg(short = 'O', long)]
+        optimize: bool,
+
+        /// Also write a listing of addresses, instruction words and source
+        /// lines
+        #[arg(long, value_name = "FILE")]
+        listing: Option<PathBuf>,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Disassemble a binary or Intel HEX file, holding one program or a
+    /// whole 4KB EEPROM bank
+    Disassemble {
+        /// Input binary file, or `-` for stdin
+        input: PathBuf,
+
+        /// Output assembly file, or `-` for stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Keep trailing zero padding words
+        #[arg(long)]
+        keep_nops: bool,
+
+        /// Name delay memory and registers with inferred MEM and EQU
+        /// directives
+        #[arg(long)]
+        symbols: bool,
+
+        /// Comment each instruction with the role of its register
+        #[arg(long)]
+        annotate: bool,
+
+        /// Print registers by numeric address instead of by name, as
+        /// SpinASM accepts them
+        #[arg(long)]
+        numeric_registers: bool,
+    },
+
+    /// Assemble up to eight programs into a 4KB EEPROM image
+    Bank {
+        /// Assembly files for slots 0 onward
+        inputs: Vec<PathBuf>,
+
+        /// Place a program in a specific slot (repeatable)
+        #[arg(long = "slot", value_name = "SLOT=FILE", value_parser = parse_slot)]
+        slots: Vec<(usize, PathBuf)>,
+
+        /// Output image file
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "bin")]
+        format: BankFormat,
+    },
+
+    /// Write a program or EEPROM image to a 24LC32A through a serial bridge
+    Flash {
+        /// Program or 4KB image (.bin, .hex or .asm)
+        input: PathBuf,
+
+        /// Serial port of the bridge
+        #[arg(short, long)]
+        port: String,
+
+        /// Write a single program to this slot instead of a whole image
+        #[arg(short, long)]
+        slot: Option<usize>,
+
+        /// Read back what was written and compare it
+        #[arg(long)]
+        verify: bool,
+
+        /// Baud rate of the serial port
+        #[arg(long, default_value_t = 115200)]
+        baud: u32,
+    },
+
+    /// Assemble and validate an assembly file without generating output,
+    /// reporting warnings and resource usage
+    Check {
+        /// Input assembly file, or `-` for stdin
+        input: PathBuf,
+
+        /// Define an equate, overriding any EQU of the same name in the
+        /// source (repeatable). A bare NAME is defined as 1.
+        #[arg(short = 'D', long = "define", value_name = "NAME=VALUE", value_parser = parse_define)]
+        defines: Vec<(String, Value)>,
+    },
+
+    /// Rewrite programs written for older versions of the tools, such as
+    /// pots read as REG16-REG18
+    Migrate {
+        /// Assembly (.asm, .spn) or DSL (.rs) files to rewrite in place
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Report what would change without writing, failing if anything
+        /// would
+        #[arg(long)]
+        check: bool,
+
+        /// Rewrite REG16-REG18 that an assembly program reads but never
+        /// writes to POT0-POT2, instead of only listing them
+        #[arg(long)]
+        assume_pots: bool,
+    },
+
+    /// Compare two revisions of an assembly file
+    Compare {
+        /// Old revision
+        old: PathBuf,
+
+        /// New revision
+        new: PathBuf,
+    },
+
+    /// Compare the instructions of two programs, exiting with status 1 if
+    /// they differ
+    Diff {
+        /// Old program (.bin, .hex or .asm)
+        old: PathBuf,
+
+        /// New program (.bin, .hex or .asm)
+        new: PathBuf,
+
+        /// Line up decoded instructions, ignoring padding and changes that
+        /// don't affect what runs
+        #[arg(long)]
+        semantic: bool,
+    },
+
+    /// Describe what each instruction of a program does
+    Explain {
+        /// Program to explain (.bin, .hex or .asm)
+        input: PathBuf,
+    },
+
+    /// Print each word of a program or EEPROM image with its address and
+    /// disassembly
+    Hexdump {
+        /// Program or EEPROM image to dump (.bin, .hex or .asm), or `-` for
+        /// stdin
+        input: PathBuf,
+
+        /// Show trailing padding word by word instead of summarizing it
+        #[arg(short, long)]
+        all: bool,
+    },
+
+    /// Convert a program to Rust source using the fv1-dsl builder
+    ToDsl {
+        /// Program to convert (.asm, .bin or .hex), or `-` for stdin
+        input: PathBuf,
+
+        /// Output Rust file, or `-` for stdout (defaults to input filename
+        /// with .rs extension, or stdout when reading stdin)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Start a new patch from a commented template
+    New {
+        /// Name of the patch, used for the file or project directory
+        name: PathBuf,
+
+        /// Program to start from
+        #[arg(short, long, value_enum, default_value = "blank")]
+        template: Template,
+
+        /// Create a Cargo project using fv1-dsl instead of an .asm file
+        #[arg(long)]
+        rust: bool,
+    },
+
+    /// Enter instructions interactively and see their encodings
+    Repl,
+
+    /// Print a description of the instruction set
+    DumpIsa {
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "json")]
+        format: IsaFormat,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum BankFormat {
+    /// Raw 4096-byte image
+    Bin,
+    /// Intel HEX
+    Hex,
+    /// Motorola S-records
+    Srec,
+}
+
+/// Parse a `NAME=VALUE` define, taking the value as an `EQU` would
+fn parse_define(arg: &str) -> Result<(String, Value), String> {
+    let (name, value) = arg.split_once('=').unwrap_or((arg, "1"));
+    let source = format!("equ {}, {}", name.trim(), value.trim());
+    let program = FV1Parser::new(&source)
+        .parse()
+        .map_err(|_| format!("invalid define '{}'", arg))?;
+    match program.directives.into_iter().next() {
+        Some(Directive::Equate {
+            value: Value::Identifier(value),
+            ..
+        }) => Err(format!(
+            "define value '{}' must be a number or register",
+            value
+        )),
+        Some(Directive::Equate { name, value }) => Ok((name, value)),
+        _ => Err(format!("invalid define '{}'", arg)),
+    }
+}
+
+/// Parse a `SLOT=FILE` placement
+fn parse_slot(arg: &str) -> Result<(usize, PathBuf), String> {
+    let (slot, file) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected SLOT=FILE, got '{}'", arg))?;
+    let slot = slot
+        .parse()
+        .map_err(|_| format!("invalid slot '{}'", slot))?;
+    Ok((slot, PathBuf::from(file)))
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum IsaFormat {
+    /// JSON description of mnemonics, operands and bit layouts
+    Json,
+}
+
+/// Run a command with the built-in output formats
+pub fn run(args: Args) -> Result<ExitCode> {
+    run_with_formats(args, &FormatRegistry::builtin())
+}
+
+/// Run a command, looking up output formats in `formats`
+///
+/// Returns the status the process should exit with when the command
+/// worked but has a result to report, as `diff` does when the programs
+/// differ. The process is never exited here.
+pub fn run_with_formats(args: Args, formats: &FormatRegistry) -> Result<ExitCode> {
+    match args.command {
+        Commands::Assemble {
+            inputs,
+            output,
+            out_dir,
+            format,
+            name,
+            pot_labels,
+            defines,
+            optimize,
+            listing,
+            verbose,
+        } => {
+            let mut inputs = expand_globs(inputs)?;
+            let export = ExportOptions { name, pot_labels };
+            let options = AssembleOptions {
+                defines,
+                optimize,
+                listing,
+                verbose,
+            };
+            if inputs.len() > 1 {
+                if output.is_some() || options.listing.is_some() {
+                    return Err(miette!(
+                        help = "use --out-dir to choose where the outputs go",
+                        "--output and --listing take a single input"
+                    ));
+                }
+                assemble_files(formats, inputs, out_dir, &format, export, &options)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let input = inputs.remove(0);
+            let output = match out_dir {
+                Some(dir) => {
+                    fs::create_dir_all(&dir)
+                        .into_diagnostic()
+                        .wrap_err_with(|| format!("Failed to create {}", dir.display()))?;
+                    Some(output_in(&dir, &input, find_exporter(formats, &format)?))
+                }
+                None => output,
+            };
+            assemble_file(formats, input, output, &format, export, &options).map(|_| ())
+        }
+        Commands::Disassemble {
+            input,
+            output,
+            keep_nops,
+            symbols,
+            annotate,
+            numeric_registers,
+        } => {
+            let disassembler = Disassembler::new()
+                .with_strip_nops(!keep_nops)
+                .with_inferred_symbols(symbols)
+                .with_register_roles(annotate)
+                .with_symbolic_names(!numeric_registers);
+            disassemble_file(input, output, &disassembler)
+        }
+        Commands::Bank {
+            inputs,
+            slots,
+            output,
+            format,
+        } => bank_file(inputs, slots, output, format),
+        Commands::Flash {
+            input,
+            port,
+            slot,
+            verify,
+            baud,
+        } => flash_file(input, &port, slot, verify, baud),
+        Commands::Check { input, defines } => check_file(input, &defines),
+        Commands::Migrate {
+            inputs,
+            check,
+            assume_pots,
+        } => migrate_files(inputs, check, assume_pots),
+        Commands::Compare { old, new } => compare_files(old, new),
+        Commands::Diff { old, new, semantic } => {
+            if diff_files(old, new, semantic)? {
+                return Ok(ExitCode::FAILURE);
+            }
+            Ok(())
+        }
+        Commands::Explain { input } => {
+            print!("{}", format_explanation(&load_binary(&input)?));
+            Ok(())
+        }
+        Commands::Hexdump { input, all } => hexdump_file(input, all),
+        Commands::ToDsl { input, output } => to_dsl_file(input, output),
+        Commands::New {
+            name,
+            template,
+            rust,
+        } => new_patch(name, template, rust),
+        Commands::Repl => run_repl(formats),
+        Commands::DumpIsa { format } => {
+            match format {
+                IsaFormat::Json => print!("{}", fv1_asm::isa::to_json()),
+            }
+            Ok(())
+        }
+    }?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Settings of the assemble command besides its input and output
+#[derive(Debug, Clone, Default)]
+pub struct AssembleOptions {
+    /// Equates defined before parsing, overriding the source's
+    pub defines: Vec<(String, Value)>,
+    /// Apply peephole optimizations and report what changed
+    pub optimize: bool,
+    /// Write a listing of the assembled program to this file
+    pub listing: Option<PathBuf>,
+    /// Print progress
+    pub verbose: bool,
+}
+
+/// Look up an output format by name
+fn find_exporter<'a>(formats: &'a FormatRegistry, format: &str) -> Result<&'a dyn Exporter> {
+    formats.get(format).ok_or_else(|| {
+        miette!(
+            "Unknown output format '{}' (available: {})",
+            format,
+            formats.names().join(", ")
+        )
+    })
+}
+
+/// Path in `dir` for the output of `input` in a format
+fn output_in(dir: &Path, input: &Path, exporter: &dyn Exporter) -> PathBuf {
+    let name = input.file_name().unwrap_or(input.as_os_str());
+    dir.join(name).with_extension(exporter.extension())
+}
+
+/// Expand glob patterns among `inputs` in file name order, for shells that
+/// leave them alone. Paths that exist are kept as they are.
+fn expand_globs(inputs: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+    for input in inputs {
+        let pattern = input.to_string_lossy();
+        if !pattern.contains(['*', '?', '[']) || input.exists() {
+            expanded.push(input);
+            continue;
+        }
+
+        let matches: Vec<PathBuf> = glob::glob(&pattern)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Invalid pattern {}", pattern))?
+            .filter_map(|entry| entry.ok())
+            .collect();
+        if matches.is_empty() {
+            return Err(miette!("No files match {}", pattern));
+        }
+        expanded.extend(matches);
+    }
+    Ok(expanded)
+}
+
+/// Assemble several files, continuing past failures, and print a summary
+/// table. Outputs go in `out_dir` when given, otherwise next to their
+/// inputs.
+pub fn assemble_files(
+    formats: &FormatRegistry,
+    inputs: Vec<PathBuf>,
+    out_dir: Option<PathBuf>,
+    format: &str,
+    export: ExportOptions,
+    options: &AssembleOptions,
+) -> Result<()> {
+    let exporter = find_exporter(formats, format)?;
+    if let Some(dir) = &out_dir {
+        fs::create_dir_all(dir)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    // Instructions in each program, or None if it failed
+    let mut results = Vec::new();
+    for input in inputs {
+        let output = out_dir.as_ref().map(|dir| output_in(dir, &input, exporter));
+        let result = assemble_file(
+            formats,
+            input.clone(),
+            output,
+            format,
+            export.clone(),
+            options,
+        );
+        match result {
+            Ok(binary) => results.push((input, Some(binary.without_padding().len()))),
+            Err(error) => {
+                eprintln!("{:?}", error);
+                results.push((input, None));
+            }
+        }
+    }
+
+    let width = results
+        .iter()
+        .map(|(input, _)| input.display().to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("FILE".len());
+    println!();
+    println!("{:<width$}  RESULT  INSTRUCTIONS", "FILE");
+    for (input, instructions) in &results {
+        match instructions {
+            Some(count) => println!("{:<width$}  ok      {}", input.display(), count),
+            None => println!("{:<width$}  FAILED", input.display()),
+        }
+    }
+
+    let failed = results
+        .iter()
+        .filter(|(_, result)| result.is_none())
+        .count();
+    println!("{} assembled, {} failed", results.len() - failed, failed);
+
+    if failed > 0 {
+        return Err(miette!(
+            "{} of {} files failed to assemble",
+            failed,
+            results.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Assemble `input` and write it in the named output format, returning the
+/// binary
+pub fn assemble_file(
+    formats: &FormatRegistry,
+    input: PathBuf,
+    output: Option<PathBuf>,
+    format: &str,
+    export: ExportOptions,
+    options: &AssembleOptions,
+) -> Result<Binary> {
+    let AssembleOptions {
+        optimize, verbose, ..
+    } = *options;
+    let exporter = find_exporter(formats, format)?;
+
+    // Determine output path, writing to stdout when reading from stdin
+    let output_path = output.unwrap_or_else(|| {
+        if is_stdio(&input) {
+            PathBuf::from(STDIO)
+        } else {
+            let mut path = input.clone();
+            path.set_extension(exporter.extension());
+            path
+        }
+    });
+
+    // Keep status messages out of output written to stdout
+    let to_stdout = is_stdio(&output_path);
+    macro_rules! status {
+        ($($arg:tt)*) => {
+            if to_stdout {
+                eprintln!($($arg)*)
+            } else {
+                println!($($arg)*)
+            }
+        };
+    }
+
+    if verbose {
+        status!("FV-1 Assembler");
+        status!("==============");
+        status!("Input:  {}", input_name(&input));
+        status!("Format: {}", exporter.description());
+        status!();
+    }
+
+    // Read input file
+    let source = read_source(&input)?;
+
+    // Parse
+    if verbose {
+        status!("Parsing...");
+    }
+    let source_name = input_name(&input);
+    let mut program = parser_with(&source, &options.defines)
+        .parse_named(&source_name)
+        .wrap_err("Failed to parse assembly program")?;
+
+    if verbose {
+        status!("Program has {} instructions", program.instructions().len());
+    }
+
+    // Optimize
+    if optimize {
+        let (optimized, report) = fv1_asm::codegen::optimize(&program);
+        status!(
+            "Optimized {} instructions to {}",
+            report.before,
+            report.after
+        );
+        for change in &report.changes {
+            status!("  {}", change);
+        }
+        program = optimized;
+    }
+
+    // Assemble
+    if verbose {
+        status!("Assembling...");
+    }
+    let assembler = Assembler::new();
+    let binary = assembler
+        .assemble_named(&program, &source_name, &source)
+        .wrap_err("Failed to assemble program")?;
+
+    if verbose {
+        status!("Generated {} instruction binary", binary.len());
+        status!(
+            "CRC-32 {:08X}, fingerprint {}",
+            binary.crc32(),
+            binary.fingerprint()
+        );
+    }
+
+    // Generate output based on format
+    let contents = exporter.export(&binary, &export);
+    write_output(&output_path, &contents, exporter.is_binary())?;
+
+    if let Some(listing) = &options.listing {
+        fs::write(listing, format_listing(&program, &binary, &source))
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to write listing: {}", listing.display()))?;
+        if verbose {
+            status!("Listing written to: {}", listing.display());
+        }
+    }
+
+    if verbose {
+        if to_stdout {
+            status!("Output written to stdout");
+        } else {
+            status!("Output written to: {}", output_path.display());
+        }
+    } else if !to_stdout {
+        println!("✓ Successfully assembled to {}", output_path.display());
+    }
+
+    Ok(binary)
+}
+
+/// Disassemble a binary file to assembly source
+///
+/// Intel HEX input is recognized by its `:` record marks. A 4096-byte image
+/// is disassembled as a bank, one program per filled slot.
+pub fn disassemble_file(
+    input: PathBuf,
+    output: Option<PathBuf>,
+    disassembler: &Disassembler,
+) -> Result<()> {
+    // Read binary file
+    let bytes = read_image(&input)?;
+
+    let source = if bytes.len() == BANK_SIZE {
+        disassembler
+            .disassemble_bank_to_source(&bytes)
+            .wrap_err("Failed to disassemble bank")?
+    } else {
+        let binary = Binary::from_bytes(&bytes).wrap_err("Failed to parse binary file")?;
+        disassembler
+            .disassemble_to_source(&binary)
+            .wrap_err("Failed to disassemble binary")?
+    };
+
+    // Determine output path, writing to stdout when reading from stdin
+    let output_path = output.unwrap_or_else(|| {
+        if is_stdio(&input) {
+            PathBuf::from(STDIO)
+        } else {
+            let mut path = input.clone();
+            path.set_extension("asm");
+            path
+        }
+    });
+
+    // Write output
+    write_output(&output_path, source.as_bytes(), false)?;
+
+    if !is_stdio(&output_path) {
+        println!("✓ Successfully disassembled to {}", output_path.display());
+    }
+
+    Ok(())
+}
+
+/// Assemble programs into slots of an EEPROM image and write it. `inputs`
+/// fill slots 0 onward, then `slots` places programs in specific slots,
+/// warning when one replaces another.
+pub fn bank_file(
+    inputs: Vec<PathBuf>,
+    slots: Vec<(usize, PathBuf)>,
+    output: PathBuf,
+    format: BankFormat,
+) -> Result<()> {
+    if inputs.is_empty() && slots.is_empty() {
+        return Err(miette!("No programs given"));
+    }
+    if inputs.len() > PROGRAM_SLOTS {
+        return Err(miette!(
+            "{} programs given but a bank holds {}",
+            inputs.len(),
+            PROGRAM_SLOTS
+        ));
+    }
+
+    let placements = inputs.into_iter().enumerate().chain(slots);
+    let mut image = EepromImage::new();
+    for (slot, input) in placements {
+        let program = parse_file(&input)?;
+        let binary = Assembler::new()
+            .assemble(&program)
+            .wrap_err_with(|| format!("Failed to assemble {}", input.display()))?;
+        image = image
+            .with_slot(slot, binary)
+            .wrap_err_with(|| format!("Failed to place {} in slot {}", input.display(), slot))?;
+        println!("  slot {}: {}", slot, input.display());
+    }
+    for warning in image.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+
+    let contents = match format {
+        BankFormat::Bin => image.to_bytes(),
+        BankFormat::Hex => image.to_hex().into_bytes(),
+        BankFormat::Srec => image.bank().to_srec().into_bytes(),
+    };
+    fs::write(&output, contents)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to write output file: {}", output.display()))?;
+
+    println!("✓ Successfully built bank {}", output.display());
+    Ok(())
+}
+
+/// Assemble and analyze an assembly file without writing output, printing
+/// a summary and any warnings
+pub fn check_file(input: PathBuf, defines: &[(String, Value)]) -> Result<()> {
+    let source = read_source(&input)?;
+    let name = input_name(&input);
+
+    let program = parser_with(&source, defines)
+        .parse_named(&name)
+        .wrap_err("Failed to parse assembly program")?;
+
+    // Assemble without writing anything, reporting every error
+    let (_, warnings) = Assembler::new()
+        .assemble_named_with_warnings(&program, &name, &source)
+        .wrap_err("Failed to assemble program")?;
+    let findings = fv1_asm::analysis::validate(&program);
+
+    println!("✓ {} is valid", name);
+    println!("  {} instructions", program.instructions().len());
+
+    let symbols = SymbolTable::from_program(&program);
+    let count = |kind: fn(&SymbolKind) -> bool| symbols.iter().filter(|s| kind(&s.kind)).count();
+    println!(
+        "  {} equates, {} memory regions, {} labels",
+        count(|kind| matches!(kind, SymbolKind::Equate(_))),
+        count(|kind| matches!(kind, SymbolKind::Memory { .. })),
+        count(|kind| matches!(kind, SymbolKind::Label { .. }))
+    );
+
+    let resources = fv1_asm::resource_report(&program);
+    let memory: usize = resources.regions.iter().map(|region| region.len).sum();
+    println!(
+        "  {}/{} registers, {}/{} words of delay memory",
+        resources.registers.len(),
+        NUM_REGISTERS,
+        memory,
+        DELAY_RAM_SIZE
+    );
+
+    let paths = fv1_asm::path_report(&program);
+    if paths.path_count > 1 {
+        println!(
+            "  {} paths ({} to {} instructions)",
+            paths.path_count, paths.shortest, paths.longest
+        );
+    }
+    if !paths.unreachable.is_empty() {
+        println!("  unreachable instructions: {:?}", paths.unreachable);
+    }
+
+    if let Some(latency) = fv1_asm::latency_report(&program).overall() {
+        let max = match (latency.max, latency.max_ms()) {
+            (Some(samples), Some(ms)) => format!("{} samples ({:.2} ms)", samples, ms),
+            _ => "unbounded (feedback)".to_string(),
+        };
+        println!(
+            "  latency: {} samples ({:.2} ms) to {}",
+            latency.min,
+            latency.min_ms(),
+            max
+        );
+    }
+
+    // Warnings go to stderr, rendered with the source like errors
+    if !warnings.is_empty() || !findings.is_empty() {
+        println!("  {} warnings", warnings.len() + findings.len());
+    }
+    for warning in warnings {
+        eprintln!(
+            "{:?}",
+            miette::Report::new(SourceError::new(warning, &name, &*source))
+        );
+    }
+    for finding in findings {
+        eprintln!(
+            "{:?}",
+            miette::Report::new(SourceError::new(finding, &name, &*source))
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the instruction diff and resource changes between two revisions
+pub fn compare_files(old: PathBuf, new: PathBuf) -> Result<()> {
+    let old_program = parse_file(&old)?;
+    let new_program = parse_file(&new)?;
+
+    print!(
+        "{}",
+        compare::compare_report(
+            &old.display().to_string(),
+            &old_program,
+            &new.display().to_string(),
+            &new_program,
+        )
+    );
+
+    Ok(())
+}
+
+/// Print the instructions that differ between two programs, returning
+/// whether there were any
+///
+/// Words are compared at the same address, padding included, unless
+/// `semantic` is set, in which case the decoded instructions are lined up
+/// so an insertion shows up once.
+pub fn diff_files(old: PathBuf, new: PathBuf, semantic: bool) -> Result<bool> {
+    let old_binary = load_binary(&old)?;
+    let new_binary = load_binary(&new)?;
+
+    let lines: Vec<String> = if semantic {
+        diff_binaries(&old_binary, &new_binary)
+            .wrap_err("Failed to decode programs")?
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    } else {
+        diff_words_exact(&old_binary, &new_binary)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    };
+    if lines.is_empty() {
+        return Ok(false);
+    }
+
+    println!("--- {}", input_name(&old));
+    println!("+++ {}", input_name(&new));
+    for line in lines {
+        println!("{}", line);
+    }
+
+    Ok(true)
+}
+
+/// Convert an assembly source or binary to Rust source for a function
+/// that rebuilds it with the `fv1-dsl` program builder
+pub fn to_dsl_file(input: PathBuf, output: Option<PathBuf>) -> Result<()> {
+    let source = if is_source(&input) {
+        format_dsl(&parse_file(&input)?)
+    } else {
+        Disassembler::new()
+            .to_dsl_source(&load_binary(&input)?)
+            .wrap_err("Failed to disassemble binary")?
+    };
+
+    let output_path = output.unwrap_or_else(|| {
+        if is_stdio(&input) {
+            PathBuf::from(STDIO)
+        } else {
+            input.with_extension("rs")
+        }
+    });
+    write_output(&output_path, source.as_bytes(), false)?;
+
+    if !is_stdio(&output_path) {
+        println!("✓ Successfully converted to {}", output_path.display());
+    }
+
+    Ok(())
+}
+
+/// Rewrite old idioms in assembly or DSL sources in place, or with `check`
+/// only report them. Rewrites that may apply but aren't safe to make are
+/// listed for checking by hand.
+pub fn migrate_files(inputs: Vec<PathBuf>, check: bool, assume_pots: bool) -> Result<()> {
+    let mut outdated = 0;
+    for input in inputs {
+        let name = input_name(&input);
+        let source = read_source(&input)?;
+        let migration = if input.extension().is_some_and(|extension| extension == "rs") {
+            migrate_dsl(&source)
+        } else {
+            migrate_asm(&source, assume_pots)
+                .map_err(|error| SourceError::new(error, &name, &source))
+                .wrap_err_with(|| format!("Failed to parse {}", name))?
+        };
+
+        if migration.changes.is_empty() {
+            println!("✓ {} is up to date", name);
+        } else {
+            outdated += 1;
+            if check {
+                println!("{} needs {} changes", name, migration.changes.len());
+            } else {
+                fs::write(&input, &migration.source)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Failed to write {}", name))?;
+                println!("✓ Migrated {} ({} changes)", name, migration.changes.len());
+            }
+            for change in &migration.changes {
+                println!("  {}", change);
+            }
+        }
+
+        if !migration.unsure.is_empty() {
+            println!("  Left alone, may be pots (check by hand):");
+            for change in &migration.unsure {
+                println!("    {}", change);
+            }
+        }
+    }
+
+    if check && outdated > 0 {
+        return Err(miette!("{} files need migrating", outdated));
+    }
+    Ok(())
+}
+
+/// Create `name.asm` from a template, or with `rust` a Cargo project in
+/// the directory `name`. Existing files are never overwritten.
+pub fn new_patch(name: PathBuf, template: Template, rust: bool) -> Result<()> {
+    let patch = name
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .ok_or_else(|| miette!("Invalid patch name: {}", name.display()))?;
+
+    if !rust {
+        let path = name.with_extension("asm");
+        if path.exists() {
+            return Err(miette!("{} already exists", path.display()));
+        }
+        fs::write(&path, template.source(&patch))
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+        println!("✓ Created {}", path.display());
+        return Ok(());
+    }
+
+    if name.exists() {
+        return Err(miette!("{} already exists", name.display()));
+    }
+    for (file, contents) in template.rust_project(&patch)? {
+        let path = name.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, contents)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+    }
+    println!("✓ Created Cargo project {}", name.display());
+
+    Ok(())
+}
+
+/// Run an interactive session on stdin until `:quit` or end of input
+pub fn run_repl(formats: &FormatRegistry) -> Result<()> {
+    let mut session = repl::Session::new(formats);
+    println!("FV-1 REPL. Type instructions to encode them, :help for commands.");
+
+    let mut line = String::new();
+    loop {
+        print!("fv1> ");
+        io::stdout().flush().into_diagnostic()?;
+
+        line.clear();
+        if io::stdin().read_line(&mut line).into_diagnostic()? == 0 {
+            println!();
+            break;
+        }
+        match session.eval(&line) {
+            Some(reply) => print!("{}", reply),
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a program to one slot of the EEPROM, or a whole image, through
+/// a serial bridge
+pub fn flash_file(
+    input: PathBuf,
+    port: &str,
+    slot: Option<usize>,
+    verify: bool,
+    baud: u32,
+) -> Result<()> {
+    let image = if is_source(&input) {
+        load_binary(&input)?.to_bytes()
+    } else {
+        read_image(&input)?
+    };
+
+    let address = match (slot, image.len()) {
+        (Some(slot), PROGRAM_SIZE) if slot < PROGRAM_SLOTS => slot * PROGRAM_SIZE,
+        (Some(slot), PROGRAM_SIZE) => {
+            return Err(miette!(
+                "Slot {} is out of range (0-{})",
+                slot,
+                PROGRAM_SLOTS - 1
+            ))
+        }
+        (None, BANK_SIZE) => 0,
+        (Some(_), BANK_SIZE) => {
+            return Err(miette!(
+                "{} is a whole EEPROM image, which can't go in one slot",
+                input_name(&input)
+            ))
+        }
+        (None, PROGRAM_SIZE) => {
+            return Err(miette!(
+                help = "pass --slot to choose where it goes",
+                "{} is a single program",
+                input_name(&input)
+            ))
+        }
+        (_, size) => {
+            return Err(miette!(
+                "{} is {} bytes, not a program ({} bytes) or EEPROM image ({} bytes)",
+                input_name(&input),
+                size,
+                PROGRAM_SIZE,
+                BANK_SIZE
+            ))
+        }
+    };
+
+    let port = serialport::new(port, baud)
+        .timeout(Duration::from_secs(2))
+        .open()
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to open serial port {}", port))?;
+    let mut programmer = flash::Programmer::new(port);
+    let bridge = programmer.handshake()?;
+    println!("Connected to {}", bridge);
+
+    programmer.write(address, &image)?;
+    println!("✓ Wrote {} bytes at 0x{:04X}", image.len(), address);
+
+    if verify {
+        programmer.verify(address, &image)?;
+        println!("✓ Verified");
+    }
+
+    Ok(())
+}
+
+/// Size in bytes of one program
+const PROGRAM_SIZE: usize = MAX_INSTRUCTIONS * 4;
+
+/// Size in bytes of an EEPROM image
+const BANK_SIZE: usize = PROGRAM_SLOTS * PROGRAM_SIZE;
+
+/// Check whether a path names an assembly source file
+fn is_source(input: &Path) -> bool {
+    input.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("asm") || extension.eq_ignore_ascii_case("spn")
+    })
+}
+
+/// Read a binary program or EEPROM image, decoding Intel HEX recognized by
+/// its `:` record marks. A short HEX program is zero-padded to full size.
+fn read_image(input: &Path) -> Result<Vec<u8>> {
+    let bytes = read_input(input)?;
+    if !bytes.trim_ascii_start().starts_with(b":") {
+        return Ok(bytes);
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    let image = match Binary::from_hex(&text) {
+        Ok(binary) => {
+            let mut bytes = binary.to_bytes();
+            bytes.resize(PROGRAM_SIZE, 0);
+            bytes
+        }
+        Err(CodegenError::InvalidBinarySize { .. }) => Bank::from_hex(&text)
+            .wrap_err("Failed to parse HEX file")?
+            .to_bytes(),
+        Err(error) => return Err(error).wrap_err("Failed to parse HEX file"),
+    };
+    Ok(image)
+}
+
+/// Load a program as a binary, assembling `.asm` and `.spn` sources and
+/// reading Intel HEX by its `:` record marks
+fn load_binary(input: &Path) -> Result<Binary> {
+    if is_source(input) {
+        let name = input_name(input);
+        let source = read_source(input)?;
+        let program = FV1Parser::new(&source)
+            .parse_named(&name)
+            .wrap_err_with(|| format!("Failed to parse {}", name))?;
+        return Assembler::new()
+            .assemble_named(&program, &name, &source)
+            .wrap_err_with(|| format!("Failed to assemble {}", name));
+    }
+
+    Binary::from_bytes(&read_image(input)?)
+        .wrap_err_with(|| format!("Failed to parse binary file: {}", input_name(input)))
+}
+
+/// Print a decoded hex dump of a program or EEPROM image
+fn hexdump_file(input: PathBuf, all: bool) -> Result<()> {
+    let bytes = if is_source(&input) {
+        load_binary(&input)?.to_bytes()
+    } else {
+        read_image(&input)?
+    };
+    let dump = format_hexdump(&bytes, all)
+        .wrap_err_with(|| format!("Failed to parse binary file: {}", input_name(&input)))?;
+    print!("{}", dump);
+    Ok(())
+}
+
+/// Path that stands for stdin as an input or stdout as an output
+const STDIO: &str = "-";
+
+/// Check whether a path stands for stdin or stdout
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO
+}
+
+/// Name of an input for messages
+fn input_name(path: &Path) -> String {
+    if is_stdio(path) {
+        "<stdin>".to_string()
+    } else {
+        path.display().to_string()
+    }
+}
+
+/// Read an input file, or stdin for `-`
+fn read_input(input: &Path) -> Result<Vec<u8>> {
+    if is_stdio(input) {
+        let mut bytes = Vec::new();
+        io::stdin()
+            .read_to_end(&mut bytes)
+            .into_diagnostic()
+            .wrap_err("Failed to read stdin")?;
+        Ok(bytes)
+    } else {
+        fs::read(input)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to read input file: {}", input.display()))
+    }
+}
+
+/// Read a source file, or stdin for `-`
+fn read_source(input: &Path) -> Result<String> {
+    String::from_utf8(read_input(input)?)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("{} is not UTF-8 text", input_name(input)))
+}
+
+/// Write an output file, or stdout for `-`. Binary output is not written
+/// to a terminal.
+fn write_output(output: &Path, contents: &[u8], binary: bool) -> Result<()> {
+    if !is_stdio(output) {
+        return fs::write(output, contents)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to write output file: {}", output.display()));
+    }
+
+    let mut stdout = io::stdout();
+    if binary && stdout.is_terminal() {
+        return Err(miette!(
+            help =
+                "redirect stdout to a file or pipe, or choose a text format such as --format hex",
+            "Refusing to write binary output to a terminal"
+        ));
+    }
+    stdout
+        .write_all(contents)
+        .and_then(|()| stdout.flush())
+        .into_diagnostic()
+        .wrap_err("Failed to write stdout")
+}
+
+/// Parser for `source` with equates defined from the command line
+fn parser_with<'source>(source: &'source str, defines: &[(String, Value)]) -> FV1Parser<'source> {
+    defines
+        .iter()
+        .fold(FV1Parser::new(source), |parser, (name, value)| {
+            parser.with_define(name.clone(), value.clone())
+        })
+}
+
+/// Read and parse an assembly file
+fn parse_file(input: &Path) -> Result<Program> {
+    let source = read_source(input)?;
+
+    FV1Parser::new(&source)
+        .parse_named(&input_name(input))
+        .wrap_err_with(|| format!("Failed to parse {}", input_name(input)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(name: &str) -> String {
+        format!(
+            "{}/../fv1-examples/examples/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        )
+    }
+
+    #[test]
+    fn test_run_check() {
+        let args = Args::try_parse_from(["fv1", "check", &example("delay_echo.asm")]).unwrap();
+        assert_eq!(run(args).unwrap(), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn test_run_diff_returns_status() {
+        let diff = |old: &str, new: &str| {
+            let args = Args::try_parse_from(["fv1", "diff", &example(old), &example(new)]).unwrap();
+            run(args).unwrap()
+        };
+        assert_eq!(diff("delay_echo.asm", "delay_echo.asm"), ExitCode::SUCCESS);
+        assert_eq!(diff("delay_echo.asm", "passthrough.asm"), ExitCode::FAILURE);
+    }
+
+    #[test]
+    fn test_run_unknown_format() {
+        let args = Args::try_parse_from([
+            "fv1",
+            "assemble",
+            &example("delay_echo.asm"),
+            "--format",
+            "sysex",
+        ])
+        .unwrap();
+        let err = run(args).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("available: bin, hex, srec, c, h, rust, arduino"));
+    }
+}
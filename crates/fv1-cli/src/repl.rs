@@ -0,0 +1,308 @@
+//! Interactive Session
+//!
+//! Backs `fv1 repl`, which encodes instructions as they are typed. Each
+//! line is added to the session's source only if the whole session still
+//! parses and assembles, so the session is always a valid program apart
+//! from labels not defined yet. Since skips only jump forward, a skip to a
+//! label is accepted before the label is typed: until then the label is
+//! pending and the skip is encoded as if it went to the end of the program.
+//! Lines starting with `:` are commands for inspecting and saving the
+//! session.
+
+use crate::formats::{ExportOptions, FormatRegistry};
+use fv1_asm::{
+    codegen::format_instruction, Assembler, Binary, ParseError, Parser as FV1Parser, Program,
+};
+use std::fs;
+use std::path::Path;
+
+/// Help text for the session commands
+const HELP: &str = "\
+Type an instruction, directive or label to add it to the session.
+Commands:
+  :list             show the instructions and their encodings
+  :source           show the session source
+  :undo             remove the last line
+  :clear            start over
+  :save FILE        write the session source to FILE
+  :assemble FILE    assemble the session to FILE, in the format its
+                    extension names (bin by default)
+  :help             show this help
+  :quit             leave the session
+";
+
+/// Lines entered so far in an interactive session
+pub struct Session<'a> {
+    formats: &'a FormatRegistry,
+    lines: Vec<String>,
+}
+
+impl<'a> Session<'a> {
+    /// Start an empty session, assembling to the formats in `formats`
+    pub fn new(formats: &'a FormatRegistry) -> Self {
+        Self {
+            formats,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Source of the session so far
+    pub fn source(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// Handle a line of input, returning the text to show, or `None` when
+    /// the session should end
+    pub fn eval(&mut self, line: &str) -> Option<String> {
+        let line = line.trim();
+        let Some(command) = line.strip_prefix(':') else {
+            return Some(self.add(line));
+        };
+
+        let (command, arg) = match command.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (command, ""),
+        };
+        let reply = match command {
+            "quit" | "q" | "exit" => return None,
+            "help" | "h" => HELP.to_string(),
+            "list" | "l" => match self.build(&self.source()) {
+                Ok(build) if !build.program.instructions().is_empty() => {
+                    format_words(&build.program, &build.binary, 0) + &format_pending(&build.pending)
+                }
+                Ok(_) => "(no instructions)\n".to_string(),
+                Err(error) => format!("error: {}\n", error),
+            },
+            "source" => self.source(),
+            "undo" => match self.lines.pop() {
+                Some(line) => format!("removed: {}\n", line),
+                None => "nothing to undo\n".to_string(),
+            },
+            "clear" => {
+                self.lines.clear();
+                "cleared\n".to_string()
+            }
+            "save" if !arg.is_empty() => match fs::write(arg, self.source()) {
+                Ok(()) => format!("saved to {}\n", arg),
+                Err(error) => format!("error: {}\n", error),
+            },
+            "assemble" if !arg.is_empty() => match self.assemble_to(Path::new(arg)) {
+                Ok(()) => format!("assembled to {}\n", arg),
+                Err(error) => format!("error: {}\n", error),
+            },
+            "save" | "assemble" => format!("usage: :{} FILE\n", command),
+            _ => format!("unknown command :{} (try :help)\n", command),
+        };
+        Some(reply)
+    }
+
+    /// Add a line if the session still builds with it, showing the
+    /// encodings of any instructions it adds
+    fn add(&mut self, line: &str) -> String {
+        if line.is_empty() {
+            return String::new();
+        }
+
+        let (before, was_pending) = match self.build(&self.source()) {
+            Ok(build) => (build.program.instructions().len(), build.pending),
+            Err(_) => (0, Vec::new()),
+        };
+        let source = format!("{}{}\n", self.source(), line);
+        match self.build(&source) {
+            Ok(build) => {
+                self.lines.push(line.to_string());
+                let mut reply = format_words(&build.program, &build.binary, before);
+                for label in was_pending
+                    .iter()
+                    .filter(|label| !build.pending.contains(label))
+                {
+                    reply.push_str(&format!("resolved label: {}\n", label));
+                }
+                reply + &format_pending(&build.pending)
+            }
+            Err(error) => format!("error: {}\n", error),
+        }
+    }
+
+    /// Parse and assemble `source`, placing labels it skips to but doesn't
+    /// define at the end of the program
+    fn build(&self, source: &str) -> Result<Build, String> {
+        let mut provisional = source.to_string();
+        let mut pending = Vec::new();
+        let program = loop {
+            match FV1Parser::new(&provisional).parse() {
+                Ok(program) => break program,
+                Err(ParseError::UndefinedLabel { name, .. }) if !pending.contains(&name) => {
+                    provisional.push_str(&format!("{}:\n", name));
+                    pending.push(name);
+                }
+                Err(error) => return Err(error.to_string()),
+            }
+        };
+        let binary = Assembler::new()
+            .assemble(&program)
+            .map_err(|error| error.to_string())?;
+        Ok(Build {
+            program,
+            binary,
+            pending,
+        })
+    }
+
+    /// Assemble the session to `path` in the format matching its extension
+    fn assemble_to(&self, path: &Path) -> Result<(), String> {
+        let Build {
+            binary, pending, ..
+        } = self.build(&self.source())?;
+        if let Some(label) = pending.first() {
+            return Err(format!("undefined label: {}", label));
+        }
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().into_owned());
+        let exporter = self
+            .formats
+            .names()
+            .into_iter()
+            .filter_map(|name| self.formats.get(name))
+            .find(|exporter| Some(exporter.extension()) == extension.as_deref())
+            .or_else(|| self.formats.get("bin"))
+            .ok_or("no output format for this file")?;
+
+        let name = path
+            .file_stem()
+            .map_or("fv1_program".into(), |stem| stem.to_string_lossy());
+        let options = ExportOptions {
+            name: name.into_owned(),
+            pot_labels: Vec::new(),
+        };
+        fs::write(path, exporter.export(&binary, &options)).map_err(|error| error.to_string())
+    }
+}
+
+/// A session built into a program
+struct Build {
+    program: Program,
+    binary: Binary,
+    /// Labels skipped to but not defined yet
+    pending: Vec<String>,
+}
+
+/// Note labels that are skipped to but not defined yet
+fn format_pending(pending: &[String]) -> String {
+    if pending.is_empty() {
+        String::new()
+    } else {
+        format!("pending labels: {}\n", pending.join(", "))
+    }
+}
+
+/// Format the instructions of a program from index `from` on, with their
+/// encoded words
+fn format_words(program: &Program, binary: &Binary, from: usize) -> String {
+    program
+        .instructions()
+        .into_iter()
+        .enumerate()
+        .skip(from)
+        .map(|(index, inst)| {
+            format!(
+                "{:03}  {:08X}  {}\n",
+                index,
+                binary.instructions()[index],
+                format_instruction(inst)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let formats = FormatRegistry::builtin();
+        let mut session = Session::new(&formats);
+
+        assert_eq!(
+            session.eval("rdax adcl, 1.0").unwrap(),
+            "000  40000284  RDAX ADCL, 1\n"
+        );
+        assert_eq!(session.eval("equ out, dacl").unwrap(), "");
+        assert_eq!(
+            session.eval("wrax out, 0.0").unwrap(),
+            "001  000002C6  WRAX DACL, 0\n"
+        );
+        assert!(session
+            .eval("rdax adcl, 3.0")
+            .unwrap()
+            .starts_with("error: coefficient 3 out of range"));
+        assert_eq!(
+            session.source(),
+            "rdax adcl, 1.0\nequ out, dacl\nwrax out, 0.0\n"
+        );
+
+        assert_eq!(
+            session.eval(":list").unwrap(),
+            "000  40000284  RDAX ADCL, 1\n001  000002C6  WRAX DACL, 0\n"
+        );
+        assert_eq!(session.eval(":undo").unwrap(), "removed: wrax out, 0.0\n");
+        assert_eq!(session.eval(":clear").unwrap(), "cleared\n");
+        assert_eq!(session.eval(":list").unwrap(), "(no instructions)\n");
+        assert_eq!(session.eval(":save").unwrap(), "usage: :save FILE\n");
+        assert!(session
+            .eval(":frobnicate")
+            .unwrap()
+            .starts_with("unknown command"));
+        assert_eq!(session.eval(":quit"), None);
+    }
+
+    #[test]
+    fn test_forward_labels() {
+        let formats = FormatRegistry::builtin();
+        let mut session = Session::new(&formats);
+        let output = std::env::temp_dir().join(format!("fv1-repl-{}.bin", std::process::id()));
+        let assemble = format!(":assemble {}", output.display());
+
+        assert_eq!(
+            session.eval("rdax adcl, 1.0").unwrap(),
+            "000  40000284  RDAX ADCL, 1\n"
+        );
+        assert_eq!(
+            session.eval("skp neg, done").unwrap(),
+            "001  08000011  SKP NEG, 0\npending labels: done\n"
+        );
+        assert_eq!(
+            session.eval("wrax dacl, 0.0").unwrap(),
+            "002  000002C6  WRAX DACL, 0\npending labels: done\n"
+        );
+        assert_eq!(
+            session.eval(":list").unwrap(),
+            "000  40000284  RDAX ADCL, 1\n001  08200011  SKP NEG, 1\n\
+             002  000002C6  WRAX DACL, 0\npending labels: done\n"
+        );
+        assert_eq!(
+            session.eval(&assemble).unwrap(),
+            "error: undefined label: done\n"
+        );
+
+        assert_eq!(
+            session.eval("done: clr").unwrap(),
+            "003  0000000E  CLR\nresolved label: done\n"
+        );
+        assert_eq!(
+            session.eval(":list").unwrap(),
+            "000  40000284  RDAX ADCL, 1\n001  08200011  SKP NEG, 1\n\
+             002  000002C6  WRAX DACL, 0\n003  0000000E  CLR\n"
+        );
+        assert_eq!(
+            session.eval(&assemble).unwrap(),
+            format!("assembled to {}\n", output.display())
+        );
+        let _ = fs::remove_file(output);
+    }
+}
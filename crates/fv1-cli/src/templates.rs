@@ -0,0 +1,112 @@
+//! Starter Programs
+//!
+//! Commented programs for `fv1 new` to start a patch from, each listing
+//! what its pots do and laying out its delay memory. A template can also be
+//! turned into a Cargo project that builds the same program with the
+//! `fv1-dsl` builder.
+
+use fv1_asm::{codegen::format_dsl, Parser as FV1Parser};
+use miette::{Context, Result};
+
+/// A starter program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Template {
+    /// Passes the input through, with the pots unassigned
+    Blank,
+    /// Echo with delay time, feedback and mix pots
+    Delay,
+    /// Small room reverb with time and mix pots
+    Reverb,
+    /// Tremolo with rate and depth pots
+    Tremolo,
+}
+
+impl Template {
+    /// Assembly source of the template for a patch called `name`
+    pub fn source(self, name: &str) -> String {
+        let source = match self {
+            Template::Blank => include_str!("../templates/blank.asm"),
+            Template::Delay => include_str!("../templates/delay.asm"),
+            Template::Reverb => include_str!("../templates/reverb.asm"),
+            Template::Tremolo => include_str!("../templates/tremolo.asm"),
+        };
+        source.replace("{name}", name)
+    }
+
+    /// Files of a Cargo project called `name` that builds the template with
+    /// `fv1-dsl` and writes the binary, as (relative path, contents) pairs
+    pub fn rust_project(self, name: &str) -> Result<Vec<(&'static str, String)>> {
+        let source = self.source(name);
+        let program = FV1Parser::new(&source)
+            .parse()
+            .wrap_err("Failed to parse template")?;
+
+        // The header comment describes the patch and its pots
+        let mut main: String = source
+            .lines()
+            .take_while(|line| line.starts_with(';'))
+            .map(|line| format!("//!{}\n", line.trim_start_matches(';')))
+            .collect();
+        main.push('\n');
+        main.push_str(&format_dsl(&program));
+        main.push_str(&format!(
+            "
+fn main() {{
+    let binary = fv1_asm::Assembler::new()
+        .assemble(&program())
+        .expect(\"program assembles\");
+    std::fs::write(\"{name}.bin\", binary.to_bytes()).expect(\"failed to write {name}.bin\");
+    println!(\"Wrote {name}.bin\");
+}}
+"
+        ));
+
+        let manifest = format!(
+            "[package]
+name = \"{name}\"
+version = \"0.1.0\"
+edition = \"2021\"
+
+[dependencies]
+fv1-asm = {{ git = \"https://github.com/bspaulding/fv1_rust\" }}
+fv1-dsl = {{ git = \"https://github.com/bspaulding/fv1_rust\" }}
+"
+        );
+
+        Ok(vec![("Cargo.toml", manifest), ("src/main.rs", main)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+    use fv1_asm::{analysis::validate, Assembler};
+
+    #[test]
+    fn test_templates_assemble_cleanly() {
+        for template in Template::value_variants() {
+            let source = template.source("patch");
+            assert!(source.starts_with("; patch"));
+
+            let program = FV1Parser::new(&source).parse().unwrap();
+            let (_, warnings) = Assembler::new().assemble_with_warnings(&program).unwrap();
+            assert_eq!(warnings, vec![], "{:?}", template);
+            assert_eq!(validate(&program), vec![], "{:?}", template);
+        }
+    }
+
+    #[test]
+    fn test_rust_project() {
+        let files = Template::Tremolo.rust_project("wobble").unwrap();
+        assert_eq!(files[0].0, "Cargo.toml");
+        assert!(files[0].1.starts_with("[package]\nname = \"wobble\"\n"));
+
+        let (path, main) = &files[1];
+        assert_eq!(*path, "src/main.rs");
+        assert!(main.starts_with("//! wobble: tremolo\n//!\n//! POT0: rate\n"));
+        assert!(main.contains("use fv1_dsl::{prelude::*, Program};\n"));
+        assert!(main.contains("        .inst(wlds(Lfo::SIN0, 50, 32767))\n"));
+        assert!(main.contains("std::fs::write(\"wobble.bin\", binary.to_bytes())"));
+    }
+}
@@ -0,0 +1,10 @@
+; {name}
+;
+; POT0: unused
+; POT1: unused
+; POT2: unused
+
+; Pass the left input through to both outputs
+rdax adcl, 1.0
+wrax dacl, 1.0
+wrax dacr, 0.0
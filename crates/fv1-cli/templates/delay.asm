@@ -0,0 +1,34 @@
+; {name}: echo with feedback
+;
+; POT0: delay time, up to about 1 second
+; POT1: feedback
+; POT2: dry/wet mix
+;
+; Mono: the left input feeds both outputs.
+
+mem delay 32000         ; delay line, about 1 s at 32768 Hz
+
+equ wet, reg0            ; delayed sample
+equ time, reg1           ; smoothed delay time
+
+; Smooth POT0 to avoid zipper noise, then point ADDR_PTR into the delay
+rdax pot0, 1.0
+rdfx time, 0.001
+wrax time, 0.95         ; keep the delay inside the line
+sof 1.0, 0.02           ; and above zero
+wrax addr_ptr, 0.0
+
+; Read the delayed sample and feed it back with the input
+rmpa 1.0
+wrax wet, 1.0
+mulx pot1               ; feedback amount
+rdax adcl, 1.0
+wra delay, 0.0
+
+; Crossfade from dry to wet with POT2
+rdax wet, 1.0
+rdax adcl, -1.0
+mulx pot2
+rdax adcl, 1.0
+wrax dacl, 1.0
+wrax dacr, 0.0
@@ -0,0 +1,58 @@
+; {name}: small room reverb
+;
+; POT0: reverb time
+; POT1: unused
+; POT2: dry/wet mix
+;
+; Mono: the left input feeds both outputs.
+
+mem ap1 156             ; input diffusers
+mem ap2 223
+mem ap3 332
+mem ap4 548
+mem del1 3559           ; reverb loop
+mem del2 4211
+
+equ krt, reg0            ; reverb time coefficient
+equ apout, reg1          ; diffused input
+equ wet, reg2            ; reverb output
+
+; Reverb time from POT0, 0.3 to 0.85
+rdax pot0, 0.55
+sof 1.0, 0.3
+wrax krt, 0.0
+
+; Diffuse the input through all-pass filters in series
+rdax adcl, 0.5
+rda ap1#, 0.5
+wrap ap1, -0.5
+rda ap2#, 0.5
+wrap ap2, -0.5
+rda ap3#, 0.5
+wrap ap3, -0.5
+rda ap4#, 0.5
+wrap ap4, -0.5
+wrax apout, 0.0
+
+; Two delays in a loop, decaying by the reverb time each pass
+rda del2#, 1.0
+mulx krt
+rdax apout, 1.0
+wra del1, 0.0
+rda del1#, 1.0
+mulx krt
+rdax apout, 1.0
+wra del2, 0.0
+
+; Tap the loop for the output
+rda del1#, 0.6
+rda del2#, 0.6
+wrax wet, 0.0
+
+; Crossfade from dry to wet with POT2
+rdax wet, 1.0
+rdax adcl, -1.0
+mulx pot2
+rdax adcl, 1.0
+wrax dacl, 1.0
+wrax dacr, 0.0
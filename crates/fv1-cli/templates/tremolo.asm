@@ -0,0 +1,32 @@
+; {name}: tremolo
+;
+; POT0: rate
+; POT1: depth
+; POT2: unused
+;
+; Mono: the left input feeds both outputs.
+
+equ gain, reg0           ; current gain, 1 - depth to 1
+
+; Start the LFO on the first sample only
+skp run, start
+wlds sin0, 50, 32767
+
+; Rate from POT0, kept above zero
+start:
+rdax pot0, 0.9
+sof 1.0, 0.05
+wrax sin0_rate, 0.0
+
+; Turn the LFO into a gain between 1 - depth and 1
+cho rdal, sin0, 0, 0    ; LFO, -1 to 1
+sof 0.5, 0.5            ; 0 to 1
+mulx pot1               ; 0 to depth
+sof -1.0, 0.999
+wrax gain, 0.0
+
+; Apply the gain
+rdax adcl, 1.0
+mulx gain
+wrax dacl, 1.0
+wrax dacr, 0.0
@@ -0,0 +1,859 @@
+//! End-to-end tests for the fv1 command line interface
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+const PASSTHROUGH: &str = "rdax adcl, 1.0\nwrax dacl, 0.0\n";
+
+fn fv1() -> Command {
+    Command::cargo_bin("fv1-cli").unwrap()
+}
+
+/// Write `source` to `name` in `dir` and return its path
+fn write_source(dir: &Path, name: &str, source: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, source).unwrap();
+    path
+}
+
+#[test]
+fn test_assemble_default_output() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+
+    fv1()
+        .arg("assemble")
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pass.bin"));
+
+    let bytes = fs::read(dir.path().join("pass.bin")).unwrap();
+    assert_eq!(bytes.len(), 512);
+}
+
+#[test]
+fn test_assemble_formats() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+
+    fv1()
+        .args(["assemble", "--format", "hex"])
+        .arg(&input)
+        .assert()
+        .success();
+    let hex = fs::read_to_string(dir.path().join("pass.hex")).unwrap();
+    assert!(hex.ends_with(":00000001FF\n"));
+
+    fv1()
+        .args(["assemble", "--format", "srec"])
+        .arg(&input)
+        .assert()
+        .success();
+    let srec = fs::read_to_string(dir.path().join("pass.s19")).unwrap();
+    assert!(srec.starts_with("S0030000FC\n"));
+    assert!(srec.ends_with("S9030000FC\n"));
+
+    fv1()
+        .args(["assemble", "--format", "c", "--name", "my_patch"])
+        .arg(&input)
+        .assert()
+        .success();
+    let c = fs::read_to_string(dir.path().join("pass.c")).unwrap();
+    assert!(c.contains("my_patch"));
+
+    fv1()
+        .args(["assemble", "--format", "rust", "--name", "my_patch"])
+        .arg(&input)
+        .assert()
+        .success();
+    let rust = fs::read_to_string(dir.path().join("pass.rs")).unwrap();
+    assert!(rust.contains("pub const MY_PATCH: [u32; 128] = ["));
+
+    fv1()
+        .args(["assemble", "--format", "h", "--name", "my_patch"])
+        .args(["--pot-label", "Time", "--pot-label", "Mix"])
+        .arg(&input)
+        .assert()
+        .success();
+    let header = fs::read_to_string(dir.path().join("pass.h")).unwrap();
+    assert!(header.contains("#define MY_PATCH_POT1_LABEL \"Mix\"\n"));
+    assert!(header.contains("#define MY_PATCH_INSTRUCTIONS 2\n"));
+}
+
+#[test]
+fn test_assemble_optimize_report() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(
+        dir.path(),
+        "opt.asm",
+        "rdax adcl, 1.0\nwrax dacl, 0.0\nclr\nnop\n",
+    );
+
+    fv1()
+        .args(["assemble", "--optimize"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Optimized 4 instructions to 2"))
+        .stdout(predicate::str::contains(
+            "instruction 2: removed CLR, ACC is already zero",
+        ))
+        .stdout(predicate::str::contains("instruction 3: removed NOP"));
+}
+
+#[test]
+fn test_assemble_explicit_output() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+    let output = dir.path().join("out").with_extension("rom");
+
+    fv1()
+        .arg("assemble")
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .success();
+
+    assert!(output.exists());
+    assert!(!dir.path().join("pass.bin").exists());
+}
+
+#[test]
+fn test_assemble_listing() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+    let listing = dir.path().join("pass.lst");
+
+    fv1()
+        .arg("assemble")
+        .arg(&input)
+        .arg("--listing")
+        .arg(&listing)
+        .assert()
+        .success();
+
+    let listing = fs::read_to_string(listing).unwrap();
+    assert!(listing.contains("000   40000284     1  rdax adcl, 1.0\n"));
+    assert!(listing.contains("001   000002C6     2  wrax dacl, 0.0\n"));
+    assert!(dir.path().join("pass.bin").exists());
+}
+
+#[test]
+fn test_assemble_verbose_checksum() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+
+    fv1()
+        .args(["assemble", "--verbose"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(r"CRC-32 [0-9A-F]{8}, fingerprint [0-9a-f]{4}-[0-9a-f]{4}\n")
+                .unwrap(),
+        );
+}
+
+#[test]
+fn test_assemble_pipeline() {
+    let output = fv1()
+        .args(["assemble", "-", "--format", "hex", "-o", "-"])
+        .write_stdin(PASSTHROUGH)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let hex = String::from_utf8(output).unwrap();
+    assert!(hex.starts_with(":10000000400002840000"));
+    assert!(hex.ends_with(":00000001FF\n"));
+
+    // Reading stdin writes to stdout by default, and binary output is fine
+    // when stdout is not a terminal
+    let output = fv1()
+        .args(["assemble", "-"])
+        .write_stdin(PASSTHROUGH)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(output.len(), 512);
+    assert_eq!(&output[..4], &[0x40, 0x00, 0x02, 0x84]);
+
+    fv1()
+        .args(["disassemble", "-"])
+        .write_stdin(output)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("RDAX ADCL"));
+}
+
+#[test]
+fn test_assemble_unknown_format() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+
+    fv1()
+        .args(["assemble", "--format", "sysex"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown output format 'sysex'"));
+}
+
+#[test]
+fn test_assemble_parse_error() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "bad.asm", "rdax adcl\n");
+
+    fv1()
+        .arg("assemble")
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to parse assembly program"));
+
+    assert!(!dir.path().join("bad.bin").exists());
+}
+
+#[test]
+fn test_errors_show_source() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "loud.asm", "rdax adcl, 1.0\nsof 3.0, 0.0\n");
+
+    fv1()
+        .env("NO_COLOR", "1")
+        .arg("check")
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("loud.asm:2:5"))
+        .stderr(predicate::str::contains("sof 3.0, 0.0"));
+
+    let input = write_source(dir.path(), "ramp.asm", "wlds rmp0, 10, 100\n");
+    fv1()
+        .env("NO_COLOR", "1")
+        .arg("assemble")
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("ramp.asm:1:1"))
+        .stderr(predicate::str::contains("wlds rmp0, 10, 100"));
+}
+
+#[test]
+fn test_assemble_reports_all_errors() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(
+        dir.path(),
+        "lfos.asm",
+        "wlds rmp0, 10, 100\nrdax adcl, 1.0\nwlds rmp1, 10, 100\n",
+    );
+
+    fv1()
+        .env("NO_COLOR", "1")
+        .arg("assemble")
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("2 errors in program"))
+        .stderr(predicate::str::contains("lfos.asm:1:1"))
+        .stderr(predicate::str::contains("lfos.asm:3:1"));
+}
+
+#[test]
+fn test_missing_input() {
+    let dir = TempDir::new().unwrap();
+
+    fv1()
+        .arg("check")
+        .arg(dir.path().join("missing.asm"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read input file"));
+}
+
+#[test]
+fn test_migrate() {
+    let dir = TempDir::new().unwrap();
+    let asm = write_source(
+        dir.path(),
+        "vol.asm",
+        "rdax adcl, 1.0\nmulx reg16\nwrax dacl, 0.0\n",
+    );
+    let rs = write_source(dir.path(), "vol.rs", "b.inst(mulx(Register::REG(16)));\n");
+
+    // Assembly registers are only listed unless pots are assumed
+    fv1()
+        .arg("migrate")
+        .arg(&asm)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+        "is up to date\n  Left alone, may be pots (check by hand):\n    line 2: reg16 -> pot0\n",
+    ));
+    fv1()
+        .args(["migrate", "--check", "--assume-pots"])
+        .arg(&asm)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "needs 1 changes\n  line 2: reg16 -> pot0\n",
+        ));
+    assert!(fs::read_to_string(&asm).unwrap().contains("reg16"));
+
+    fv1()
+        .args(["migrate", "--assume-pots"])
+        .arg(&asm)
+        .arg(&rs)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("✓ Migrated"));
+    assert_eq!(
+        fs::read_to_string(&asm).unwrap(),
+        "rdax adcl, 1.0\nmulx pot0\nwrax dacl, 0.0\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&rs).unwrap(),
+        "b.inst(mulx(Register::POT0));\n"
+    );
+
+    fv1()
+        .args(["migrate", "--check"])
+        .arg(&asm)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("is up to date"));
+}
+
+#[test]
+fn test_check() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+
+    fv1()
+        .arg("check")
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("is valid"))
+        .stdout(predicate::str::contains("2 instructions"))
+        .stdout(predicate::str::contains(
+            "  0 equates, 0 memory regions, 0 labels\n  0/32 registers, 0/32768 words of delay memory\n",
+        ))
+        .stderr("");
+}
+
+#[test]
+fn test_defines() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(
+        dir.path(),
+        "echo.asm",
+        "equ time, 1000\nmem dly 16000\nrdax adcl, 1.0\nwra dly, 0.0\nrda time, 1.0\nwrax dacl, 0.0\n",
+    );
+
+    fv1()
+        .args(["check", "-D", "time=2000", "-D", "stereo"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("  2 equates, 1 memory regions"));
+
+    fv1()
+        .args(["assemble", "-D", "time=2000", "-o", "-"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::function(|bytes: &[u8]| {
+            // RDA 2000, 1.0
+            bytes[8..12] == [0x40, 0x00, 0xFA, 0x00]
+        }));
+
+    fv1()
+        .args(["check", "-D", "time=later"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("must be a number or register"));
+}
+
+#[test]
+fn test_check_warnings() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(
+        dir.path(),
+        "warn.asm",
+        "mem dly 100\nrdax adcl, 1.0\nwrax reg0, 0.0\ncho rda, sin1, 0, dly\nwrax dacl, 0.0\n",
+    );
+
+    fv1()
+        .arg("check")
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  0 equates, 1 memory regions, 0 labels\n  1/32 registers, 101/32768 words of delay memory\n",
+        ))
+        .stdout(predicate::str::contains("  2 warnings\n"))
+        .stderr(predicate::str::contains("register REG0 written but never read"))
+        .stderr(predicate::str::contains(
+            "SIN1 is used but never set up with WLDS or WLDR",
+        ));
+
+    // Assembly errors are caught without writing any output
+    let input = write_source(dir.path(), "long.asm", &"clr\n".repeat(130));
+    fv1()
+        .arg("check")
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("program too large"));
+    assert!(!dir.path().join("long.bin").exists());
+}
+
+#[test]
+fn test_disassemble_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+
+    fv1().arg("assemble").arg(&input).assert().success();
+    fv1()
+        .arg("disassemble")
+        .arg(dir.path().join("pass.bin"))
+        .arg("-o")
+        .arg(dir.path().join("round.asm"))
+        .assert()
+        .success();
+
+    let source = fs::read_to_string(dir.path().join("round.asm")).unwrap();
+    assert!(source.contains("RDAX ADCL"));
+    assert!(source.contains("WRAX DACL"));
+}
+
+#[test]
+fn test_disassemble_hex_and_options() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(
+        dir.path(),
+        "dly.asm",
+        "rdax adcl, 1.0\nwrax reg0, 0.0\nrda 100, 0.5\nmulx reg0\nwrax dacl, 0.0\n",
+    );
+    fv1()
+        .args(["assemble", "--format", "hex"])
+        .arg(&input)
+        .assert()
+        .success();
+
+    let output = dir.path().join("out.asm");
+    fv1()
+        .arg("disassemble")
+        .arg(dir.path().join("dly.hex"))
+        .arg("-o")
+        .arg(&output)
+        .args(["--symbols", "--annotate"])
+        .assert()
+        .success();
+    let source = fs::read_to_string(&output).unwrap();
+    assert!(source.starts_with("MEM dly0 100\nEQU temp0, REG0\n"));
+    assert!(source.contains("WRAX temp0, 0 ; temp\n"));
+    assert!(!source.contains("NOP"));
+
+    fv1().arg("assemble").arg(&input).assert().success();
+    fv1()
+        .arg("disassemble")
+        .arg(dir.path().join("dly.bin"))
+        .arg("-o")
+        .arg(&output)
+        .arg("--keep-nops")
+        .assert()
+        .success();
+    let source = fs::read_to_string(&output).unwrap();
+    assert_eq!(source.matches("RDA 0, 0").count(), 123);
+
+    fv1()
+        .arg("disassemble")
+        .arg(dir.path().join("dly.bin"))
+        .args(["-o", "-", "--numeric-registers"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "RDAX 20, 1\nWRAX 32, 0\nRDA 100, 0.5\nMULX 32\nWRAX 22, 0\n",
+        ));
+}
+
+#[test]
+fn test_disassemble_bank() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+    fv1().arg("assemble").arg(&input).assert().success();
+
+    // The program in slots 0 and 3 of an otherwise empty image
+    let program = fs::read(dir.path().join("pass.bin")).unwrap();
+    let mut image = vec![0u8; 4096];
+    image[..512].copy_from_slice(&program);
+    image[3 * 512..4 * 512].copy_from_slice(&program);
+    fs::write(dir.path().join("bank.bin"), image).unwrap();
+
+    fv1()
+        .arg("disassemble")
+        .arg(dir.path().join("bank.bin"))
+        .assert()
+        .success();
+    let source = fs::read_to_string(dir.path().join("bank.asm")).unwrap();
+    assert!(source.starts_with("; ==== Program 0 ====\n"));
+    assert!(source.contains("; ==== Program 3 ====\n"));
+    assert!(!source.contains("Program 1"));
+}
+
+#[test]
+fn test_bank() {
+    let dir = TempDir::new().unwrap();
+    let pass = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+    let clr = write_source(dir.path(), "clr.asm", "clr\nwrax dacl, 0.0\n");
+    let output = dir.path().join("bank.bin");
+
+    fv1()
+        .arg("bank")
+        .args([&pass, &clr])
+        .arg(format!("--slot=5={}", pass.display()))
+        .arg(format!("--slot=1={}", pass.display()))
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: program in slot 1 overwritten",
+        ));
+
+    let image = fs::read(&output).unwrap();
+    assert_eq!(image.len(), 4096);
+    let program = &image[..512];
+    assert_eq!(&image[512..1024], program);
+    assert_eq!(&image[5 * 512..6 * 512], program);
+    assert!(image[2 * 512..5 * 512].iter().all(|&byte| byte == 0));
+
+    fv1()
+        .arg("bank")
+        .arg(&pass)
+        .args(["--format", "hex", "-o"])
+        .arg(dir.path().join("bank.hex"))
+        .assert()
+        .success();
+    let hex = fs::read_to_string(dir.path().join("bank.hex")).unwrap();
+    assert_eq!(hex.lines().count(), 257);
+
+    fv1()
+        .arg("bank")
+        .arg(format!("--slot=8={}", pass.display()))
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid program slot 8"));
+}
+
+#[test]
+fn test_disassemble_invalid_size() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("short.bin");
+    fs::write(&input, [0u8; 16]).unwrap();
+
+    fv1()
+        .arg("disassemble")
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to parse binary file"));
+}
+
+#[test]
+fn test_dump_isa() {
+    fv1()
+        .arg("dump-isa")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"mnemonic\": \"RDAX\""));
+}
+
+#[test]
+fn test_compare() {
+    let dir = TempDir::new().unwrap();
+    let old = write_source(dir.path(), "old.asm", PASSTHROUGH);
+    let new = write_source(
+        dir.path(),
+        "new.asm",
+        "rdax adcl, 0.5\nwrax reg0, 1.0\nwrax dacl, 0.0\n",
+    );
+
+    fv1()
+        .arg("compare")
+        .arg(&old)
+        .arg(&new)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "- RDAX ADCL, 1\n+ RDAX ADCL, 0.5\n",
+        ))
+        .stdout(predicate::str::contains("instructions: 2 -> 3 (+1)"));
+}
+
+#[test]
+fn test_diff() {
+    let dir = TempDir::new().unwrap();
+    let old = write_source(dir.path(), "old.asm", PASSTHROUGH);
+    let new = write_source(
+        dir.path(),
+        "new.asm",
+        "clr\nrdax adcl, 1.0\nwrax dacl, 0.0\n",
+    );
+    fv1()
+        .args(["assemble", "--format", "hex"])
+        .arg(&old)
+        .assert()
+        .success();
+
+    // The same program as source and as HEX
+    fv1()
+        .arg("diff")
+        .arg(&old)
+        .arg(dir.path().join("old.hex"))
+        .assert()
+        .success()
+        .stdout("");
+
+    fv1()
+        .arg("diff")
+        .arg(dir.path().join("old.hex"))
+        .arg(&new)
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "0: 40000284 RDAX ADCL, 1 => 0000000E CLR\n",
+        ))
+        .stdout(predicate::str::contains("2: 00000000 RDA 0, 0 => 000002C6"));
+
+    fv1()
+        .args(["diff", "--semantic"])
+        .arg(&old)
+        .arg(&new)
+        .assert()
+        .code(1)
+        .stdout(predicate::str::ends_with("+ 0: CLR\n"));
+}
+
+#[test]
+fn test_explain() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+    fv1().arg("assemble").arg(&input).assert().success();
+
+    fv1()
+        .arg("explain")
+        .arg(dir.path().join("pass.bin"))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "000  40000284  RDAX ADCL, 1\n               Add left input ADCL times 1 (+0.0 dB) to ACC\n",
+        ))
+        .stdout(predicate::str::ends_with("002  padding to 128 instructions\n"));
+}
+
+#[test]
+fn test_hexdump() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+
+    fv1().arg("hexdump").arg(&input).assert().success().stdout(
+        "0000  40000284  RDAX ADCL, 1\n\
+             0004  000002C6  WRAX DACL, 0\n\
+             0008  ........  126 padding words\n",
+    );
+
+    fv1()
+        .args(["bank", "-o"])
+        .arg(dir.path().join("bank.bin"))
+        .arg(&input)
+        .assert()
+        .success();
+    fv1()
+        .arg("hexdump")
+        .arg(dir.path().join("bank.bin"))
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "slot 0\n0000  40000284  RDAX ADCL, 1\n",
+        ))
+        .stdout(predicate::str::contains(
+            "\nslot 7\n0E00  ........  128 padding words\n",
+        ));
+}
+
+#[test]
+fn test_flash_checks_image_before_opening_port() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+
+    fv1()
+        .args(["flash", "--port", "/dev/does-not-exist"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is a single program"));
+
+    fv1()
+        .args(["flash", "--port", "/dev/does-not-exist", "--slot", "8"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Slot 8 is out of range (0-7)"));
+
+    fv1()
+        .args(["flash", "--port", "/dev/does-not-exist", "--slot", "2"])
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to open serial port /dev/does-not-exist",
+        ));
+}
+
+#[test]
+fn test_to_dsl() {
+    let dir = TempDir::new().unwrap();
+    let input = write_source(dir.path(), "pass.asm", PASSTHROUGH);
+
+    fv1()
+        .arg("to-dsl")
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("pass.rs"));
+    let source = fs::read_to_string(dir.path().join("pass.rs")).unwrap();
+    assert!(source.starts_with("use fv1_dsl::{prelude::*, Program};\n"));
+    assert!(source.contains("        .inst(rdax(Register::ADCL, 1.0))\n"));
+
+    // A binary converts to the same instructions
+    fv1().arg("assemble").arg(&input).assert().success();
+    fv1()
+        .arg("to-dsl")
+        .arg(dir.path().join("pass.bin"))
+        .args(["-o", "-"])
+        .assert()
+        .success()
+        .stdout(source);
+}
+
+#[test]
+fn test_assemble_batch() {
+    let dir = TempDir::new().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir(&src).unwrap();
+    write_source(&src, "a.asm", PASSTHROUGH);
+    write_source(&src, "b.asm", "rdax adcl, 3.0\n");
+    write_source(&src, "c.asm", "clr\nwrax dacl, 0.0\nwrax dacr, 0.0\n");
+    let out = dir.path().join("build");
+
+    // The pattern is passed through unexpanded, as from a shell that
+    // doesn't expand globs
+    fv1()
+        .arg("assemble")
+        .arg(src.join("*.asm"))
+        .arg("--out-dir")
+        .arg(&out)
+        .args(["--format", "hex"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("RESULT  INSTRUCTIONS\n"))
+        .stdout(predicate::str::is_match(r"a\.asm +ok      2\n").unwrap())
+        .stdout(predicate::str::is_match(r"b\.asm +FAILED\n").unwrap())
+        .stdout(predicate::str::is_match(r"c\.asm +ok      3\n").unwrap())
+        .stdout(predicate::str::contains("2 assembled, 1 failed\n"))
+        .stderr(predicate::str::contains("1 of 3 files failed to assemble"));
+
+    assert!(out.join("a.hex").exists());
+    assert!(!out.join("b.hex").exists());
+    assert!(out.join("c.hex").exists());
+
+    fv1()
+        .arg("assemble")
+        .arg(src.join("a.asm"))
+        .arg(src.join("c.asm"))
+        .args(["-o", "out.bin"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--output and --listing take a single input",
+        ));
+}
+
+#[test]
+fn test_new() {
+    let dir = TempDir::new().unwrap();
+
+    fv1()
+        .current_dir(dir.path())
+        .args(["new", "echo", "--template", "delay"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Created echo.asm"));
+    let source = fs::read_to_string(dir.path().join("echo.asm")).unwrap();
+    assert!(source.starts_with("; echo: echo with feedback\n"));
+    assert!(source.contains("mem delay 32000"));
+
+    // The new program assembles without warnings
+    fv1()
+        .current_dir(dir.path())
+        .args(["check", "echo.asm"])
+        .assert()
+        .success()
+        .stderr("");
+
+    fv1()
+        .current_dir(dir.path())
+        .args(["new", "echo"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("echo.asm already exists"));
+
+    fv1()
+        .current_dir(dir.path())
+        .args(["new", "wobble", "--template", "tremolo", "--rust"])
+        .assert()
+        .success();
+    let manifest = fs::read_to_string(dir.path().join("wobble/Cargo.toml")).unwrap();
+    assert!(manifest.contains("name = \"wobble\""));
+    let main = fs::read_to_string(dir.path().join("wobble/src/main.rs")).unwrap();
+    assert!(main.contains("pub fn program() -> Program {"));
+}
+
+#[test]
+fn test_repl() {
+    let dir = TempDir::new().unwrap();
+
+    fv1()
+        .current_dir(dir.path())
+        .arg("repl")
+        .write_stdin("rdax adcl, 1.0\nwrax dacl, 0.0\n:save pass.asm\n:assemble pass.hex\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "fv1> 000  40000284  RDAX ADCL, 1\n",
+        ))
+        .stdout(predicate::str::contains(
+            "fv1> 001  000002C6  WRAX DACL, 0\n",
+        ));
+
+    assert_eq!(
+        fs::read_to_string(dir.path().join("pass.asm")).unwrap(),
+        PASSTHROUGH
+    );
+    let hex = fs::read_to_string(dir.path().join("pass.hex")).unwrap();
+    assert!(hex.starts_with(":10000000400002840000"));
+}
@@ -0,0 +1,161 @@
+/// Named register and delay memory allocation
+///
+/// Hands out general purpose registers and delay RAM regions by name, and
+/// records the allocations as `EQU`/`MEM` directives so exported assembly
+/// can refer to them symbolically.
+use crate::blocks::Delay;
+use fv1_asm::{Directive, Register, Value, DELAY_RAM_SIZE, NUM_REGISTERS};
+use std::fmt;
+
+/// Error returned when an allocation cannot be satisfied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllocError {
+    /// All general purpose registers are in use
+    OutOfRegisters { name: String },
+    /// Not enough delay RAM is left for the requested length
+    OutOfMemory {
+        name: String,
+        length: u16,
+        available: usize,
+    },
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllocError::OutOfRegisters { name } => {
+                write!(f, "no register left to allocate for {}", name)
+            }
+            AllocError::OutOfMemory {
+                name,
+                length,
+                available,
+            } => write!(
+                f,
+                "cannot allocate {} samples of delay memory for {} ({} available)",
+                length, name, available
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AllocError {}
+
+/// Sequential allocator for registers and delay memory
+#[derive(Debug, Clone, Default)]
+pub struct Allocator {
+    registers: Vec<(String, Register)>,
+    memory: Vec<(String, Delay)>,
+    next_addr: usize,
+}
+
+impl Allocator {
+    /// Create a new allocator with nothing allocated
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate the next free general purpose register
+    pub fn reg(&mut self, name: impl Into<String>) -> Result<Register, AllocError> {
+        let name = name.into();
+        let index = self.registers.len();
+        if index >= NUM_REGISTERS {
+            return Err(AllocError::OutOfRegisters { name });
+        }
+
+        let reg = Register::REG(index as u8);
+        self.registers.push((name, reg));
+        Ok(reg)
+    }
+
+    /// Allocate a delay line of `length` samples
+    ///
+    /// Like SpinASM's `MEM`, this reserves `length + 1` locations so that
+    /// the end of the delay is addressable.
+    pub fn mem(&mut self, name: impl Into<String>, length: u16) -> Result<Delay, AllocError> {
+        let name = name.into();
+        let available = DELAY_RAM_SIZE - self.next_addr;
+        if length as usize + 1 > available {
+            return Err(AllocError::OutOfMemory {
+                name,
+                length,
+                available,
+            });
+        }
+
+        let delay = Delay::new(self.next_addr as u16, length);
+        self.next_addr += length as usize + 1;
+        self.memory.push((name, delay));
+        Ok(delay)
+    }
+
+    /// `EQU` and `MEM` directives describing the allocations so far
+    pub fn directives(&self) -> Vec<Directive> {
+        let equates = self.registers.iter().map(|(name, reg)| Directive::Equate {
+            name: name.clone(),
+            value: Value::Register(*reg),
+        });
+        let memory = self
+            .memory
+            .iter()
+            .map(|(name, delay)| Directive::MemoryAllocation {
+                name: name.clone(),
+                size: delay.length,
+            });
+        equates.chain(memory).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alloc_registers() {
+        let mut alloc = Allocator::new();
+        assert_eq!(alloc.reg("lp").unwrap(), Register::REG(0));
+        assert_eq!(alloc.reg("hp").unwrap(), Register::REG(1));
+    }
+
+    #[test]
+    fn test_alloc_out_of_registers() {
+        let mut alloc = Allocator::new();
+        for i in 0..NUM_REGISTERS {
+            alloc.reg(format!("r{}", i)).unwrap();
+        }
+        assert_eq!(
+            alloc.reg("extra"),
+            Err(AllocError::OutOfRegisters {
+                name: "extra".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_alloc_memory() {
+        let mut alloc = Allocator::new();
+        let a = alloc.mem("a", 1000).unwrap();
+        let b = alloc.mem("b", 200).unwrap();
+        assert_eq!((a.buffer, a.length), (0, 1000));
+        assert_eq!((b.buffer, b.length), (1001, 200));
+        assert!(alloc.mem("c", 32000).is_err());
+    }
+
+    #[test]
+    fn test_alloc_directives() {
+        let mut alloc = Allocator::new();
+        alloc.reg("lp").unwrap();
+        alloc.mem("dly", 4000).unwrap();
+
+        let directives = alloc.directives();
+        assert_eq!(directives.len(), 2);
+        assert!(matches!(
+            &directives[0],
+            Directive::Equate { name, value: Value::Register(Register::REG(0)) } if name == "lp"
+        ));
+        assert!(matches!(
+            &directives[1],
+            Directive::MemoryAllocation { name, size: 4000 } if name == "dly"
+        ));
+    }
+}
@@ -22,8 +22,8 @@ use crate::{Instruction, Register};
 /// use fv1_dsl::blocks;
 ///
 /// let program = ProgramBuilder::new()
-///     .inst(blocks::gain(Register::ADCL, Register::REG(16))) // POT0
-///     .inst(mulx(Register::REG(16)))  // Apply the gain
+///     .inst(blocks::gain(Register::ADCL, Register::POT0))
+///     .inst(mulx(Register::POT0))  // Apply the gain
 ///     .inst(wrax(Register::DACL, 0.0))
 ///     .build();
 /// ```
@@ -55,7 +55,7 @@ pub fn gain(input: Register, _amount: Register) -> Instruction {
 ///
 /// let mut builder = ProgramBuilder::new();
 /// builder.add_inst(rdax(Register::ADCL, 1.0));  // Input now in ACC
-/// for inst in blocks::lowpass(Register::ACC, Register::REG(16), Register::REG(1)) {
+/// for inst in blocks::lowpass(Register::ACC, Register::POT0, Register::REG(1)) {
 ///     builder.add_inst(inst);
 /// }
 /// builder.add_inst(wrax(Register::DACL, 0.0));
@@ -126,7 +126,7 @@ pub fn soft_clip(threshold: f32) -> Vec<Instruction> {
 /// }
 ///
 /// // Process and write back with feedback
-/// builder.add_inst(mulx(Register::REG(17))); // POT1 for feedback
+/// builder.add_inst(mulx(Register::POT1)); // Feedback
 /// builder.add_inst(rdax(Register::REG(0), 1.0));
 ///
 /// for inst in delay.write(0.0) {
@@ -135,6 +135,7 @@ pub fn soft_clip(threshold: f32) -> Vec<Instruction> {
 ///
 /// let program = builder.build();
 /// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Delay {
     /// Starting address in delay RAM
     pub buffer: u16,
@@ -179,7 +180,7 @@ mod tests {
 
     #[test]
     fn test_gain_block() {
-        let inst = gain(Register::ADCL, Register::REG(16));
+        let inst = gain(Register::ADCL, Register::POT0);
         match inst {
             Instruction::RDAX { reg, coeff } => {
                 assert_eq!(reg, Register::ADCL);
@@ -191,7 +192,7 @@ mod tests {
 
     #[test]
     fn test_lowpass_block() {
-        let instructions = lowpass(Register::ACC, Register::REG(16), Register::REG(1));
+        let instructions = lowpass(Register::ACC, Register::POT0, Register::REG(1));
         assert_eq!(instructions.len(), 4);
 
         // Verify the sequence
@@ -205,7 +206,7 @@ mod tests {
 
         match &instructions[1] {
             Instruction::MULX { reg } => {
-                assert_eq!(*reg, Register::REG(16));
+                assert_eq!(*reg, Register::POT0);
             }
             _ => panic!("Expected MULX instruction"),
         }
@@ -1,7 +1,10 @@
+pub mod alloc;
 pub mod blocks;
 pub mod ops;
 pub mod typed;
 
+pub use alloc::{AllocError, Allocator};
+pub use fv1_asm::coeff;
 pub use fv1_asm::{
     ChoFlags, ChoMode, Control, Instruction, Lfo, Program, Register, SkipCondition, Statement,
 };
@@ -29,6 +32,8 @@ use std::collections::HashMap;
 pub struct ProgramBuilder {
     instructions: Vec<Instruction>,
     labels: HashMap<String, usize>,
+    allocator: Allocator,
+    symbols: bool,
 }
 
 impl ProgramBuilder {
@@ -37,6 +42,8 @@ impl ProgramBuilder {
         Self {
             instructions: Vec::new(),
             labels: HashMap::new(),
+            allocator: Allocator::new(),
+            symbols: false,
         }
     }
 
@@ -64,10 +71,40 @@ impl ProgramBuilder {
         self
     }
 
+    /// Allocate a named general purpose register
+    pub fn alloc_reg(&mut self, name: impl Into<String>) -> Result<Register, AllocError> {
+        self.allocator.reg(name)
+    }
+
+    /// Allocate a named delay line of `length` samples
+    pub fn alloc_mem(
+        &mut self,
+        name: impl Into<String>,
+        length: u16,
+    ) -> Result<blocks::Delay, AllocError> {
+        self.allocator.mem(name, length)
+    }
+
+    /// Control whether allocations are emitted as `EQU`/`MEM` directives,
+    /// so exported assembly refers to them by name
+    pub fn with_symbols(mut self, enable: bool) -> Self {
+        self.symbols = enable;
+        self
+    }
+
+    /// Build the program and format it as assembly source
+    pub fn to_asm(self) -> String {
+        fv1_asm::codegen::format_program(&self.build())
+    }
+
     /// Build the final program
     pub fn build(self) -> Program {
         let mut program = Program::new();
 
+        if self.symbols {
+            program.directives = self.allocator.directives();
+        }
+
         // Add all instructions
         for inst in self.instructions {
             program.add_statement(Statement::Instruction(inst));
@@ -117,6 +154,54 @@ mod tests {
         assert_eq!(builder.instructions.len(), 1);
     }
 
+    #[test]
+    fn test_builder_export_symbols() {
+        let mut builder = ProgramBuilder::new().with_symbols(true);
+        let lp = builder.alloc_reg("lp").unwrap();
+        let delay = builder.alloc_mem("dly", 4000).unwrap();
+        builder
+            .add_inst(Instruction::WRA {
+                addr: delay.buffer,
+                coeff: 0.0,
+            })
+            .add_inst(Instruction::RDA {
+                addr: delay.buffer + delay.length,
+                coeff: 0.5,
+            })
+            .add_inst(Instruction::WRAX {
+                reg: lp,
+                coeff: 0.0,
+            });
+
+        let asm = builder.to_asm();
+        assert!(asm.starts_with("EQU lp, REG0\nMEM dly 4000\n"));
+        assert!(asm.contains("WRA dly, 0\n"));
+        assert!(asm.contains("RDA dly#, 0.5\n"));
+        assert!(asm.contains("WRAX lp, 0\n"));
+
+        // The exported symbols resolve back to the same operands
+        let reparsed = fv1_asm::Parser::new(&asm).parse().unwrap();
+        assert_eq!(
+            *reparsed.instructions()[1],
+            Instruction::RDA {
+                addr: delay.buffer + delay.length,
+                coeff: 0.5,
+            }
+        );
+    }
+
+    #[test]
+    fn test_builder_export_without_symbols() {
+        let mut builder = ProgramBuilder::new();
+        let lp = builder.alloc_reg("lp").unwrap();
+        builder.add_inst(Instruction::WRAX {
+            reg: lp,
+            coeff: 0.0,
+        });
+
+        assert_eq!(builder.to_asm(), "WRAX REG0, 0\n");
+    }
+
     #[test]
     fn test_builder_add_label() {
         let mut builder = ProgramBuilder::new();
@@ -26,6 +26,16 @@ pub fn wrax(reg: Register, coeff: f32) -> Instruction {
     Instruction::WRAX { reg, coeff }
 }
 
+/// Write ACC to register, high-shelf: [REG] = ACC, ACC = ACC * C + PACC
+pub fn wrhx(reg: Register, coeff: f32) -> Instruction {
+    Instruction::WRHX { reg, coeff }
+}
+
+/// Write ACC to register, low-shelf: [REG] = ACC, ACC = (PACC - ACC) * C + PACC
+pub fn wrlx(reg: Register, coeff: f32) -> Instruction {
+    Instruction::WRLX { reg, coeff }
+}
+
 /// Write ACC to delay RAM: [ADDR] = ACC * C, ACC = ACC * D
 pub fn wra(addr: u16, coeff: f32) -> Instruction {
     Instruction::WRA { addr, coeff }
@@ -87,6 +97,11 @@ pub fn xor(mask: u32) -> Instruction {
     Instruction::XOR { mask }
 }
 
+/// Bitwise complement of ACC
+pub fn not() -> Instruction {
+    Instruction::NOT
+}
+
 /// Shift left
 pub fn shl() -> Instruction {
     Instruction::SHL
@@ -107,6 +122,11 @@ pub fn nop() -> Instruction {
     Instruction::NOP
 }
 
+/// Raw 32-bit instruction word
+pub fn raw(word: u32) -> Instruction {
+    Instruction::RAW(word)
+}
+
 /// Exponential conversion
 pub fn exp(coeff: f32, offset: f32) -> Instruction {
     Instruction::EXP { coeff, offset }
@@ -152,6 +172,11 @@ pub fn cho(mode: ChoMode, lfo: Lfo, flags: ChoFlags, addr: u16) -> Instruction {
     }
 }
 
+/// CHO SOF - Scale ACC by an LFO and add an S.15 offset
+pub fn cho_sof(lfo: Lfo, flags: ChoFlags, offset: f32) -> Instruction {
+    Instruction::CHO_SOF { lfo, flags, offset }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -180,6 +205,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_wrlx() {
+        let inst = wrlx(Register::REG(2), -1.0);
+        match inst {
+            Instruction::WRLX { reg, coeff } => {
+                assert_eq!(reg, Register::REG(2));
+                assert_eq!(coeff, -1.0);
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+    }
+
+    #[test]
+    fn test_wrhx() {
+        let inst = wrhx(Register::REG(2), -0.5);
+        match inst {
+            Instruction::WRHX { reg, coeff } => {
+                assert_eq!(reg, Register::REG(2));
+                assert_eq!(coeff, -0.5);
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+    }
+
     #[test]
     fn test_sof() {
         let inst = sof(0.5, 0.25);
@@ -209,9 +258,21 @@ mod tests {
         assert_eq!(inst, Instruction::CLR);
     }
 
+    #[test]
+    fn test_not() {
+        let inst = not();
+        assert_eq!(inst, Instruction::NOT);
+    }
+
     #[test]
     fn test_nop() {
         let inst = nop();
         assert_eq!(inst, Instruction::NOP);
     }
+
+    #[test]
+    fn test_raw() {
+        let inst = raw(0x12345678);
+        assert_eq!(inst, Instruction::RAW(0x12345678));
+    }
 }
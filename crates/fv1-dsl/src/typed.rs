@@ -86,6 +86,15 @@ impl<S> TypedBuilder<S> {
         }
     }
 
+    /// Raw 32-bit instruction word (its effect on ACC is not tracked)
+    pub fn raw(mut self, word: u32) -> TypedBuilder<S> {
+        self.builder = self.builder.inst(Instruction::RAW(word));
+        TypedBuilder {
+            builder: self.builder,
+            _state: PhantomData,
+        }
+    }
+
     /// Build the final program
     pub fn build(self) -> fv1_asm::Program {
         self.builder.build()
@@ -106,6 +115,32 @@ impl TypedBuilder<Audio> {
         }
     }
 
+    /// Write to register as a high-shelf filter (stays in Audio state)
+    ///
+    /// WRHX writes the accumulator to a register, then loads the accumulator
+    /// with `ACC * coeff + PACC`, where PACC is the accumulator value from
+    /// the previous instruction.
+    pub fn wrhx(mut self, reg: Register, coeff: f32) -> TypedBuilder<Audio> {
+        self.builder = self.builder.inst(Instruction::WRHX { reg, coeff });
+        TypedBuilder {
+            builder: self.builder,
+            _state: PhantomData,
+        }
+    }
+
+    /// Write to register as a low-shelf filter (stays in Audio state)
+    ///
+    /// WRLX writes the accumulator to a register, then loads the accumulator
+    /// with `(PACC - ACC) * coeff + PACC`, where PACC is the accumulator
+    /// value from the previous instruction.
+    pub fn wrlx(mut self, reg: Register, coeff: f32) -> TypedBuilder<Audio> {
+        self.builder = self.builder.inst(Instruction::WRLX { reg, coeff });
+        TypedBuilder {
+            builder: self.builder,
+            _state: PhantomData,
+        }
+    }
+
     /// Multiply accumulator by register (stays in Audio state)
     ///
     /// MULX multiplies the accumulator by the value in a register.
@@ -236,6 +271,16 @@ impl TypedBuilder<Audio> {
             _state: PhantomData,
         }
     }
+
+    /// Bitwise NOT (stays in Audio state)
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(mut self) -> TypedBuilder<Audio> {
+        self.builder = self.builder.inst(Instruction::NOT);
+        TypedBuilder {
+            builder: self.builder,
+            _state: PhantomData,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -295,6 +340,17 @@ mod tests {
         assert_eq!(program.instructions().len(), 5);
     }
 
+    #[test]
+    fn test_typed_builder_with_raw() {
+        let program = TypedBuilder::new()
+            .rdax(Register::ADCL, 1.0)
+            .raw(0x00000011)
+            .wrax(Register::DACL, 0.0)
+            .build();
+
+        assert_eq!(*program.instructions()[1], Instruction::RAW(0x00000011));
+    }
+
     #[test]
     fn test_typed_builder_delay_operations() {
         let program = TypedBuilder::new()
@@ -312,11 +368,11 @@ mod tests {
         let program = TypedBuilder::new()
             .clr()
             .rdax(Register::ADCL, 1.0)
-            .mulx(Register::REG(16)) // POT0
+            .mulx(Register::POT0)
             .sof(0.8, 0.0)
             .wrax(Register::REG(0), 0.0)
             .rda(8000, 0.5)
-            .mulx(Register::REG(17)) // POT1
+            .mulx(Register::POT1)
             .rdax(Register::REG(0), 1.0)
             .wrax(Register::DACL, 0.0)
             .build();
@@ -324,6 +380,28 @@ mod tests {
         assert_eq!(program.instructions().len(), 9);
     }
 
+    #[test]
+    fn test_typed_builder_low_shelf() {
+        let program = TypedBuilder::new()
+            .rdax(Register::ADCL, 1.0)
+            .wrlx(Register::REG(0), -1.0)
+            .wrax(Register::DACL, 0.0)
+            .build();
+
+        assert_eq!(program.instructions().len(), 3);
+    }
+
+    #[test]
+    fn test_typed_builder_high_shelf() {
+        let program = TypedBuilder::new()
+            .rdax(Register::ADCL, 1.0)
+            .wrhx(Register::REG(0), -0.5)
+            .wrax(Register::DACL, 0.0)
+            .build();
+
+        assert_eq!(program.instructions().len(), 3);
+    }
+
     #[test]
     fn test_typed_builder_absa() {
         let program = TypedBuilder::new()
@@ -354,9 +432,10 @@ mod tests {
             .and(0xFFFF)
             .or(0x0001)
             .xor(0x0001)
+            .not()
             .wrax(Register::DACL, 0.0)
             .build();
 
-        assert_eq!(program.instructions().len(), 5);
+        assert_eq!(program.instructions().len(), 6);
     }
 }
@@ -19,7 +19,7 @@ mod typed_builder_tests {
         // Same gain control as before, but with type safety
         let program = TypedBuilder::new()
             .rdax(Register::ADCL, 1.0) // Transitions to Audio state
-            .mulx(Register::REG(16)) // POT0, stays in Audio state
+            .mulx(Register::POT0) // Stays in Audio state
             .wrax(Register::DACL, 0.0) // Stays in Audio state
             .build();
 
@@ -55,10 +55,10 @@ mod typed_builder_tests {
             .rdax(Register::ADCL, 1.0)
             .wrax(Register::REG(0), 0.0)
             .rda(4000, 0.5)
-            .mulx(Register::REG(17)) // POT1
+            .mulx(Register::POT1)
             .rdax(Register::REG(0), 1.0)
             .wra(0, 0.0)
-            .mulx(Register::REG(18)) // POT2
+            .mulx(Register::POT2)
             .rdax(Register::REG(0), 1.0)
             .wrax(Register::DACL, 0.0)
             .build();
@@ -100,7 +100,7 @@ mod typed_builder_tests {
             .sof(0.9, 0.0) // Scale down slightly
             .wrax(Register::REG(0), 0.5) // Store and keep half in ACC
             .rda(8000, 0.6) // Read delayed signal
-            .mulx(Register::REG(16)) // Modulate with POT0
+            .mulx(Register::POT0) // Modulate with POT0
             .rdax(Register::REG(0), 1.0) // Add dry signal
             .sof(0.8, 0.0) // Scale output
             .wrax(Register::DACL, 0.0) // Output
@@ -345,7 +345,7 @@ WRAX DACL, 0.0
 
     #[test]
     fn test_gain_control_example_equivalence() {
-        // Assembly source (POT0 maps to REG16)
+        // Assembly source
         let asm_source = r#"
 ; Gain control using POT0
 ; POT0 controls the volume from 0 to 100%
@@ -360,17 +360,17 @@ MULX POT0
 WRAX DACL, 0.0
 "#;
 
-        // DSL version using macro (POT0 = REG16)
+        // DSL version using macro
         let dsl_program_macro = fv1_program! {
             rdax(Register::ADCL, 1.0);
-            mulx(Register::REG(16));
+            mulx(Register::POT0);
             wrax(Register::DACL, 0.0);
         };
 
         // DSL version using builder
         let dsl_program_builder = ProgramBuilder::new()
             .inst(rdax(Register::ADCL, 1.0))
-            .inst(mulx(Register::REG(16)))
+            .inst(mulx(Register::POT0))
             .inst(wrax(Register::DACL, 0.0))
             .build();
 
@@ -392,7 +392,7 @@ WRAX DACL, 0.0
 
     #[test]
     fn test_delay_echo_example_equivalence() {
-        // Assembly source (POT1 = REG17, POT2 = REG18)
+        // Assembly source
         let asm_source = r#"
 ; Simple delay/echo effect
 ; A basic echo with fixed delay time
@@ -420,15 +420,15 @@ RDAX REG0, 1.0          ; Add dry signal
 WRAX DACL, 0.0
 "#;
 
-        // DSL version using macro (POT1 = REG17, POT2 = REG18)
+        // DSL version using macro
         let dsl_program_macro = fv1_program! {
             rdax(Register::ADCL, 1.0);
             wrax(Register::REG(0), 0.0);
             rda(4000, 0.5);
-            mulx(Register::REG(17));
+            mulx(Register::POT1);
             rdax(Register::REG(0), 1.0);
             wra(0, 0.0);
-            mulx(Register::REG(18));
+            mulx(Register::POT2);
             rdax(Register::REG(0), 1.0);
             wrax(Register::DACL, 0.0);
         };
@@ -438,10 +438,10 @@ WRAX DACL, 0.0
             .inst(rdax(Register::ADCL, 1.0))
             .inst(wrax(Register::REG(0), 0.0))
             .inst(rda(4000, 0.5))
-            .inst(mulx(Register::REG(17)))
+            .inst(mulx(Register::POT1))
             .inst(rdax(Register::REG(0), 1.0))
             .inst(wra(0, 0.0))
-            .inst(mulx(Register::REG(18)))
+            .inst(mulx(Register::POT2))
             .inst(rdax(Register::REG(0), 1.0))
             .inst(wrax(Register::DACL, 0.0))
             .build();
@@ -497,7 +497,7 @@ WRAX DACL, 0.0
         // Using high-level blocks - passthrough is just read + write
         // The blocks::gain function reads the input
         let mut builder = ProgramBuilder::new();
-        builder.add_inst(blocks::gain(Register::ADCL, Register::REG(16)));
+        builder.add_inst(blocks::gain(Register::ADCL, Register::POT0));
         builder.add_inst(wrax(Register::DACL, 0.0));
         let dsl_program = builder.build();
 
@@ -513,7 +513,7 @@ WRAX DACL, 0.0
 
     #[test]
     fn test_gain_control_with_blocks() {
-        // Original assembly (POT0 = REG16)
+        // Original assembly
         let asm_source = r#"
 RDAX ADCL, 1.0
 MULX POT0
@@ -522,8 +522,8 @@ WRAX DACL, 0.0
 
         // Using high-level blocks - gain reads input, then we multiply
         let mut builder = ProgramBuilder::new();
-        builder.add_inst(blocks::gain(Register::ADCL, Register::REG(16)));
-        builder.add_inst(mulx(Register::REG(16))); // POT0
+        builder.add_inst(blocks::gain(Register::ADCL, Register::POT0));
+        builder.add_inst(mulx(Register::POT0));
         builder.add_inst(wrax(Register::DACL, 0.0));
         let dsl_program = builder.build();
 
@@ -538,7 +538,7 @@ WRAX DACL, 0.0
 
     #[test]
     fn test_delay_echo_with_blocks() {
-        // Original assembly (POT1 = REG17, POT2 = REG18)
+        // Original assembly
         let asm_source = r#"
 RDAX ADCL, 1.0
 WRAX REG0, 0.0
@@ -570,7 +570,7 @@ WRAX DACL, 0.0
         builder.add_inst(sof(0.5, 0.0)); // Scale to match original 0.5 coefficient
 
         // Add feedback
-        builder.add_inst(mulx(Register::REG(17))); // POT1
+        builder.add_inst(mulx(Register::POT1));
         builder.add_inst(rdax(Register::REG(0), 1.0));
 
         // Write to delay line using Delay block
@@ -579,7 +579,7 @@ WRAX DACL, 0.0
         }
 
         // Mix wet/dry
-        builder.add_inst(mulx(Register::REG(18))); // POT2
+        builder.add_inst(mulx(Register::POT2));
         builder.add_inst(rdax(Register::REG(0), 1.0));
 
         // Output
@@ -633,7 +633,7 @@ WRAX DACL, 0.0
         builder.add_inst(rda(4000, 0.5));
 
         // Add feedback
-        builder.add_inst(mulx(Register::REG(17))); // POT1
+        builder.add_inst(mulx(Register::POT1));
         builder.add_inst(rdax(Register::REG(0), 1.0));
 
         // Write to delay line using Delay block
@@ -642,7 +642,7 @@ WRAX DACL, 0.0
         }
 
         // Mix wet/dry
-        builder.add_inst(mulx(Register::REG(18))); // POT2
+        builder.add_inst(mulx(Register::POT2));
         builder.add_inst(rdax(Register::REG(0), 1.0));
 
         // Output
@@ -668,7 +668,7 @@ WRAX DACL, 0.0
         builder.add_inst(rdax(Register::ADCL, 1.0));
 
         // Apply lowpass filter
-        for inst in blocks::lowpass(Register::ACC, Register::REG(16), Register::REG(1)) {
+        for inst in blocks::lowpass(Register::ACC, Register::POT0, Register::REG(1)) {
             builder.add_inst(inst);
         }
 
@@ -719,11 +719,11 @@ WRAX DACL, 0.0
         let mut builder = ProgramBuilder::new();
 
         // Gain control
-        builder.add_inst(blocks::gain(Register::ADCL, Register::REG(16))); // POT0
-        builder.add_inst(mulx(Register::REG(16)));
+        builder.add_inst(blocks::gain(Register::ADCL, Register::POT0));
+        builder.add_inst(mulx(Register::POT0));
 
         // Lowpass filter
-        for inst in blocks::lowpass(Register::ACC, Register::REG(17), Register::REG(1)) {
+        for inst in blocks::lowpass(Register::ACC, Register::POT1, Register::REG(1)) {
             builder.add_inst(inst);
         }
 
@@ -746,3 +746,50 @@ WRAX DACL, 0.0
         assert_eq!(program.instructions().len(), 10);
     }
 }
+
+/// Tests that disassembled DSL source builds the original program
+mod disassembled_source_tests {
+    use fv1_asm::{Assembler, Disassembler, Parser};
+
+    /// Output of `Disassembler::to_dsl_source` for the source below
+    mod generated {
+        use fv1_dsl::{prelude::*, Program};
+
+        pub fn program() -> Program {
+            ProgramBuilder::new()
+                .inst(skp(SkipCondition::RUN, 1))
+                .inst(wlds(Lfo::SIN0, 12, 100))
+                .inst(rdax(Register::ADCL, 1.0))
+                .inst(cho(
+                    ChoMode::RDA,
+                    Lfo::SIN0,
+                    ChoFlags::from_bits(ChoFlags::REG | ChoFlags::COMPC),
+                    1000,
+                ))
+                .inst(sof(0.333313, -0.5))
+                .inst(wrax(Register::DACL, 0.0))
+                .build()
+        }
+    }
+
+    #[test]
+    fn test_dsl_source_rebuilds_binary() {
+        let source = "\
+skp run, 1
+wlds sin0, 12, 100
+rdax adcl, 1.0
+cho rda, sin0, reg|compc, 1000
+sof 0.3333, -0.5
+wrax dacl, 0.0
+";
+        let assembler = Assembler::new();
+        let binary = assembler
+            .assemble(&Parser::new(source).parse().unwrap())
+            .unwrap();
+        let dsl = Disassembler::new().to_dsl_source(&binary).unwrap();
+        assert!(dsl.contains(".inst(sof(0.333313, -0.5))"));
+
+        let rebuilt = assembler.assemble(&generated::program()).unwrap();
+        assert_eq!(rebuilt.instructions(), binary.instructions());
+    }
+}
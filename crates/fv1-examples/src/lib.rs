@@ -90,8 +90,6 @@ pub mod dsl_examples {
     /// This program reads the left ADC input, multiplies it by POT0 for volume
     /// control, and outputs to the left DAC.
     ///
-    /// Note: POT0 maps to REG16 in the FV-1 architecture.
-    ///
     /// # Example
     /// ```
     /// use fv1_examples::dsl_examples;
@@ -105,7 +103,7 @@ pub mod dsl_examples {
     pub fn gain_control() -> fv1_asm::Program {
         fv1_program! {
             rdax(Register::ADCL, 1.0);
-            mulx(Register::REG(16)); // POT0
+            mulx(Register::POT0);
             wrax(Register::DACL, 0.0);
         }
     }
@@ -114,7 +112,7 @@ pub mod dsl_examples {
     pub fn gain_control_typed() -> fv1_asm::Program {
         TypedBuilder::new()
             .rdax(Register::ADCL, 1.0)
-            .mulx(Register::REG(16)) // POT0
+            .mulx(Register::POT0)
             .wrax(Register::DACL, 0.0)
             .build()
     }
@@ -122,8 +120,8 @@ pub mod dsl_examples {
     /// Delay echo effect with feedback and mix controls
     ///
     /// This program creates a simple delay/echo effect:
-    /// - POT1 (REG17) controls feedback amount
-    /// - POT2 (REG18) controls wet/dry mix
+    /// - POT1 controls feedback amount
+    /// - POT2 controls wet/dry mix
     /// - Fixed delay time at address 4000
     ///
     /// # Example
@@ -141,10 +139,10 @@ pub mod dsl_examples {
             .rdax(Register::ADCL, 1.0)
             .wrax(Register::REG(0), 0.0) // Save input
             .rda(4000, 0.5) // Read delayed signal
-            .mulx(Register::REG(17)) // POT1 - feedback
+            .mulx(Register::POT1) // Feedback
             .rdax(Register::REG(0), 1.0) // Add input
             .wra(0, 0.0) // Write to delay line
-            .mulx(Register::REG(18)) // POT2 - wet amount
+            .mulx(Register::POT2) // Wet amount
             .rdax(Register::REG(0), 1.0) // Add dry signal
             .wrax(Register::DACL, 0.0) // Output
             .build()
@@ -172,12 +170,12 @@ pub mod dsl_examples {
         let mut builder = ProgramBuilder::new();
 
         // Input gain control
-        builder.add_inst(blocks::gain(Register::ADCL, Register::REG(16)));
-        builder.add_inst(mulx(Register::REG(16))); // POT0 controls input gain
+        builder.add_inst(blocks::gain(Register::ADCL, Register::POT0));
+        builder.add_inst(mulx(Register::POT0));
 
         // One-pole lowpass filter
-        // Filter state stored in REG1, cutoff controlled by POT1 (REG17)
-        for inst in blocks::lowpass(Register::ACC, Register::REG(17), Register::REG(1)) {
+        // Filter state stored in REG1, cutoff controlled by POT1
+        for inst in blocks::lowpass(Register::ACC, Register::POT1, Register::REG(1)) {
             builder.add_inst(inst);
         }
 
@@ -229,7 +227,7 @@ pub mod dsl_examples {
         builder.add_inst(rda(4000, 0.3));
 
         // Scale by POT1 for wet amount
-        builder.add_inst(mulx(Register::REG(17))); // POT1
+        builder.add_inst(mulx(Register::POT1));
 
         // Add dry signal
         builder.add_inst(rdax(Register::REG(0), 1.0));
@@ -239,7 +237,7 @@ pub mod dsl_examples {
 
         // Write to delay line with feedback (POT0)
         builder.add_inst(rdax(Register::REG(0), 1.0));
-        builder.add_inst(mulx(Register::REG(16))); // POT0 - feedback
+        builder.add_inst(mulx(Register::POT0)); // Feedback
         builder.add_inst(rdax(Register::REG(1), 1.0));
         for inst in delay.write(0.0) {
             builder.add_inst(inst);